@@ -1,7 +1,9 @@
 //! Backend service configuration
 
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use boomaga_core::constants::{
     DEFAULT_DBUS_PATH, DEFAULT_MAX_JOB_HISTORY, DEFAULT_TIMEOUT_SECS,
@@ -10,6 +12,14 @@ use boomaga_core::constants::{
 /// Backend service configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackendConfig {
+    /// IPP listen address
+    ///
+    /// Defaults to loopback. Binding `0.0.0.0` or a LAN interface exposes the
+    /// printer to every host that can reach it, so only do that on trusted
+    /// networks.
+    #[serde(default = "default_bind_address")]
+    pub bind_address: IpAddr,
+
     /// IPP service port
     pub ipp_port: u16,
 
@@ -28,7 +38,8 @@ pub struct BackendConfig {
     /// Job queue size
     pub job_queue_size: usize,
 
-    /// Queue timeout (seconds)
+    /// Queue timeout (seconds): how long a job waits for room in a full
+    /// queue before it is refused, and how often idle workers log
     pub queue_timeout: u64,
 
     /// Enable debug logging
@@ -48,11 +59,35 @@ pub struct BackendConfig {
 
     /// Maximum job size in bytes
     pub max_job_size: u64,
+
+    /// Directory holding submitted documents until their job ends
+    #[serde(default = "default_spool_dir")]
+    pub spool_dir: PathBuf,
+
+    /// Keep waiting jobs across restarts, journalled in `queue` under the
+    /// spool directory
+    #[serde(default)]
+    pub persist_queue: bool,
+
+    /// When failed jobs are tried again
+    #[serde(default)]
+    pub retry: RetryPolicy,
+
+    /// What to do with a two-sided job the downstream printer can only
+    /// print one-sided
+    #[serde(default)]
+    pub unsupported_duplex: UnsupportedDuplex,
+
+    /// Parsed documents kept for identical resubmissions (MB); 0 disables
+    /// the cache
+    #[serde(default = "default_document_cache_size")]
+    pub document_cache_size: u64,
 }
 
 impl Default for BackendConfig {
     fn default() -> Self {
         Self {
+            bind_address: default_bind_address(),
             ipp_port: 631,
             ipc_socket_path: PathBuf::from(boomaga_core::constants::IPC_SOCKET_PATH),
             dbus_service_name: boomaga_core::constants::DBUS_SERVICE_NAME.to_string(),
@@ -65,11 +100,92 @@ impl Default for BackendConfig {
             dns_sd: true,
             dns_sd_service_type: "ipp".to_string(),
             job_timeout: 300,
-            max_job_size: 100 * 1024 * 1024, // 100 MB
+            max_job_size: boomaga_core::constants::DEFAULT_MAX_JOB_SIZE,
+            spool_dir: default_spool_dir(),
+            persist_queue: false,
+            retry: RetryPolicy::default(),
+            unsupported_duplex: UnsupportedDuplex::default(),
+            document_cache_size: default_document_cache_size(),
         }
     }
 }
 
+/// Handling of two-sided jobs for a printer without duplex support
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnsupportedDuplex {
+    /// Print two-sided by hand, see `PrintOptions::manual_duplex`
+    Emulate,
+    /// Refuse the job
+    #[default]
+    Reject,
+}
+
+/// When and how soon a failed job is tried again
+///
+/// Only transient errors, see [`boomaga_core::Error::is_transient`], are
+/// retried. The delay doubles after each attempt, up to `max_delay`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included; 1 never retries
+    pub max_attempts: u32,
+    /// Delay before the first retry (milliseconds)
+    pub base_delay: u64,
+    /// Longest delay between attempts (milliseconds)
+    pub max_delay: u64,
+    /// Fraction of each delay, from 0 to 1, that is randomly left out so
+    /// retries of several jobs spread out
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: 500,
+            max_delay: 10_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Check whether a job failing with `error` on attempt `attempt`,
+    /// counted from 1, should be tried again
+    pub fn should_retry(&self, error: &boomaga_core::Error, attempt: u32) -> bool {
+        attempt < self.max_attempts && error.is_transient()
+    }
+
+    /// Get the delay before retrying after attempt `attempt`, counted from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let doubled = self
+            .base_delay
+            .saturating_mul(1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX));
+        let delay = doubled.min(self.max_delay) as f64;
+
+        let jitter = self.jitter.clamp(0.0, 1.0) * fastrand::f64();
+        Duration::from_millis((delay * (1.0 - jitter)) as u64)
+    }
+}
+
+/// Loopback, so the printer is not reachable from other hosts by default
+fn default_bind_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+/// 256 MB of parsed documents
+fn default_document_cache_size() -> u64 {
+    256
+}
+
+/// `spool` under the user cache directory, or the temp directory without one
+fn default_spool_dir() -> PathBuf {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.cache_dir().join("boomaga").join("spool"))
+        .unwrap_or_else(|| std::env::temp_dir().join("boomaga-spool"))
+}
+
 impl BackendConfig {
     /// Validate configuration
     pub fn validate(&self) -> anyhow::Result<()> {
@@ -85,14 +201,18 @@ impl BackendConfig {
             return Err(anyhow::anyhow!("Job queue size must be greater than 0"));
         }
 
-        if self.ipp_port < 1024 {
-            return Err(anyhow::anyhow!("IPP port must be between 1024 and 65535"));
+        if self.ipp_port == 0 {
+            return Err(anyhow::anyhow!("IPP port must be greater than 0"));
         }
 
         if self.max_job_size == 0 {
             return Err(anyhow::anyhow!("Max job size must be greater than 0"));
         }
 
+        if self.retry.max_attempts == 0 {
+            return Err(anyhow::anyhow!("Retry attempts must be greater than 0"));
+        }
+
         Ok(())
     }
 
@@ -114,6 +234,12 @@ impl BackendConfig {
         self
     }
 
+    /// Set the IPP listen address
+    pub fn with_bind_address(mut self, address: IpAddr) -> Self {
+        self.bind_address = address;
+        self
+    }
+
     /// Set custom IPP port
     pub fn with_port(mut self, port: u16) -> Self {
         self.ipp_port = port;
@@ -127,6 +253,7 @@ impl From<BackendConfig> for boomaga_core::constants::AppConfig {
             ipc_socket_path: config.ipc_socket_path.display().to_string(),
             dbus_service_name: config.dbus_service_name.to_string(),
             dbus_path: DEFAULT_DBUS_PATH.to_string(),
+            ipp_bind_address: config.bind_address.to_string(),
             ipp_port: config.ipp_port,
             max_job_history: DEFAULT_MAX_JOB_HISTORY,
             timeout_secs: DEFAULT_TIMEOUT_SECS,
@@ -6,19 +6,25 @@
 mod backend_config;
 mod preview_config;
 mod settings;
+mod settings_patch;
+mod shortcut;
 mod defaults;
 
-pub use backend_config::BackendConfig;
-pub use preview_config::{PreviewConfig, PrintSettings};
-pub use settings::Settings;
+pub use backend_config::{BackendConfig, RetryPolicy, UnsupportedDuplex};
+pub use preview_config::{KeybindingConfig, Keybindings, PreviewConfig, PrintSettings};
+pub use settings::{PerformanceSettings, Profile, Settings, UISettings, ZoomMode};
+pub use shortcut::{find_conflicts, parse_shortcut, Key, Modifiers, Shortcut};
 pub use defaults::constants::*;
 
 // Re-export types from boomaga_core
-pub use boomaga_core::PrintOptions;
+pub use boomaga_core::{CustomLayout, PatchOutcome, PrintOptions, RenderQuality, RenderSettings, SettingsPatch};
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Result};
-use tracing::{info, debug};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{info, debug, warn};
 
 /// Application configuration errors
 #[derive(Debug, thiserror::Error)]
@@ -35,6 +41,9 @@ pub enum ConfigError {
     #[error("Failed to save TOML: {0}")]
     TomlSer(#[from] toml::ser::Error),
 
+    #[error("Failed to merge configuration sources: {0}")]
+    Sources(#[from] config::ConfigError),
+
     #[error("Failed to save configuration: {0}")]
     Save(#[from] std::io::Error),
 
@@ -48,6 +57,9 @@ pub enum ConfigError {
     Permission(String),
 }
 
+/// Prefix of environment variables overriding config fields, e.g. `BOOMAGA_DEFAULT_ZOOM`
+pub const ENV_PREFIX: &str = "BOOMAGA";
+
 /// Configuration manager
 pub struct ConfigManager {
     backend_config_path: PathBuf,
@@ -77,36 +89,69 @@ impl ConfigManager {
         })
     }
 
+    /// Create a configuration manager rooted at explicit directories
+    pub fn with_dirs(config_dir: &Path, state_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(config_dir)?;
+        std::fs::create_dir_all(state_dir)?;
+
+        Ok(Self {
+            backend_config_path: config_dir.join("backend.toml"),
+            preview_config_path: config_dir.join("preview.toml"),
+            settings_path: state_dir.join("settings.json"),
+        })
+    }
+
     /// Load backend configuration
+    ///
+    /// Defaults are overlaid by the config file, then by `BOOMAGA_*` variables.
     pub fn load_backend(&self) -> Result<BackendConfig, ConfigError> {
+        self.load_backend_from(None)
+    }
+
+    /// Load backend configuration with an explicit environment
+    pub fn load_backend_with_env(&self, env: HashMap<String, String>) -> Result<BackendConfig, ConfigError> {
+        self.load_backend_from(Some(env))
+    }
+
+    fn load_backend_from(&self, env: Option<HashMap<String, String>>) -> Result<BackendConfig, ConfigError> {
         debug!("Loading backend configuration from {:?}", self.backend_config_path);
 
         if !self.backend_config_path.exists() {
-            // Use defaults
             info!("Backend config file not found, using defaults");
-            return Ok(BackendConfig::default());
         }
 
-        let toml_content = std::fs::read_to_string(&self.backend_config_path)?;
-        let backend_config: BackendConfig = toml::from_str(&toml_content)?;
+        let backend_config: BackendConfig =
+            load_layered(&BackendConfig::default(), &self.backend_config_path, env)?;
         backend_config.validate()?;
 
         Ok(backend_config)
     }
 
     /// Load preview configuration
+    ///
+    /// Defaults are overlaid by the config file, then by `BOOMAGA_*` variables.
     pub fn load_preview(&self) -> Result<PreviewConfig, ConfigError> {
+        self.load_preview_from(None)
+    }
+
+    /// Load preview configuration with an explicit environment
+    pub fn load_preview_with_env(&self, env: HashMap<String, String>) -> Result<PreviewConfig, ConfigError> {
+        self.load_preview_from(Some(env))
+    }
+
+    fn load_preview_from(&self, env: Option<HashMap<String, String>>) -> Result<PreviewConfig, ConfigError> {
         debug!("Loading preview configuration from {:?}", self.preview_config_path);
 
         if !self.preview_config_path.exists() {
-            // Use defaults
             info!("Preview config file not found, using defaults");
-            return Ok(PreviewConfig::default());
         }
 
-        let toml_content = std::fs::read_to_string(&self.preview_config_path)?;
-        let preview_config: PreviewConfig = toml::from_str(&toml_content)?;
+        let preview_config: PreviewConfig =
+            load_layered(&PreviewConfig::default(), &self.preview_config_path, env)?;
         preview_config.validate()?;
+        for (shortcut, actions) in preview_config.keybindings.conflicts() {
+            warn!("Shortcut {} is bound to several actions: {}", shortcut, actions.join(", "));
+        }
 
         Ok(preview_config)
     }
@@ -121,8 +166,11 @@ impl ConfigManager {
             return Ok(Settings::default());
         }
 
-        let settings: Settings =
+        let mut settings: Settings =
             serde_json::from_str(&std::fs::read_to_string(&self.settings_path)?)?;
+        for correction in settings.validate() {
+            warn!("{}, using the default", correction);
+        }
 
         Ok(settings)
     }
@@ -132,7 +180,7 @@ impl ConfigManager {
         debug!("Saving backend configuration to {:?}", self.backend_config_path);
 
         let toml = toml::to_string_pretty(config)?;
-        std::fs::write(&self.backend_config_path, toml)?;
+        write_atomic(&self.backend_config_path, toml.as_bytes())?;
 
         Ok(())
     }
@@ -142,7 +190,7 @@ impl ConfigManager {
         debug!("Saving preview configuration to {:?}", self.preview_config_path);
 
         let toml = toml::to_string_pretty(config)?;
-        std::fs::write(&self.preview_config_path, toml)?;
+        write_atomic(&self.preview_config_path, toml.as_bytes())?;
 
         Ok(())
     }
@@ -152,11 +200,49 @@ impl ConfigManager {
         debug!("Saving settings to {:?}", self.settings_path);
 
         let json = serde_json::to_string_pretty(settings)?;
-        std::fs::write(&self.settings_path, json)?;
+        write_atomic(&self.settings_path, json.as_bytes())?;
 
         Ok(())
     }
 
+    /// Load the settings, change them and save them if the change succeeds
+    ///
+    /// Used for profile and layout edits, e.g.
+    /// `manager.update_settings(|settings| settings.remove_profile("Office"))`.
+    pub fn update_settings<T>(
+        &self,
+        change: impl FnOnce(&mut Settings) -> Result<T, ConfigError>,
+    ) -> Result<T, ConfigError> {
+        let mut settings = self.load_settings()?;
+        let result = change(&mut settings)?;
+        self.save_settings(&settings)?;
+        Ok(result)
+    }
+
+    /// Restore the backend configuration file to defaults
+    pub fn reset_backend(&self) -> Result<BackendConfig, ConfigError> {
+        info!("Resetting backend configuration at {:?}", self.backend_config_path);
+        let config = BackendConfig::default();
+        self.save_backend(&config)?;
+        Ok(config)
+    }
+
+    /// Restore the preview configuration file to defaults
+    pub fn reset_preview(&self) -> Result<PreviewConfig, ConfigError> {
+        info!("Resetting preview configuration at {:?}", self.preview_config_path);
+        let config = PreviewConfig::default();
+        self.save_preview(&config)?;
+        Ok(config)
+    }
+
+    /// Restore the settings file to defaults
+    pub fn reset_settings(&self) -> Result<Settings, ConfigError> {
+        info!("Resetting settings at {:?}", self.settings_path);
+        let settings = Settings::default();
+        self.save_settings(&settings)?;
+        Ok(settings)
+    }
+
     /// Get backend config path
     pub fn backend_config_path(&self) -> &PathBuf {
         &self.backend_config_path
@@ -173,6 +259,36 @@ impl ConfigManager {
     }
 }
 
+/// Merge defaults, an optional TOML file and `BOOMAGA_*` variables
+///
+/// `env` replaces the process environment when given.
+fn load_layered<T>(defaults: &T, path: &Path, env: Option<HashMap<String, String>>) -> Result<T, ConfigError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let merged = config::Config::builder()
+        .add_source(config::Config::try_from(defaults)?)
+        .add_source(config::File::from(path).format(config::FileFormat::Toml).required(false))
+        .add_source(
+            config::Environment::with_prefix(ENV_PREFIX)
+                .try_parsing(true)
+                .source(env),
+        )
+        .build()?;
+
+    Ok(merged.try_deserialize()?)
+}
+
+/// Replace `path` with `contents` via a temporary file, so readers never see a partial write
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)
+}
+
 impl Default for ConfigManager {
     fn default() -> Self {
         Self::new().expect("Failed to create config manager")
@@ -221,6 +337,212 @@ mod tests {
         assert!(config.preview_config_path().exists() || true);
     }
 
+    #[test]
+    fn reset_restores_defaults_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        let backend = BackendConfig {
+            worker_threads: 7,
+            ..Default::default()
+        };
+        config.save_backend(&backend).unwrap();
+        let preview = PreviewConfig {
+            default_zoom: 3.0,
+            ..Default::default()
+        };
+        config.save_preview(&preview).unwrap();
+        std::fs::write(config.settings_path(), "not json").unwrap();
+
+        config.reset_backend().unwrap();
+        config.reset_preview().unwrap();
+        config.reset_settings().unwrap();
+
+        let backend: BackendConfig =
+            toml::from_str(&std::fs::read_to_string(config.backend_config_path()).unwrap()).unwrap();
+        assert_eq!(backend.worker_threads, BackendConfig::default().worker_threads);
+        assert_eq!(config.load_preview().unwrap().default_zoom, PreviewConfig::default().default_zoom);
+        let settings: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(config.settings_path()).unwrap()).unwrap();
+        assert_eq!(settings, serde_json::to_value(Settings::default()).unwrap());
+    }
+
+    #[test]
+    fn environment_overrides_config_file() {
+        let dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        let preview = PreviewConfig {
+            default_zoom: 3.0,
+            max_cache_size: 64,
+            ..Default::default()
+        };
+        config.save_preview(&preview).unwrap();
+
+        let env = HashMap::from([("BOOMAGA_DEFAULT_ZOOM".to_string(), "2.0".to_string())]);
+        let loaded = config.load_preview_with_env(env).unwrap();
+
+        assert_eq!(loaded.default_zoom, 2.0);
+        assert_eq!(loaded.max_cache_size, 64);
+    }
+
+    fn two_up_a5() -> CustomLayout {
+        CustomLayout {
+            name: "Handout".to_string(),
+            pages_per_sheet: 2,
+            booklet: false,
+            output_size: boomaga_core::PageSize::A5,
+            margin: 18.0,
+            gutter: 6.0,
+        }
+    }
+
+    #[test]
+    fn custom_layouts_are_saved_and_listed_after_built_ins() {
+        let dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        let mut settings = Settings::default();
+        settings.add_custom_layout(two_up_a5()).unwrap();
+        config.save_settings(&settings).unwrap();
+
+        let mut loaded = config.load_settings().unwrap();
+        assert_eq!(loaded.custom_layouts, vec![two_up_a5()]);
+        assert_eq!(loaded.layout_names().last(), Some("Handout"));
+
+        loaded.rename_custom_layout("Handout", "Slides").unwrap();
+        assert_eq!(loaded.custom_layouts[0].name, "Slides");
+        assert_eq!(loaded.remove_custom_layout("Slides").unwrap().pages_per_sheet, 2);
+        assert!(loaded.custom_layouts.is_empty());
+    }
+
+    #[test]
+    fn custom_layout_names_must_be_unique() {
+        let mut settings = Settings::default();
+        settings.add_custom_layout(two_up_a5()).unwrap();
+
+        let built_in = CustomLayout { name: "4-Up".to_string(), ..two_up_a5() };
+        assert!(matches!(settings.add_custom_layout(built_in), Err(ConfigError::Invalid(_))));
+        assert!(settings.add_custom_layout(two_up_a5()).is_err());
+        assert!(settings.rename_custom_layout("Handout", "Booklet").is_err());
+        assert!(settings.remove_custom_layout("Missing").is_err());
+        assert_eq!(settings.custom_layouts, vec![two_up_a5()]);
+    }
+
+    fn office_duplex() -> Profile {
+        Profile {
+            name: "Office duplex".to_string(),
+            printer: Some("office".to_string()),
+            print: PrintSettings { copies: 2, pages_per_sheet: 2, ..Default::default() },
+            layout: Some("Handout".to_string()),
+        }
+    }
+
+    #[test]
+    fn profiles_are_created_applied_and_deleted_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        config
+            .update_settings(|settings| {
+                settings.add_custom_layout(two_up_a5())?;
+                settings.add_profile(office_duplex())
+            })
+            .unwrap();
+
+        let printer = config
+            .update_settings(|settings| Ok(settings.apply_profile("Office duplex")?.printer.clone()))
+            .unwrap();
+        assert_eq!(printer.as_deref(), Some("office"));
+        let settings = config.load_settings().unwrap();
+        assert_eq!(settings.active_profile.as_deref(), Some("Office duplex"));
+        assert_eq!(settings.print.last_printer.as_deref(), Some("office"));
+        assert_eq!(settings.print.last_print_settings.as_ref().unwrap().copies, 2);
+        let mut options = PrintOptions::default();
+        settings.print.last_print_settings.as_ref().unwrap().apply_to(&mut options);
+        assert_eq!(options.pages_per_sheet, boomaga_core::PagesPerSheet::Two);
+
+        let removed = config.update_settings(|settings| settings.remove_profile("Office duplex")).unwrap();
+        assert_eq!(removed.name, "Office duplex");
+        let settings = config.load_settings().unwrap();
+        assert!(settings.profiles.is_empty());
+        assert_eq!(settings.active_profile, None);
+        // The defaults the profile set stay in place
+        assert_eq!(settings.print.last_printer.as_deref(), Some("office"));
+    }
+
+    #[test]
+    fn invalid_profile_changes_are_not_saved() {
+        let dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        config.update_settings(|settings| settings.add_profile(Profile { layout: None, ..office_duplex() })).unwrap();
+
+        let unknown_layout = Profile { name: "Home".to_string(), ..office_duplex() };
+        assert!(config.update_settings(|settings| settings.add_profile(unknown_layout)).is_err());
+        let duplicate = Profile { layout: None, ..office_duplex() };
+        assert!(config.update_settings(|settings| settings.add_profile(duplicate)).is_err());
+        assert!(config.update_settings(|settings| settings.apply_profile("Home").map(|_| ())).is_err());
+
+        let settings = config.load_settings().unwrap();
+        assert_eq!(settings.profiles.len(), 1);
+        assert_eq!(settings.active_profile, None);
+    }
+
+    /// Save settings with one field changed and load them back
+    fn reload(change: impl FnOnce(&mut Settings)) -> Settings {
+        let dir = TempDir::new().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        let mut settings = Settings::default();
+        change(&mut settings);
+        config.save_settings(&settings).unwrap();
+        config.load_settings().unwrap()
+    }
+
+    #[test]
+    fn default_settings_are_valid() {
+        assert!(Settings::default().validate().is_empty());
+    }
+
+    #[test]
+    fn default_backend_config_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        assert_eq!(config.load_backend().unwrap().ipp_port, 631);
+    }
+
+    #[test]
+    fn undersized_window_is_reset_on_load() {
+        let settings = reload(|settings| settings.window.size = Some((640, 480)));
+        assert_eq!(settings.window.size, Some((1200, 800)));
+
+        let settings = reload(|settings| settings.window.size = Some((1024, 700)));
+        assert_eq!(settings.window.size, Some((1024, 700)));
+    }
+
+    #[test]
+    fn out_of_range_zoom_is_reset_on_load() {
+        assert_eq!(reload(|settings| settings.document.zoom_level = 10.0).document.zoom_level, 1.0);
+        assert_eq!(reload(|settings| settings.document.zoom_level = 0.0).document.zoom_level, 1.0);
+        assert_eq!(reload(|settings| settings.document.zoom_level = 2.0).document.zoom_level, 2.0);
+    }
+
+    #[test]
+    fn zero_thumbnail_size_is_reset_on_load() {
+        let settings = reload(|settings| settings.performance.thumbnail_size = 0);
+        assert_eq!(settings.performance.thumbnail_size, 120);
+    }
+
+    #[test]
+    fn unparseable_keybindings_are_dropped_on_load() {
+        let settings = reload(|settings| {
+            settings.keybindings.insert("print".to_string(), "Ctrl+P".to_string());
+            settings.keybindings.insert("zoom_in".to_string(), "Ctrl+".to_string());
+            settings.keybindings.insert("quit".to_string(), String::new());
+            settings.document.zoom_level = 2.0;
+        });
+
+        assert_eq!(settings.keybindings.len(), 1);
+        assert_eq!(settings.keybindings["print"], "Ctrl+P");
+        // Other fields are kept
+        assert_eq!(settings.document.zoom_level, 2.0);
+    }
+
     #[test]
     fn test_default_backend_config() {
         let config = BackendConfig::default();
@@ -234,4 +556,81 @@ mod tests {
         assert_eq!(config.default_zoom, 1.0);
         assert_eq!(config.auto_zoom, true);
     }
+
+    #[test]
+    fn duplicated_keybindings_are_reported() {
+        assert!(Keybindings::default().conflicts().is_empty());
+
+        let mut keybindings = Keybindings::default();
+        keybindings.view.fit_page = "ctrl+p".to_string();
+        keybindings.zoom.zoom_out = "Ctrl+Right".to_string();
+
+        let conflicts = keybindings.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].0.to_string(), "Ctrl+Right");
+        assert_eq!(conflicts[0].1, vec!["navigation.next_page", "zoom.zoom_out"]);
+        assert_eq!(conflicts[1].0.to_string(), "Ctrl+P");
+        assert_eq!(conflicts[1].1, vec!["print.fit_page", "view.fit_page"]);
+
+        // Conflicts are a warning, not a load failure
+        let config = PreviewConfig { keybindings, ..Default::default() };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn preview_message_ttl_defaults_when_missing() {
+        let mut value = toml::Value::try_from(PreviewConfig::default()).unwrap();
+        value.as_table_mut().unwrap().remove("message_ttl_secs");
+        let config: PreviewConfig = value.try_into().unwrap();
+
+        assert_eq!(config.message_ttl_secs, boomaga_core::constants::DEFAULT_MESSAGE_TTL_SECS);
+        assert!(PreviewConfig { message_ttl_secs: 0, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn preview_zoom_is_limited_to_app_bounds() {
+        let zoom = |default_zoom| PreviewConfig { default_zoom, ..Default::default() }.validate();
+
+        assert!(zoom(boomaga_core::MAX_ZOOM).is_ok());
+        assert!(zoom(5.0).is_err());
+        assert!(zoom(0.1).is_err());
+    }
+
+    #[test]
+    fn render_quality_caps_print_resolution() {
+        use boomaga_core::PrintQuality;
+
+        let draft = RenderQuality::High.render_settings(PrintQuality::Draft);
+        let high = RenderQuality::High.render_settings(PrintQuality::High);
+        assert!(draft.dpi < high.dpi);
+        assert!(!draft.antialias && !draft.dither);
+        assert!(high.antialias && high.dither);
+
+        assert_eq!(RenderQuality::Low.render_settings(PrintQuality::High).dpi, PrintQuality::Draft.dpi());
+    }
+
+    #[test]
+    fn transient_errors_retry_with_backoff() {
+        use std::time::Duration;
+
+        let policy = RetryPolicy { max_attempts: 4, base_delay: 100, max_delay: 350, jitter: 0.0 };
+        let busy = boomaga_core::Error::Busy("printer busy".into());
+
+        assert!((1..4).all(|attempt| policy.should_retry(&busy, attempt)));
+        assert!(!policy.should_retry(&busy, 4));
+        let delays: Vec<_> = (1..4).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(delays, [100, 200, 350].map(Duration::from_millis));
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        assert!((50..=100).contains(&(jittered.delay(1).as_millis() as u64)));
+    }
+
+    #[test]
+    fn high_severity_errors_are_not_retried() {
+        let policy = RetryPolicy::default();
+        let invalid = boomaga_core::Error::Validation("copies must be positive".into());
+
+        assert_eq!(invalid.severity(), boomaga_core::error::ErrorSeverity::High);
+        assert!(!policy.should_retry(&invalid, 1));
+    }
 }
@@ -1,14 +1,19 @@
 //! Preview application configuration
 
 use serde::{Deserialize, Serialize};
-use boomaga_core::{Orientation, MarginMode, constants::{DEFAULT_IPC_SOCKET, DEFAULT_DBUS_SERVICE, DEFAULT_DBUS_PATH}};
+use boomaga_core::{Orientation, MarginMode, PagesPerSheet, PrintOptions, constants::{DEFAULT_IPC_SOCKET, DEFAULT_DBUS_SERVICE, DEFAULT_DBUS_PATH}};
 use boomaga_core::constants::{
     DEFAULT_IPP_PORT, DEFAULT_MAX_JOB_HISTORY, DEFAULT_TIMEOUT_SECS,
     DEFAULT_MAX_CONCURRENT_JOBS, DEFAULT_WORKER_THREADS, DEFAULT_JOB_QUEUE_SIZE,
-    DEFAULT_THUMBNAIL_SIZE, DEFAULT_PREVIEW_ZOOM_LEVELS,
+    DEFAULT_THUMBNAIL_SIZE, DEFAULT_PREVIEW_ZOOM_LEVELS, MAX_ZOOM, MIN_ZOOM,
 };
 use std::path::PathBuf;
 
+use crate::shortcut::{find_conflicts, parse_shortcut, Shortcut};
+
+/// Smallest usable window size
+pub(crate) const MIN_WINDOW_SIZE: (u32, u32) = (800, 600);
+
 /// Preview application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PreviewConfig {
@@ -53,6 +58,14 @@ pub struct PreviewConfig {
 
     /// Keybindings
     pub keybindings: Keybindings,
+
+    /// Seconds a backend notification stays valid; older ones are dropped
+    #[serde(default = "default_message_ttl_secs")]
+    pub message_ttl_secs: u64,
+}
+
+fn default_message_ttl_secs() -> u64 {
+    boomaga_core::constants::DEFAULT_MESSAGE_TTL_SECS
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,6 +151,50 @@ pub struct KeybindingConfig {
     pub fit_page: String,
 }
 
+impl Keybindings {
+    /// List each group name with its bindings
+    pub fn groups(&self) -> [(&'static str, &KeybindingConfig); 4] {
+        [
+            ("navigation", &self.navigation),
+            ("zoom", &self.zoom),
+            ("print", &self.print),
+            ("view", &self.view),
+        ]
+    }
+
+    /// Find shortcuts bound to more than one action in any group
+    ///
+    /// Actions are named `group.action`, e.g. `view.next_page`.
+    pub fn conflicts(&self) -> Vec<(Shortcut, Vec<String>)> {
+        let named: Vec<(String, &str)> = self
+            .groups()
+            .into_iter()
+            .flat_map(|(group, bindings)| {
+                bindings
+                    .bindings()
+                    .into_iter()
+                    .map(move |(action, shortcut)| (format!("{}.{}", group, action), shortcut))
+            })
+            .collect();
+        find_conflicts(named.iter().map(|(action, shortcut)| (action.as_str(), *shortcut)))
+    }
+}
+
+impl KeybindingConfig {
+    /// List each action name with its shortcut; unbound actions have an empty one
+    pub fn bindings(&self) -> [(&'static str, &str); 7] {
+        [
+            ("next_page", &self.next_page),
+            ("prev_page", &self.prev_page),
+            ("first_page", &self.first_page),
+            ("last_page", &self.last_page),
+            ("zoom_in", &self.zoom_in),
+            ("zoom_out", &self.zoom_out),
+            ("fit_page", &self.fit_page),
+        ]
+    }
+}
+
 impl Default for PreviewConfig {
     fn default() -> Self {
         Self {
@@ -155,6 +212,7 @@ impl Default for PreviewConfig {
             default_print_settings: PrintSettings::default(),
             enable_shortcuts: true,
             keybindings: Keybindings::default(),
+            message_ttl_secs: default_message_ttl_secs(),
         }
     }
 }
@@ -173,6 +231,31 @@ impl Default for PrintSettings {
     }
 }
 
+impl PrintSettings {
+    /// Set these defaults on `options`, leaving the other options as they are
+    ///
+    /// Page counts with no N-up layout print one page per sheet.
+    pub fn apply_to(&self, options: &mut PrintOptions) {
+        options.copies = self.copies;
+        options.collate = self.collate;
+        options.duplex = match self.duplex {
+            DuplexMode::None => boomaga_core::DuplexMode::None,
+            DuplexMode::LongEdge => boomaga_core::DuplexMode::LongEdge,
+            DuplexMode::ShortEdge => boomaga_core::DuplexMode::ShortEdge,
+        };
+        options.orientation = self.orientation;
+        options.pages_per_sheet = match self.pages_per_sheet {
+            2 => PagesPerSheet::Two,
+            4 => PagesPerSheet::Four,
+            6 => PagesPerSheet::Six,
+            8 => PagesPerSheet::Eight,
+            _ => PagesPerSheet::One,
+        };
+        options.margins = self.margins;
+        options.scale = self.scale;
+    }
+}
+
 impl Default for Keybindings {
     fn default() -> Self {
         Self {
@@ -219,22 +302,38 @@ impl Default for Keybindings {
 impl PreviewConfig {
     /// Validate configuration
     pub fn validate(&self) -> anyhow::Result<()> {
-        if self.default_window_size.0 < 800 {
-            return Err(anyhow::anyhow!("Window width must be at least 800"));
+        if self.default_window_size.0 < MIN_WINDOW_SIZE.0 {
+            return Err(anyhow::anyhow!("Window width must be at least {}", MIN_WINDOW_SIZE.0));
         }
 
-        if self.default_window_size.1 < 600 {
-            return Err(anyhow::anyhow!("Window height must be at least 600"));
+        if self.default_window_size.1 < MIN_WINDOW_SIZE.1 {
+            return Err(anyhow::anyhow!("Window height must be at least {}", MIN_WINDOW_SIZE.1));
         }
 
-        if self.default_zoom <= 0.0 || self.default_zoom > 5.0 {
-            return Err(anyhow::anyhow!("Default zoom must be between 0 and 5"));
+        if !(MIN_ZOOM..=MAX_ZOOM).contains(&self.default_zoom) {
+            return Err(anyhow::anyhow!(
+                "Default zoom must be between {} and {}",
+                MIN_ZOOM,
+                MAX_ZOOM
+            ));
         }
 
         if self.max_cache_size == 0 {
             return Err(anyhow::anyhow!("Max cache size must be greater than 0"));
         }
 
+        if self.message_ttl_secs == 0 {
+            return Err(anyhow::anyhow!("Message TTL must be greater than 0"));
+        }
+
+        for (group, bindings) in self.keybindings.groups() {
+            for (action, shortcut) in bindings.bindings() {
+                if !shortcut.is_empty() {
+                    parse_shortcut(shortcut).map_err(|e| anyhow::anyhow!("{}.{}: {}", group, action, e))?;
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -257,6 +356,7 @@ impl From<PreviewConfig> for boomaga_core::constants::AppConfig {
             ipc_socket_path: DEFAULT_IPC_SOCKET.to_string(),
             dbus_service_name: DEFAULT_DBUS_SERVICE.to_string(),
             dbus_path: DEFAULT_DBUS_PATH.to_string(),
+            ipp_bind_address: boomaga_core::constants::DEFAULT_IPP_BIND_ADDRESS.to_string(),
             ipp_port: DEFAULT_IPP_PORT,
             max_job_history: DEFAULT_MAX_JOB_HISTORY,
             timeout_secs: DEFAULT_TIMEOUT_SECS,
@@ -3,7 +3,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use boomaga_core::PrintOptions;
+use boomaga_core::constants::{MAX_ZOOM, MIN_ZOOM};
+use boomaga_core::{BuiltInLayout, CustomLayout, RenderQuality};
+
+use crate::preview_config::MIN_WINDOW_SIZE;
+use crate::shortcut::parse_shortcut;
+use crate::ConfigError;
 
 /// Application settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +30,34 @@ pub struct Settings {
 
     /// Keybindings
     pub keybindings: HashMap<String, String>,
+
+    /// Layouts saved by the user, listed after the built-in presets
+    #[serde(default)]
+    pub custom_layouts: Vec<CustomLayout>,
+
+    /// Named print setups the user switches between
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+
+    /// Name of the profile last applied, see [`Settings::apply_profile`]
+    #[serde(default)]
+    pub active_profile: Option<String>,
+}
+
+/// Print defaults saved under a name, such as "Office duplex"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Name shown in the profile list
+    pub name: String,
+
+    /// Printer to use, or the last used one when unset
+    pub printer: Option<String>,
+
+    /// Print defaults, margins included
+    pub print: crate::PrintSettings,
+
+    /// Layout preset or saved layout by name, see [`Settings::layout_names`]
+    pub layout: Option<String>,
 }
 
 /// Window settings
@@ -125,7 +158,7 @@ pub struct PerformanceSettings {
     pub render_quality: RenderQuality,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ZoomMode {
     /// Fit page
@@ -144,21 +177,6 @@ pub enum ZoomMode {
     Custom,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum RenderQuality {
-    /// Low quality
-    Low,
-
-    /// Medium quality
-    Medium,
-
-    /// High quality
-    High,
-
-    /// Ultra quality
-    Ultra,
-}
-
 impl Default for Settings {
     fn default() -> Self {
         Self {
@@ -168,7 +186,174 @@ impl Default for Settings {
             ui: UISettings::default(),
             performance: PerformanceSettings::default(),
             keybindings: HashMap::new(),
+            custom_layouts: Vec::new(),
+            profiles: Vec::new(),
+            active_profile: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Reset invalid fields to their defaults
+    ///
+    /// Each field is checked on its own, so one bad value does not discard
+    /// the rest of the settings. Returns a description of every correction.
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut corrections = Vec::new();
+
+        if let Some((width, height)) = self.window.size {
+            if width < MIN_WINDOW_SIZE.0 || height < MIN_WINDOW_SIZE.1 {
+                self.window.size = WindowSettings::default().size;
+                corrections.push(format!(
+                    "Window size {}x{} is below the minimum of {}x{}",
+                    width, height, MIN_WINDOW_SIZE.0, MIN_WINDOW_SIZE.1
+                ));
+            }
+        }
+
+        if !(MIN_ZOOM..=MAX_ZOOM).contains(&self.document.zoom_level) {
+            corrections.push(format!(
+                "Zoom level {} is outside {} to {}",
+                self.document.zoom_level, MIN_ZOOM, MAX_ZOOM
+            ));
+            self.document.zoom_level = DocumentSettings::default().zoom_level;
+        }
+
+        if self.performance.thumbnail_size == 0 {
+            self.performance.thumbnail_size = PerformanceSettings::default().thumbnail_size;
+            corrections.push("Thumbnail size must be greater than 0".to_string());
+        }
+
+        self.keybindings.retain(|action, shortcut| {
+            let valid = parse_shortcut(shortcut).is_ok();
+            if !valid {
+                corrections.push(format!("Keybinding {:?} for {} is not a valid shortcut", shortcut, action));
+            }
+            valid
+        });
+
+        corrections
+    }
+
+    /// Get the names of the built-in layout presets followed by the saved ones
+    pub fn layout_names(&self) -> impl Iterator<Item = &str> {
+        BuiltInLayout::ALL
+            .into_iter()
+            .map(|layout| -> &str { layout.name() })
+            .chain(self.custom_layouts.iter().map(|layout| layout.name.as_str()))
+    }
+
+    /// Save a layout
+    ///
+    /// Names must be unique among saved layouts and built-in presets.
+    pub fn add_custom_layout(&mut self, layout: CustomLayout) -> Result<(), ConfigError> {
+        self.check_layout_name(&layout.name)?;
+        self.custom_layouts.push(layout);
+        Ok(())
+    }
+
+    /// Remove a saved layout, returning it
+    pub fn remove_custom_layout(&mut self, name: &str) -> Result<CustomLayout, ConfigError> {
+        let index = self.custom_layout_index(name)?;
+        Ok(self.custom_layouts.remove(index))
+    }
+
+    /// Give a saved layout a new name
+    pub fn rename_custom_layout(&mut self, name: &str, new_name: &str) -> Result<(), ConfigError> {
+        let index = self.custom_layout_index(name)?;
+        if name != new_name {
+            self.check_layout_name(new_name)?;
+        }
+        self.custom_layouts[index].name = new_name.to_string();
+        Ok(())
+    }
+
+    /// Get a profile by name
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Save a new profile
+    ///
+    /// Names must be unique, and the layout, if any, must be one of
+    /// [`Self::layout_names`].
+    pub fn add_profile(&mut self, profile: Profile) -> Result<(), ConfigError> {
+        if profile.name.trim().is_empty() {
+            return Err(ConfigError::Invalid("Profile name must not be empty".to_string()));
+        }
+        if self.profile(&profile.name).is_some() {
+            return Err(ConfigError::Invalid(format!("A profile named {} already exists", profile.name)));
+        }
+        self.check_profile_layout(&profile)?;
+        self.profiles.push(profile);
+        Ok(())
+    }
+
+    /// Replace the profile of the same name
+    pub fn update_profile(&mut self, profile: Profile) -> Result<(), ConfigError> {
+        let index = self.profile_index(&profile.name)?;
+        self.check_profile_layout(&profile)?;
+        self.profiles[index] = profile;
+        Ok(())
+    }
+
+    /// Remove a profile, returning it
+    ///
+    /// Print defaults applied from the profile are kept.
+    pub fn remove_profile(&mut self, name: &str) -> Result<Profile, ConfigError> {
+        let index = self.profile_index(name)?;
+        if self.active_profile.as_deref() == Some(name) {
+            self.active_profile = None;
+        }
+        Ok(self.profiles.remove(index))
+    }
+
+    /// Make a profile's printer and print settings the print defaults
+    pub fn apply_profile(&mut self, name: &str) -> Result<&Profile, ConfigError> {
+        let index = self.profile_index(name)?;
+        let profile = &self.profiles[index];
+        if let Some(printer) = &profile.printer {
+            self.print.last_printer = Some(printer.clone());
+        }
+        self.print.last_print_settings = Some(profile.print.clone());
+        self.active_profile = Some(name.to_string());
+        Ok(&self.profiles[index])
+    }
+
+    fn profile_index(&self, name: &str) -> Result<usize, ConfigError> {
+        self.profiles
+            .iter()
+            .position(|profile| profile.name == name)
+            .ok_or_else(|| ConfigError::Invalid(format!("No profile named {}", name)))
+    }
+
+    fn check_profile_layout(&self, profile: &Profile) -> Result<(), ConfigError> {
+        match &profile.layout {
+            Some(layout) if !self.layout_names().any(|name| name == layout) => {
+                Err(ConfigError::Invalid(format!("No layout named {}", layout)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn custom_layout_index(&self, name: &str) -> Result<usize, ConfigError> {
+        self.custom_layouts
+            .iter()
+            .position(|layout| layout.name == name)
+            .ok_or_else(|| ConfigError::Invalid(format!("No saved layout named {}", name)))
+    }
+
+    fn check_layout_name(&self, name: &str) -> Result<(), ConfigError> {
+        if name.trim().is_empty() {
+            return Err(ConfigError::Invalid("Layout name must not be empty".to_string()));
+        }
+        if BuiltInLayout::from_name(name).is_some() {
+            return Err(ConfigError::Invalid(format!("{} is a built-in layout", name)));
         }
+        if self.custom_layouts.iter().any(|layout| layout.name == name) {
+            return Err(ConfigError::Invalid(format!("A layout named {} already exists", name)));
+        }
+        Ok(())
     }
 }
 
@@ -211,6 +396,15 @@ impl Default for UISettings {
     }
 }
 
+impl PerformanceSettings {
+    /// Get the pixel budget for one rendered page, limited by the hard cap
+    pub fn page_pixel_budget(&self) -> u64 {
+        self.render_quality
+            .page_pixel_budget()
+            .min(boomaga_core::MAX_PAGE_PIXELS)
+    }
+}
+
 impl Default for PerformanceSettings {
     fn default() -> Self {
         Self {
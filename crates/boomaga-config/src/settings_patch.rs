@@ -0,0 +1,103 @@
+//! Applying typed live configuration updates
+
+use boomaga_core::{PatchOutcome, SettingsPatch};
+
+use crate::{BackendConfig, Settings};
+
+impl Settings {
+    /// Apply the user-settings part of a patch
+    pub fn apply_patch(&mut self, patch: &SettingsPatch) -> PatchOutcome {
+        let mut outcome = PatchOutcome::default();
+
+        if let Some(render_quality) = patch.render_quality {
+            self.performance.render_quality = render_quality;
+            outcome.applied.push("render_quality".to_string());
+        }
+
+        if let Some(thumbnail_size) = patch.thumbnail_size {
+            self.performance.thumbnail_size = thumbnail_size;
+            outcome.applied.push("thumbnail_size".to_string());
+        }
+
+        if let Some(zoom_level) = patch.zoom_level {
+            self.document.zoom_level = zoom_level;
+            outcome.applied.push("zoom_level".to_string());
+        }
+
+        if let Some(dark_mode) = patch.dark_mode {
+            self.ui.dark_mode = dark_mode;
+            outcome.applied.push("dark_mode".to_string());
+        }
+
+        if let Some(show_status_bar) = patch.show_status_bar {
+            self.ui.show_status_bar = show_status_bar;
+            outcome.applied.push("show_status_bar".to_string());
+        }
+
+        outcome
+    }
+}
+
+impl BackendConfig {
+    /// Apply the backend part of a patch
+    ///
+    /// `max_concurrent_jobs` can change while the service runs; socket, port,
+    /// and worker-pool sizes are only read at startup and are reported back in
+    /// [`PatchOutcome::requires_restart`].
+    pub fn apply_patch(&mut self, patch: &SettingsPatch) -> PatchOutcome {
+        let mut outcome = PatchOutcome::default();
+
+        if let Some(max_concurrent_jobs) = patch.max_concurrent_jobs {
+            self.max_concurrent_jobs = max_concurrent_jobs;
+            outcome.applied.push("max_concurrent_jobs".to_string());
+        }
+
+        if let Some(worker_threads) = patch.worker_threads {
+            self.worker_threads = worker_threads;
+            outcome.requires_restart.push("worker_threads".to_string());
+        }
+
+        if let Some(job_queue_size) = patch.job_queue_size {
+            self.job_queue_size = job_queue_size;
+            outcome.requires_restart.push("job_queue_size".to_string());
+        }
+
+        if let Some(ipp_port) = patch.ipp_port {
+            self.ipp_port = ipp_port;
+            outcome.requires_restart.push("ipp_port".to_string());
+        }
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_patch_changes_nothing() {
+        let mut settings = Settings::default();
+        let outcome = settings.apply_patch(&SettingsPatch::default());
+
+        assert!(SettingsPatch::default().is_empty());
+        assert!(outcome.applied.is_empty());
+    }
+
+    #[test]
+    fn backend_patch_reports_restart_only_fields() {
+        let mut config = BackendConfig::default();
+        let patch = SettingsPatch {
+            max_concurrent_jobs: Some(8),
+            ipp_port: Some(8631),
+            ..Default::default()
+        };
+
+        let outcome = config.apply_patch(&patch);
+
+        assert_eq!(config.max_concurrent_jobs, 8);
+        assert_eq!(config.ipp_port, 8631);
+        assert_eq!(outcome.applied, vec!["max_concurrent_jobs"]);
+        assert_eq!(outcome.requires_restart, vec!["ipp_port"]);
+    }
+}
@@ -0,0 +1,235 @@
+//! Keyboard shortcuts written as strings such as `Ctrl+Shift+Right`
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ConfigError;
+
+/// Modifier keys held for a shortcut
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub super_key: bool,
+}
+
+/// The key pressed for a shortcut
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A printable key; letters are upper case
+    Character(char),
+    /// A function key, F1 to F24
+    Function(u8),
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Escape,
+    Enter,
+    Tab,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+}
+
+/// A key with the modifiers held for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+/// Parse a shortcut such as `Ctrl+Right`, `Ctrl++` or `F11`
+///
+/// Modifier and key names are case-insensitive.
+pub fn parse_shortcut(text: &str) -> Result<Shortcut, ConfigError> {
+    let text = text.trim();
+    let invalid = |reason: String| ConfigError::Invalid(format!("Invalid shortcut {:?}: {}", text, reason));
+
+    // A trailing "++" is the plus key after a separator
+    let (modifier_names, key_name) = if text == "+" {
+        ("", "+")
+    } else if let Some(rest) = text.strip_suffix("++") {
+        (rest, "+")
+    } else {
+        match text.rsplit_once('+') {
+            Some((rest, key)) => (rest, key),
+            None => ("", text),
+        }
+    };
+
+    let mut modifiers = Modifiers::default();
+    if !modifier_names.is_empty() {
+        for name in modifier_names.split('+') {
+            let held = match name.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "shift" => &mut modifiers.shift,
+                "alt" => &mut modifiers.alt,
+                "super" | "meta" | "win" => &mut modifiers.super_key,
+                "" => return Err(invalid("empty modifier".to_string())),
+                other => return Err(invalid(format!("unknown modifier {}", other))),
+            };
+            *held = true;
+        }
+    }
+
+    let key = parse_key(key_name.trim()).ok_or_else(|| match key_name.trim() {
+        "" => invalid("missing key".to_string()),
+        name => invalid(format!("unknown key {}", name)),
+    })?;
+
+    Ok(Shortcut { modifiers, key })
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return (!c.is_whitespace() && !c.is_control()).then(|| Key::Character(c.to_ascii_uppercase()));
+    }
+
+    let lower = name.to_ascii_lowercase();
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&number).then_some(Key::Function(number));
+    }
+
+    Some(match lower.as_str() {
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" | "pgup" => Key::PageUp,
+        "pagedown" | "pgdn" => Key::PageDown,
+        "escape" | "esc" => Key::Escape,
+        "enter" | "return" => Key::Enter,
+        "tab" => Key::Tab,
+        "space" => Key::Space,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "insert" | "ins" => Key::Insert,
+        "plus" => Key::Character('+'),
+        "minus" => Key::Character('-'),
+        _ => return None,
+    })
+}
+
+impl FromStr for Shortcut {
+    type Err = ConfigError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_shortcut(text)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Character(c) => write!(f, "{}", c),
+            Key::Function(number) => write!(f, "F{}", number),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl fmt::Display for Shortcut {
+    /// Write the shortcut in its canonical form, e.g. `Ctrl+Shift+Right`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let held = [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.super_key, "Super"),
+        ];
+        for (_, name) in held.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Find shortcuts bound to more than one action
+///
+/// Takes `(action, shortcut)` pairs. Empty and unparseable shortcuts are
+/// ignored. Returns each shared shortcut with its actions, in the order
+/// the shortcuts were first seen.
+pub fn find_conflicts<'a>(bindings: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<(Shortcut, Vec<String>)> {
+    let mut order = Vec::new();
+    let mut actions: HashMap<Shortcut, Vec<String>> = HashMap::new();
+
+    for (action, text) in bindings {
+        let Ok(shortcut) = parse_shortcut(text) else {
+            continue;
+        };
+        let bound = actions.entry(shortcut).or_default();
+        if bound.is_empty() {
+            order.push(shortcut);
+        }
+        bound.push(action.to_string());
+    }
+
+    order
+        .into_iter()
+        .filter_map(|shortcut| {
+            let bound = actions.remove(&shortcut)?;
+            (bound.len() > 1).then_some((shortcut, bound))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifier_combinations() {
+        let shortcut = parse_shortcut("ctrl+Shift+right").unwrap();
+        assert!(shortcut.modifiers.ctrl && shortcut.modifiers.shift);
+        assert!(!shortcut.modifiers.alt && !shortcut.modifiers.super_key);
+        assert_eq!(shortcut.key, Key::Right);
+        assert_eq!(shortcut.to_string(), "Ctrl+Shift+Right");
+
+        assert_eq!(parse_shortcut("Ctrl++").unwrap().key, Key::Character('+'));
+        assert_eq!(parse_shortcut("Ctrl+-").unwrap().key, Key::Character('-'));
+        assert_eq!(parse_shortcut("Ctrl+0").unwrap().key, Key::Character('0'));
+        assert_eq!(parse_shortcut("Super+p").unwrap().to_string(), "Super+P");
+        assert_eq!(parse_shortcut("F11").unwrap(), Shortcut { modifiers: Modifiers::default(), key: Key::Function(11) });
+        assert_eq!("Alt+Esc".parse::<Shortcut>().unwrap().to_string(), "Alt+Escape");
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        for text in ["Ctrl+Banana", "Hyper+A", "Ctrl+", "", "F25", "Ctrl++A"] {
+            assert!(
+                matches!(parse_shortcut(text), Err(ConfigError::Invalid(_))),
+                "{:?} should be rejected",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn finds_actions_sharing_a_shortcut() {
+        let conflicts = find_conflicts([
+            ("next_page", "Ctrl+Right"),
+            ("zoom_in", "ctrl+plus"),
+            ("last_page", "Ctrl+right"),
+            ("bigger", "Ctrl++"),
+            ("fit_page", ""),
+            ("print", "Ctrl+P"),
+        ]);
+
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].0.to_string(), "Ctrl+Right");
+        assert_eq!(conflicts[0].1, vec!["next_page", "last_page"]);
+        assert_eq!(conflicts[1].0.to_string(), "Ctrl++");
+        assert_eq!(conflicts[1].1, vec!["zoom_in", "bigger"]);
+    }
+}
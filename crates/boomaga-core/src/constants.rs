@@ -19,6 +19,9 @@ pub const DEFAULT_DBUS_SERVICE: &str = "org.boomaga.IPP";
 pub const DEFAULT_DBUS_PATH: &str = "/org/boomaga/IPP";
 pub const DBUS_OBJECT_PATH: &str = DEFAULT_DBUS_PATH;
 
+/// IPP listen address; loopback keeps the printer private to this host
+pub const DEFAULT_IPP_BIND_ADDRESS: &str = "127.0.0.1";
+
 /// IPP service port
 pub const DEFAULT_IPP_PORT: u16 = 631;
 pub const IPP_PORT: u16 = DEFAULT_IPP_PORT;
@@ -29,12 +32,25 @@ pub const DEFAULT_THUMBNAIL_SIZE: (usize, usize) = (120, 120);
 /// Default preview zoom levels
 pub const DEFAULT_PREVIEW_ZOOM_LEVELS: [f64; 6] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0];
 
+/// Preview zoom bounds, shared by the config and the app
+pub const MIN_ZOOM: f64 = 0.25;
+pub const MAX_ZOOM: f64 = 4.0;
+
+/// Hard cap on the pixels of one rendered page (256 MiB as ARGB32)
+pub const MAX_PAGE_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Largest allowed print scale factor
+pub const MAX_PRINT_SCALE: f64 = 4.0;
+
 /// Maximum number of jobs to keep in history
 pub const DEFAULT_MAX_JOB_HISTORY: usize = 100;
 
 /// Default timeout for operations
 pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
+/// How old an IPC message may be before it is dropped
+pub const DEFAULT_MESSAGE_TTL_SECS: u64 = 30;
+
 /// Max concurrent job processing
 pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 4;
 
@@ -44,6 +60,9 @@ pub const DEFAULT_WORKER_THREADS: usize = 2;
 /// Job queue size
 pub const DEFAULT_JOB_QUEUE_SIZE: usize = 100;
 
+/// Largest document contents accepted for one job (100 MiB)
+pub const DEFAULT_MAX_JOB_SIZE: u64 = 100 * 1024 * 1024;
+
 // Backward compatibility aliases
 pub const IPC_SOCKET_PATH: &str = DEFAULT_IPC_SOCKET;
 pub const DBUS_SERVICE_NAME: &str = DEFAULT_DBUS_SERVICE;
@@ -62,6 +81,7 @@ pub struct AppConfig {
     pub ipc_socket_path: String,
     pub dbus_service_name: String,
     pub dbus_path: String,
+    pub ipp_bind_address: String,
     pub ipp_port: u16,
     pub max_job_history: usize,
     pub timeout_secs: u64,
@@ -76,6 +96,7 @@ impl Default for AppConfig {
             ipc_socket_path: DEFAULT_IPC_SOCKET.to_string(),
             dbus_service_name: DEFAULT_DBUS_SERVICE.to_string(),
             dbus_path: DEFAULT_DBUS_PATH.to_string(),
+            ipp_bind_address: DEFAULT_IPP_BIND_ADDRESS.to_string(),
             ipp_port: DEFAULT_IPP_PORT,
             max_job_history: DEFAULT_MAX_JOB_HISTORY,
             timeout_secs: DEFAULT_TIMEOUT_SECS,
@@ -0,0 +1,95 @@
+//! Page layouts saved by the user
+
+use serde::{Deserialize, Serialize};
+use crate::PageSize;
+
+/// Built-in layout presets, which saved layouts may not take the names of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltInLayout {
+    /// One page per sheet
+    SinglePage,
+    /// Two pages per sheet
+    TwoUp,
+    /// Four pages per sheet
+    FourUp,
+    /// Six pages per sheet
+    SixUp,
+    /// Eight pages per sheet
+    EightUp,
+    /// Saddle-stitched booklet, two pages per side
+    Booklet,
+}
+
+impl BuiltInLayout {
+    /// All built-in presets, in the order they are listed
+    pub const ALL: [BuiltInLayout; 6] = [
+        BuiltInLayout::SinglePage,
+        BuiltInLayout::TwoUp,
+        BuiltInLayout::FourUp,
+        BuiltInLayout::SixUp,
+        BuiltInLayout::EightUp,
+        BuiltInLayout::Booklet,
+    ];
+
+    /// Get the name shown in the layout list
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltInLayout::SinglePage => "Single Page",
+            BuiltInLayout::TwoUp => "2-Up",
+            BuiltInLayout::FourUp => "4-Up",
+            BuiltInLayout::SixUp => "6-Up",
+            BuiltInLayout::EightUp => "8-Up",
+            BuiltInLayout::Booklet => "Booklet",
+        }
+    }
+
+    /// Get the one-line description shown next to the name
+    pub fn description(self) -> &'static str {
+        match self {
+            BuiltInLayout::SinglePage => "One page per sheet",
+            BuiltInLayout::TwoUp => "Two pages per sheet",
+            BuiltInLayout::FourUp => "Four pages per sheet",
+            BuiltInLayout::SixUp => "Six pages per sheet",
+            BuiltInLayout::EightUp => "Eight pages per sheet",
+            BuiltInLayout::Booklet => "Saddle-stitched booklet, two pages per side",
+        }
+    }
+
+    /// Get the number of pages on each side of a sheet
+    pub fn pages_per_sheet(self) -> u8 {
+        match self {
+            BuiltInLayout::SinglePage => 1,
+            BuiltInLayout::TwoUp | BuiltInLayout::Booklet => 2,
+            BuiltInLayout::FourUp => 4,
+            BuiltInLayout::SixUp => 6,
+            BuiltInLayout::EightUp => 8,
+        }
+    }
+
+    /// Find a built-in preset by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.name() == name)
+    }
+}
+
+/// A layout saved by the user under its own name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomLayout {
+    /// Name shown next to the built-in presets
+    pub name: String,
+
+    /// Pages per sheet
+    pub pages_per_sheet: u8,
+
+    /// Fold the sheets into a saddle-stitched booklet
+    pub booklet: bool,
+
+    /// Output sheet size
+    pub output_size: PageSize,
+
+    /// Margin around the sheet (points)
+    pub margin: f64,
+
+    /// Space between cells (points)
+    pub gutter: f64,
+}
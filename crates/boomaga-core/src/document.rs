@@ -1,7 +1,11 @@
 //! Document types and handling
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::outline::{self, Bookmark};
+use crate::parser::ParserRegistry;
 use crate::{Error, Result};
 
 /// Represents a supported PDF, PWG Raster, or JPEG document
@@ -16,6 +20,9 @@ pub struct Document {
     pub subject: Option<String>,
     pub keywords: Vec<String>,
     pub pages: Vec<Page>,
+    /// Bookmarks, empty when the document has no outline
+    #[serde(default)]
+    pub outline: Vec<Bookmark>,
 }
 
 impl Document {
@@ -35,9 +42,43 @@ impl Document {
             subject: None,
             keywords: Vec::new(),
             pages: Vec::new(),
+            outline: Vec::new(),
         }
     }
 
+    /// Open a document and parse it with the parser registered for its type
+    ///
+    /// The type is detected from the first bytes of the file and wins over
+    /// a declared type that disagrees. Without either, the extension
+    /// decides. See [`ParserRegistry::open`].
+    pub async fn from_path(path: impl AsRef<Path>, file_type: Option<FileType>) -> Result<Self> {
+        ParserRegistry::global().open(path.as_ref(), file_type)
+    }
+
+    /// Join documents, in order, into one
+    ///
+    /// Pages are renumbered continuously from 1 and bookmarks follow their
+    /// pages. The path, type, and title come from the first document.
+    pub fn concatenate(id: String, documents: Vec<Document>) -> Result<Self> {
+        let mut documents = documents.into_iter();
+        let first = documents
+            .next()
+            .ok_or_else(|| Error::Validation("Nothing to concatenate".into()))?;
+
+        let mut merged = Self::new(id, first.file_path.clone(), first.file_type);
+        merged.title = first.title.clone();
+        for document in std::iter::once(first).chain(documents) {
+            let offset = merged.page_count();
+            merged.outline.extend(document.outline.into_iter().map(|bookmark| bookmark.offset(offset)));
+            for mut page in document.pages {
+                page.number = merged.page_count() + 1;
+                merged.add_page(page);
+            }
+        }
+
+        Ok(merged)
+    }
+
     /// Add a page to the document
     pub fn add_page(&mut self, page: Page) {
         self.pages.push(page);
@@ -48,6 +89,24 @@ impl Document {
         self.pages.len()
     }
 
+    /// Get a page by its 1-based number
+    pub fn page(&self, number: usize) -> Option<&Page> {
+        number.checked_sub(1).and_then(|index| self.pages.get(index))
+    }
+
+    /// Iterate over the pages `start..=end` (1-based, inclusive)
+    pub fn pages_in_range(&self, start: usize, end: usize) -> Result<impl Iterator<Item = &Page>> {
+        if start == 0 || start > end || end > self.page_count() {
+            return Err(Error::Validation(format!(
+                "Page range {}-{} is outside 1-{}",
+                start,
+                end,
+                self.page_count()
+            )));
+        }
+        Ok(self.pages[start - 1..end].iter())
+    }
+
     /// Get the last page
     pub fn last_page(&self) -> Option<&Page> {
         self.pages.last()
@@ -58,9 +117,23 @@ impl Document {
         self.pages.is_empty()
     }
 
+    /// Get the bytes held by the contents of all pages
+    pub fn total_size_bytes(&self) -> u64 {
+        self.pages.iter().map(|page| page.byte_size() as u64).sum()
+    }
+
     /// Parse metadata from file
+    ///
+    /// A PDF outline that cannot be read leaves the document without bookmarks.
     pub async fn parse_metadata(&mut self) -> Result<()> {
-        // TODO: Implement metadata parsing
+        if self.file_type == FileType::Pdf {
+            match outline::read_outline(&self.file_path) {
+                Ok(bookmarks) => self.outline = bookmarks,
+                Err(e) => tracing::debug!(error = %e, "Not reading document outline"),
+            }
+        }
+
+        // TODO: Parse the remaining metadata
         Ok(())
     }
 }
@@ -73,16 +146,128 @@ pub struct Page {
     pub height: f64,    // Points (1/72 inch)
     pub orientation: Orientation,
     pub contents: PageContents,
+    /// PDF page the page was read from, if any
+    #[serde(default)]
+    pub source: Option<PageSource>,
+}
+
+/// A page of a PDF file, drawn with the file's fonts and images
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageSource {
+    pub path: PathBuf,
+    /// Index of the page in the file, from 0
+    pub index: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PageContents {
     /// Page contains vector graphics
     Vector(Vec<GraphicsElement>),
-    /// Page contains bitmap/raster graphics
-    Raster { width: usize, height: usize, data: Vec<u8> },
-    /// Page contains raw PDF bytes
-    Pdf { stream: Vec<u8> },
+    /// Page contains bitmap/raster graphics (shared, not copied, on clone)
+    ///
+    /// Rows of `width` pixels in `format` start every `stride` bytes; bytes
+    /// past the pixels of a row are padding.
+    Raster { width: usize, height: usize, stride: usize, format: PixelFormat, data: Arc<[u8]> },
+    /// Page contains raw PDF bytes (shared, not copied, on clone)
+    Pdf { stream: Arc<[u8]> },
+}
+
+/// Layout of one pixel in raster page data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PixelFormat {
+    /// Red, green, blue and alpha bytes, alpha not premultiplied
+    Rgba8,
+    /// Red, green and blue bytes, opaque
+    Rgb8,
+    /// One gray byte, opaque
+    Gray8,
+    /// Cairo's ARGB32: native-endian 32-bit words, alpha premultiplied
+    Argb32,
+}
+
+impl PixelFormat {
+    /// Get the bytes taken by one pixel
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Argb32 => 4,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Gray8 => 1,
+        }
+    }
+
+    /// Read one pixel as RGBA, alpha not premultiplied
+    fn to_rgba(self, pixel: &[u8]) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgba8 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            PixelFormat::Rgb8 => [pixel[0], pixel[1], pixel[2], 255],
+            PixelFormat::Gray8 => [pixel[0], pixel[0], pixel[0], 255],
+            PixelFormat::Argb32 => {
+                let [a, r, g, b] = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]).to_be_bytes();
+                let unpremultiply = |value: u8| match a {
+                    0 => 0,
+                    a => ((u32::from(value) * 255 + u32::from(a) / 2) / u32::from(a)).min(255) as u8,
+                };
+                [unpremultiply(r), unpremultiply(g), unpremultiply(b), a]
+            }
+        }
+    }
+}
+
+impl PageContents {
+    /// Create raster contents of RGBA rows without padding
+    pub fn rgba(width: usize, height: usize, data: Arc<[u8]>) -> Self {
+        PageContents::Raster { width, height, stride: width * 4, format: PixelFormat::Rgba8, data }
+    }
+
+    /// Read one raster pixel as RGBA, alpha not premultiplied
+    ///
+    /// Returns `None` outside the image, past the end of the data, or for
+    /// contents that are not raster.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        let PageContents::Raster { width, height, stride, format, data } = self else {
+            return None;
+        };
+        if x >= *width || y >= *height {
+            return None;
+        }
+        let start = y * stride + x * format.bytes_per_pixel();
+        data.get(start..start + format.bytes_per_pixel()).map(|pixel| format.to_rgba(pixel))
+    }
+
+    /// Repack raster contents as RGBA rows without padding
+    ///
+    /// Contents that are already packed RGBA, and contents that are not
+    /// raster, are left alone. Fails when the stride is narrower than a row
+    /// or the data is too short for the rows.
+    pub fn pack_rgba(&mut self) -> Result<()> {
+        let PageContents::Raster { width, height, stride, format, data } = self else {
+            return Ok(());
+        };
+        let (width, height, stride, format) = (*width, *height, *stride, *format);
+        let row = width * format.bytes_per_pixel();
+        if stride < row {
+            return Err(Error::Render(format!(
+                "Raster stride {} is narrower than a row of {} {:?} pixels",
+                stride, width, format
+            )));
+        }
+        let needed = height.checked_sub(1).map_or(0, |rows| rows * stride + row);
+        if data.len() < needed {
+            return Err(Error::Render(format!("Raster data has {} bytes, expected {}", data.len(), needed)));
+        }
+        if format == PixelFormat::Rgba8 && stride == row {
+            return Ok(());
+        }
+
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for pixel in data[y * stride..y * stride + row].chunks_exact(format.bytes_per_pixel()) {
+                pixels.extend_from_slice(&format.to_rgba(pixel));
+            }
+        }
+        *self = PageContents::rgba(width, height, Arc::from(pixels));
+        Ok(())
+    }
 }
 
 impl Page {
@@ -94,6 +279,7 @@ impl Page {
             height,
             orientation,
             contents: PageContents::Vector(Vec::new()),
+            source: None,
         }
     }
 
@@ -105,6 +291,43 @@ impl Page {
             PageContents::Pdf { .. } => true,
         }
     }
+
+    /// Derive the orientation from the page dimensions
+    pub fn detected_orientation(&self) -> Orientation {
+        if self.width > self.height {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        }
+    }
+
+    /// Check if the stored orientation disagrees with the dimensions
+    pub fn has_orientation_mismatch(&self) -> bool {
+        self.orientation.is_landscape() != self.detected_orientation().is_landscape()
+    }
+
+    /// Get the bytes held by the page contents
+    ///
+    /// Raster and PDF pages count their data; vector pages count their
+    /// elements and the text and path data they own.
+    pub fn byte_size(&self) -> usize {
+        match &self.contents {
+            PageContents::Vector(elements) => elements
+                .iter()
+                .map(|element| {
+                    std::mem::size_of::<GraphicsElement>()
+                        + match element {
+                            GraphicsElement::Path { elements, .. } => std::mem::size_of_val(elements.as_slice()),
+                            GraphicsElement::Text { content, font, .. } => content.len() + font.len(),
+                            GraphicsElement::Image { path, .. } => path.as_os_str().len(),
+                            GraphicsElement::Rectangle { .. } => 0,
+                        }
+                })
+                .sum(),
+            PageContents::Raster { data, .. } => data.len(),
+            PageContents::Pdf { stream } => stream.len(),
+        }
+    }
 }
 
 /// File type enumeration
@@ -116,6 +339,50 @@ pub enum FileType {
     PwgRaster,
     /// JPEG image
     Jpeg,
+    /// PostScript document
+    PostScript,
+}
+
+impl FileType {
+    /// Bytes needed by [`FileType::detect`]
+    pub const DETECT_LENGTH: usize = 8;
+
+    /// Recognize a file type from the first bytes of a file
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"%PDF-") {
+            Some(FileType::Pdf)
+        } else if bytes.starts_with(b"%!PS") {
+            Some(FileType::PostScript)
+        } else if bytes.starts_with(b"RaS2") {
+            Some(FileType::PwgRaster)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(FileType::Jpeg)
+        } else {
+            None
+        }
+    }
+
+    /// Recognize the type of a file from its first bytes, or else from its
+    /// extension
+    pub fn of_file(path: &Path) -> Result<Self> {
+        let mut header = Vec::with_capacity(Self::DETECT_LENGTH);
+        std::fs::File::open(path)?.take(Self::DETECT_LENGTH as u64).read_to_end(&mut header)?;
+        Self::detect(&header)
+            .or_else(|| Self::from_extension(path))
+            .ok_or_else(|| Error::Unsupported(format!("Unsupported document format: {}", path.display())))
+    }
+
+    /// Guess a file type from the extension of a path
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(FileType::Pdf),
+            "ps" => Some(FileType::PostScript),
+            "pwg" => Some(FileType::PwgRaster),
+            "jpg" | "jpeg" => Some(FileType::Jpeg),
+            _ => None,
+        }
+    }
 }
 
 /// Pages per sheet enumeration
@@ -159,6 +426,102 @@ pub enum DuplexMode {
     ShortEdge,
 }
 
+/// Color mode enumeration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// Print pages as they are
+    #[default]
+    Auto,
+    /// Print in color
+    Color,
+    /// Print in shades of gray
+    Grayscale,
+    /// Print in black and white only
+    Monochrome,
+}
+
+/// Print quality enumeration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrintQuality {
+    /// Fast, low resolution output
+    Draft,
+    /// Normal output
+    #[default]
+    Normal,
+    /// Slow, high resolution output
+    High,
+}
+
+impl PrintQuality {
+    /// Get the resolution pages are rasterized at for printing
+    pub fn dpi(&self) -> f64 {
+        match self {
+            PrintQuality::Draft => 150.0,
+            PrintQuality::Normal => 300.0,
+            PrintQuality::High => 600.0,
+        }
+    }
+}
+
+/// Render quality limit applied to previews and printed pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderQuality {
+    /// Low quality
+    Low,
+
+    /// Medium quality
+    Medium,
+
+    /// High quality
+    High,
+
+    /// Ultra quality
+    Ultra,
+}
+
+/// How pages are rasterized for printing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// Resolution in dots per inch
+    pub dpi: f64,
+    /// Smooth the edges of shapes and text
+    pub antialias: bool,
+    /// Dither gray levels when printing in black and white
+    pub dither: bool,
+}
+
+impl RenderQuality {
+    /// Get the pixel budget for one rendered page at this quality
+    pub fn page_pixel_budget(&self) -> u64 {
+        let megapixels = match self {
+            RenderQuality::Low => 4,
+            RenderQuality::Medium => 8,
+            RenderQuality::High => 16,
+            RenderQuality::Ultra => 32,
+        };
+        megapixels * 1024 * 1024
+    }
+
+    /// Get the render settings for a job's print quality
+    ///
+    /// The print quality picks the resolution, which lower render qualities
+    /// cap. Draft jobs skip antialiasing and dithering to render faster.
+    pub fn render_settings(&self, print_quality: PrintQuality) -> RenderSettings {
+        let max_dpi = match self {
+            RenderQuality::Low => 150.0,
+            RenderQuality::Medium => 300.0,
+            RenderQuality::High => 600.0,
+            RenderQuality::Ultra => 1200.0,
+        };
+        let draft = print_quality == PrintQuality::Draft;
+        RenderSettings {
+            dpi: print_quality.dpi().min(max_dpi),
+            antialias: !draft && *self != RenderQuality::Low,
+            dither: !draft,
+        }
+    }
+}
+
 /// Graphics element types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GraphicsElement {
@@ -253,6 +616,17 @@ impl PageSize {
         }
     }
 
+    /// Get `(width, height)` in points as laid out in `orientation`
+    ///
+    /// Landscape orientations swap the two sides.
+    pub fn dimensions(&self, orientation: Orientation) -> (f64, f64) {
+        if orientation.is_landscape() {
+            (self.height_points(), self.width_points())
+        } else {
+            (self.width_points(), self.height_points())
+        }
+    }
+
     /// Get width in millimeters
     pub fn width_mm(&self) -> f64 {
         self.width_points() * 25.4 / 72.0
@@ -383,6 +757,34 @@ impl Color {
     pub fn blue() -> Self {
         Self { r: 0, g: 0, b: 255, a: 255 }
     }
+
+    /// Parse `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` (the `#` is optional)
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || Error::Validation(format!("Invalid hex color: {:?}", hex));
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let channel = |range: std::ops::Range<usize>| {
+            u8::from_str_radix(&digits[range], 16).map_err(|_| invalid())
+        };
+        // Short forms repeat each digit: `f` is `ff`
+        let short = |index: usize| channel(index..index + 1).map(|value| value * 17);
+
+        match digits.len() {
+            3 => Ok(Self::rgb(short(0)?, short(1)?, short(2)?)),
+            4 => Ok(Self::rgba(short(0)?, short(1)?, short(2)?, short(3)?)),
+            6 => Ok(Self::rgb(channel(0..2)?, channel(2..4)?, channel(4..6)?)),
+            8 => Ok(Self::rgba(channel(0..2)?, channel(2..4)?, channel(4..6)?, channel(6..8)?)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Format as `#rrggbbaa`
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
 }
 
 impl Default for Color {
@@ -390,3 +792,216 @@ impl Default for Color {
         Self::black()
     }
 }
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document_with_pages(count: usize) -> Document {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=count {
+            document.add_page(Page::new(number, 612.0, 792.0, Orientation::Portrait));
+        }
+        document
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn file_type_is_detected_from_header_bytes() {
+        assert_eq!(FileType::detect(b"%PDF-1.7\n%\xe2\xe3"), Some(FileType::Pdf));
+        assert_eq!(FileType::detect(b"%!PS-Adobe-3.0\n"), Some(FileType::PostScript));
+        assert_eq!(FileType::detect(b"%!PS\n"), Some(FileType::PostScript));
+        assert_eq!(FileType::detect(b"RaS2PwgRaster"), Some(FileType::PwgRaster));
+        assert_eq!(FileType::detect(&[0xFF, 0xD8, 0xFF, 0xE0]), Some(FileType::Jpeg));
+        assert_eq!(FileType::detect(b"Hello"), None);
+        assert_eq!(FileType::detect(b"%PD"), None);
+    }
+
+    #[tokio::test]
+    async fn detected_type_wins_over_declared_type() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("job.pdf");
+        std::fs::write(&path, "%!PS-Adobe-3.0\nshowpage\n").unwrap();
+
+        let document = Document::from_path(&path, Some(FileType::Pdf)).await.unwrap();
+        assert_eq!(document.file_type, FileType::PostScript);
+    }
+
+    #[tokio::test]
+    async fn pdf_is_opened_with_pages_and_outline() {
+        let document = Document::from_path(fixture("outline.pdf"), None).await.unwrap();
+
+        assert_eq!(document.file_type, FileType::Pdf);
+        assert_eq!(document.page_count(), 3);
+        assert_eq!(document.page(1).map(|page| (page.width, page.height)), Some((595.0, 842.0)));
+        assert_eq!(document.page(3).unwrap().number, 3);
+        assert_eq!(document.outline.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn text_file_is_unsupported() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "Just some notes").unwrap();
+
+        let result = Document::from_path(&path, None).await;
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn concatenated_pages_are_numbered_continuously() {
+        let mut second = document_with_pages(3);
+        second.outline.push(Bookmark {
+            title: "Appendix".into(),
+            page: Some(2),
+            children: Vec::new(),
+        });
+
+        let merged = Document::concatenate("batch".into(), vec![document_with_pages(2), second]).unwrap();
+
+        let numbers: Vec<_> = merged.pages.iter().map(|page| page.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+        assert_eq!(merged.outline[0].page, Some(4));
+        assert!(Document::concatenate("empty".into(), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn wide_page_is_detected_as_landscape() {
+        let page = Page::new(1, 842.0, 595.0, Orientation::Portrait);
+
+        assert_eq!(page.detected_orientation(), Orientation::Landscape);
+        assert!(page.has_orientation_mismatch());
+    }
+
+    #[test]
+    fn total_size_counts_page_data() {
+        let mut document = document_with_pages(1);
+        document.add_page(Page {
+            contents: PageContents::rgba(4, 2, Arc::from(vec![0u8; 4 * 2 * 4])),
+            ..Page::new(2, 612.0, 792.0, Orientation::Portrait)
+        });
+        document.add_page(Page {
+            contents: PageContents::Pdf { stream: Arc::from(&b"q 1 0 0 1 0 0 cm Q"[..]) },
+            ..Page::new(3, 612.0, 792.0, Orientation::Portrait)
+        });
+
+        assert_eq!(document.pages[0].byte_size(), 0);
+        assert_eq!(document.pages[1].byte_size(), 32);
+        assert_eq!(document.total_size_bytes(), 32 + 18);
+    }
+
+    #[test]
+    fn landscape_dimensions_swap_sides() {
+        assert_eq!(PageSize::A4.dimensions(Orientation::Portrait), (595.0, 842.0));
+        assert_eq!(PageSize::A4.dimensions(Orientation::UpsideDownPortrait), (595.0, 842.0));
+        assert_eq!(PageSize::A4.dimensions(Orientation::Landscape), (842.0, 595.0));
+        assert_eq!(PageSize::A4.dimensions(Orientation::UpsideDownLandscape), (842.0, 595.0));
+    }
+
+    #[test]
+    fn color_parses_short_and_long_hex() {
+        assert_eq!(Color::from_hex("#fff").unwrap(), Color::white());
+        assert_eq!(Color::from_hex("#00ff00").unwrap(), Color::green());
+        assert_eq!(Color::from_hex("12345678").unwrap(), Color::rgba(0x12, 0x34, 0x56, 0x78));
+        assert_eq!(Color::from_hex("#f008").unwrap(), Color::rgba(255, 0, 0, 0x88));
+    }
+
+    #[test]
+    fn color_rejects_malformed_hex() {
+        for hex in ["", "#", "#ff", "#12345", "#gggggg", "#1234567890", "#ééé", "#+f+f+f"] {
+            assert!(Color::from_hex(hex).is_err(), "{hex:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn color_formats_as_rrggbbaa() {
+        let color = Color::rgba(0x12, 0x34, 0x56, 0x78);
+
+        assert_eq!(color.to_hex(), "#12345678");
+        assert_eq!(color.to_string(), "#12345678");
+        assert_eq!(Color::from_hex(&color.to_hex()).unwrap(), color);
+    }
+
+    #[test]
+    fn page_access_is_one_based() {
+        let document = document_with_pages(3);
+
+        assert!(document.page(0).is_none());
+        assert_eq!(document.page(1).unwrap().number, 1);
+        assert_eq!(document.page(3).unwrap().number, 3);
+        assert!(document.page(4).is_none());
+    }
+
+    #[test]
+    fn pages_in_range_is_inclusive() {
+        let document = document_with_pages(5);
+
+        let numbers: Vec<_> = document.pages_in_range(2, 4).unwrap().map(|page| page.number).collect();
+
+        assert_eq!(numbers, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn pages_in_range_rejects_out_of_range() {
+        let document = document_with_pages(5);
+
+        assert!(document.pages_in_range(0, 2).is_err());
+        assert!(document.pages_in_range(4, 6).is_err());
+        assert!(document.pages_in_range(3, 2).is_err());
+    }
+
+    #[test]
+    fn padded_raster_rows_are_read_by_stride() {
+        // 3 RGB pixels per row, padded to 12 bytes
+        let mut data = vec![0xEE; 12 * 2];
+        data[12 + 6..12 + 9].copy_from_slice(&[10, 20, 30]);
+        let mut contents =
+            PageContents::Raster { width: 3, height: 2, stride: 12, format: PixelFormat::Rgb8, data: Arc::from(data) };
+
+        assert_eq!(contents.pixel(2, 1), Some([10, 20, 30, 255]));
+        assert_eq!(contents.pixel(3, 1), None);
+
+        contents.pack_rgba().unwrap();
+        match &contents {
+            PageContents::Raster { stride, format, data, .. } => {
+                assert_eq!((*stride, *format, data.len()), (12, PixelFormat::Rgba8, 24));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(contents.pixel(2, 1), Some([10, 20, 30, 255]));
+        assert_eq!(contents.pixel(0, 0), Some([0xEE, 0xEE, 0xEE, 255]));
+    }
+
+    #[test]
+    fn raster_shorter_than_its_stride_needs_is_rejected() {
+        let data = Arc::from(vec![0; 20]);
+        let mut contents = PageContents::Raster { width: 2, height: 2, stride: 16, format: PixelFormat::Rgba8, data };
+
+        assert!(matches!(contents.pack_rgba(), Err(Error::Render(_))));
+        assert_eq!(contents.pixel(1, 1), None);
+    }
+
+    #[test]
+    fn cloned_raster_page_shares_buffer() {
+        let mut page = Page::new(1, 612.0, 792.0, Orientation::Portrait);
+        page.contents = PageContents::rgba(2, 2, Arc::from(vec![0u8; 16]));
+
+        let copy = page.clone();
+
+        match (&page.contents, &copy.contents) {
+            (PageContents::Raster { data: a, .. }, PageContents::Raster { data: b, .. }) => {
+                assert!(Arc::ptr_eq(a, b));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
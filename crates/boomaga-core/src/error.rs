@@ -53,6 +53,9 @@ pub enum Error {
     #[error("Timeout: {0}")]
     Timeout(String),
 
+    #[error("Busy: {0}")]
+    Busy(String),
+
     #[error("Graphics backend error: {0}")]
     Graphics(String),
 
@@ -61,18 +64,46 @@ pub enum Error {
 
     #[error("Unknown error: {0}")]
     Unknown(String),
+
+    /// An error with a description of what was being done when it happened
+    ///
+    /// Display shows the context followed by the cause, so logging the
+    /// error shows the whole chain.
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
 }
 
 impl Error {
+    /// Wrap this error with a description of what was being done
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Self::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Get the error under any context
+    ///
+    /// Context around an error from another crate is returned as is.
+    pub fn root(&self) -> &Error {
+        match self {
+            Self::Context { source, .. } => source.downcast_ref::<Error>().map_or(self, Error::root),
+            _ => self,
+        }
+    }
+
     /// Check if this is a transient error that should be retried
     pub fn is_transient(&self) -> bool {
-        matches!(self, Self::Io(_) | Self::Timeout(_) | Self::Bus(_))
+        matches!(self.root(), Self::Io(_) | Self::Timeout(_) | Self::Bus(_) | Self::Busy(_))
     }
 
     /// Check if this is a user-facing error
     pub fn is_user_error(&self) -> bool {
         matches!(
-            self,
+            self.root(),
             Self::Document(_)
                 | Self::Job(_)
                 | Self::Config(_)
@@ -83,9 +114,9 @@ impl Error {
 
     /// Get the error severity
     pub fn severity(&self) -> ErrorSeverity {
-        match self {
+        match self.root() {
             Self::Validation(_) | Self::Permission(_) => ErrorSeverity::High,
-            Self::Timeout(_) => ErrorSeverity::Medium,
+            Self::Timeout(_) | Self::Busy(_) => ErrorSeverity::Medium,
             Self::Job(_) => ErrorSeverity::Medium,
             Self::Document(_) | Self::Parse(_) | Self::Render(_) => ErrorSeverity::Low,
             Self::Config(_) | Self::Ipc(_) => ErrorSeverity::Medium,
@@ -97,6 +128,34 @@ impl Error {
     }
 }
 
+/// Attach context to a failed result, see [`Error::Context`]
+pub trait ResultExt<T> {
+    /// Describe what was being done if the result is an error
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    /// Like [`ResultExt::context`], building the description only on error
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E> ResultExt<T> for std::result::Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|error| Error::Context {
+            context: context.into(),
+            source: Box::new(error),
+        })
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|error| Error::Context {
+            context: context().into(),
+            source: Box::new(error),
+        })
+    }
+}
+
 /// Error severity levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ErrorSeverity {
@@ -107,3 +166,33 @@ pub enum ErrorSeverity {
     /// Error is informational and non-critical
     Low,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn context_shows_the_underlying_error() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let error = Err::<(), _>(io)
+            .context("Failed to read report.pdf")
+            .map_err(|error| error.context("Failed to load job 42"))
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to load job 42: Failed to read report.pdf: no such file"
+        );
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn context_keeps_the_classification_of_the_cause() {
+        let error = Error::Timeout("printer did not answer".into()).context("Failed to print job 42");
+
+        assert!(matches!(error.root(), Error::Timeout(_)));
+        assert!(error.is_transient());
+        assert_eq!(error.severity(), ErrorSeverity::Medium);
+    }
+}
@@ -1,14 +1,46 @@
 //! Print job types and handling
 
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 use crate::{Error, Result, FileType};
-use crate::document::{Orientation, DuplexMode, PagesPerSheet, MarginMode};
+use crate::constants::MAX_PRINT_SCALE;
+use crate::document::{Orientation, DuplexMode, PagesPerSheet, MarginMode, ColorMode, PrintQuality, PageSize};
+
+/// Namespace for content-derived job ids
+const JOB_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6cc0297a_dbff_4a44_b8f4_06286e802860);
 
 /// Unique identifier for a print job
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct JobId(pub Uuid);
 
+impl JobId {
+    /// Create a random job id
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    /// Derive a reproducible id from document bytes
+    pub fn from_content(bytes: &[u8]) -> Self {
+        Self(Uuid::new_v5(&JOB_ID_NAMESPACE, bytes))
+    }
+
+    /// Derive a reproducible id from document bytes and print options
+    pub fn from_content_and_options(bytes: &[u8], options: &PrintOptions) -> Result<Self> {
+        let mut name = serde_json::to_vec(options)
+            .map_err(|e| Error::Job(format!("Failed to encode print options: {}", e)))?;
+        name.extend_from_slice(bytes);
+        Ok(Self::from_content(&name))
+    }
+}
+
+impl Default for JobId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl std::fmt::Display for JobId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -68,6 +100,41 @@ impl std::fmt::Display for JobStatus {
     }
 }
 
+impl JobStatus {
+    /// Check if the job has finished, successfully or not
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Cancelled | JobStatus::Failed | JobStatus::Aborted
+        )
+    }
+
+    /// Get the IPP `job-state` enum value (RFC 8011 §5.3.7)
+    pub fn ipp_state(&self) -> i32 {
+        match self {
+            JobStatus::Queued => 3,
+            JobStatus::Held => 4,
+            JobStatus::Processing => 5,
+            JobStatus::Cancelled => 7,
+            JobStatus::Failed | JobStatus::Aborted => 8,
+            JobStatus::Completed => 9,
+        }
+    }
+
+    /// Get the IPP `job-state-reasons` keywords
+    pub fn reasons(&self) -> Vec<String> {
+        let reason = match self {
+            JobStatus::Queued => "job-queued",
+            JobStatus::Held => "job-hold-until-specified",
+            JobStatus::Processing => "job-printing",
+            JobStatus::Cancelled => "job-canceled-by-user",
+            JobStatus::Failed | JobStatus::Aborted => "aborted-by-system",
+            JobStatus::Completed => "job-completed-successfully",
+        };
+        vec![reason.to_string()]
+    }
+}
+
 /// Job priority levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum JobPriority {
@@ -92,20 +159,82 @@ impl std::fmt::Display for JobPriority {
     }
 }
 
+/// A job waiting in the backend queue, as listed to clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub job_id: JobId,
+    /// Name of the submitted file
+    pub name: String,
+    pub priority: JobPriority,
+    /// Time since the job was submitted
+    pub age: Duration,
+    /// Whether the job is held and will not be dequeued until released
+    pub held: bool,
+}
+
 /// Print job metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobMetadata {
     pub job_id: JobId,
+    /// Integer `job-id` IPP clients know the job by; 0 until the backend
+    /// assigns one
+    #[serde(default)]
+    pub ipp_job_id: i32,
     pub name: String,
     pub user: String,
-    pub created_at: std::time::SystemTime,
-    pub completed_at: Option<std::time::SystemTime>,
+    pub created_at: SystemTime,
+    pub completed_at: Option<SystemTime>,
     pub pages_printed: usize,
     pub status: JobStatus,
     pub priority: JobPriority,
-    pub file_path: std::path::PathBuf,
+    pub file_path: PathBuf,
     pub file_type: FileType,
     pub pages: Vec<PageInfo>,
+    /// Hash of the submitted document bytes, if they could be read
+    pub content_hash: Option<String>,
+    /// Documents printed after `file_path` as part of the same job
+    #[serde(default)]
+    pub batch_documents: Vec<PathBuf>,
+    /// Statistics of the finished job
+    #[serde(default)]
+    pub statistics: Option<JobStatistics>,
+}
+
+impl JobMetadata {
+    /// Create metadata for a queued, normal-priority job created now
+    ///
+    /// The name is taken from the file name.
+    pub fn new(job_id: JobId, file_path: PathBuf, file_type: FileType) -> Self {
+        let name = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "untitled".to_string());
+
+        Self {
+            job_id,
+            ipp_job_id: 0,
+            name,
+            user: String::new(),
+            created_at: SystemTime::now(),
+            completed_at: None,
+            pages_printed: 0,
+            status: JobStatus::Queued,
+            priority: JobPriority::Normal,
+            file_path,
+            file_type,
+            pages: Vec::new(),
+            content_hash: None,
+            batch_documents: Vec::new(),
+            statistics: None,
+        }
+    }
+
+    /// Create metadata for a newly submitted job
+    pub fn from_request(request: &PrintJobRequest) -> Self {
+        let mut metadata = Self::new(request.job_id.clone(), request.file_path.clone(), request.file_type);
+        metadata.batch_documents = request.batch_documents.clone();
+        metadata
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,10 +249,57 @@ pub struct PageInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrintJobRequest {
     pub job_id: JobId,
-    pub file_path: std::path::PathBuf,
+    pub file_path: PathBuf,
     pub file_type: FileType,
     pub printer_name: Option<String>,
     pub options: PrintOptions,
+    /// Documents printed after `file_path` as part of the same job
+    #[serde(default)]
+    pub batch_documents: Vec<PathBuf>,
+}
+
+/// Several documents printed, in order, as one job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintBatch {
+    pub documents: Vec<PathBuf>,
+    pub options: PrintOptions,
+}
+
+impl PrintBatch {
+    /// Validate the batch
+    pub fn validate(&self) -> Result<()> {
+        if self.documents.is_empty() {
+            return Err(Error::Validation("A batch needs at least one document".into()));
+        }
+        self.options.validate()
+    }
+
+    /// Build the request printing the batch as one job
+    ///
+    /// The request is for the first document, of the type its contents
+    /// show, with the remaining documents following it.
+    pub fn request(&self, job_id: JobId) -> Result<PrintJobRequest> {
+        self.validate()?;
+        Ok(PrintJobRequest {
+            job_id,
+            file_path: self.documents[0].clone(),
+            file_type: FileType::of_file(&self.documents[0])?,
+            printer_name: None,
+            options: self.options.clone(),
+            batch_documents: self.documents[1..].to_vec(),
+        })
+    }
+}
+
+/// Medium a job is printed on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Media {
+    /// Paper size; pages keep their own size when unset
+    pub size: Option<PageSize>,
+    /// Input tray, e.g. `tray-1` or `manual`
+    pub source: Option<String>,
+    /// Kind of medium, e.g. `stationery` or `photographic`
+    pub media_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -136,6 +312,16 @@ pub struct PrintOptions {
     pub pages_per_sheet: PagesPerSheet,
     pub scale: f64,
     pub margins: MarginMode,
+    #[serde(default)]
+    pub color_mode: ColorMode,
+    #[serde(default)]
+    pub print_quality: PrintQuality,
+    #[serde(default)]
+    pub media: Media,
+    /// Print two-sided on a one-sided printer: the fronts of the sheets
+    /// first, then the backs once the stack has been turned over
+    #[serde(default)]
+    pub manual_duplex: bool,
 }
 
 impl Default for PrintOptions {
@@ -149,6 +335,10 @@ impl Default for PrintOptions {
             pages_per_sheet: PagesPerSheet::One,
             scale: 1.0,
             margins: MarginMode::Normal,
+            color_mode: ColorMode::Auto,
+            print_quality: PrintQuality::Normal,
+            media: Media::default(),
+            manual_duplex: false,
         }
     }
 }
@@ -160,17 +350,160 @@ impl PrintOptions {
             return Err(Error::Validation("Copies must be greater than 0".into()));
         }
 
-        if !matches!(self.page_range, None | Some((_, _))) {
-            // Range will be validated when pages are loaded
+        if !(self.scale > 0.0 && self.scale <= MAX_PRINT_SCALE) {
+            return Err(Error::Validation(format!(
+                "Scale must be greater than 0 and at most {}",
+                MAX_PRINT_SCALE
+            )));
+        }
+
+        // The upper bound is checked against the document once it is loaded
+        if let Some((start, end)) = self.page_range {
+            if start == 0 || start > end {
+                return Err(Error::Validation(format!("Invalid page range {}-{}", start, end)));
+            }
+        }
+
+        if self.manual_duplex && self.duplex == DuplexMode::None {
+            return Err(Error::Validation("Manual duplex needs a two-sided duplex mode".into()));
         }
 
         Ok(())
     }
 
+    /// Start building options from the defaults
+    pub fn builder() -> PrintOptionsBuilder {
+        PrintOptionsBuilder::default()
+    }
+
     /// Check if this is a booklet job
     pub fn is_booklet(&self) -> bool {
         matches!(self.pages_per_sheet, PagesPerSheet::Two)
     }
+
+    /// List the fields that differ from `other`, as field name, value here
+    /// and value in `other`
+    pub fn diff(&self, other: &PrintOptions) -> Vec<(&'static str, String, String)> {
+        let page_range = |range: Option<(usize, usize)>| match range {
+            Some((start, end)) => format!("{}-{}", start, end),
+            None => "all".to_string(),
+        };
+        let media = |media: &Media| {
+            let size = media.size.map_or_else(|| "document".to_string(), |size| format!("{:?}", size));
+            [Some(size), media.source.clone(), media.media_type.clone()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let fields = [
+            ("copies", self.copies.to_string(), other.copies.to_string()),
+            ("collate", self.collate.to_string(), other.collate.to_string()),
+            ("duplex", format!("{:?}", self.duplex), format!("{:?}", other.duplex)),
+            ("orientation", format!("{:?}", self.orientation), format!("{:?}", other.orientation)),
+            ("page_range", page_range(self.page_range), page_range(other.page_range)),
+            (
+                "pages_per_sheet",
+                (self.pages_per_sheet as u8).to_string(),
+                (other.pages_per_sheet as u8).to_string(),
+            ),
+            ("scale", self.scale.to_string(), other.scale.to_string()),
+            ("margins", format!("{:?}", self.margins), format!("{:?}", other.margins)),
+            ("color_mode", format!("{:?}", self.color_mode), format!("{:?}", other.color_mode)),
+            ("print_quality", format!("{:?}", self.print_quality), format!("{:?}", other.print_quality)),
+            ("media", media(&self.media), media(&other.media)),
+            ("manual_duplex", self.manual_duplex.to_string(), other.manual_duplex.to_string()),
+        ];
+
+        fields.into_iter().filter(|(_, old, new)| old != new).collect()
+    }
+}
+
+/// Fluent builder for [`PrintOptions`]
+#[derive(Debug, Clone, Default)]
+pub struct PrintOptionsBuilder {
+    options: PrintOptions,
+}
+
+impl PrintOptionsBuilder {
+    /// Set the number of copies
+    pub fn copies(mut self, copies: u32) -> Self {
+        self.options.copies = copies;
+        self
+    }
+
+    /// Set whether copies are collated
+    pub fn collate(mut self, collate: bool) -> Self {
+        self.options.collate = collate;
+        self
+    }
+
+    /// Set the duplex mode
+    pub fn duplex(mut self, duplex: DuplexMode) -> Self {
+        self.options.duplex = duplex;
+        self
+    }
+
+    /// Set whether two-sided printing is done by hand, see
+    /// [`PrintOptions::manual_duplex`]
+    pub fn manual_duplex(mut self, manual_duplex: bool) -> Self {
+        self.options.manual_duplex = manual_duplex;
+        self
+    }
+
+    /// Set the page orientation
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.options.orientation = orientation;
+        self
+    }
+
+    /// Set the number of pages per sheet
+    pub fn number_up(mut self, pages_per_sheet: PagesPerSheet) -> Self {
+        self.options.pages_per_sheet = pages_per_sheet;
+        self
+    }
+
+    /// Print only pages `start..=end` (1-based)
+    pub fn page_range(mut self, start: usize, end: usize) -> Self {
+        self.options.page_range = Some((start, end));
+        self
+    }
+
+    /// Set the scale factor
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Set the margins
+    pub fn margins(mut self, margins: MarginMode) -> Self {
+        self.options.margins = margins;
+        self
+    }
+
+    /// Set the color mode
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.options.color_mode = color_mode;
+        self
+    }
+
+    /// Set the print quality
+    pub fn print_quality(mut self, print_quality: PrintQuality) -> Self {
+        self.options.print_quality = print_quality;
+        self
+    }
+
+    /// Set the medium to print on
+    pub fn media(mut self, media: Media) -> Self {
+        self.options.media = media;
+        self
+    }
+
+    /// Validate and return the options
+    pub fn build(self) -> Result<PrintOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
 }
 
 /// Job completion statistics
@@ -183,3 +516,115 @@ pub struct JobStatistics {
     pub success_rate: f64,
     pub average_processing_time_per_page: std::time::Duration,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_content_yields_same_job_id() {
+        assert_eq!(JobId::from_content(b"%PDF-1.7 one"), JobId::from_content(b"%PDF-1.7 one"));
+        assert_ne!(JobId::from_content(b"%PDF-1.7 one"), JobId::from_content(b"%PDF-1.7 two"));
+    }
+
+    #[test]
+    fn options_change_content_job_id() {
+        let mut options = PrintOptions::default();
+        let first = JobId::from_content_and_options(b"%PDF-1.7", &options).unwrap();
+        options.copies += 1;
+        let second = JobId::from_content_and_options(b"%PDF-1.7", &options).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(first.get_version_num(), 5);
+    }
+
+    #[test]
+    fn new_metadata_has_queued_defaults() {
+        let job_id = JobId::new();
+        let before = SystemTime::now();
+        let metadata = JobMetadata::new(job_id.clone(), PathBuf::from("/tmp/report.pdf"), FileType::Pdf);
+
+        assert_eq!(metadata.job_id, job_id);
+        assert_eq!(metadata.name, "report.pdf");
+        assert_eq!(metadata.status, JobStatus::Queued);
+        assert_eq!(metadata.priority, JobPriority::Normal);
+        assert!(metadata.pages.is_empty());
+        assert_eq!(metadata.pages_printed, 0);
+        assert!(metadata.completed_at.is_none());
+        assert!(metadata.created_at >= before);
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_scale() {
+        for scale in [0.0, -1.0, MAX_PRINT_SCALE + 0.1, f64::INFINITY] {
+            let options = PrintOptions { scale, ..PrintOptions::default() };
+            assert!(options.validate().is_err(), "scale {scale} should be rejected");
+        }
+
+        let options = PrintOptions { scale: MAX_PRINT_SCALE, ..PrintOptions::default() };
+        assert!(options.validate().is_ok());
+    }
+
+    #[test]
+    fn builder_sets_requested_options() {
+        let options = PrintOptions::builder()
+            .copies(3)
+            .duplex(DuplexMode::LongEdge)
+            .number_up(PagesPerSheet::Four)
+            .page_range(2, 5)
+            .scale(0.5)
+            .build()
+            .unwrap();
+
+        assert_eq!(options.copies, 3);
+        assert_eq!(options.duplex, DuplexMode::LongEdge);
+        assert_eq!(options.pages_per_sheet, PagesPerSheet::Four);
+        assert_eq!(options.page_range, Some((2, 5)));
+        assert_eq!(options.scale, 0.5);
+        assert_eq!(options.margins, MarginMode::Normal);
+    }
+
+    #[test]
+    fn diff_lists_only_changed_fields() {
+        let before = PrintOptions::default();
+        let after = PrintOptions {
+            copies: 3,
+            page_range: Some((2, 5)),
+            ..PrintOptions::default()
+        };
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                ("copies", "1".to_string(), "3".to_string()),
+                ("page_range", "all".to_string(), "2-5".to_string()),
+            ]
+        );
+        assert!(after.diff(&after.clone()).is_empty());
+    }
+
+    #[test]
+    fn builder_rejects_invalid_options() {
+        assert!(PrintOptions::builder().copies(0).build().is_err());
+        assert!(PrintOptions::builder().scale(0.0).build().is_err());
+        assert!(PrintOptions::builder().scale(f64::NAN).build().is_err());
+        assert!(PrintOptions::builder().page_range(0, 3).build().is_err());
+        assert!(PrintOptions::builder().page_range(4, 2).build().is_err());
+    }
+
+    #[test]
+    fn batch_request_detects_the_first_type_and_carries_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second.ps");
+        std::fs::write(&first, b"%!PS-Adobe-3.0\n").unwrap();
+        std::fs::write(&second, b"%PDF-1.7\n").unwrap();
+        let batch = PrintBatch { documents: vec![first.clone(), second.clone()], options: PrintOptions::default() };
+
+        let request = batch.request(JobId::new()).unwrap();
+
+        assert_eq!((request.file_path, request.file_type), (first, FileType::PostScript));
+        assert_eq!(request.batch_documents, vec![second]);
+        assert!(PrintBatch { documents: Vec::new(), options: PrintOptions::default() }.request(JobId::new()).is_err());
+    }
+}
@@ -3,27 +3,38 @@
 //! This crate provides common types, error handling, and utilities shared
 //! across all boomaga components.
 
+pub mod custom_layout;
 pub mod error;
 pub mod job;
 pub mod document;
+pub mod outline;
+pub mod parser;
 pub mod printer;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod settings_patch;
 pub mod constants;
 
-pub use error::{Error, Result};
-pub use job::{JobStatus, JobMetadata, JobPriority, PrintJobRequest, PrintOptions, PageInfo, JobId};
-pub use document::{Document, Page, PageSize, Orientation, PageContents, GraphicsElement, PathElement, Color, FileType, PagesPerSheet, MarginMode, DuplexMode};
-pub use printer::{PrinterInfo, PrinterCapabilities, PageLayout};
+pub use error::{Error, Result, ResultExt};
+pub use job::{JobStatus, JobMetadata, JobPriority, JobStatistics, JobSummary, Media, PrintBatch, PrintJobRequest, PrintOptions, PrintOptionsBuilder, PageInfo, JobId};
+pub use document::{Document, Page, PageSource, PageSize, Orientation, PageContents, PixelFormat, GraphicsElement, PathElement, Color, FileType, PagesPerSheet, MarginMode, DuplexMode, ColorMode, PrintQuality, RenderQuality, RenderSettings};
+pub use custom_layout::{BuiltInLayout, CustomLayout};
+pub use outline::Bookmark;
+pub use parser::{DocumentParser, ParserRegistry};
+pub use printer::{PrinterInfo, PrinterStatus, PrinterCapabilities, PageLayout};
+pub use settings_patch::{PatchOutcome, SettingsPatch};
 
 // Re-export constants explicitly
 pub use constants::{
     APP_NAME, APP_VERSION, APP_DESCRIPTION,
     CONFIG_DIR, CACHE_DIR, STATE_DIR,
     DEFAULT_IPC_SOCKET, DEFAULT_DBUS_SERVICE,
-    DEFAULT_DBUS_PATH, DEFAULT_IPP_PORT, IPP_PORT,
+    DEFAULT_DBUS_PATH, DEFAULT_IPP_BIND_ADDRESS, DEFAULT_IPP_PORT, IPP_PORT,
     DEFAULT_THUMBNAIL_SIZE, DEFAULT_PREVIEW_ZOOM_LEVELS,
+    MIN_ZOOM, MAX_ZOOM, MAX_PRINT_SCALE, MAX_PAGE_PIXELS,
     DEFAULT_MAX_JOB_HISTORY, DEFAULT_TIMEOUT_SECS,
     DEFAULT_MAX_CONCURRENT_JOBS, DEFAULT_WORKER_THREADS,
-    DEFAULT_JOB_QUEUE_SIZE, AppConfig,
+    DEFAULT_JOB_QUEUE_SIZE, DEFAULT_MAX_JOB_SIZE, AppConfig,
     IPC_SOCKET_PATH, DBUS_SERVICE_NAME, MAX_CONCURRENT_JOBS, WORKER_THREADS, JOB_QUEUE_SIZE,
     THUMBNAIL_SIZE, ZOOM_LEVELS,
 };
@@ -0,0 +1,193 @@
+//! PDF outline (bookmark) extraction
+
+use crate::{Error, Result};
+use qpdf::{QPdf, QPdfArray, QPdfDictionary, QPdfObject, QPdfObjectLike, QPdfObjectType};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Deepest name tree searched for a named destination
+const MAX_NAME_TREE_DEPTH: usize = 32;
+
+/// An entry in a document outline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub title: String,
+    /// Target page (1-based), if the destination is a page of this document
+    pub page: Option<usize>,
+    pub children: Vec<Bookmark>,
+}
+
+impl Bookmark {
+    /// Shift this bookmark and its children `pages` pages later
+    pub fn offset(mut self, pages: usize) -> Self {
+        self.page = self.page.map(|page| page + pages);
+        self.children = self.children.into_iter().map(|child| child.offset(pages)).collect();
+        self
+    }
+}
+
+/// 1-based page numbers keyed by page object id and generation
+type PageNumbers = HashMap<(u32, u32), usize>;
+
+/// Read the outline of a PDF file
+///
+/// Documents without an outline yield an empty list.
+pub fn read_outline(path: &Path) -> Result<Vec<Bookmark>> {
+    let pdf = QPdf::read(path)
+        .map_err(|e| Error::Pdf(e.to_string()).context(format!("Failed to read {}", path.display())))?;
+    outline_of(&pdf)
+}
+
+fn outline_of(pdf: &QPdf) -> Result<Vec<Bookmark>> {
+    let pages: PageNumbers = pdf
+        .get_pages()
+        .map_err(|e| Error::Pdf(format!("Failed to read pages: {}", e)))?
+        .iter()
+        .enumerate()
+        .map(|(index, page)| ((page.get_id(), page.get_generation()), index + 1))
+        .collect();
+
+    let Some(root) = pdf.get_root() else {
+        return Ok(Vec::new());
+    };
+    let first = root
+        .get("/Outlines")
+        .and_then(dictionary)
+        .and_then(|outlines| outlines.get("/First"));
+
+    let mut visited = HashSet::new();
+    Ok(read_siblings(&root, first, &pages, &mut visited))
+}
+
+/// Read an item and the items following it, with their children
+///
+/// Items already seen end the list, so cyclic outlines terminate.
+fn read_siblings(
+    root: &QPdfDictionary,
+    mut next: Option<QPdfObject>,
+    pages: &PageNumbers,
+    visited: &mut HashSet<(u32, u32)>,
+) -> Vec<Bookmark> {
+    let mut bookmarks = Vec::new();
+
+    while let Some(object) = next {
+        if !visited.insert((object.get_id(), object.get_generation())) {
+            break;
+        }
+        let Some(item) = dictionary(object) else {
+            break;
+        };
+
+        bookmarks.push(Bookmark {
+            title: item.get("/Title").map(|title| title.as_string()).unwrap_or_default(),
+            page: destination_page(root, &item, pages),
+            children: read_siblings(root, item.get("/First"), pages, visited),
+        });
+        next = item.get("/Next");
+    }
+
+    bookmarks
+}
+
+/// Resolve the page an outline item points at, from `/Dest` or a GoTo action
+fn destination_page(root: &QPdfDictionary, item: &QPdfDictionary, pages: &PageNumbers) -> Option<usize> {
+    let destination = match item.get("/Dest") {
+        Some(destination) => destination,
+        None => {
+            let action = dictionary(item.get("/A")?)?;
+            if action.get("/S")?.as_name() != "/GoTo" {
+                return None;
+            }
+            action.get("/D")?
+        }
+    };
+
+    match destination.get_type() {
+        QPdfObjectType::Name | QPdfObjectType::String => {
+            explicit_page(named_destination(root, &destination)?, pages)
+        }
+        _ => explicit_page(destination, pages),
+    }
+}
+
+/// Get the page of an explicit destination (`[page /XYZ ...]`, or a
+/// dictionary holding one under `/D`)
+fn explicit_page(destination: QPdfObject, pages: &PageNumbers) -> Option<usize> {
+    match destination.get_type() {
+        QPdfObjectType::Array => {
+            let page = QPdfArray::from(destination).get(0)?;
+            pages.get(&(page.get_id(), page.get_generation())).copied()
+        }
+        QPdfObjectType::Dictionary => {
+            let inner = QPdfDictionary::from(destination).get("/D")?;
+            if inner.get_type() != QPdfObjectType::Array {
+                return None;
+            }
+            explicit_page(inner, pages)
+        }
+        _ => None,
+    }
+}
+
+/// Look up a named destination in `/Dests` (names) or the `/Names` tree (strings)
+fn named_destination(root: &QPdfDictionary, name: &QPdfObject) -> Option<QPdfObject> {
+    if name.get_type() == QPdfObjectType::Name {
+        return dictionary(root.get("/Dests")?)?.get(&name.as_name());
+    }
+
+    let tree = dictionary(dictionary(root.get("/Names")?)?.get("/Dests")?)?;
+    find_in_name_tree(&tree, &name.as_string(), 0)
+}
+
+fn find_in_name_tree(node: &QPdfDictionary, key: &str, depth: usize) -> Option<QPdfObject> {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return None;
+    }
+
+    if let Some(names) = node.get("/Names").map(QPdfArray::from) {
+        return (0..names.len())
+            .step_by(2)
+            .find(|&index| names.get(index).is_some_and(|name| name.as_string() == key))
+            .and_then(|index| names.get(index + 1));
+    }
+
+    let kids = QPdfArray::from(node.get("/Kids")?);
+    kids.iter()
+        .filter_map(dictionary)
+        .find_map(|kid| find_in_name_tree(&kid, key, depth + 1))
+}
+
+/// View an object as a dictionary, if it is one
+fn dictionary(object: QPdfObject) -> Option<QPdfDictionary> {
+    (object.get_type() == QPdfObjectType::Dictionary).then(|| QPdfDictionary::from(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn reads_two_level_outline() {
+        let outline = read_outline(&fixture("outline.pdf")).unwrap();
+
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].title, "Chapter 1");
+        assert_eq!(outline[0].page, Some(1));
+        assert_eq!(outline[0].children.len(), 1);
+        assert_eq!(outline[0].children[0].title, "Section 1.1");
+        assert_eq!(outline[0].children[0].page, Some(2));
+        assert_eq!(outline[1].title, "Chapter 2");
+        assert_eq!(outline[1].page, Some(3));
+        assert!(outline[1].children.is_empty());
+    }
+
+    #[test]
+    fn document_without_outline_has_no_bookmarks() {
+        assert!(read_outline(&fixture("no-outline.pdf")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! Document parsers, chosen by file type
+
+use qpdf::{QPdf, QPdfArray, QPdfDictionary, QPdfObject, QPdfObjectLike, QPdfObjectType, QPdfScalar};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::document::{Document, FileType, Orientation, Page, PageSize, PageSource};
+use crate::outline;
+use crate::{Error, Result, Uuid};
+
+/// Deepest page tree searched for an inherited page attribute
+const MAX_PAGE_TREE_DEPTH: usize = 32;
+
+/// Most PostScript header lines searched for DSC comments
+const MAX_DSC_HEADER_LINES: usize = 100;
+
+/// Turns a file of some type into a [`Document`]
+pub trait DocumentParser: Send + Sync {
+    /// Check whether this parser reads files of a type
+    fn can_parse(&self, file_type: &FileType) -> bool;
+
+    /// Parse a file into a document
+    fn parse(&self, path: &Path) -> Result<Document>;
+}
+
+/// The parsers available for opening documents
+///
+/// Parsers registered later take precedence, so a registered parser can
+/// replace a built-in one for the same type.
+pub struct ParserRegistry {
+    parsers: RwLock<Vec<Arc<dyn DocumentParser>>>,
+}
+
+impl ParserRegistry {
+    /// Create a registry holding the built-in PDF and PostScript parsers
+    pub fn new() -> Self {
+        let registry = Self::empty();
+        registry.register(Arc::new(PdfParser));
+        registry.register(Arc::new(PostScriptParser));
+        registry
+    }
+
+    /// Create a registry without any parser
+    pub fn empty() -> Self {
+        Self {
+            parsers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Get the registry used by [`Document::from_path`]
+    pub fn global() -> &'static ParserRegistry {
+        static GLOBAL: OnceLock<ParserRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(ParserRegistry::new)
+    }
+
+    /// Add a parser, ahead of those registered before it
+    pub fn register(&self, parser: Arc<dyn DocumentParser>) {
+        self.parsers.write().unwrap_or_else(|e| e.into_inner()).push(parser);
+    }
+
+    /// Get the parser for a file type
+    pub fn find(&self, file_type: FileType) -> Option<Arc<dyn DocumentParser>> {
+        self.parsers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .rev()
+            .find(|parser| parser.can_parse(&file_type))
+            .cloned()
+    }
+
+    /// Open a document with the parser for its type
+    ///
+    /// The type is detected from the first bytes of the file and wins over
+    /// a declared type that disagrees. Without either, the extension
+    /// decides. Types without a parser fail with [`Error::Unsupported`].
+    pub fn open(&self, path: &Path, file_type: Option<FileType>) -> Result<Document> {
+        let mut header = Vec::with_capacity(FileType::DETECT_LENGTH);
+        std::fs::File::open(path)?.take(FileType::DETECT_LENGTH as u64).read_to_end(&mut header)?;
+        let unsupported = || Error::Unsupported(format!("Unsupported document format: {}", path.display()));
+
+        let file_type = match (file_type, FileType::detect(&header)) {
+            (Some(declared), Some(detected)) if declared != detected => {
+                tracing::warn!(
+                    path = %path.display(),
+                    ?declared,
+                    ?detected,
+                    "Document type does not match its contents"
+                );
+                detected
+            }
+            (Some(file_type), _) | (None, Some(file_type)) => file_type,
+            (None, None) => FileType::from_extension(path).ok_or_else(unsupported)?,
+        };
+
+        let parser = self.find(file_type).ok_or_else(unsupported)?;
+        let mut document = parser.parse(path)?;
+        document.file_type = file_type;
+        Ok(document)
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads PDF pages with their sizes, and the outline
+pub struct PdfParser;
+
+impl DocumentParser for PdfParser {
+    fn can_parse(&self, file_type: &FileType) -> bool {
+        *file_type == FileType::Pdf
+    }
+
+    fn parse(&self, path: &Path) -> Result<Document> {
+        let mut document = Document::new(Uuid::new_v4().to_string(), path.to_path_buf(), FileType::Pdf);
+        document.pages = read_pdf_pages(path)?;
+        match outline::read_outline(path) {
+            Ok(bookmarks) => document.outline = bookmarks,
+            Err(e) => tracing::debug!(error = %e, "Not reading document outline"),
+        }
+        Ok(document)
+    }
+}
+
+/// Reads the DSC header comments of a PostScript file
+///
+/// `%%Title` names the document, and `%%Pages` pages are created at the
+/// `%%BoundingBox` size, or A4 without one.
+pub struct PostScriptParser;
+
+impl DocumentParser for PostScriptParser {
+    fn can_parse(&self, file_type: &FileType) -> bool {
+        *file_type == FileType::PostScript
+    }
+
+    fn parse(&self, path: &Path) -> Result<Document> {
+        let mut document = Document::new(Uuid::new_v4().to_string(), path.to_path_buf(), FileType::PostScript);
+        let (mut width, mut height) = PageSize::A4.dimensions(Orientation::Portrait);
+        let mut pages = 0;
+
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        for line in reader.split(b'\n').take(MAX_DSC_HEADER_LINES) {
+            let line = String::from_utf8_lossy(&line?).trim_end().to_string();
+            if line == "%%EndComments" {
+                break;
+            }
+            if let Some(title) = line.strip_prefix("%%Title:") {
+                document.title = title.trim().trim_matches(['(', ')']).to_string();
+            } else if let Some(count) = line.strip_prefix("%%Pages:") {
+                pages = count.trim().parse().unwrap_or(0);
+            } else if let Some(bounds) = line.strip_prefix("%%BoundingBox:") {
+                let bounds: Vec<f64> = bounds.split_whitespace().filter_map(|value| value.parse().ok()).collect();
+                if let [left, bottom, right, top] = bounds[..] {
+                    (width, height) = ((right - left).abs(), (top - bottom).abs());
+                }
+            }
+        }
+
+        let orientation = if width > height { Orientation::Landscape } else { Orientation::Portrait };
+        document.pages = (1..=pages).map(|number| Page::new(number, width, height, orientation)).collect();
+        Ok(document)
+    }
+}
+
+/// Read the pages of a PDF with their sizes, numbered from 1
+///
+/// Rotated pages have their width and height swapped. Each page keeps its
+/// place in the file so it can be rendered from there.
+fn read_pdf_pages(path: &Path) -> Result<Vec<Page>> {
+    let pdf = QPdf::read(path)
+        .map_err(|e| Error::Pdf(e.to_string()).context(format!("Failed to read {}", path.display())))?;
+    let pages = pdf
+        .get_pages()
+        .map_err(|e| Error::Pdf(format!("Failed to read pages: {}", e)))?;
+
+    pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let media_box = inherited(page, "/MediaBox")
+                .filter(|media_box| media_box.get_type() == QPdfObjectType::Array)
+                .map(QPdfArray::from)
+                .ok_or_else(|| Error::Pdf(format!("Page {} has no MediaBox", index + 1)))?;
+            let corner = |at: usize| media_box.get(at).map(|value| QPdfScalar::from(value).as_f64()).unwrap_or(0.0);
+            let (mut width, mut height) = ((corner(2) - corner(0)).abs(), (corner(3) - corner(1)).abs());
+
+            let rotate = inherited(page, "/Rotate").map_or(0, |rotate| QPdfScalar::from(rotate).as_i64());
+            if rotate.rem_euclid(180) == 90 {
+                std::mem::swap(&mut width, &mut height);
+            }
+            let orientation = if width > height { Orientation::Landscape } else { Orientation::Portrait };
+            Ok(Page {
+                source: Some(PageSource { path: path.to_path_buf(), index }),
+                ..Page::new(index + 1, width, height, orientation)
+            })
+        })
+        .collect()
+}
+
+/// Get a page attribute, looking it up in the page's ancestors if needed
+fn inherited(page: &QPdfDictionary, key: &str) -> Option<QPdfObject> {
+    if let Some(value) = page.get(key) {
+        return Some(value);
+    }
+
+    let mut parent = page.get("/Parent");
+    for _ in 0..MAX_PAGE_TREE_DEPTH {
+        let node = parent
+            .filter(|parent| parent.get_type() == QPdfObjectType::Dictionary)
+            .map(QPdfDictionary::from)?;
+        if let Some(value) = node.get(key) {
+            return Some(value);
+        }
+        parent = node.get("/Parent");
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses JPEG files into a one-page document titled after the parser
+    struct FakeParser(&'static str);
+
+    impl DocumentParser for FakeParser {
+        fn can_parse(&self, file_type: &FileType) -> bool {
+            matches!(file_type, FileType::Jpeg | FileType::Pdf)
+        }
+
+        fn parse(&self, path: &Path) -> Result<Document> {
+            let mut document = Document::new("fake".into(), path.to_path_buf(), FileType::Jpeg);
+            document.title = self.0.to_string();
+            document.add_page(Page::new(1, 100.0, 100.0, Orientation::Portrait));
+            Ok(document)
+        }
+    }
+
+    #[test]
+    fn registered_parser_is_selected_for_its_type() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let image = dir.path().join("photo.jpg");
+        std::fs::write(&image, [0xFF, 0xD8, 0xFF, 0xE0, 0x00]).unwrap();
+        let pdf = dir.path().join("doc.pdf");
+        std::fs::write(&pdf, "%PDF-1.7\n").unwrap();
+
+        let registry = ParserRegistry::new();
+        assert!(matches!(registry.open(&image, None), Err(Error::Unsupported(_))));
+
+        registry.register(Arc::new(FakeParser("custom")));
+        let document = registry.open(&image, None).unwrap();
+        assert_eq!(document.title, "custom");
+        assert_eq!(document.file_type, FileType::Jpeg);
+        // The custom parser also replaces the built-in PDF parser
+        let document = registry.open(&pdf, None).unwrap();
+        assert_eq!((document.title.as_str(), document.file_type), ("custom", FileType::Pdf));
+        assert!(ParserRegistry::empty().find(FileType::Pdf).is_none());
+    }
+
+    #[test]
+    fn postscript_pages_come_from_dsc_comments() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("report.ps");
+        std::fs::write(
+            &path,
+            "%!PS-Adobe-3.0\n%%Title: (Report)\n%%Pages: 2\n%%BoundingBox: 0 0 842 595\n%%EndComments\nshowpage\n",
+        )
+        .unwrap();
+
+        let document = ParserRegistry::new().open(&path, None).unwrap();
+        assert_eq!(document.title, "Report");
+        assert_eq!(document.page_count(), 2);
+        assert_eq!(document.page(2).map(|page| (page.width, page.height)), Some((842.0, 595.0)));
+        assert!(document.page(1).unwrap().orientation.is_landscape());
+    }
+}
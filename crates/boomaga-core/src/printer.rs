@@ -47,6 +47,32 @@ impl std::fmt::Display for PrinterStatus {
     }
 }
 
+impl PrinterStatus {
+    /// Get the IPP `printer-state` keyword
+    pub fn ipp_state(&self) -> &'static str {
+        match self {
+            PrinterStatus::Idle => "idle",
+            PrinterStatus::Busy => "processing",
+            PrinterStatus::Paused
+            | PrinterStatus::Stopped
+            | PrinterStatus::Error
+            | PrinterStatus::Offline => "stopped",
+        }
+    }
+
+    /// Get the IPP `printer-state-reasons` keywords (RFC 8011 §5.4.12)
+    pub fn reasons(&self) -> Vec<String> {
+        let reasons: &[&str] = match self {
+            PrinterStatus::Idle | PrinterStatus::Busy => &["none"],
+            PrinterStatus::Paused => &["paused"],
+            PrinterStatus::Stopped => &["shutdown"],
+            PrinterStatus::Error => &["other-error"],
+            PrinterStatus::Offline => &["offline-report"],
+        };
+        reasons.iter().map(|reason| reason.to_string()).collect()
+    }
+}
+
 /// Printer capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrinterCapabilities {
@@ -79,6 +105,56 @@ impl Default for PrinterCapabilities {
     }
 }
 
+impl PrinterCapabilities {
+    /// Get what both printers support, e.g. for forwarding through a chain
+    ///
+    /// Lists keep the order of `self`.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self {
+            supports_color: self.supports_color && other.supports_color,
+            supports_duplex: self.supports_duplex && other.supports_duplex,
+            supports_multiple_copies: self.supports_multiple_copies && other.supports_multiple_copies,
+            supports_collating: self.supports_collating && other.supports_collating,
+            supports_pages_per_sheet: self.supports_pages_per_sheet && other.supports_pages_per_sheet,
+            supported_duplex_modes: common(&self.supported_duplex_modes, &other.supported_duplex_modes),
+            supported_page_sizes: common(&self.supported_page_sizes, &other.supported_page_sizes),
+            supported_orientations: common(&self.supported_orientations, &other.supported_orientations),
+            supported_margins: common(&self.supported_margins, &other.supported_margins),
+            supported_languages: common(&self.supported_languages, &other.supported_languages),
+        }
+    }
+
+    /// Get what either printer supports
+    ///
+    /// Lists keep the order of `self`, followed by additions from `other`.
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            supports_color: self.supports_color || other.supports_color,
+            supports_duplex: self.supports_duplex || other.supports_duplex,
+            supports_multiple_copies: self.supports_multiple_copies || other.supports_multiple_copies,
+            supports_collating: self.supports_collating || other.supports_collating,
+            supports_pages_per_sheet: self.supports_pages_per_sheet || other.supports_pages_per_sheet,
+            supported_duplex_modes: union(&self.supported_duplex_modes, &other.supported_duplex_modes),
+            supported_page_sizes: union(&self.supported_page_sizes, &other.supported_page_sizes),
+            supported_orientations: union(&self.supported_orientations, &other.supported_orientations),
+            supported_margins: union(&self.supported_margins, &other.supported_margins),
+            supported_languages: union(&self.supported_languages, &other.supported_languages),
+        }
+    }
+}
+
+/// Items of `a` that also appear in `b`
+fn common<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
+    a.iter().filter(|item| b.contains(item)).cloned().collect()
+}
+
+/// Items of `a`, then items of `b` not already listed
+fn union<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
+    let mut items = a.to_vec();
+    items.extend(b.iter().filter(|item| !a.contains(item)).cloned());
+    items
+}
+
 /// Supported page layouts
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PageLayout {
@@ -108,3 +184,54 @@ pub enum PaperSize {
     /// Custom { width, height }
     Custom { width: f64, height: f64 },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stopped_reports_shutdown_reason() {
+        assert_eq!(PrinterStatus::Stopped.ipp_state(), "stopped");
+        assert_eq!(PrinterStatus::Stopped.reasons(), vec!["shutdown"]);
+    }
+
+    fn color_duplex_printer() -> PrinterCapabilities {
+        PrinterCapabilities {
+            supports_color: true,
+            supports_duplex: true,
+            supported_duplex_modes: vec![DuplexMode::None, DuplexMode::LongEdge, DuplexMode::ShortEdge],
+            supported_page_sizes: vec![PageSize::A4, PageSize::Letter, PageSize::A3],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn intersection_keeps_only_shared_capabilities() {
+        let grayscale_simplex = PrinterCapabilities {
+            supported_page_sizes: vec![PageSize::Letter, PageSize::A4],
+            ..Default::default()
+        };
+
+        let effective = color_duplex_printer().intersect(&grayscale_simplex);
+
+        assert!(!effective.supports_color);
+        assert!(!effective.supports_duplex);
+        assert_eq!(effective.supported_duplex_modes, vec![DuplexMode::None]);
+        assert_eq!(effective.supported_page_sizes, vec![PageSize::A4, PageSize::Letter]);
+    }
+
+    #[test]
+    fn merge_combines_capabilities() {
+        let merged = PrinterCapabilities::default().merge(&color_duplex_printer());
+
+        assert!(merged.supports_color && merged.supports_duplex);
+        assert_eq!(merged.supported_page_sizes, vec![PageSize::A4, PageSize::Letter, PageSize::A3]);
+        assert_eq!(merged.supported_duplex_modes.len(), 3);
+    }
+
+    #[test]
+    fn offline_reports_offline_reason() {
+        assert_eq!(PrinterStatus::Offline.ipp_state(), "stopped");
+        assert_eq!(PrinterStatus::Offline.reasons(), vec!["offline-report"]);
+    }
+}
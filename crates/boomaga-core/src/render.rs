@@ -0,0 +1,387 @@
+//! Rasterizing single pages with Cairo, and Poppler for pages read from a PDF
+//!
+//! Used wherever a page is needed as an image outside the preview window,
+//! such as pages rendered for printing and streamed to the preview.
+
+use cairo::{Antialias, Context, FontOptions, Format, ImageSurface};
+use poppler::PopplerDocument;
+
+use crate::constants::MAX_PAGE_PIXELS;
+use std::sync::Arc;
+
+use crate::document::{Color, GraphicsElement, Page, PageContents, PageSource, PathElement, PixelFormat};
+use crate::{Error, Result};
+
+impl Page {
+    /// Get the resolution at which the page fits in `width` x `height` pixels
+    pub fn dpi_to_fit(&self, width: usize, height: usize) -> f32 {
+        let scale = (width as f64 / self.width).min(height as f64 / self.height);
+        (scale * 72.0) as f32
+    }
+
+    /// Render the page to a PNG image at `dpi`, antialiased
+    pub fn render_to_png(&self, dpi: f32) -> Result<Vec<u8>> {
+        let surface = self.render_to_surface(dpi, true)?;
+        let mut png = Vec::new();
+        surface
+            .write_to_png(&mut png)
+            .map_err(|e| Error::Render(format!("Failed to encode page {}: {}", self.number, e)))?;
+        Ok(png)
+    }
+
+    /// Render the page to raster contents at `dpi`, in Cairo's ARGB32 format
+    pub fn render_to_raster(&self, dpi: f32, antialias: bool) -> Result<PageContents> {
+        let mut surface = self.render_to_surface(dpi, antialias)?;
+        let (width, height, stride) = (surface.width() as usize, surface.height() as usize, surface.stride() as usize);
+        let data = surface.data().map_err(|e| Error::Graphics(e.to_string()))?;
+        Ok(PageContents::Raster { width, height, stride, format: PixelFormat::Argb32, data: Arc::from(&data[..]) })
+    }
+
+    /// Render the page to an ARGB32 image surface at `dpi`, on white
+    ///
+    /// Pages read from a PDF are drawn from their page in the file, fonts
+    /// and images included. Other pages are drawn from their contents; PDF
+    /// contents cannot be drawn without the file. Images of vector pages are
+    /// drawn only when they are PNG files. Without `antialias`, shapes and
+    /// text get hard edges.
+    pub fn render_to_surface(&self, dpi: f32, antialias: bool) -> Result<ImageSurface> {
+        if !dpi.is_finite() || dpi <= 0.0 {
+            return Err(Error::Validation(format!("Invalid render resolution {} dpi", dpi)));
+        }
+        let scale = f64::from(dpi) / 72.0;
+        let (width, height) = ((self.width * scale).ceil(), (self.height * scale).ceil());
+        if !(width >= 1.0 && height >= 1.0 && width * height <= MAX_PAGE_PIXELS as f64) {
+            return Err(Error::Render(format!(
+                "Page {} is {}x{} pixels at {} dpi, outside 1-{} pixels",
+                self.number, width, height, dpi, MAX_PAGE_PIXELS
+            )));
+        }
+
+        let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32).map_err(cairo_error)?;
+        let context = Context::new(&surface).map_err(cairo_error)?;
+        context.set_source_rgb(1.0, 1.0, 1.0);
+        context.paint().map_err(cairo_error)?;
+        context.scale(scale, scale);
+        if !antialias {
+            let mut options = FontOptions::new().map_err(cairo_error)?;
+            options.set_antialias(Antialias::None);
+            context.set_font_options(&options);
+            context.set_antialias(Antialias::None);
+        }
+
+        match (&self.source, &self.contents) {
+            (Some(source), _) => draw_source(&context, source, self.number)?,
+            (None, PageContents::Vector(elements)) => {
+                for element in elements {
+                    draw_element(&context, element)?;
+                }
+            }
+            (None, PageContents::Raster { .. }) => {
+                draw_raster(&context, &self.contents, self.width, self.height)?;
+            }
+            (None, PageContents::Pdf { .. }) => {
+                return Err(Error::Render(format!("Page {} has PDF contents but no source file", self.number)));
+            }
+        }
+
+        drop(context);
+        surface.flush();
+        Ok(surface)
+    }
+}
+
+impl PageContents {
+    /// Encode raster contents as a PNG image at their own size
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let Some(image) = raster_surface(self)? else {
+            return Err(Error::Render("Only raster contents with pixels can be encoded as PNG".to_string()));
+        };
+        let mut png = Vec::new();
+        image
+            .write_to_png(&mut png)
+            .map_err(|e| Error::Render(format!("Failed to encode image: {}", e)))?;
+        Ok(png)
+    }
+}
+
+fn cairo_error(error: cairo::Error) -> Error {
+    Error::Graphics(error.to_string())
+}
+
+fn set_color(context: &Context, color: &Color) {
+    let channel = |value: u8| f64::from(value) / 255.0;
+    context.set_source_rgba(channel(color.r), channel(color.g), channel(color.b), channel(color.a));
+}
+
+/// Draw a page from its PDF file, with the resources it refers to
+fn draw_source(context: &Context, source: &PageSource, number: usize) -> Result<()> {
+    let document = PopplerDocument::new_from_file(&source.path, None).map_err(|e| {
+        Error::Render(format!("Failed to open {} for page {}: {}", source.path.display(), number, e))
+    })?;
+    let page = document.get_page(source.index).ok_or_else(|| {
+        Error::Render(format!("Page {} is not in {}", number, source.path.display()))
+    })?;
+    page.render(context);
+    Ok(())
+}
+
+/// Fill, then stroke, the current path
+fn paint_path(context: &Context, fill: Option<&Color>, stroke: Option<&Color>, stroke_width: f64) -> Result<()> {
+    if let Some(fill) = fill {
+        set_color(context, fill);
+        context.fill_preserve().map_err(cairo_error)?;
+    }
+    if let Some(stroke) = stroke {
+        set_color(context, stroke);
+        context.set_line_width(stroke_width);
+        context.stroke_preserve().map_err(cairo_error)?;
+    }
+    context.new_path();
+    Ok(())
+}
+
+/// Draw one vector element, in points from the top-left corner
+fn draw_element(context: &Context, element: &GraphicsElement) -> Result<()> {
+    match element {
+        GraphicsElement::Path { elements, stroke, fill, stroke_width } => {
+            for path_element in elements {
+                match *path_element {
+                    PathElement::MoveTo { x, y } => context.move_to(x, y),
+                    PathElement::LineTo { x, y } => context.line_to(x, y),
+                    PathElement::CurveTo { cp1, cp2, end } => {
+                        context.curve_to(cp1.0, cp1.1, cp2.0, cp2.1, end.0, end.1)
+                    }
+                    PathElement::Close => context.close_path(),
+                }
+            }
+            paint_path(context, fill.as_ref(), stroke.as_ref(), *stroke_width)
+        }
+        GraphicsElement::Rectangle { x, y, width, height, fill, stroke, stroke_width } => {
+            context.rectangle(*x, *y, *width, *height);
+            paint_path(context, fill.as_ref(), stroke.as_ref(), *stroke_width)
+        }
+        GraphicsElement::Text { content, font, size, x, y, color } => {
+            context.select_font_face(font, cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+            context.set_font_size(*size);
+            set_color(context, color);
+            context.move_to(*x, *y);
+            context.show_text(content).map_err(cairo_error)?;
+            Ok(())
+        }
+        GraphicsElement::Image { path, x, y, width, height } => {
+            let image = std::fs::File::open(path)
+                .ok()
+                .and_then(|mut file| ImageSurface::create_from_png(&mut file).ok());
+            let Some(image) = image.filter(|image| image.width() > 0 && image.height() > 0) else {
+                tracing::debug!(path = %path.display(), "Not drawing image that is not a PNG");
+                return Ok(());
+            };
+
+            context.save().map_err(cairo_error)?;
+            context.translate(*x, *y);
+            context.scale(width / f64::from(image.width()), height / f64::from(image.height()));
+            context.set_source_surface(&image, 0.0, 0.0).map_err(cairo_error)?;
+            context.paint().map_err(cairo_error)?;
+            context.restore().map_err(cairo_error)
+        }
+    }
+}
+
+/// Draw raster contents stretched over the whole page
+fn draw_raster(context: &Context, contents: &PageContents, page_width: f64, page_height: f64) -> Result<()> {
+    let Some(image) = raster_surface(contents)? else {
+        return Ok(());
+    };
+
+    context.save().map_err(cairo_error)?;
+    context.scale(page_width / image.width() as f64, page_height / image.height() as f64);
+    context.set_source_surface(&image, 0.0, 0.0).map_err(cairo_error)?;
+    context.paint().map_err(cairo_error)?;
+    context.restore().map_err(cairo_error)
+}
+
+/// Copy raster contents into an image surface of their size, or `None` when
+/// they are not raster or have no pixels
+fn raster_surface(contents: &PageContents) -> Result<Option<ImageSurface>> {
+    let mut contents = contents.clone();
+    contents.pack_rgba()?;
+    let PageContents::Raster { width: pixel_width, height: pixel_height, data, .. } = contents else {
+        return Ok(None);
+    };
+    if pixel_width == 0 || pixel_height == 0 {
+        return Ok(None);
+    }
+
+    let mut image =
+        ImageSurface::create(Format::ARgb32, pixel_width as i32, pixel_height as i32).map_err(cairo_error)?;
+    let stride = image.stride() as usize;
+    {
+        let mut pixels = image.data().map_err(|e| Error::Graphics(e.to_string()))?;
+        for (row, source) in data.chunks_exact(pixel_width * 4).enumerate() {
+            let target = &mut pixels[row * stride..row * stride + pixel_width * 4];
+            for (to, from) in target.chunks_exact_mut(4).zip(source.chunks_exact(4)) {
+                // Cairo wants premultiplied native-endian ARGB, BGRA in memory
+                let premultiply = |value: u8| ((u32::from(value) * u32::from(from[3]) + 127) / 255) as u8;
+                let [r, g, b] = [premultiply(from[0]), premultiply(from[1]), premultiply(from[2])];
+                let argb = u32::from_be_bytes([from[3], r, g, b]);
+                to.copy_from_slice(&argb.to_ne_bytes());
+            }
+        }
+    }
+    image.mark_dirty();
+    Ok(Some(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Orientation;
+
+    /// Build a PDF of numbered objects, the first one being the catalog
+    fn pdf(objects: &[Vec<u8>]) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, body) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(body);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+        for offset in &offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", offsets.len() + 1, xref).as_bytes(),
+        );
+        pdf
+    }
+
+    fn stream(dictionary: &str, data: &str) -> Vec<u8> {
+        format!("<< {} /Length {} >>\nstream\n{}\nendstream", dictionary, data.len(), data).into_bytes()
+    }
+
+    fn decode(png: &[u8]) -> ImageSurface {
+        ImageSurface::create_from_png(&mut &png[..]).unwrap()
+    }
+
+    /// Read the color of one pixel as RGB
+    fn pixel(image: &mut ImageSurface, x: usize, y: usize) -> [u8; 3] {
+        let stride = image.stride() as usize;
+        let data = image.data().unwrap();
+        let argb = u32::from_ne_bytes(data[y * stride + x * 4..y * stride + x * 4 + 4].try_into().unwrap());
+        let [_, r, g, b] = argb.to_be_bytes();
+        [r, g, b]
+    }
+
+    #[test]
+    fn pdf_page_renders_from_its_file_with_resources() {
+        // A blue square, drawn by a form in the page resources, in the
+        // bottom-left corner of an A4 page
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("square.pdf");
+        let objects = [
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+                /Resources << /XObject << /Square 5 0 R >> >> /Contents 4 0 R >>"
+                .to_vec(),
+            stream("", "/Square Do"),
+            stream("/Type /XObject /Subtype /Form /BBox [0 0 100 100]", "0 0 1 rg 0 0 100 100 re f"),
+        ];
+        std::fs::write(&path, pdf(&objects)).unwrap();
+        let page = Page {
+            contents: PageContents::Pdf { stream: Arc::from(&b"/Square Do"[..]) },
+            source: Some(PageSource { path, index: 0 }),
+            ..Page::new(1, 595.0, 842.0, Orientation::Portrait)
+        };
+
+        let mut image = decode(&page.render_to_png(144.0).unwrap());
+
+        assert_eq!((image.width(), image.height()), (1190, 1684));
+        assert_eq!(pixel(&mut image, 10, 1674), [0, 0, 255]);
+        assert_eq!(pixel(&mut image, 400, 400), [255, 255, 255]);
+    }
+
+    #[test]
+    fn pdf_contents_without_a_file_are_not_rendered() {
+        let page = Page {
+            contents: PageContents::Pdf { stream: Arc::from(&b"0 0 1 rg 0 0 100 100 re f"[..]) },
+            ..Page::new(1, 595.0, 842.0, Orientation::Portrait)
+        };
+
+        assert!(matches!(page.render_to_png(72.0), Err(Error::Render(_))));
+    }
+
+    #[test]
+    fn vector_page_renders_to_raster_contents() {
+        let page = Page {
+            contents: PageContents::Vector(vec![GraphicsElement::Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: 36.0,
+                height: 36.0,
+                fill: Some(Color::rgb(255, 0, 0)),
+                stroke: None,
+                stroke_width: 0.0,
+            }]),
+            ..Page::new(1, 72.0, 72.0, Orientation::Portrait)
+        };
+
+        let contents = page.render_to_raster(144.0, true).unwrap();
+
+        assert!(matches!(contents, PageContents::Raster { width: 144, height: 144, .. }));
+        assert_eq!(contents.pixel(10, 10), Some([255, 0, 0, 255]));
+        assert_eq!(contents.pixel(100, 100), Some([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn raster_page_is_stretched_over_the_page() {
+        let red = [255, 0, 0, 255].repeat(4);
+        let page = Page {
+            contents: PageContents::rgba(2, 2, Arc::from(red)),
+            ..Page::new(1, 72.0, 36.0, Orientation::Landscape)
+        };
+
+        let mut image = decode(&page.render_to_png(72.0).unwrap());
+
+        assert_eq!((image.width(), image.height()), (72, 36));
+        assert_eq!(pixel(&mut image, 36, 18), [255, 0, 0]);
+    }
+
+    #[test]
+    fn raster_contents_encode_to_png_at_their_size() {
+        let pixels = [[255, 0, 0, 255], [0, 0, 255, 255]].concat();
+        let contents = PageContents::rgba(2, 1, Arc::from(pixels));
+
+        let mut image = decode(&contents.to_png().unwrap());
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(pixel(&mut image, 0, 0), [255, 0, 0]);
+        assert_eq!(pixel(&mut image, 1, 0), [0, 0, 255]);
+        assert!(PageContents::Vector(Vec::new()).to_png().is_err());
+    }
+
+    #[test]
+    fn padded_gray_raster_page_is_read_by_stride() {
+        // One gray pixel per row, padded to 4 bytes: dark on top, light below
+        let rows = [40, 0xFF, 0xFF, 0xFF, 200, 0, 0, 0];
+        let page = Page {
+            contents: PageContents::Raster {
+                width: 1,
+                height: 2,
+                stride: 4,
+                format: crate::document::PixelFormat::Gray8,
+                data: Arc::from(&rows[..]),
+            },
+            ..Page::new(1, 36.0, 72.0, Orientation::Portrait)
+        };
+
+        let mut image = decode(&page.render_to_png(72.0).unwrap());
+
+        assert_eq!(pixel(&mut image, 18, 9), [40, 40, 40]);
+        assert_eq!(pixel(&mut image, 18, 63), [200, 200, 200]);
+    }
+}
@@ -0,0 +1,60 @@
+//! Typed live configuration updates
+//!
+//! A patch only names the changed values; `boomaga-config` applies it to the
+//! stored settings and backend configuration.
+
+use serde::{Deserialize, Serialize};
+
+use crate::document::RenderQuality;
+
+/// A partial update to the user settings and backend configuration.
+///
+/// Every field is optional; `None` leaves the current value untouched. Clients
+/// send a patch to the running backend in a `ConfigUpdate` request, which
+/// applies what it can at once and stores the rest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SettingsPatch {
+    /// Preview render quality
+    pub render_quality: Option<RenderQuality>,
+
+    /// Thumbnail size (in pixels)
+    pub thumbnail_size: Option<usize>,
+
+    /// Document zoom level
+    pub zoom_level: Option<f64>,
+
+    /// Dark mode enabled
+    pub dark_mode: Option<bool>,
+
+    /// Show status bar
+    pub show_status_bar: Option<bool>,
+
+    /// Maximum concurrent job processing
+    pub max_concurrent_jobs: Option<usize>,
+
+    /// Number of worker threads
+    pub worker_threads: Option<usize>,
+
+    /// Job queue size
+    pub job_queue_size: Option<usize>,
+
+    /// IPP service port
+    pub ipp_port: Option<u16>,
+}
+
+/// Result of applying a [`SettingsPatch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchOutcome {
+    /// Fields which took effect immediately
+    pub applied: Vec<String>,
+
+    /// Fields which were stored but only take effect after a restart
+    pub requires_restart: Vec<String>,
+}
+
+impl SettingsPatch {
+    /// Check if the patch changes nothing
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
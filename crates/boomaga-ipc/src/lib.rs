@@ -6,11 +6,13 @@
 
 pub mod notifier;
 pub mod protocol;
+#[cfg(target_os = "linux")]
+pub mod shm;
 pub mod transport;
 
-pub use notifier::{NotificationSender, NotificationServer};
+pub use notifier::{IncomingRequest, NotificationSender, NotificationServer, RequestReceiver};
 pub use protocol::{
-    Message, MessageDestination, MessagePayload, MessageSource, MessageType, Request, Response,
-    PROTOCOL_VERSION,
+    ImageFormat, Message, MessageDestination, MessagePayload, MessageSource, MessageType, Request,
+    Response, PROTOCOL_VERSION,
 };
 pub use transport::{UnixSocket, UnixSocketTransport};
@@ -4,30 +4,56 @@ use std::collections::VecDeque;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use tokio::io::AsyncWriteExt;
-use tokio::net::{UnixListener, UnixStream};
-use tokio::sync::mpsc;
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::UnixListener;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
 
-use crate::Message;
-use crate::transport::write_message;
+use crate::protocol::{MessageSource, MessageType};
+use crate::transport::{
+    read_message, remove_stale_socket, send_message_with_fd, write_message, DEFAULT_MESSAGE_TTL,
+};
+use crate::{Message, MessagePayload};
+
+/// How long a client may take to accept a message before it is dropped.
+pub const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Sender used by backend components to enqueue preview notifications.
 pub type NotificationSender = mpsc::UnboundedSender<Message>;
 
+/// Receiver of the requests clients send to the backend.
+pub type RequestReceiver = mpsc::UnboundedReceiver<IncomingRequest>;
+
+/// A client request waiting for its answer.
+pub struct IncomingRequest {
+    /// The request as received
+    pub message: Message,
+    reply: oneshot::Sender<Message>,
+}
+
+impl IncomingRequest {
+    /// Answer the request on the connection it came in on.
+    pub fn respond(self, payload: MessagePayload) {
+        let reply = Message::new_response(self.message.message_id, MessageSource::Backend, payload);
+        let _ = self.reply.send(reply);
+    }
+}
+
 /// Server which pairs queued notifications with connecting preview clients.
 pub struct NotificationServer {
     socket_path: PathBuf,
     listener: UnixListener,
     receiver: mpsc::UnboundedReceiver<Message>,
+    requests: Option<mpsc::UnboundedSender<IncomingRequest>>,
 }
 
 impl NotificationServer {
     /// Bind a notification socket and return its message sender.
     pub fn bind(socket_path: PathBuf) -> io::Result<(Self, NotificationSender)> {
-        if socket_path.exists() {
-            fs::remove_file(&socket_path)?;
-        }
+        remove_stale_socket(&socket_path)?;
         let listener = UnixListener::bind(&socket_path)?;
         let (sender, receiver) = mpsc::unbounded_channel();
         Ok((
@@ -35,41 +61,136 @@ impl NotificationServer {
                 socket_path,
                 listener,
                 receiver,
+                requests: None,
             },
             sender,
         ))
     }
 
+    /// Hand client requests other than heartbeats to the returned receiver.
+    ///
+    /// Without a receiver such requests are ignored.
+    pub fn requests(&mut self) -> RequestReceiver {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.requests = Some(sender);
+        receiver
+    }
+
     /// Accept preview clients and deliver each queued notification once.
+    ///
+    /// A client closes its side of the connection to wait for a
+    /// notification, or sends a heartbeat request to be answered at once,
+    /// see [`crate::UnixSocketTransport::ping`]. Other requests go to
+    /// [`Self::requests`] and are answered on their connection. Each
+    /// message is written by a task of its own, so a slow client holds up
+    /// no other. A notification whose receiver has gone away, or does not
+    /// take it within [`DELIVERY_TIMEOUT`], goes to the next one.
     pub async fn run(mut self) -> io::Result<()> {
-        let mut clients: VecDeque<UnixStream> = VecDeque::new();
+        let mut clients: VecDeque<OwnedWriteHalf> = VecDeque::new();
         let mut messages: VecDeque<Message> = VecDeque::new();
+        // Each client's first message, or its end of input
+        let (greetings, mut greeted) = mpsc::unbounded_channel();
+        // Notifications handed back by clients that did not take them
+        let (returns, mut returned) = mpsc::unbounded_channel();
+        let requests = self.requests.take();
 
         loop {
             tokio::select! {
                 accepted = self.listener.accept() => {
-                    let (mut stream, _) = accepted?;
-                    if let Some(message) = messages.pop_front() {
-                        write_message(&mut stream, &message).await?;
-                        stream.shutdown().await?;
-                    } else {
-                        clients.push_back(stream);
-                    }
+                    let (stream, _) = accepted?;
+                    let (reader, writer) = stream.into_split();
+                    let greetings = greetings.clone();
+                    tokio::spawn(async move {
+                        let _ = greetings.send((writer, read_message(reader, DEFAULT_MESSAGE_TTL).await));
+                    });
                 }
+                Some((mut writer, greeting)) = greeted.recv() => match greeting {
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                        if let Some(message) = messages.pop_front() {
+                            dispatch(writer, message, returns.clone());
+                        } else {
+                            clients.push_back(writer);
+                        }
+                    }
+                    Ok(Message {
+                        message_id,
+                        message_type: MessageType::Request,
+                        payload: MessagePayload::Heartbeat { nonce },
+                        ..
+                    }) => {
+                        let reply = Message::new_response(
+                            message_id,
+                            MessageSource::Backend,
+                            MessagePayload::Heartbeat { nonce },
+                        );
+                        tokio::spawn(async move {
+                            let _ = deliver(&mut writer, reply).await;
+                        });
+                    }
+                    Ok(message) => match (&requests, &message.message_type) {
+                        (Some(requests), MessageType::Request) => {
+                            let (reply, replied) = oneshot::channel();
+                            let _ = requests.send(IncomingRequest { message, reply });
+                            tokio::spawn(async move {
+                                if let Ok(reply) = replied.await {
+                                    let _ = deliver(&mut writer, reply).await;
+                                }
+                            });
+                        }
+                        _ => debug!("Ignoring {:?} message from a client", message.message_type),
+                    },
+                    Err(error) => debug!("Dropping client: {}", error),
+                },
                 message = self.receiver.recv() => {
                     let Some(message) = message else { return Ok(()); };
-                    if let Some(mut stream) = clients.pop_front() {
-                        write_message(&mut stream, &message).await?;
-                        stream.shutdown().await?;
-                    } else {
-                        messages.push_back(message);
+                    match clients.pop_front() {
+                        Some(writer) => dispatch(writer, message, returns.clone()),
+                        None => messages.push_back(message),
                     }
                 }
+                Some(message) = returned.recv() => match clients.pop_front() {
+                    Some(writer) => dispatch(writer, message, returns.clone()),
+                    None => messages.push_front(message),
+                },
             }
         }
     }
 }
 
+/// Deliver a message from a task of its own, sending it to `returns` if
+/// the client does not take it
+fn dispatch(mut writer: OwnedWriteHalf, message: Message, returns: mpsc::UnboundedSender<Message>) {
+    tokio::spawn(async move {
+        if let Err(message) = deliver(&mut writer, message).await {
+            let _ = returns.send(message);
+        }
+    });
+}
+
+/// Write one message and close the connection, handing the message back
+/// if the client is gone or too slow to take it
+async fn deliver(writer: &mut OwnedWriteHalf, message: Message) -> Result<(), Message> {
+    let write = async {
+        match message.fd {
+            Some(_) => send_message_with_fd(writer.as_ref(), &message).await,
+            None => write_message(writer, &message).await,
+        }
+    };
+    match tokio::time::timeout(DELIVERY_TIMEOUT, write).await {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => {
+            debug!("Client left before message {}: {}", message.message_id, error);
+            return Err(message);
+        }
+        Err(_) => {
+            debug!("Client did not take message {} within {:?}", message.message_id, DELIVERY_TIMEOUT);
+            return Err(message);
+        }
+    }
+    let _ = writer.shutdown().await;
+    Ok(())
+}
+
 impl Drop for NotificationServer {
     fn drop(&mut self) {
         let _ = fs::remove_file(&self.socket_path);
@@ -105,4 +226,37 @@ mod tests {
         assert_eq!(received.message_id, message.message_id);
         server_task.abort();
     }
+
+    #[tokio::test]
+    async fn slow_client_does_not_hold_up_others() {
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+        let (server, sender) = NotificationServer::bind(socket_path.clone()).unwrap();
+        let server_task = tokio::spawn(server.run());
+        let notification = |data: Vec<u8>| {
+            Message::new_notification(
+                MessageSource::Backend,
+                MessageDestination::Preview,
+                MessagePayload::Custom { data_type: "notification".to_owned(), data },
+            )
+        };
+
+        // A client that asks for a notification but never reads it
+        let mut slow = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+        slow.shutdown().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sender.send(notification(vec![0; 4 << 20])).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let transport = UnixSocketTransport::new(socket_path);
+        let receive_task = tokio::spawn(async move { transport.receive_message().await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let message = notification(vec![]);
+        sender.send(message.clone()).unwrap();
+
+        let received = tokio::time::timeout(DELIVERY_TIMEOUT / 2, receive_task).await.unwrap().unwrap().unwrap();
+        assert_eq!(received.message_id, message.message_id);
+        drop(slow);
+        server_task.abort();
+    }
 }
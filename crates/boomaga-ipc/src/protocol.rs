@@ -1,8 +1,10 @@
 //! IPC protocol messages
 
-use boomaga_core::{JobId, JobStatus, PrintOptions};
+use boomaga_core::{Error, JobId, JobStatus, JobSummary, PatchOutcome, PrintBatch, PrintOptions, Result, SettingsPatch};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
 
 /// Current JSON protocol version.
 pub const PROTOCOL_VERSION: u16 = 1;
@@ -39,6 +41,10 @@ pub struct Message {
     pub payload: MessagePayload,
     /// Timestamp
     pub timestamp: i64,
+    /// Descriptor passed next to the message, such as the shared-memory
+    /// segment of a rendered page; never serialized
+    #[serde(skip)]
+    pub fd: Option<Arc<OwnedFd>>,
 }
 
 /// Message source
@@ -63,6 +69,15 @@ pub enum MessageDestination {
     Broadcast,
 }
 
+/// Encoding of the pixels sent inline with a rendered page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    /// Straight RGBA, four bytes per pixel, rows without padding
+    Rgba8,
+    /// PNG file
+    Png,
+}
+
 /// Message payload
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MessagePayload {
@@ -80,10 +95,25 @@ pub enum MessagePayload {
         document_id: String,
         page_count: usize,
     },
-    /// Page rendered
+    /// Page of a job rendered, its `width` x `height` pixels sent inline in
+    /// `format`
     PageRendered {
+        job_id: JobId,
+        page_number: usize,
+        width: usize,
+        height: usize,
+        format: ImageFormat,
+        image_data: Arc<[u8]>,
+    },
+    /// Page of a job rendered into a shared-memory segment passed as a
+    /// descriptor
+    PageRenderedShm {
+        job_id: JobId,
         page_number: usize,
-        image_data: Vec<u8>,
+        shm_name: String,
+        width: usize,
+        height: usize,
+        stride: usize,
     },
     /// Printer info
     PrinterInfo {
@@ -91,13 +121,32 @@ pub enum MessagePayload {
         description: String,
         status: String,
     },
+    /// Fronts of a manual duplex job are printed; the user turns the
+    /// `sheets` printed sheets over and feeds them back, then releases the job
+    ManualDuplexPrompt { job_id: JobId, sheets: usize },
     /// Job queue update
     JobQueueUpdate {
         queue_size: usize,
         active_jobs: usize,
     },
     /// Configuration update
-    ConfigUpdate { key: String, value: String },
+    ConfigUpdate { patch: SettingsPatch },
+    /// Result of applying a configuration update
+    ConfigUpdateResult { outcome: PatchOutcome },
+    /// Liveness check, answered with a response echoing `nonce`
+    Heartbeat { nonce: u64 },
+    /// Cancel every queued job and abort the running ones
+    PurgeJobs,
+    /// Result of a purge: how many jobs were cancelled or aborted
+    PurgeJobsResult { purged: usize },
+    /// Print several documents, in order, as one job
+    PrintBatch { batch: PrintBatch },
+    /// Result of a batch submission: the job it became, if it was accepted
+    PrintBatchResult { job_id: Option<JobId> },
+    /// List the jobs waiting in the queue
+    ListQueue,
+    /// Waiting jobs, held ones included, in the order they would be started
+    QueueListing { jobs: Vec<JobSummary> },
     /// Custom data
     Custom { data_type: String, data: Vec<u8> },
 }
@@ -122,6 +171,7 @@ impl Message {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as i64,
+            fd: None,
         }
     }
 
@@ -138,6 +188,7 @@ impl Message {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as i64,
+            fd: None,
         }
     }
 
@@ -160,6 +211,7 @@ impl Message {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as i64,
+            fd: None,
         }
     }
 
@@ -186,6 +238,70 @@ impl Message {
     pub fn is_expired(&self, ttl: i64) -> bool {
         self.age() > ttl
     }
+
+    /// Check that source, destination, type and payload fit together
+    pub fn validate(&self) -> Result<()> {
+        match (&self.source, &self.destination) {
+            (MessageSource::Backend, MessageDestination::Backend)
+            | (MessageSource::Preview, MessageDestination::Preview) => {
+                return Err(Error::Validation(format!(
+                    "Message {} is addressed to its own sender ({:?})",
+                    self.message_id, self.source
+                )));
+            }
+            _ => {}
+        }
+
+        let replies = matches!(self.message_type, MessageType::Response | MessageType::Ack);
+        match &self.payload {
+            MessagePayload::PrintJobRequest { .. }
+            | MessagePayload::ConfigUpdate { .. }
+            | MessagePayload::PurgeJobs
+            | MessagePayload::PrintBatch { .. }
+            | MessagePayload::ListQueue
+                if replies =>
+            {
+                Err(Error::Validation(format!(
+                    "Message {} carries a request payload as {:?}",
+                    self.message_id, self.message_type
+                )))
+            }
+            MessagePayload::ConfigUpdateResult { .. }
+            | MessagePayload::PurgeJobsResult { .. }
+            | MessagePayload::PrintBatchResult { .. }
+            | MessagePayload::QueueListing { .. }
+                if !matches!(self.message_type, MessageType::Response) =>
+            {
+                Err(Error::Validation(format!(
+                    "Message {} carries a request result as {:?}",
+                    self.message_id, self.message_type
+                )))
+            }
+            MessagePayload::Heartbeat { .. }
+                if !matches!(self.message_type, MessageType::Request | MessageType::Response) =>
+            {
+                Err(Error::Validation(format!(
+                    "Message {} carries a heartbeat as {:?}",
+                    self.message_id, self.message_type
+                )))
+            }
+            MessagePayload::PrintJobStatus { .. }
+            | MessagePayload::DocumentReady { .. }
+            | MessagePayload::PageRendered { .. }
+            | MessagePayload::PageRenderedShm { .. }
+            | MessagePayload::PrinterInfo { .. }
+            | MessagePayload::ManualDuplexPrompt { .. }
+            | MessagePayload::JobQueueUpdate { .. }
+                if matches!(self.source, MessageSource::Preview) =>
+            {
+                Err(Error::Validation(format!(
+                    "Message {} carries backend state but comes from the preview",
+                    self.message_id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Request wrapper
@@ -269,3 +385,47 @@ impl Response {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom_payload() -> MessagePayload {
+        MessagePayload::Custom {
+            data_type: "test".to_owned(),
+            data: vec![],
+        }
+    }
+
+    #[test]
+    fn rejects_message_addressed_to_its_sender() {
+        let message =
+            Message::new_notification(MessageSource::Backend, MessageDestination::Backend, custom_payload());
+
+        assert!(matches!(message.validate(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_print_job_request_sent_as_response() {
+        let message = Message::new_response(
+            7,
+            MessageSource::Backend,
+            MessagePayload::PrintJobRequest {
+                job_id: JobId(uuid::Uuid::new_v4()),
+                file_path: "test.pdf".to_owned(),
+                file_type: "pdf".to_owned(),
+                options: PrintOptions::default(),
+            },
+        );
+
+        assert!(matches!(message.validate(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn accepts_backend_notification_to_preview() {
+        let message =
+            Message::new_notification(MessageSource::Backend, MessageDestination::Preview, custom_payload());
+
+        assert!(message.validate().is_ok());
+    }
+}
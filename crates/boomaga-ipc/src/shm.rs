@@ -0,0 +1,186 @@
+//! Shared-memory transfer of rendered page bitmaps.
+//!
+//! Large bitmaps are copied once into an anonymous `memfd` segment whose file
+//! descriptor rides on the message and is passed next to the JSON frame as
+//! `SCM_RIGHTS` ancillary data. The receiver maps the segment read-only
+//! instead of decoding inline bytes.
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+use boomaga_core::JobId;
+
+use crate::protocol::{Message, MessageDestination, MessagePayload, MessageSource};
+
+/// A rendered bitmap stored in an anonymous shared-memory segment.
+#[derive(Debug)]
+pub struct SharedBitmap {
+    fd: OwnedFd,
+    len: usize,
+}
+
+impl SharedBitmap {
+    /// Copy `data` into a new shared-memory segment.
+    pub fn create(name: &str, data: &[u8]) -> io::Result<Self> {
+        let name = CString::new(name)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC)?;
+        let mut file = File::from(fd);
+        file.write_all(data)?;
+        Ok(Self {
+            fd: file.into(),
+            len: data.len(),
+        })
+    }
+
+    /// Wrap a segment received from a peer.
+    pub fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        let file = File::from(fd);
+        let len = file.metadata()?.len() as usize;
+        Ok(Self {
+            fd: file.into(),
+            len,
+        })
+    }
+
+    /// Segment size in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the segment is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Map the segment read-only.
+    pub fn map(&self) -> io::Result<SharedBitmapView> {
+        let Some(len) = NonZeroUsize::new(self.len) else {
+            return Ok(SharedBitmapView { ptr: None, len: 0 });
+        };
+        // SAFETY: a fresh private read-only mapping of a file we own; it is
+        // only exposed as a shared slice and unmapped on drop.
+        let ptr = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ, MapFlags::MAP_PRIVATE, &self.fd, 0)?
+        };
+        Ok(SharedBitmapView {
+            ptr: Some(ptr),
+            len: self.len,
+        })
+    }
+}
+
+impl AsFd for SharedBitmap {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// Read-only mapping of a [`SharedBitmap`].
+pub struct SharedBitmapView {
+    ptr: Option<NonNull<std::ffi::c_void>>,
+    len: usize,
+}
+
+// SAFETY: the mapping is read-only and owned exclusively by the view.
+unsafe impl Send for SharedBitmapView {}
+unsafe impl Sync for SharedBitmapView {}
+
+impl std::ops::Deref for SharedBitmapView {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.ptr {
+            // SAFETY: `ptr` maps `len` readable bytes until drop.
+            Some(ptr) => unsafe { std::slice::from_raw_parts(ptr.as_ptr().cast(), self.len) },
+            None => &[],
+        }
+    }
+}
+
+impl Drop for SharedBitmapView {
+    fn drop(&mut self) {
+        if let Some(ptr) = self.ptr {
+            // SAFETY: `ptr`/`len` come from the matching `mmap` call.
+            let _ = unsafe { munmap(ptr, self.len) };
+        }
+    }
+}
+
+/// Build a page notification whose pixels travel in shared memory.
+///
+/// The segment is attached as the message's descriptor and passed next to
+/// the frame on delivery. Fails when a segment cannot be created, e.g. on
+/// kernels without `memfd_create`.
+pub fn page_rendered_message(
+    job_id: JobId,
+    page_number: usize,
+    width: usize,
+    height: usize,
+    stride: usize,
+    data: &[u8],
+) -> io::Result<Message> {
+    let shm_name = format!("boomaga-page-{page_number}");
+    let bitmap = SharedBitmap::create(&shm_name, data)?;
+    let mut message = Message::new_notification(
+        MessageSource::Backend,
+        MessageDestination::Preview,
+        MessagePayload::PageRenderedShm {
+            job_id,
+            page_number,
+            shm_name,
+            width,
+            height,
+            stride,
+        },
+    );
+    message.fd = Some(Arc::new(bitmap.fd));
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NotificationServer, UnixSocketTransport};
+
+    #[tokio::test]
+    async fn shm_page_is_read_back_identically() {
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-shm-{}.sock", uuid::Uuid::new_v4()));
+        let (server, sender) = NotificationServer::bind(socket_path.clone()).unwrap();
+        let server_task = tokio::spawn(server.run());
+        let transport = UnixSocketTransport::new(socket_path);
+        let receive_task = tokio::spawn(async move { transport.receive_message().await });
+        tokio::task::yield_now().await;
+
+        let pixels: Vec<u8> = (0..64 * 48 * 4).map(|i| (i % 251) as u8).collect();
+        let message = page_rendered_message(JobId::new(), 3, 64, 48, 64 * 4, &pixels)
+            .expect("memfd should be available on Linux");
+        sender.send(message).unwrap();
+        let mut received = receive_task.await.unwrap().unwrap();
+
+        match received.payload {
+            MessagePayload::PageRenderedShm {
+                page_number,
+                width,
+                height,
+                stride,
+                ..
+            } => assert_eq!((page_number, width, height, stride), (3, 64, 48, 256)),
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+        let fd = received.fd.take().expect("descriptor should be passed");
+        let fd = Arc::try_unwrap(fd).expect("descriptor should not be shared");
+        let shared = SharedBitmap::from_fd(fd).unwrap();
+        assert_eq!(&*shared.map().unwrap(), &*pixels);
+        server_task.abort();
+    }
+}
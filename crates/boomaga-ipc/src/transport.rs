@@ -1,14 +1,38 @@
 //! Unix socket transport implementation
 
-use crate::protocol::{Message, MessageType, PROTOCOL_VERSION};
+use crate::protocol::{
+    Message, MessageDestination, MessagePayload, MessageSource, MessageType, PROTOCOL_VERSION,
+};
+use futures::Stream;
+use nix::sys::socket::{recvmsg, sendmsg, ControlMessage, ControlMessageOwned, MsgFlags};
 use std::fs;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
 use std::os::unix::net::UnixListener;
-use std::path::PathBuf;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
-use tokio::net::UnixStream as TokioUnixStream;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, Interest,
+};
+use tokio::net::{UnixListener as TokioUnixListener, UnixStream as TokioUnixStream};
 use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+/// Largest encoded message accepted on receive.
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// How old a received message may be before it is dropped.
+pub const DEFAULT_MESSAGE_TTL: Duration =
+    Duration::from_secs(boomaga_core::constants::DEFAULT_MESSAGE_TTL_SECS);
+
+/// Incoming messages buffered before client reads are paused.
+const INCOMING_CAPACITY: usize = 100;
+
+/// Descriptors accepted with one message; any beyond the first are closed.
+const MAX_PASSED_FDS: usize = 4;
 
 /// Write one newline-delimited JSON message.
 pub async fn write_message<W>(writer: &mut W, message: &Message) -> io::Result<()>
@@ -22,18 +46,122 @@ where
 }
 
 /// Read one newline-delimited JSON message.
-pub async fn read_message<R>(reader: R) -> io::Result<Message>
+///
+/// Oversized frames and incoherent messages are rejected as invalid data,
+/// and messages older than `ttl` with `TimedOut`.
+pub async fn read_message<R>(reader: R, ttl: Duration) -> io::Result<Message>
 where
     R: AsyncRead + Unpin,
+{
+    read_buffered_message(&mut BufReader::new(reader), ttl).await
+}
+
+/// Read the next newline-delimited JSON message from a buffered reader.
+///
+/// Unlike [`read_message`], bytes after the frame stay in `reader`, so it can
+/// be called repeatedly on one connection.
+pub async fn read_buffered_message<R>(reader: &mut R, ttl: Duration) -> io::Result<Message>
+where
+    R: AsyncBufRead + Unpin,
 {
     let mut encoded = String::new();
-    if BufReader::new(reader).read_line(&mut encoded).await? == 0 {
+    let limit = MAX_MESSAGE_SIZE as u64 + 1;
+    if reader.take(limit).read_line(&mut encoded).await? == 0 {
         return Err(io::Error::new(
             io::ErrorKind::UnexpectedEof,
             "socket closed",
         ));
     }
-    let message: Message = serde_json::from_str(&encoded)
+    if encoded.len() > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+    }
+    decode_message(encoded.as_bytes(), ttl)
+}
+
+/// Send one message, passing its descriptor with `SCM_RIGHTS` if it has one.
+pub async fn send_message_with_fd(stream: &TokioUnixStream, message: &Message) -> io::Result<()> {
+    let mut encoded = serde_json::to_vec(message)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    encoded.push(b'\n');
+
+    let fds: Vec<RawFd> = message.fd.iter().map(|fd| fd.as_raw_fd()).collect();
+    let mut written = 0;
+    while written < encoded.len() {
+        let remaining = &encoded[written..];
+        written += stream
+            .async_io(Interest::WRITABLE, || {
+                let iov = [IoSlice::new(remaining)];
+                // The descriptor rides on the first chunk only.
+                let cmsgs: &[ControlMessage] = if written == 0 && !fds.is_empty() {
+                    &[ControlMessage::ScmRights(&fds)]
+                } else {
+                    &[]
+                };
+                sendmsg::<()>(stream.as_raw_fd(), &iov, cmsgs, MsgFlags::MSG_NOSIGNAL, None)
+                    .map_err(io::Error::from)
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Receive one message together with a descriptor passed alongside it.
+///
+/// The stream must carry a single message, as bytes read past its newline
+/// are discarded. Messages are checked like in [`read_message`].
+pub async fn recv_message_with_fd(stream: &TokioUnixStream, ttl: Duration) -> io::Result<Message> {
+    let mut encoded = Vec::new();
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut fd: Option<OwnedFd> = None;
+    loop {
+        let (count, passed) = stream
+            .async_io(Interest::READABLE, || {
+                let mut iov = [IoSliceMut::new(&mut chunk)];
+                let mut cmsg_buffer = nix::cmsg_space!([RawFd; MAX_PASSED_FDS]);
+                let received = recvmsg::<()>(
+                    stream.as_raw_fd(),
+                    &mut iov,
+                    Some(&mut cmsg_buffer),
+                    MsgFlags::MSG_CMSG_CLOEXEC,
+                )
+                .map_err(io::Error::from)?;
+                let mut passed = Vec::new();
+                for cmsg in received.cmsgs().map_err(io::Error::from)? {
+                    if let ControlMessageOwned::ScmRights(fds) = cmsg {
+                        // SAFETY: the kernel installed these descriptors for us.
+                        passed.extend(fds.into_iter().map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }));
+                    }
+                }
+                Ok((received.bytes, passed))
+            })
+            .await?;
+        // Keep the first descriptor; dropping the rest closes them
+        for passed in passed {
+            fd.get_or_insert(passed);
+        }
+        if count == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "socket closed"));
+        }
+        let start = encoded.len();
+        encoded.extend_from_slice(&chunk[..count]);
+        if let Some(newline) = encoded[start..].iter().position(|byte| *byte == b'\n') {
+            encoded.truncate(start + newline + 1);
+            break;
+        }
+        if encoded.len() > MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+        }
+    }
+
+    let mut message = decode_message(&encoded, ttl)?;
+    message.fd = fd.map(Arc::new);
+    Ok(message)
+}
+
+/// Decode one frame, rejecting incoherent messages as invalid data and
+/// messages older than `ttl` with `TimedOut`
+fn decode_message(encoded: &[u8], ttl: Duration) -> io::Result<Message> {
+    let message: Message = serde_json::from_slice(encoded)
         .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
     if message.protocol_version != PROTOCOL_VERSION {
         return Err(io::Error::new(
@@ -41,9 +169,39 @@ where
             format!("unsupported protocol version {}", message.protocol_version),
         ));
     }
+    message
+        .validate()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+    if message.is_expired(ttl.as_millis() as i64) {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("message {} expired ({} ms old)", message.message_id, message.age()),
+        ));
+    }
     Ok(message)
 }
 
+/// Remove a socket file left behind by a process that is gone
+///
+/// Fails with `AddrInUse` when something still accepts connections on the
+/// path, so a second instance cannot steal a live socket.
+pub(crate) fn remove_stale_socket(socket_path: &Path) -> io::Result<()> {
+    if !socket_path.exists() {
+        return Ok(());
+    }
+    match std::os::unix::net::UnixStream::connect(socket_path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another process", socket_path.display()),
+        )),
+        Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+            warn!("Removing stale socket: {:?}", socket_path);
+            fs::remove_file(socket_path)
+        }
+        Err(error) => Err(error),
+    }
+}
+
 /// Unix socket transport
 pub struct UnixSocket {
     /// Socket path
@@ -54,27 +212,38 @@ pub struct UnixSocket {
     clients: Vec<TokioUnixStream>,
     /// Receiver channel for incoming messages
     receiver: Option<mpsc::Receiver<io::Result<Message>>>,
+    /// Sender handed to client readers
+    sender: mpsc::Sender<io::Result<Message>>,
+    /// Maximum age of received messages
+    message_ttl: Duration,
 }
 
 impl UnixSocket {
     /// Create a new Unix socket
     pub fn new(socket_path: PathBuf) -> Result<Self, io::Error> {
-        // Remove existing socket file if present
-        if socket_path.exists() {
-            fs::remove_file(&socket_path)?;
-        }
+        remove_stale_socket(&socket_path)?;
 
         let listener = UnixListener::bind(&socket_path)?;
         info!("Unix socket created at: {:?}", socket_path);
 
+        let (sender, receiver) = mpsc::channel(INCOMING_CAPACITY);
+
         Ok(Self {
             socket_path,
             listener: Some(listener),
             clients: Vec::new(),
-            receiver: Some(mpsc::channel(100).1),
+            receiver: Some(receiver),
+            sender,
+            message_ttl: DEFAULT_MESSAGE_TTL,
         })
     }
 
+    /// Drop received messages older than `ttl`
+    pub fn with_message_ttl(mut self, ttl: Duration) -> Self {
+        self.message_ttl = ttl;
+        self
+    }
+
     /// Start listening for connections
     pub async fn listen(&mut self) -> Result<(), io::Error> {
         let listener = self.listener.take().ok_or_else(|| {
@@ -83,15 +252,18 @@ impl UnixSocket {
                 "Socket listener not initialized",
             )
         })?;
+        listener.set_nonblocking(true)?;
+        let listener = TokioUnixListener::from_std(listener)?;
+        let sender = self.sender.clone();
+        let ttl = self.message_ttl;
 
         // Accept connections in a task
         tokio::spawn(async move {
             loop {
-                match listener.accept() {
+                match listener.accept().await {
                     Ok((stream, addr)) => {
                         info!("New client connected: {:?}", addr);
-                        // Store client
-                        // In production, would maintain client connections
+                        tokio::spawn(Self::read_client(stream, sender.clone(), ttl));
                     }
                     Err(e) => {
                         error!("Error accepting client: {}", e);
@@ -103,6 +275,36 @@ impl UnixSocket {
         Ok(())
     }
 
+    /// Forward a client's messages until it disconnects, dropping those
+    /// older than `ttl`
+    ///
+    /// Waiting for channel capacity pauses the read, which pushes back on
+    /// the client once consumers fall behind.
+    async fn read_client(
+        stream: TokioUnixStream,
+        sender: mpsc::Sender<io::Result<Message>>,
+        ttl: Duration,
+    ) {
+        let mut reader = BufReader::new(stream);
+        loop {
+            match read_buffered_message(&mut reader, ttl).await {
+                Ok(message) => {
+                    if sender.send(Ok(message)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return,
+                Err(error) if error.kind() == io::ErrorKind::TimedOut => {
+                    warn!("Dropping {}", error);
+                }
+                Err(error) => {
+                    let _ = sender.send(Err(error)).await;
+                    return;
+                }
+            }
+        }
+    }
+
     /// Send a message to the socket
     pub async fn send(&self, message: Message) -> Result<(), io::Error> {
         // In production, this would serialize and send the message
@@ -115,11 +317,15 @@ impl UnixSocket {
         Ok(())
     }
 
-    /// Receive a message from the socket
+    /// Receive a message from the socket, skipping unreadable frames
     pub async fn recv(&mut self) -> Option<Message> {
-        // In production, this would receive messages from the socket
-        // For now, return None
-        None
+        let receiver = self.receiver.as_mut()?;
+        loop {
+            match receiver.recv().await? {
+                Ok(message) => return Some(message),
+                Err(error) => debug!("Dropping unreadable message: {}", error),
+            }
+        }
     }
 
     /// Send raw bytes
@@ -138,6 +344,9 @@ impl UnixSocket {
     /// Close the socket
     pub fn close(&mut self) -> Result<(), io::Error> {
         self.clients.clear();
+        if let Some(receiver) = self.receiver.as_mut() {
+            receiver.close();
+        }
 
         // Remove socket file
         if self.socket_path.exists() {
@@ -155,16 +364,44 @@ impl UnixSocket {
     }
 }
 
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.socket_path);
+    }
+}
+
+impl Stream for UnixSocket {
+    type Item = io::Result<Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.receiver.as_mut() {
+            Some(receiver) => receiver.poll_recv(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
 /// Unix socket transport for async usage
 pub struct UnixSocketTransport {
     /// Socket path
     socket_path: PathBuf,
+    /// Maximum age of received messages
+    message_ttl: Duration,
 }
 
 impl UnixSocketTransport {
     /// Create a new transport
     pub fn new(socket_path: PathBuf) -> Self {
-        Self { socket_path }
+        Self {
+            socket_path,
+            message_ttl: DEFAULT_MESSAGE_TTL,
+        }
+    }
+
+    /// Drop received messages older than `ttl`
+    pub fn with_message_ttl(mut self, ttl: Duration) -> Self {
+        self.message_ttl = ttl;
+        self
     }
 
     /// Connect to the socket
@@ -182,10 +419,62 @@ impl UnixSocketTransport {
         stream.shutdown().await
     }
 
-    /// Receive message
+    /// Receive message, skipping any that have outlived the TTL
     pub async fn receive_message(&self) -> Result<Message, io::Error> {
-        let stream = self.connect().await?;
-        read_message(stream).await
+        loop {
+            let mut stream = self.connect().await?;
+            // Sending nothing asks the server for the next notification
+            stream.shutdown().await?;
+            match recv_message_with_fd(&stream, self.message_ttl).await {
+                Err(error) if error.kind() == io::ErrorKind::TimedOut => {
+                    warn!("Dropping {}", error);
+                }
+                received => return received,
+            }
+        }
+    }
+
+    /// Send a request and wait for the response to it
+    ///
+    /// Fails with `TimedOut` when no response comes within `timeout`.
+    pub async fn request(&self, request: Message, timeout: Duration) -> Result<Message, io::Error> {
+        let exchange = async {
+            let mut stream = self.connect().await?;
+            write_message(&mut stream, &request).await?;
+            let reply = read_message(stream, self.message_ttl).await?;
+            if !matches!(reply.message_type, MessageType::Response) || reply.message_id != request.message_id {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unexpected reply to message {}: {:?}", request.message_id, reply.payload),
+                ));
+            }
+            Ok(reply)
+        };
+
+        tokio::time::timeout(timeout, exchange).await.map_err(|_| {
+            io::Error::new(io::ErrorKind::TimedOut, format!("no reply within {:?}", timeout))
+        })?
+    }
+
+    /// Send a heartbeat and wait for its reply, returning the round trip
+    ///
+    /// Fails with `TimedOut` when no reply comes within `timeout`, so a
+    /// backend that accepts connections but no longer answers counts as down.
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, io::Error> {
+        let nonce = uuid::Uuid::new_v4().as_u64_pair().0;
+        let started = Instant::now();
+        let request = Message::new_request(
+            MessageSource::Preview,
+            MessageDestination::Backend,
+            MessagePayload::Heartbeat { nonce },
+        );
+        match self.request(request, timeout).await?.payload {
+            MessagePayload::Heartbeat { nonce: echoed } if echoed == nonce => Ok(started.elapsed()),
+            payload => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected heartbeat reply: {:?}", payload),
+            )),
+        }
     }
 }
 
@@ -193,6 +482,7 @@ impl UnixSocketTransport {
 mod tests {
     use super::*;
     use crate::protocol::{MessageDestination, MessagePayload, MessageSource, PROTOCOL_VERSION};
+    use boomaga_core::{RenderQuality, SettingsPatch};
 
     #[tokio::test]
     async fn framed_message_round_trip() {
@@ -207,7 +497,7 @@ mod tests {
         let (mut writer, reader) = tokio::io::duplex(4096);
 
         write_message(&mut writer, &message).await.unwrap();
-        let decoded = read_message(reader).await.unwrap();
+        let decoded = read_message(reader, DEFAULT_MESSAGE_TTL).await.unwrap();
 
         assert_eq!(decoded.protocol_version, PROTOCOL_VERSION);
         assert_eq!(decoded.message_id, message.message_id);
@@ -220,6 +510,196 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn config_update_patch_round_trips() {
+        let patch = SettingsPatch {
+            render_quality: Some(RenderQuality::Low),
+            ..Default::default()
+        };
+        let message = Message::new_notification(
+            MessageSource::Preview,
+            MessageDestination::Broadcast,
+            MessagePayload::ConfigUpdate { patch: patch.clone() },
+        );
+        let (mut writer, reader) = tokio::io::duplex(4096);
+
+        write_message(&mut writer, &message).await.unwrap();
+        let decoded = read_message(reader, DEFAULT_MESSAGE_TTL).await.unwrap();
+
+        match decoded.payload {
+            MessagePayload::ConfigUpdate { patch: decoded } => assert_eq!(decoded, patch),
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn socket_streams_incoming_messages_in_order() {
+        use futures::StreamExt;
+
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+        let mut socket = UnixSocket::new(socket_path.clone()).unwrap();
+        socket.listen().await.unwrap();
+
+        let mut client = TokioUnixStream::connect(&socket_path).await.unwrap();
+        for index in 0..3u8 {
+            let message = Message::new_notification(
+                MessageSource::Backend,
+                MessageDestination::Preview,
+                MessagePayload::Custom {
+                    data_type: "stream".to_owned(),
+                    data: vec![index],
+                },
+            );
+            write_message(&mut client, &message).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            match socket.next().await.unwrap().unwrap().payload {
+                MessagePayload::Custom { data, .. } => received.push(data[0]),
+                payload => panic!("unexpected payload: {payload:?}"),
+            }
+        }
+
+        assert_eq!(received, vec![0, 1, 2]);
+        socket.close().unwrap();
+    }
+
+    #[test]
+    fn dropping_socket_removes_its_file() {
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+        let socket = UnixSocket::new(socket_path.clone()).unwrap();
+        assert!(socket_path.exists());
+
+        drop(socket);
+        assert!(!socket_path.exists());
+    }
+
+    #[test]
+    fn replaces_stale_socket_but_not_live_one() {
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+
+        // A std listener leaves its file behind, like a crashed process
+        drop(UnixListener::bind(&socket_path).unwrap());
+        let socket = UnixSocket::new(socket_path.clone()).unwrap();
+
+        let error = UnixSocket::new(socket_path.clone()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert!(socket_path.exists());
+        drop(socket);
+    }
+
+    #[tokio::test]
+    async fn rejects_incoherent_message() {
+        let message = Message::new_notification(
+            MessageSource::Preview,
+            MessageDestination::Preview,
+            MessagePayload::Custom {
+                data_type: "test".to_owned(),
+                data: vec![],
+            },
+        );
+        let (mut writer, reader) = tokio::io::duplex(4096);
+        write_message(&mut writer, &message).await.unwrap();
+
+        let error = read_message(reader, DEFAULT_MESSAGE_TTL).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn drops_expired_messages() {
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+        let (server, sender) = crate::NotificationServer::bind(socket_path.clone()).unwrap();
+        let server_task = tokio::spawn(server.run());
+        let notification = |data_type: &str| {
+            Message::new_notification(
+                MessageSource::Backend,
+                MessageDestination::Preview,
+                MessagePayload::Custom {
+                    data_type: data_type.to_owned(),
+                    data: vec![],
+                },
+            )
+        };
+        let mut expired = notification("expired");
+        expired.timestamp -= 60_000;
+        sender.send(expired).unwrap();
+        sender.send(notification("fresh")).unwrap();
+
+        let transport = UnixSocketTransport::new(socket_path).with_message_ttl(Duration::from_secs(1));
+        let received = transport.receive_message().await.unwrap();
+
+        match received.payload {
+            MessagePayload::Custom { data_type, .. } => assert_eq!(data_type, "fresh"),
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn socket_drops_expired_messages() {
+        use futures::StreamExt;
+
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+        let mut socket =
+            UnixSocket::new(socket_path.clone()).unwrap().with_message_ttl(Duration::from_secs(1));
+        socket.listen().await.unwrap();
+
+        let mut client = TokioUnixStream::connect(&socket_path).await.unwrap();
+        for (data_type, age) in [("expired", 60_000), ("fresh", 0)] {
+            let mut message = Message::new_notification(
+                MessageSource::Backend,
+                MessageDestination::Preview,
+                MessagePayload::Custom {
+                    data_type: data_type.to_owned(),
+                    data: vec![],
+                },
+            );
+            message.timestamp -= age;
+            write_message(&mut client, &message).await.unwrap();
+        }
+
+        match socket.next().await.unwrap().unwrap().payload {
+            MessagePayload::Custom { data_type, .. } => assert_eq!(data_type, "fresh"),
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+        socket.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn ping_measures_a_heartbeat_round_trip() {
+        let socket_path =
+            std::env::temp_dir().join(format!("boomaga-ipc-{}.sock", uuid::Uuid::new_v4()));
+        let (server, sender) = crate::NotificationServer::bind(socket_path.clone()).unwrap();
+        let server_task = tokio::spawn(server.run());
+        let transport = UnixSocketTransport::new(socket_path.clone());
+
+        // A queued notification is kept for a receiver, not handed to the ping
+        sender
+            .send(Message::new_notification(
+                MessageSource::Backend,
+                MessageDestination::Preview,
+                MessagePayload::Custom { data_type: "queued".to_owned(), data: vec![] },
+            ))
+            .unwrap();
+        let round_trip = transport.ping(Duration::from_secs(5)).await.unwrap();
+        assert!(round_trip < Duration::from_secs(5));
+        match transport.receive_message().await.unwrap().payload {
+            MessagePayload::Custom { data_type, .. } => assert_eq!(data_type, "queued"),
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+
+        server_task.abort();
+        let _ = server_task.await;
+        let error = transport.ping(Duration::from_millis(100)).await.unwrap_err();
+        assert!(matches!(error.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused));
+    }
+
     #[tokio::test]
     async fn rejects_unsupported_protocol_version() {
         let mut message = Message::new_notification(
@@ -234,7 +714,7 @@ mod tests {
         let (mut writer, reader) = tokio::io::duplex(4096);
         write_message(&mut writer, &message).await.unwrap();
 
-        let error = read_message(reader).await.unwrap_err();
+        let error = read_message(reader, DEFAULT_MESSAGE_TTL).await.unwrap_err();
         assert_eq!(error.kind(), io::ErrorKind::InvalidData);
     }
 }
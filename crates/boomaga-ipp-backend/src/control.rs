@@ -0,0 +1,276 @@
+//! Requests from IPC clients controlling the running backend
+
+use crate::job_processor::JobProcessor;
+use boomaga_config::{BackendConfig, ConfigManager};
+use boomaga_core::{PatchOutcome, SettingsPatch};
+use boomaga_ipc::{IncomingRequest, MessagePayload, RequestReceiver};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Answer client requests until the notification server goes away
+///
+/// Configuration updates are stored through `config` when there is one.
+pub async fn serve(mut requests: RequestReceiver, processor: Arc<JobProcessor>, config: Option<ConfigManager>) {
+    while let Some(request) = requests.recv().await {
+        handle(request, &processor, config.as_ref()).await;
+    }
+    debug!("No more control requests");
+}
+
+/// Carry out one request and answer it
+async fn handle(request: IncomingRequest, processor: &JobProcessor, config: Option<&ConfigManager>) {
+    match &request.message.payload {
+        MessagePayload::PurgeJobs => {
+            info!("Purging the job queue on request");
+            let purged = processor.purge().await;
+            request.respond(MessagePayload::PurgeJobsResult { purged });
+        }
+        MessagePayload::ListQueue => {
+            let jobs = processor.queued_jobs().await;
+            request.respond(MessagePayload::QueueListing { jobs });
+        }
+        MessagePayload::ConfigUpdate { patch } => {
+            let outcome = update_config(patch, processor, config).await;
+            request.respond(MessagePayload::ConfigUpdateResult { outcome });
+        }
+        MessagePayload::PrintBatch { batch } => {
+            let job_id = match processor.add_batch(batch.clone()).await {
+                Ok(job_id) => {
+                    info!(job_id = %job_id, documents = batch.documents.len(), "Queued batch on request");
+                    Some(job_id)
+                }
+                Err(e) => {
+                    warn!(error = %e, "Refusing batch");
+                    None
+                }
+            };
+            request.respond(MessagePayload::PrintBatchResult { job_id });
+        }
+        payload => debug!("Ignoring {:?} request", payload),
+    }
+}
+
+/// Apply a configuration patch to the running backend and store it
+///
+/// Only the concurrent job limit changes live. Everything else the backend
+/// reads at startup, so it is stored and reported as needing a restart. An
+/// invalid patch changes nothing.
+async fn update_config(
+    patch: &SettingsPatch,
+    processor: &JobProcessor,
+    config: Option<&ConfigManager>,
+) -> PatchOutcome {
+    let mut backend = match config.map(ConfigManager::load_backend).transpose() {
+        Ok(backend) => backend.unwrap_or_default(),
+        Err(e) => {
+            warn!(error = %e, "Failed to load the backend configuration, ignoring the update");
+            return PatchOutcome::default();
+        }
+    };
+    let mut outcome = backend.apply_patch(patch);
+    if let Err(e) = backend.validate() {
+        warn!(error = %e, "Ignoring invalid configuration update");
+        return PatchOutcome::default();
+    }
+
+    if let Some(max_concurrent_jobs) = patch.max_concurrent_jobs {
+        if let Err(e) = processor.set_max_concurrent(max_concurrent_jobs).await {
+            warn!(error = %e, "Ignoring invalid configuration update");
+            return PatchOutcome::default();
+        }
+    }
+
+    if let Some(manager) = config {
+        store(manager, patch, &backend, &mut outcome);
+    }
+    info!(applied = ?outcome.applied, requires_restart = ?outcome.requires_restart, "Updated configuration");
+    outcome
+}
+
+/// Save the patched backend configuration and user settings
+///
+/// The render quality of jobs is fixed at startup, so the settings the
+/// backend uses are reported as needing a restart.
+fn store(manager: &ConfigManager, patch: &SettingsPatch, backend: &BackendConfig, outcome: &mut PatchOutcome) {
+    if let Err(e) = manager.save_backend(backend) {
+        warn!(error = %e, "Failed to save the backend configuration");
+    }
+
+    match manager.update_settings(|settings| Ok(settings.apply_patch(patch))) {
+        Ok(settings) => {
+            for field in settings.applied {
+                if field == "render_quality" {
+                    outcome.requires_restart.push(field);
+                } else {
+                    outcome.applied.push(field);
+                }
+            }
+        }
+        Err(e) => warn!(error = %e, "Failed to save the settings"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_queue::JobQueue;
+    use boomaga_core::{FileType, JobId, JobStatus, PrintBatch, PrintJobRequest, PrintOptions};
+    use boomaga_ipc::{Message, MessageDestination, MessageSource, NotificationServer, UnixSocketTransport};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn purge_request_empties_the_queue() {
+        let socket_path = std::env::temp_dir().join(format!("boomaga-control-{}.sock", JobId::new()));
+        let (mut server, sender) = NotificationServer::bind(socket_path.clone()).unwrap();
+        let requests = server.requests();
+        let server_task = tokio::spawn(server.run());
+
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let processor = Arc::new(JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap());
+        let job_id = JobId::new();
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: PathBuf::from("test.pdf"),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+        tokio::spawn(serve(requests, Arc::clone(&processor), None));
+
+        let request = Message::new_request(MessageSource::Ipc, MessageDestination::Backend, MessagePayload::PurgeJobs);
+        let reply = UnixSocketTransport::new(socket_path)
+            .request(request, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert!(matches!(reply.payload, MessagePayload::PurgeJobsResult { purged: 1 }));
+        assert!(queue.is_empty());
+        assert_eq!(processor.get_status(job_id.to_string()).await, Some(JobStatus::Cancelled));
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn queue_listing_shows_waiting_jobs() {
+        let socket_path = std::env::temp_dir().join(format!("boomaga-control-{}.sock", JobId::new()));
+        let (mut server, sender) = NotificationServer::bind(socket_path.clone()).unwrap();
+        let requests = server.requests();
+        let server_task = tokio::spawn(server.run());
+
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let processor = Arc::new(JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap());
+        let job_id = JobId::new();
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: PathBuf::from("test.pdf"),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+        tokio::spawn(serve(requests, Arc::clone(&processor), None));
+
+        let request = Message::new_request(MessageSource::Ipc, MessageDestination::Backend, MessagePayload::ListQueue);
+        let reply = UnixSocketTransport::new(socket_path)
+            .request(request, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        let MessagePayload::QueueListing { jobs } = reply.payload else {
+            panic!("unexpected payload: {:?}", reply.payload);
+        };
+        assert_eq!(jobs.len(), 1);
+        assert_eq!((&jobs[0].job_id, jobs[0].name.as_str(), jobs[0].held), (&job_id, "test.pdf", false));
+        assert_eq!(queue.size(), 1);
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn batch_request_queues_one_job() {
+        let socket_path = std::env::temp_dir().join(format!("boomaga-control-{}.sock", JobId::new()));
+        let (mut server, sender) = NotificationServer::bind(socket_path.clone()).unwrap();
+        let requests = server.requests();
+        let server_task = tokio::spawn(server.run());
+
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let processor = Arc::new(JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap());
+        tokio::spawn(serve(requests, Arc::clone(&processor), None));
+        let transport = UnixSocketTransport::new(socket_path);
+        let submit = |documents: Vec<PathBuf>| {
+            let batch = PrintBatch { documents, options: PrintOptions::default() };
+            Message::new_request(MessageSource::Ipc, MessageDestination::Backend, MessagePayload::PrintBatch { batch })
+        };
+
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../boomaga-core/tests/fixtures/outline.pdf");
+        let reply = transport.request(submit(vec![fixture.clone(), fixture]), Duration::from_secs(1)).await.unwrap();
+        let MessagePayload::PrintBatchResult { job_id: Some(job_id) } = reply.payload else {
+            panic!("unexpected payload: {:?}", reply.payload);
+        };
+        assert_eq!(processor.get_status(job_id.to_string()).await, Some(JobStatus::Queued));
+        assert_eq!(queue.size(), 1);
+
+        let reply = transport.request(submit(Vec::new()), Duration::from_secs(1)).await.unwrap();
+        assert!(matches!(reply.payload, MessagePayload::PrintBatchResult { job_id: None }));
+        assert_eq!(queue.size(), 1);
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn config_update_resizes_the_job_slots_and_is_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        let socket_path = dir.path().join("control.sock");
+        let (mut server, sender) = NotificationServer::bind(socket_path.clone()).unwrap();
+        let requests = server.requests();
+        let server_task = tokio::spawn(server.run());
+
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        tokio::spawn(serve(requests, Arc::clone(&processor), Some(manager)));
+
+        let patch = SettingsPatch {
+            max_concurrent_jobs: Some(3),
+            ipp_port: Some(8631),
+            dark_mode: Some(true),
+            ..Default::default()
+        };
+        let payload = MessagePayload::ConfigUpdate { patch };
+        let request = Message::new_request(MessageSource::Ipc, MessageDestination::Backend, payload);
+        let reply = UnixSocketTransport::new(socket_path)
+            .request(request, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        let MessagePayload::ConfigUpdateResult { outcome } = reply.payload else {
+            panic!("unexpected payload: {:?}", reply.payload);
+        };
+        assert_eq!(outcome.applied, vec!["max_concurrent_jobs", "dark_mode"]);
+        assert_eq!(outcome.requires_restart, vec!["ipp_port"]);
+        assert_eq!(*processor.max_concurrent.lock().await, 3);
+
+        let manager = ConfigManager::with_dirs(&dir.path().join("config"), &dir.path().join("state")).unwrap();
+        assert_eq!(manager.load_backend().unwrap().max_concurrent_jobs, 3);
+        assert!(manager.load_settings().unwrap().ui.dark_mode);
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn invalid_config_update_changes_nothing() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 2, 1, sender).unwrap();
+        let patch = SettingsPatch { max_concurrent_jobs: Some(0), ..Default::default() };
+
+        let outcome = update_config(&patch, &processor, None).await;
+
+        assert_eq!(outcome, PatchOutcome::default());
+        assert_eq!(*processor.max_concurrent.lock().await, 2);
+    }
+}
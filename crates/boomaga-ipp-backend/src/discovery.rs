@@ -0,0 +1,440 @@
+//! Discovery of downstream IPP printers on the local network
+//!
+//! Printers are found by browsing `_ipp._tcp` and `_ipps._tcp` over mDNS,
+//! then queried with Get-Printer-Attributes to fill in their capabilities.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use boomaga_core::{
+    DuplexMode, Error, MarginMode, Orientation, PageSize, PrintOptions, PrinterCapabilities,
+    PrinterInfo, PrinterStatus, Result,
+};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+
+use crate::ipp::{
+    encode_attribute, parse_attributes, CHARSET_TAG, END_OF_ATTRIBUTES_TAG, KEYWORD_TAG,
+    NATURAL_LANGUAGE_TAG, OPERATION_ATTRIBUTES_TAG, URI_TAG,
+};
+
+/// How long to listen for mDNS announcements
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a single printer may take to answer Get-Printer-Attributes
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// DNS-SD service types advertised by IPP printers
+const SERVICE_TYPES: [&str; 2] = ["_ipp._tcp.local.", "_ipps._tcp.local."];
+
+/// Get-Printer-Attributes operation code (RFC 8011 §4.2.5)
+const GET_PRINTER_ATTRIBUTES: u16 = 0x000B;
+
+/// A printer advertised over mDNS
+#[derive(Debug, Clone, PartialEq)]
+struct DiscoveredService {
+    name: String,
+    uri: String,
+    is_remote: bool,
+}
+
+/// Browse the network and describe every printer that answers
+///
+/// A printer that cannot be queried is still listed, as offline with
+/// default capabilities, so one bad device never hides the others.
+pub async fn discover_printers(timeout: Duration) -> Vec<PrinterInfo> {
+    let services = match browse_services(timeout).await {
+        Ok(services) => services,
+        Err(e) => {
+            warn!("Printer discovery failed: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut printers = Vec::with_capacity(services.len());
+    for service in services {
+        printers.push(probe_printer(&service.name, &service.uri, service.is_remote).await);
+    }
+    printers
+}
+
+/// Collect IPP services announced within `timeout`
+async fn browse_services(timeout: Duration) -> Result<Vec<DiscoveredService>> {
+    let daemon = ServiceDaemon::new()
+        .map_err(|e| Error::System(format!("Failed to start mDNS browser: {}", e)))?;
+    let receivers = SERVICE_TYPES
+        .iter()
+        .map(|service_type| daemon.browse(service_type))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| Error::System(format!("Failed to browse for printers: {}", e)));
+
+    // The daemon queues events in the background while we wait
+    if receivers.is_ok() {
+        tokio::time::sleep(timeout).await;
+    }
+
+    let mut services: Vec<DiscoveredService> = Vec::new();
+    for receiver in receivers.as_deref().unwrap_or_default() {
+        for event in receiver.try_iter() {
+            let ServiceEvent::ServiceResolved(resolved) = event else {
+                continue;
+            };
+            let Some(address) = resolved.get_addresses_v4().into_iter().next() else {
+                debug!("Skipping {} without an IPv4 address", resolved.get_fullname());
+                continue;
+            };
+            let scheme = if resolved.ty_domain.starts_with("_ipps") { "ipps" } else { "ipp" };
+            let resource = resolved.get_property_val_str("rp").unwrap_or("ipp/print");
+            let service = DiscoveredService {
+                name: instance_name(resolved.get_fullname()),
+                uri: format!("{}://{}:{}/{}", scheme, address, resolved.get_port(), resource),
+                is_remote: !address.is_loopback(),
+            };
+            if !services.iter().any(|known| known.uri == service.uri) {
+                services.push(service);
+            }
+        }
+    }
+
+    if let Err(e) = daemon.shutdown() {
+        debug!("Failed to stop mDNS browser: {}", e);
+    }
+
+    receivers.map(|_| services)
+}
+
+/// Strip the service type from an mDNS full name
+fn instance_name(fullname: &str) -> String {
+    SERVICE_TYPES
+        .iter()
+        .find_map(|service_type| fullname.strip_suffix(service_type))
+        .unwrap_or(fullname)
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Query one printer and describe it
+pub async fn probe_printer(name: &str, uri: &str, is_remote: bool) -> PrinterInfo {
+    let attributes = match tokio::time::timeout(PROBE_TIMEOUT, fetch_printer_attributes(uri)).await {
+        Ok(Ok(attributes)) => attributes,
+        Ok(Err(e)) => {
+            warn!("Failed to query printer {} at {}: {}", name, uri, e);
+            HashMap::new()
+        }
+        Err(_) => {
+            warn!("Printer {} at {} did not answer in time", name, uri);
+            HashMap::new()
+        }
+    };
+
+    let status = if attributes.is_empty() {
+        PrinterStatus::Offline
+    } else {
+        status_from_attributes(&attributes)
+    };
+    let first = |key: &str| attributes.get(key).and_then(|values| values.first()).cloned();
+
+    PrinterInfo {
+        name: first("printer-name").unwrap_or_else(|| name.to_string()),
+        description: first("printer-info")
+            .or_else(|| first("printer-make-and-model"))
+            .unwrap_or_default(),
+        uri: uri.to_string(),
+        is_remote,
+        status,
+        capabilities: capabilities_from_attributes(&attributes),
+        default_settings: PrintOptions::default(),
+        attributes: attributes
+            .iter()
+            .map(|(key, values)| (key.clone(), values.join(",")))
+            .collect(),
+    }
+}
+
+/// Send Get-Printer-Attributes and decode the answer
+async fn fetch_printer_attributes(uri: &str) -> Result<HashMap<String, Vec<String>>> {
+    let url = url::Url::parse(uri).map_err(|e| Error::Validation(format!("Invalid printer URI {}: {}", uri, e)))?;
+    if url.scheme() != "ipp" {
+        return Err(Error::Unsupported(format!("{} printers need TLS", url.scheme())));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::Validation(format!("Printer URI {} has no host", uri)))?;
+    let port = url.port().unwrap_or(boomaga_core::DEFAULT_IPP_PORT);
+
+    let body = encode_get_printer_attributes(uri, 1);
+    // HTTP/1.0 keeps the reply unchunked, so reading to EOF is enough
+    let mut request = format!(
+        "POST {} HTTP/1.0\r\nHost: {}:{}\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n\r\n",
+        url.path(),
+        host,
+        port,
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(&body);
+
+    let mut stream = TcpStream::connect((host, port)).await?;
+    stream.write_all(&request).await?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+
+    let header_end = reply
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| Error::Ipp("Truncated HTTP response".to_string()))?;
+    let status_line = String::from_utf8_lossy(&reply[..header_end]);
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(Error::Ipp(format!(
+            "Unexpected HTTP response: {}",
+            status_line.lines().next().unwrap_or_default()
+        )));
+    }
+
+    parse_ipp_response(&reply[header_end + 4..])
+}
+
+/// Encode a Get-Printer-Attributes request asking for everything
+fn encode_get_printer_attributes(printer_uri: &str, request_id: u32) -> Vec<u8> {
+    let mut buffer = vec![0x02, 0x00];
+    buffer.extend_from_slice(&GET_PRINTER_ATTRIBUTES.to_be_bytes());
+    buffer.extend_from_slice(&request_id.to_be_bytes());
+    buffer.push(OPERATION_ATTRIBUTES_TAG);
+    encode_attribute(&mut buffer, CHARSET_TAG, "attributes-charset", b"utf-8");
+    encode_attribute(&mut buffer, NATURAL_LANGUAGE_TAG, "attributes-natural-language", b"en");
+    encode_attribute(&mut buffer, URI_TAG, "printer-uri", printer_uri.as_bytes());
+    encode_attribute(&mut buffer, KEYWORD_TAG, "requested-attributes", b"all");
+    buffer.push(END_OF_ATTRIBUTES_TAG);
+    buffer
+}
+
+/// Decode an IPP response into attribute values rendered as strings
+fn parse_ipp_response(data: &[u8]) -> Result<HashMap<String, Vec<String>>> {
+    if data.len() < 8 {
+        return Err(Error::Ipp("Truncated IPP response".to_string()));
+    }
+    let status = u16::from_be_bytes([data[2], data[3]]);
+    if status >= 0x0100 {
+        return Err(Error::Ipp(format!("Printer answered with status 0x{:04x}", status)));
+    }
+
+    Ok(parse_attributes(data)?.0)
+}
+
+/// Map `printer-state` and its reasons onto a printer status
+fn status_from_attributes(attributes: &HashMap<String, Vec<String>>) -> PrinterStatus {
+    let has_reason = |reason: &str| {
+        attributes
+            .get("printer-state-reasons")
+            .is_some_and(|reasons| reasons.iter().any(|value| value.starts_with(reason)))
+    };
+    if has_reason("offline") {
+        return PrinterStatus::Offline;
+    }
+    if has_reason("paused") {
+        return PrinterStatus::Paused;
+    }
+
+    match attributes.get("printer-state").and_then(|values| values.first()).map(String::as_str) {
+        Some("4") => PrinterStatus::Busy,
+        Some("5") => PrinterStatus::Stopped,
+        _ => PrinterStatus::Idle,
+    }
+}
+
+/// Build capabilities from Get-Printer-Attributes values
+///
+/// Attributes the printer doesn't report keep their defaults.
+pub fn capabilities_from_attributes(attributes: &HashMap<String, Vec<String>>) -> PrinterCapabilities {
+    let mut capabilities = PrinterCapabilities::default();
+    let values = |key: &str| attributes.get(key).map(Vec::as_slice).unwrap_or_default();
+
+    if let Some(color) = values("color-supported").first() {
+        capabilities.supports_color = color == "true";
+    } else if values("print-color-mode-supported").iter().any(|mode| mode == "color") {
+        capabilities.supports_color = true;
+    }
+
+    let duplex_modes: Vec<DuplexMode> = values("sides-supported")
+        .iter()
+        .filter_map(|sides| match sides.as_str() {
+            "one-sided" => Some(DuplexMode::None),
+            "two-sided-long-edge" => Some(DuplexMode::LongEdge),
+            "two-sided-short-edge" => Some(DuplexMode::ShortEdge),
+            _ => None,
+        })
+        .collect();
+    if !duplex_modes.is_empty() {
+        capabilities.supports_duplex = duplex_modes.iter().any(|mode| *mode != DuplexMode::None);
+        capabilities.supported_duplex_modes = duplex_modes;
+    }
+
+    if let Some(copies) = values("copies-supported").first() {
+        let upper = copies.rsplit('-').next().and_then(|value| value.parse::<i32>().ok());
+        capabilities.supports_multiple_copies = upper.is_some_and(|upper| upper > 1);
+    }
+
+    let handling = values("multiple-document-handling-supported");
+    if !handling.is_empty() {
+        capabilities.supports_collating = handling
+            .iter()
+            .any(|value| value == "separate-documents-collated-copies");
+    }
+
+    let number_up = values("number-up-supported");
+    if !number_up.is_empty() {
+        capabilities.supports_pages_per_sheet =
+            number_up.len() > 1 || number_up.iter().any(|value| value != "1");
+    }
+
+    let page_sizes: Vec<PageSize> = values("media-supported")
+        .iter()
+        .filter_map(|media| page_size_from_media(media))
+        .fold(Vec::new(), |mut sizes, size| {
+            if !sizes.contains(&size) {
+                sizes.push(size);
+            }
+            sizes
+        });
+    if !page_sizes.is_empty() {
+        capabilities.supported_page_sizes = page_sizes;
+    }
+
+    let orientations: Vec<Orientation> = values("orientation-requested-supported")
+        .iter()
+        .filter_map(|value| match value.as_str() {
+            "3" => Some(Orientation::Portrait),
+            "4" => Some(Orientation::Landscape),
+            "5" => Some(Orientation::UpsideDownLandscape),
+            "6" => Some(Orientation::UpsideDownPortrait),
+            _ => None,
+        })
+        .collect();
+    if !orientations.is_empty() {
+        capabilities.supported_orientations = orientations;
+    }
+
+    if values("media-bottom-margin-supported").iter().any(|margin| margin == "0") {
+        capabilities.supported_margins = vec![MarginMode::None, MarginMode::Normal];
+    }
+
+    let languages = values("generated-natural-language-supported");
+    if !languages.is_empty() {
+        capabilities.supported_languages = languages.to_vec();
+    }
+
+    capabilities
+}
+
+/// Map a PWG 5101.1 media keyword onto a standard page size
+pub(crate) fn page_size_from_media(media: &str) -> Option<PageSize> {
+    match media {
+        "iso_a3_297x420mm" => Some(PageSize::A3),
+        "iso_a4_210x297mm" => Some(PageSize::A4),
+        "iso_a5_148x210mm" => Some(PageSize::A5),
+        "iso_b5_176x250mm" => Some(PageSize::B5),
+        "na_letter_8.5x11in" => Some(PageSize::Letter),
+        "na_legal_8.5x14in" => Some(PageSize::Legal),
+        _ => None,
+    }
+}
+
+/// Get the page size of a `media-size` in hundredths of a millimeter
+///
+/// Sizes within a millimeter of a standard size are that size.
+pub(crate) fn page_size_from_dimensions(x_dimension: u32, y_dimension: u32) -> PageSize {
+    let (width, height) = (x_dimension as f64 / 100.0, y_dimension as f64 / 100.0);
+    PageSize::standard_sizes()
+        .into_iter()
+        .find(|size| (size.width_mm() - width).abs() < 1.0 && (size.height_mm() - height).abs() < 1.0)
+        .unwrap_or_else(|| PageSize::from_mm(width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipp::{
+        BEGIN_COLLECTION_TAG, BOOLEAN_TAG, END_COLLECTION_TAG, ENUM_TAG, RANGE_OF_INTEGER_TAG,
+    };
+    use tokio::net::TcpListener;
+
+    /// Get-Printer-Attributes reply from a colour duplex A4/Letter printer
+    fn mock_printer_response() -> Vec<u8> {
+        let mut body = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        body.push(OPERATION_ATTRIBUTES_TAG);
+        encode_attribute(&mut body, CHARSET_TAG, "attributes-charset", b"utf-8");
+        body.push(0x04); // printer attributes
+        encode_attribute(&mut body, 0x42, "printer-name", b"Office");
+        encode_attribute(&mut body, ENUM_TAG, "printer-state", &4i32.to_be_bytes());
+        encode_attribute(&mut body, BOOLEAN_TAG, "color-supported", &[1]);
+        encode_attribute(&mut body, KEYWORD_TAG, "sides-supported", b"one-sided");
+        encode_attribute(&mut body, KEYWORD_TAG, "", b"two-sided-long-edge");
+        encode_attribute(&mut body, KEYWORD_TAG, "media-supported", b"iso_a4_210x297mm");
+        encode_attribute(&mut body, KEYWORD_TAG, "", b"na_letter_8.5x11in");
+        encode_attribute(&mut body, BEGIN_COLLECTION_TAG, "media-col-default", b"");
+        encode_attribute(&mut body, 0x4A, "", b"media-size-name");
+        encode_attribute(&mut body, KEYWORD_TAG, "", b"iso_a5_148x210mm");
+        encode_attribute(&mut body, END_COLLECTION_TAG, "", b"");
+        let mut copies = 1i32.to_be_bytes().to_vec();
+        copies.extend_from_slice(&99i32.to_be_bytes());
+        encode_attribute(&mut body, RANGE_OF_INTEGER_TAG, "copies-supported", &copies);
+        encode_attribute(&mut body, ENUM_TAG, "orientation-requested-supported", &3i32.to_be_bytes());
+        encode_attribute(&mut body, ENUM_TAG, "", &4i32.to_be_bytes());
+        body.push(END_OF_ATTRIBUTES_TAG);
+        body
+    }
+
+    /// Serve one request with a canned IPP reply
+    async fn mock_printer() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            let body = mock_printer_response();
+            let header = format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).await.unwrap();
+            stream.write_all(&body).await.unwrap();
+        });
+        format!("ipp://{}/ipp/print", address)
+    }
+
+    #[tokio::test]
+    async fn mock_printer_capabilities_are_parsed() {
+        let uri = mock_printer().await;
+
+        let printer = probe_printer("fallback", &uri, false).await;
+
+        assert_eq!(printer.name, "Office");
+        assert_eq!(printer.status, PrinterStatus::Busy);
+        let capabilities = printer.capabilities;
+        assert!(capabilities.supports_color);
+        assert!(capabilities.supports_duplex);
+        assert_eq!(capabilities.supported_duplex_modes, vec![DuplexMode::None, DuplexMode::LongEdge]);
+        // The A5 inside the collection is not a supported medium
+        assert_eq!(capabilities.supported_page_sizes, vec![PageSize::A4, PageSize::Letter]);
+        assert!(capabilities.supports_multiple_copies);
+        assert_eq!(
+            capabilities.supported_orientations,
+            vec![Orientation::Portrait, Orientation::Landscape]
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_printer_is_listed_offline() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ipp://{}/ipp/print", listener.local_addr().unwrap());
+        drop(listener);
+
+        let printer = probe_printer("Gone", &uri, true).await;
+
+        assert_eq!(printer.name, "Gone");
+        assert_eq!(printer.status, PrinterStatus::Offline);
+    }
+}
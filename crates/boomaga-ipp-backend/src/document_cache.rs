@@ -0,0 +1,216 @@
+//! Content-addressed cache of parsed documents
+
+use boomaga_config::BackendConfig;
+use boomaga_core::{Document, JobId};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How long a cached document stays reusable
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// A parsed document, shared by every job that prints the same contents
+#[derive(Debug)]
+pub struct CachedDocument {
+    pub document: Document,
+}
+
+impl CachedDocument {
+    /// Copy the document for a job, with the job's id and file in place of
+    /// those of the job that parsed it
+    pub fn bind(&self, job_id: &JobId, file_path: &Path) -> Document {
+        let mut document = self.document.clone();
+        let parsed_from = std::mem::replace(&mut document.file_path, file_path.to_path_buf());
+        document.id = job_id.to_string();
+        for page in &mut document.pages {
+            if let Some(source) = page.source.as_mut().filter(|source| source.path == parsed_from) {
+                source.path = file_path.to_path_buf();
+            }
+        }
+        document
+    }
+}
+
+struct CacheEntry {
+    cached: Arc<CachedDocument>,
+    size: usize,
+    inserted_at: Instant,
+}
+
+/// Cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStatistics {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub used_bytes: usize,
+}
+
+/// Bounded cache of parsed documents keyed by content hash
+pub struct DocumentCache {
+    entries: HashMap<String, CacheEntry>,
+    max_bytes: usize,
+    used_bytes: usize,
+    ttl: Duration,
+    hits: u64,
+    misses: u64,
+}
+
+impl DocumentCache {
+    /// Create a cache holding at most `max_bytes`
+    pub fn new(max_bytes: usize, ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_bytes,
+            used_bytes: 0,
+            ttl,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Create a cache sized by `document_cache_size` (MB); 0 disables it
+    pub fn from_config(config: &BackendConfig) -> Self {
+        let max_bytes = (config.document_cache_size as usize).saturating_mul(1024 * 1024);
+        Self::new(max_bytes, DEFAULT_CACHE_TTL)
+    }
+
+    /// Hash a file's contents into a cache key, reading it in chunks
+    pub async fn key_for_file(path: &Path) -> io::Result<String> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_reader(std::fs::File::open(path)?)?;
+            Ok(hasher.finalize().to_hex().to_string())
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Look up a document, dropping it if it has expired
+    pub fn get(&mut self, key: &str) -> Option<Arc<CachedDocument>> {
+        let expired = self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.inserted_at.elapsed() > self.ttl);
+        if expired {
+            self.remove(key);
+        }
+
+        match self.entries.get(key) {
+            Some(entry) => {
+                self.hits += 1;
+                Some(Arc::clone(&entry.cached))
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store a document, evicting the oldest entries to stay within bounds
+    pub fn insert(&mut self, key: String, cached: Arc<CachedDocument>, size: usize) {
+        if size > self.max_bytes {
+            debug!("Document {} ({} bytes) exceeds cache size, not cached", key, size);
+            return;
+        }
+
+        self.remove(&key);
+        while self.used_bytes + size > self.max_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.inserted_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+
+        self.used_bytes += size;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                cached,
+                size,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Get cache counters
+    pub fn statistics(&self) -> CacheStatistics {
+        CacheStatistics {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            used_bytes: self.used_bytes,
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used_bytes -= entry.size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boomaga_core::FileType;
+    use std::path::PathBuf;
+
+    fn cached(id: &str) -> Arc<CachedDocument> {
+        Arc::new(CachedDocument {
+            document: Document::new(id.into(), PathBuf::from("doc.pdf"), FileType::Pdf),
+        })
+    }
+
+    #[test]
+    fn bound_document_takes_the_jobs_id_and_file() {
+        let parsed_from = PathBuf::from("first.pdf");
+        let mut document = Document::new("first".into(), parsed_from.clone(), FileType::Pdf);
+        let mut page = boomaga_core::Page::new(1, 595.0, 842.0, boomaga_core::Orientation::Portrait);
+        page.source = Some(boomaga_core::PageSource { path: parsed_from, index: 0 });
+        document.add_page(page);
+        let cached = CachedDocument { document };
+        let job_id = JobId::new();
+
+        let bound = cached.bind(&job_id, Path::new("second.pdf"));
+
+        assert_eq!(bound.id, job_id.to_string());
+        assert_eq!(bound.file_path, PathBuf::from("second.pdf"));
+        assert_eq!(bound.pages[0].source.as_ref().unwrap().path, PathBuf::from("second.pdf"));
+        assert_eq!(cached.document.id, "first");
+    }
+
+    #[tokio::test]
+    async fn file_key_matches_the_contents_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.pdf");
+        let contents = vec![7u8; 100_000];
+        std::fs::write(&path, &contents).unwrap();
+
+        let key = DocumentCache::key_for_file(&path).await.unwrap();
+
+        assert_eq!(key, blake3::hash(&contents).to_hex().to_string());
+    }
+
+    #[test]
+    fn evicts_oldest_entry_when_full() {
+        let mut cache = DocumentCache::new(10, DEFAULT_CACHE_TTL);
+
+        cache.insert("a".into(), cached("a"), 6);
+        cache.insert("b".into(), cached("b"), 6);
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("b").unwrap().document.id, "b");
+        assert_eq!(cache.statistics().used_bytes, 6);
+    }
+}
@@ -0,0 +1,161 @@
+//! Reachability checks for the downstream printer
+
+use crate::discovery;
+use boomaga_core::{PrinterInfo, PrinterStatus};
+use boomaga_ipc::{Message, MessageDestination, MessagePayload, MessageSource, NotificationSender};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+/// Default time between two probes of the downstream printer
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keeps the status of the downstream printer up to date
+///
+/// Each check sends Get-Printer-Attributes. A printer that does not answer
+/// is offline, and one that reports itself stopped is in error.
+pub struct PrinterMonitor {
+    printer: Arc<RwLock<PrinterInfo>>,
+    interval: Duration,
+    notifications: NotificationSender,
+}
+
+impl PrinterMonitor {
+    /// Create a monitor for a printer
+    pub fn new(printer: PrinterInfo, notifications: NotificationSender) -> Self {
+        Self {
+            printer: Arc::new(RwLock::new(printer)),
+            interval: DEFAULT_HEALTH_CHECK_INTERVAL,
+            notifications,
+        }
+    }
+
+    /// Probe the printer this often
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Get the monitored printer
+    pub fn printer(&self) -> Arc<RwLock<PrinterInfo>> {
+        Arc::clone(&self.printer)
+    }
+
+    /// Probe the printer once and record its status
+    ///
+    /// Status changes are sent to the preview.
+    pub async fn check(&self) -> PrinterStatus {
+        let (name, uri, is_remote) = {
+            let printer = self.printer.read().await;
+            (printer.name.clone(), printer.uri.clone(), printer.is_remote)
+        };
+        let status = match discovery::probe_printer(&name, &uri, is_remote).await.status {
+            PrinterStatus::Stopped => PrinterStatus::Error,
+            status => status,
+        };
+
+        let mut printer = self.printer.write().await;
+        if printer.status == status {
+            debug!(printer = %name, %status, "Printer status unchanged");
+            return status;
+        }
+
+        info!(printer = %name, from = %printer.status, to = %status, "Printer status changed");
+        printer.status = status;
+        let _ = self.notifications.send(Message::new_notification(
+            MessageSource::Backend,
+            MessageDestination::Broadcast,
+            MessagePayload::PrinterInfo {
+                name: printer.name.clone(),
+                description: printer.description.clone(),
+                status: status.to_string(),
+            },
+        ));
+        status
+    }
+
+    /// Probe the printer every interval until cancelled
+    pub async fn run(self, token: CancellationToken) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = ticker.tick() => {
+                    self.check().await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boomaga_core::{PrintOptions, PrinterCapabilities};
+    use std::collections::HashMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answer every Get-Printer-Attributes request with an idle printer
+    async fn mock_printer() -> (String, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ipp://{}/ipp/print", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 4096];
+                let _ = stream.read(&mut request).await.unwrap();
+
+                let mut body = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x04, 0x23];
+                body.extend_from_slice(&13u16.to_be_bytes());
+                body.extend_from_slice(b"printer-state");
+                body.extend_from_slice(&4u16.to_be_bytes());
+                body.extend_from_slice(&3i32.to_be_bytes());
+                body.push(0x03);
+                let header = format!(
+                    "HTTP/1.0 200 OK\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).await.unwrap();
+                stream.write_all(&body).await.unwrap();
+            }
+        });
+        (uri, server)
+    }
+
+    #[tokio::test]
+    async fn unreachable_printer_goes_offline() {
+        let (uri, server) = mock_printer().await;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let printer = PrinterInfo {
+            name: "Office".into(),
+            description: String::new(),
+            uri,
+            is_remote: true,
+            status: PrinterStatus::Idle,
+            capabilities: PrinterCapabilities::default(),
+            default_settings: PrintOptions::default(),
+            attributes: HashMap::new(),
+        };
+        let monitor = PrinterMonitor::new(printer, sender).with_interval(Duration::from_millis(10));
+
+        assert_eq!(monitor.check().await, PrinterStatus::Idle);
+        assert!(receiver.try_recv().is_err());
+
+        // Stop the mock so the port no longer answers
+        server.abort();
+        let _ = server.await;
+
+        assert_eq!(monitor.check().await, PrinterStatus::Offline);
+        assert_eq!(monitor.printer().read().await.status, PrinterStatus::Offline);
+        match receiver.try_recv().unwrap().payload {
+            MessagePayload::PrinterInfo { name, status, .. } => {
+                assert_eq!(name, "Office");
+                assert_eq!(status, "Offline");
+            }
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+    }
+}
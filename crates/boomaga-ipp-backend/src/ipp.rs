@@ -0,0 +1,206 @@
+//! IPP message encoding (RFC 8010) shared by the server and discovery
+
+use std::collections::HashMap;
+
+use boomaga_core::{Error, Result};
+
+// IPP delimiter and value tags (RFC 8010 §3.5)
+pub const OPERATION_ATTRIBUTES_TAG: u8 = 0x01;
+pub const JOB_ATTRIBUTES_TAG: u8 = 0x02;
+pub const END_OF_ATTRIBUTES_TAG: u8 = 0x03;
+pub const PRINTER_ATTRIBUTES_TAG: u8 = 0x04;
+pub const NO_VALUE_TAG: u8 = 0x13;
+pub const INTEGER_TAG: u8 = 0x21;
+pub const BOOLEAN_TAG: u8 = 0x22;
+pub const ENUM_TAG: u8 = 0x23;
+pub const RANGE_OF_INTEGER_TAG: u8 = 0x33;
+pub const BEGIN_COLLECTION_TAG: u8 = 0x34;
+pub const END_COLLECTION_TAG: u8 = 0x37;
+pub const TEXT_TAG: u8 = 0x41;
+pub const NAME_TAG: u8 = 0x42;
+pub const KEYWORD_TAG: u8 = 0x44;
+pub const URI_TAG: u8 = 0x45;
+pub const CHARSET_TAG: u8 = 0x47;
+pub const NATURAL_LANGUAGE_TAG: u8 = 0x48;
+pub const MIME_MEDIA_TYPE_TAG: u8 = 0x49;
+pub const MEMBER_ATTR_NAME_TAG: u8 = 0x4A;
+
+/// One attribute value of a response, with its syntax
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IppValue {
+    Integer(i32),
+    Enum(i32),
+    Keyword(String),
+    Text(String),
+    Name(String),
+    Uri(String),
+    MimeMediaType(String),
+    Charset(String),
+    NaturalLanguage(String),
+}
+
+impl IppValue {
+    /// Get the value tag the value is sent with
+    pub fn tag(&self) -> u8 {
+        match self {
+            IppValue::Integer(_) => INTEGER_TAG,
+            IppValue::Enum(_) => ENUM_TAG,
+            IppValue::Keyword(_) => KEYWORD_TAG,
+            IppValue::Text(_) => TEXT_TAG,
+            IppValue::Name(_) => NAME_TAG,
+            IppValue::Uri(_) => URI_TAG,
+            IppValue::MimeMediaType(_) => MIME_MEDIA_TYPE_TAG,
+            IppValue::Charset(_) => CHARSET_TAG,
+            IppValue::NaturalLanguage(_) => NATURAL_LANGUAGE_TAG,
+        }
+    }
+
+    /// Encode the value as sent on the wire
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            IppValue::Integer(number) | IppValue::Enum(number) => number.to_be_bytes().to_vec(),
+            IppValue::Keyword(value)
+            | IppValue::Text(value)
+            | IppValue::Name(value)
+            | IppValue::Uri(value)
+            | IppValue::MimeMediaType(value)
+            | IppValue::Charset(value)
+            | IppValue::NaturalLanguage(value) => value.as_bytes().to_vec(),
+        }
+    }
+
+    /// Make keyword values from strings
+    pub fn keywords<S: AsRef<str>>(keywords: impl IntoIterator<Item = S>) -> Vec<IppValue> {
+        keywords
+            .into_iter()
+            .map(|keyword| IppValue::Keyword(keyword.as_ref().to_string()))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for IppValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IppValue::Integer(number) | IppValue::Enum(number) => write!(f, "{}", number),
+            IppValue::Keyword(value)
+            | IppValue::Text(value)
+            | IppValue::Name(value)
+            | IppValue::Uri(value)
+            | IppValue::MimeMediaType(value)
+            | IppValue::Charset(value)
+            | IppValue::NaturalLanguage(value) => f.write_str(value),
+        }
+    }
+}
+
+/// Append one attribute value; an empty name continues the previous attribute
+pub fn encode_attribute(buffer: &mut Vec<u8>, tag: u8, name: &str, value: &[u8]) {
+    buffer.push(tag);
+    buffer.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(name.as_bytes());
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+/// Decode the attribute groups of an IPP message into values rendered as strings
+///
+/// Decoding starts after the 8-byte header. Integers and enums become
+/// decimal strings and ranges `low-high`. Collection members are listed
+/// under their path joined with dots, e.g. `media-col.media-size.x-dimension`,
+/// and the collection itself under its name with no values.
+/// Returns the attributes and the offset of the data following them.
+pub fn parse_attributes(data: &[u8]) -> Result<(HashMap<String, Vec<String>>, usize)> {
+    let truncated = || Error::Ipp("Truncated IPP message".to_string());
+
+    let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current = String::new();
+    // Names of the collections being read, outermost first, and the member
+    // whose values come next
+    let mut collections: Vec<String> = Vec::new();
+    let mut member = String::new();
+    let mut offset = 8;
+    loop {
+        let tag = *data.get(offset).ok_or_else(truncated)?;
+        offset += 1;
+        if tag == END_OF_ATTRIBUTES_TAG {
+            break;
+        }
+        if tag < 0x10 {
+            // Start of an attribute group
+            continue;
+        }
+
+        let read_u16 = |at: usize| -> Result<usize> {
+            data.get(at..at + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+                .ok_or_else(truncated)
+        };
+        let name_length = read_u16(offset)?;
+        let name = data.get(offset + 2..offset + 2 + name_length).ok_or_else(truncated)?;
+        offset += 2 + name_length;
+        let value_length = read_u16(offset)?;
+        let value = data.get(offset + 2..offset + 2 + value_length).ok_or_else(truncated)?;
+        offset += 2 + value_length;
+
+        match tag {
+            BEGIN_COLLECTION_TAG => {
+                if collections.is_empty() {
+                    if !name.is_empty() {
+                        current = String::from_utf8_lossy(name).into_owned();
+                    }
+                    attributes.entry(current.clone()).or_default();
+                    collections.push(current.clone());
+                } else {
+                    collections.push(member.clone());
+                }
+                continue;
+            }
+            END_COLLECTION_TAG => {
+                // Further values continue the member holding the collection
+                if let Some(name) = collections.pop() {
+                    member = name;
+                }
+                continue;
+            }
+            MEMBER_ATTR_NAME_TAG if !collections.is_empty() => {
+                member = String::from_utf8_lossy(value).into_owned();
+                continue;
+            }
+            _ if !collections.is_empty() => {
+                let path = format!("{}.{}", collections.join("."), member);
+                let values = attributes.entry(path).or_default();
+                if tag >= 0x20 {
+                    values.push(render_value(tag, value));
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        if !name.is_empty() {
+            current = String::from_utf8_lossy(name).into_owned();
+        }
+        // Out-of-band values (unknown, no-value, ...) carry nothing
+        if tag < 0x20 {
+            attributes.entry(current.clone()).or_default();
+            continue;
+        }
+        attributes.entry(current.clone()).or_default().push(render_value(tag, value));
+    }
+
+    Ok((attributes, offset))
+}
+
+/// Render one attribute value as a string
+fn render_value(tag: u8, value: &[u8]) -> String {
+    match (tag, value) {
+        (INTEGER_TAG | ENUM_TAG, [a, b, c, d]) => i32::from_be_bytes([*a, *b, *c, *d]).to_string(),
+        (BOOLEAN_TAG, [flag]) => (*flag != 0).to_string(),
+        (RANGE_OF_INTEGER_TAG, [a, b, c, d, e, f, g, h]) => format!(
+            "{}-{}",
+            i32::from_be_bytes([*a, *b, *c, *d]),
+            i32::from_be_bytes([*e, *f, *g, *h])
+        ),
+        _ => String::from_utf8_lossy(value).into_owned(),
+    }
+}
@@ -1,22 +1,92 @@
 //! Print job processor
 
+use crate::document_cache::{CachedDocument, DocumentCache};
 use crate::job_queue::JobQueue;
-use boomaga_core::{Error, JobId, JobStatus, PrintJobRequest};
-use boomaga_ipc::{Message, MessageDestination, MessagePayload, MessageSource, NotificationSender};
-use std::collections::HashMap;
+use crate::metrics::DurationHistogram;
+use boomaga_config::{
+    BackendConfig, PerformanceSettings, RenderQuality, RenderSettings, RetryPolicy, UnsupportedDuplex,
+};
+use boomaga_core::error::ErrorSeverity;
+use boomaga_core::{
+    ColorMode, Document, DuplexMode, Error, FileType, JobId, JobMetadata, JobStatistics, JobStatus, JobSummary, Page,
+    PageContents, PageSize, PrintBatch, PrintJobRequest, PrintOptions, PrinterInfo, PrinterStatus,
+};
+use boomaga_layout_engine::{image_page, DuplexPadding};
+use boomaga_ipc::{ImageFormat, Message, MessageDestination, MessagePayload, MessageSource, NotificationSender};
+#[cfg(target_os = "linux")]
+use boomaga_ipc::shm;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, Notify, RwLock, Semaphore};
 use tokio::task::JoinSet;
-use tracing::{debug, error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Largest rendered page, in bytes of RGBA pixels, sent inline as is
+const INLINE_PAGE_BYTES: usize = 64 * 1024;
+
+/// Cancellation tokens of the jobs that have not finished yet
+type Cancellations = RwLock<HashMap<String, CancellationToken>>;
+
+/// Wake-ups of the manual duplex jobs held between their two passes
+type Pauses = RwLock<HashMap<String, Arc<Notify>>>;
 
 /// Job processor
 #[derive(Clone)]
 pub struct JobProcessor {
     queue: Arc<JobQueue>,
-    max_concurrent: usize,
+    /// Jobs processed at once; a worker holds one permit per job
+    slots: Arc<Semaphore>,
+    /// Number of permits `slots` is sized for
+    pub(crate) max_concurrent: Arc<Mutex<usize>>,
     worker_threads: usize,
-    jobs: Arc<RwLock<HashMap<String, JobStatus>>>,
+    /// Whether the workers have been started
+    started: Arc<AtomicBool>,
+    /// Metadata of every job, by job id
+    pub(crate) jobs: Arc<RwLock<HashMap<String, JobMetadata>>>,
+    /// Next integer `job-id` handed to an IPP client
+    next_ipp_job_id: Arc<AtomicI32>,
     notifications: NotificationSender,
+    cache: Arc<Mutex<DocumentCache>>,
+    cancellations: Arc<Cancellations>,
+    pauses: Arc<Pauses>,
+    /// Processing times of the jobs completed so far
+    durations: Arc<std::sync::Mutex<DurationHistogram>>,
+    settings: JobSettings,
+    /// Printer the jobs go to, whose capabilities jobs must fit
+    downstream: Option<Arc<RwLock<PrinterInfo>>>,
+}
+
+/// Settings applied while processing a job
+#[derive(Debug, Clone)]
+struct JobSettings {
+    /// Largest spooled documents accepted, in bytes
+    max_job_size: u64,
+    /// Pages rendered at once
+    render_threads: usize,
+    /// Quality limit applied to each job's print quality
+    render_quality: RenderQuality,
+    /// Directory holding spooled documents, removed once their job ends
+    spool_dir: PathBuf,
+    /// When failed jobs are tried again
+    retry: RetryPolicy,
+    /// What to do with two-sided jobs the downstream printer prints one-sided
+    unsupported_duplex: UnsupportedDuplex,
+}
+
+/// How the pages of a job are rendered
+#[derive(Debug, Clone)]
+struct RenderJob {
+    job_id: JobId,
+    color_mode: ColorMode,
+    render: RenderSettings,
+    /// Numbers of the pages copied to the output unchanged, which are not
+    /// rasterized
+    passthrough: HashSet<usize>,
+    /// Pages rendered at once
+    threads: usize,
 }
 
 /// Job processing context
@@ -47,101 +117,397 @@ impl JobProcessor {
 
         Ok(Self {
             queue,
-            max_concurrent,
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent: Arc::new(Mutex::new(max_concurrent)),
             worker_threads,
+            started: Arc::new(AtomicBool::new(false)),
             jobs: Arc::new(RwLock::new(HashMap::new())),
+            next_ipp_job_id: Arc::new(AtomicI32::new(1)),
             notifications,
+            cache: Arc::new(Mutex::new(DocumentCache::from_config(&BackendConfig::default()))),
+            cancellations: Arc::new(RwLock::new(HashMap::new())),
+            pauses: Arc::new(RwLock::new(HashMap::new())),
+            durations: Arc::new(std::sync::Mutex::new(DurationHistogram::default())),
+            settings: JobSettings {
+                max_job_size: boomaga_core::constants::DEFAULT_MAX_JOB_SIZE,
+                render_threads: worker_threads,
+                render_quality: PerformanceSettings::default().render_quality,
+                spool_dir: std::env::temp_dir().join("boomaga-spool"),
+                retry: RetryPolicy::default(),
+                unsupported_duplex: UnsupportedDuplex::default(),
+            },
+            downstream: None,
         })
     }
 
+    /// Use the given document cache
+    pub fn with_document_cache(mut self, cache: DocumentCache) -> Self {
+        self.cache = Arc::new(Mutex::new(cache));
+        self
+    }
+
+    /// Fail jobs whose spooled documents exceed `max_job_size` bytes
+    pub fn with_max_job_size(mut self, max_job_size: u64) -> Self {
+        self.settings.max_job_size = max_job_size;
+        self
+    }
+
+    /// Render pages at no more than `render_quality` allows
+    pub fn with_render_quality(mut self, render_quality: RenderQuality) -> Self {
+        self.settings.render_quality = render_quality;
+        self
+    }
+
+    /// Write spooled documents into `spool_dir`
+    pub fn with_spool_dir(mut self, spool_dir: PathBuf) -> Self {
+        self.settings.spool_dir = spool_dir;
+        self
+    }
+
+    /// Retry jobs failing with transient errors as `retry` says
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.settings.retry = retry;
+        self
+    }
+
+    /// Check jobs against the capabilities of the printer they go to
+    ///
+    /// Two-sided jobs for a printer that only prints one-sided are handled
+    /// as `unsupported_duplex` says.
+    pub fn with_downstream_printer(
+        mut self,
+        printer: Arc<RwLock<PrinterInfo>>,
+        unsupported_duplex: UnsupportedDuplex,
+    ) -> Self {
+        self.downstream = Some(printer);
+        self.settings.unsupported_duplex = unsupported_duplex;
+        self
+    }
+
+    /// Store a submitted document in the spool directory
+    ///
+    /// The file is named after the job. Returns its path.
+    pub async fn spool(&self, job_id: &JobId, file_type: FileType, data: &[u8]) -> Result<PathBuf, Error> {
+        tokio::fs::create_dir_all(&self.settings.spool_dir).await?;
+        let extension = match file_type {
+            FileType::Pdf => "pdf",
+            FileType::PwgRaster => "pwg",
+            FileType::Jpeg => "jpg",
+            FileType::PostScript => "ps",
+        };
+        let path = self.settings.spool_dir.join(format!("{}.{}", job_id, extension));
+        tokio::fs::write(&path, data).await?;
+        debug!(job_id = %job_id, path = %path.display(), bytes = data.len(), "Spooled document");
+        Ok(path)
+    }
+
     /// Add a job to the queue
-    pub async fn add_job(&self, request: PrintJobRequest) -> Result<(), Error> {
+    #[instrument(skip_all, fields(job_id = %request.job_id))]
+    pub async fn add_job(&self, mut request: PrintJobRequest) -> Result<(), Error> {
         request.options.validate()?;
+        if let Some(printer) = &self.downstream {
+            let printer = printer.read().await;
+            let sides = downstream_sides(&printer, request.options.duplex, self.settings.unsupported_duplex)?;
+            if sides != request.options.duplex {
+                info!(printer = %printer.name, ?sides, "Printer has no duplex unit, printing two-sided by hand");
+                request.options.manual_duplex = true;
+            }
+        }
 
         let job_id = request.job_id.to_string();
         let notification_job_id = request.job_id.clone();
+        let mut metadata = JobMetadata::from_request(&request);
+        metadata.ipp_job_id = self.next_ipp_job_id.fetch_add(1, Ordering::SeqCst);
+
+        // Hash the document so identical resubmissions reuse the parsed copy
+        match DocumentCache::key_for_file(&request.file_path).await {
+            Ok(key) => metadata.content_hash = Some(key),
+            Err(e) => debug!(error = %e, "Not hashing job document"),
+        }
 
-        info!("Adding job {} to queue", job_id);
+        let changes = PrintOptions::default().diff(&request.options);
+        info!(?changes, "Adding job to queue");
 
-        // Add to queue
+        // Add to queue, behind earlier jobs of the same priority
         let queue_clone = Arc::clone(&self.queue);
-        queue_clone.push(request).await?;
+        queue_clone
+            .push_with_priority(request, metadata.priority, metadata.created_at)
+            .await?;
 
         // Update job status
         {
             let mut jobs = self.jobs.write().await;
-            jobs.insert(job_id, JobStatus::Queued);
+            jobs.insert(job_id.clone(), metadata);
         }
+        self.cancellations
+            .write()
+            .await
+            .insert(job_id, CancellationToken::new());
         Self::notify(&self.notifications, notification_job_id, JobStatus::Queued);
 
-        // Spawn worker task
-        for _ in 0..self.worker_threads {
-            let queue = Arc::clone(&self.queue);
-            let jobs = Arc::clone(&self.jobs);
-            let notifications = self.notifications.clone();
+        Ok(())
+    }
 
-            tokio::spawn(async move {
-                Self::process_queue(queue, jobs, notifications).await;
-            });
+    /// Add several documents as one job, printed in order
+    ///
+    /// Pages are numbered continuously across the documents, so the page
+    /// range and layout apply to the merged document.
+    pub async fn add_batch(&self, batch: PrintBatch) -> Result<JobId, Error> {
+        let job_id = JobId::new();
+        self.add_job(batch.request(job_id.clone())?).await?;
+        Ok(job_id)
+    }
+
+    /// Track the jobs the queue restored from its journal
+    ///
+    /// Call at startup, before any job is added and before [`Self::start`].
+    /// Returns how many jobs were restored.
+    pub async fn recover_queued(&self) -> usize {
+        let mut recovered = 0;
+        for (job, held) in self.queue.waiting_jobs().await {
+            let job_id = job.request.job_id.to_string();
+            if self.jobs.read().await.contains_key(&job_id) {
+                continue;
+            }
+
+            let status = if held { JobStatus::Held } else { JobStatus::Queued };
+            let mut metadata = JobMetadata::from_request(&job.request);
+            metadata.ipp_job_id = self.next_ipp_job_id.fetch_add(1, Ordering::SeqCst);
+            metadata.priority = job.priority;
+            metadata.created_at = job.created_at;
+            metadata.status = status;
+            self.jobs.write().await.insert(job_id.clone(), metadata);
+            self.cancellations
+                .write()
+                .await
+                .insert(job_id, CancellationToken::new());
+            Self::notify(&self.notifications, job.request.job_id, status);
+            recovered += 1;
+        }
+
+        if recovered > 0 {
+            info!(recovered, "Recovered queued jobs");
+        }
+        recovered
+    }
+
+    /// Change how many jobs are processed at once
+    ///
+    /// Raising the limit frees slots at once. Lowering it takes slots away as
+    /// the jobs holding them finish; running jobs are not interrupted.
+    pub async fn set_max_concurrent(&self, max_concurrent: usize) -> Result<(), Error> {
+        if max_concurrent == 0 {
+            return Err(Error::Validation(
+                "Max concurrent jobs must be greater than 0".into(),
+            ));
         }
 
+        let mut current = self.max_concurrent.lock().await;
+        if max_concurrent > *current {
+            self.slots.add_permits(max_concurrent - *current);
+        } else if max_concurrent < *current {
+            let excess = *current - max_concurrent;
+            let forgotten = self.slots.forget_permits(excess);
+            if forgotten < excess {
+                // The rest are held by running jobs; drop them once released
+                let slots = Arc::clone(&self.slots);
+                let held = (excess - forgotten) as u32;
+                tokio::spawn(async move {
+                    if let Ok(permits) = slots.acquire_many_owned(held).await {
+                        permits.forget();
+                    }
+                });
+            }
+        }
+        info!(from = *current, to = max_concurrent, "Changed the concurrent job limit");
+        *current = max_concurrent;
         Ok(())
     }
 
+    /// Start the workers taking jobs off the queue
+    ///
+    /// Starts `worker_threads` workers, of which at most `max_concurrent`
+    /// process a job at once. The workers run for the lifetime of the
+    /// runtime; calling this again does nothing.
+    pub fn start(&self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        for _ in 0..self.worker_threads {
+            let worker = self.clone();
+            tokio::spawn(async move { worker.process_queue().await });
+        }
+        debug!(workers = self.worker_threads, "Started job workers");
+    }
+
     /// Process job queue
-    async fn process_queue(
-        queue: Arc<JobQueue>,
-        jobs: Arc<RwLock<HashMap<String, JobStatus>>>,
-        notifications: NotificationSender,
-    ) {
-        let mut running = true;
-
-        while running {
-            // Wait for job to be available
-            let queue_clone = Arc::clone(&queue);
-            match queue_clone.pop().await {
-                Ok(request) => {
-                    let job_id = request.job_id.to_string();
-                    let notification_job_id = request.job_id.clone();
-
-                    // Update status to processing
-                    {
-                        let mut jobs = jobs.write().await;
-                        jobs.insert(job_id.clone(), JobStatus::Processing);
-                    }
-                    Self::notify(
-                        &notifications,
-                        notification_job_id.clone(),
-                        JobStatus::Processing,
-                    );
-
-                    info!("Processing job {}", job_id);
-
-                    // Process job
-                    match Self::process_job(request).await {
-                        Ok(_) => {
-                            info!("Job {} completed successfully", job_id);
-                            {
-                                let mut jobs = jobs.write().await;
-                                jobs.insert(job_id, JobStatus::Completed);
-                            }
-                            Self::notify(&notifications, notification_job_id, JobStatus::Completed);
-                        }
-                        Err(e) => {
-                            error!("Job {} failed: {}", job_id, e);
-                            {
-                                let mut jobs = jobs.write().await;
-                                jobs.insert(job_id, JobStatus::Failed);
-                            }
-                            Self::notify(&notifications, notification_job_id, JobStatus::Failed);
-                        }
-                    }
+    async fn process_queue(self) {
+        loop {
+            // Wait for a free slot, then for a job to fill it
+            let Ok(_permit) = Arc::clone(&self.slots).acquire_owned().await else {
+                break;
+            };
+            match self.queue.pop().await {
+                Ok(request) => self.run_job(request).await,
+                Err(e) => {
+                    warn!(error = %e, "Job worker stopping");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Run one dequeued job through to a final status
+    #[instrument(name = "job", skip_all, fields(job_id = %request.job_id))]
+    async fn run_job(&self, request: PrintJobRequest) {
+        let Self { jobs, notifications, cache, cancellations, pauses, settings, .. } = self;
+        let job_id = request.job_id.to_string();
+        let notification_job_id = request.job_id.clone();
+        let token = cancellations
+            .read()
+            .await
+            .get(&job_id)
+            .cloned()
+            .unwrap_or_default();
+
+        // Update status to processing, unless the job was purged meanwhile
+        Self::transition(jobs, notifications, &notification_job_id, JobStatus::Processing).await;
+
+        info!("Processing job");
+
+        // Process job, retrying transient failures
+        let file_path = request.file_path.clone();
+        let mut attempt = 1;
+        let result = loop {
+            let result =
+                Self::process_job(request.clone(), jobs, cache, notifications, &token, pauses, settings).await;
+            let retry = match &result {
+                Err(e) if !token.is_cancelled() && settings.retry.should_retry(e, attempt) => {
+                    log_by_severity(e, attempt, "Job attempt failed, retrying");
+                    true
+                }
+                _ => false,
+            };
+            if !retry {
+                break result;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(settings.retry.delay(attempt)) => {}
+                _ = token.cancelled() => break result,
+            }
+            attempt += 1;
+        };
+        let status = match result {
+            Ok(statistics) => {
+                info!(
+                    pages = statistics.pages_processed,
+                    bytes = statistics.bytes_processed,
+                    "Job completed successfully"
+                );
+                if let Ok(mut durations) = self.durations.lock() {
+                    durations.observe(statistics.duration.as_secs_f64());
                 }
-                Err(_) => {
-                    // Queue is empty
-                    running = false;
+                if let Some(job) = jobs.write().await.get_mut(&job_id) {
+                    job.statistics = Some(statistics);
+                }
+                JobStatus::Completed
+            }
+            Err(_) if token.is_cancelled() => {
+                info!("Job cancelled, discarding partial output");
+                JobStatus::Cancelled
+            }
+            Err(e) => {
+                log_by_severity(&e, attempt, "Job failed");
+                JobStatus::Failed
+            }
+        };
+
+        Self::remove_spooled(&settings.spool_dir, &file_path).await;
+        Self::transition(jobs, notifications, &notification_job_id, status).await;
+        cancellations.write().await.remove(&job_id);
+    }
+
+    /// Delete a job's document if it was spooled by the backend
+    ///
+    /// Documents outside the spool directory belong to the submitter and
+    /// are left alone.
+    async fn remove_spooled(spool_dir: &Path, file_path: &Path) {
+        if !file_path.starts_with(spool_dir) {
+            return;
+        }
+        match tokio::fs::remove_file(file_path).await {
+            Ok(()) => debug!(path = %file_path.display(), "Removed spooled document"),
+            Err(e) => warn!(path = %file_path.display(), error = %e, "Failed to remove spooled document"),
+        }
+    }
+
+    /// Delete spooled documents left behind by an earlier run
+    ///
+    /// Documents of jobs still waiting in the queue, such as ones restored
+    /// from its journal, are kept. Call at startup, before any job is
+    /// spooled. Returns how many files were removed.
+    pub async fn sweep_spool(&self) -> Result<usize, Error> {
+        let mut entries = match tokio::fs::read_dir(&self.settings.spool_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let waiting: HashSet<PathBuf> = self
+            .queue
+            .waiting_jobs()
+            .await
+            .into_iter()
+            .flat_map(|(job, _)| std::iter::once(job.request.file_path).chain(job.request.batch_documents))
+            .collect();
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_file() && !waiting.contains(&entry.path()) {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            info!(removed, spool_dir = %self.settings.spool_dir.display(), "Removed orphaned spool files");
+        }
+        Ok(removed)
+    }
+
+    /// Update the stored status of a job
+    ///
+    /// Final statuses stick, so a job aborted by a purge is not reported as
+    /// processing or completed afterwards. Returns whether the status changed.
+    async fn set_status(
+        jobs: &RwLock<HashMap<String, JobMetadata>>,
+        job_id: &str,
+        status: JobStatus,
+    ) -> bool {
+        let mut jobs = jobs.write().await;
+        match jobs.get_mut(job_id) {
+            Some(job) if job.status.is_terminal() => false,
+            Some(job) => {
+                job.status = status;
+                if status.is_terminal() {
+                    job.completed_at = Some(std::time::SystemTime::now());
                 }
+                true
             }
+            None => true,
+        }
+    }
+
+    /// Update the stored status of a job and tell the preview if it changed
+    async fn transition(
+        jobs: &RwLock<HashMap<String, JobMetadata>>,
+        notifications: &NotificationSender,
+        job_id: &JobId,
+        status: JobStatus,
+    ) {
+        if Self::set_status(jobs, &job_id.to_string(), status).await {
+            Self::notify(notifications, job_id.clone(), status);
         }
     }
 
@@ -153,64 +519,1477 @@ impl JobProcessor {
         ));
     }
 
+    /// Build the notification streaming a rendered page to the preview
+    ///
+    /// Small pages carry their RGBA pixels inline. Larger ones travel in
+    /// shared memory where the platform allows it, or else as a PNG image,
+    /// so that they stay well within the transport's message size limit.
+    fn page_notification(job_id: JobId, page: &Page) -> Result<Message, Error> {
+        let PageContents::Raster { width, height, data, .. } = &page.contents else {
+            return Err(Error::Render(format!("Page {} was not rendered to pixels", page.number)));
+        };
+        let (format, image_data) = if data.len() <= INLINE_PAGE_BYTES {
+            (ImageFormat::Rgba8, Arc::clone(data))
+        } else {
+            #[cfg(target_os = "linux")]
+            match shm::page_rendered_message(job_id.clone(), page.number, *width, *height, *width * 4, data) {
+                Ok(message) => return Ok(message),
+                Err(e) => debug!(error = %e, "Shared memory unavailable, sending page as PNG"),
+            }
+            Self::large_page_image(page)?
+        };
+        Ok(Message::new_notification(
+            MessageSource::Backend,
+            MessageDestination::Preview,
+            MessagePayload::PageRendered {
+                job_id,
+                page_number: page.number,
+                width: *width,
+                height: *height,
+                format,
+                image_data,
+            },
+        ))
+    }
+
+    /// Encode a page too large to send inline as PNG
+    #[cfg(feature = "render")]
+    fn large_page_image(page: &Page) -> Result<(ImageFormat, Arc<[u8]>), Error> {
+        Ok((ImageFormat::Png, Arc::from(page.contents.to_png()?)))
+    }
+
+    /// Send a page too large to send inline as raw pixels, having no PNG encoder
+    #[cfg(not(feature = "render"))]
+    fn large_page_image(page: &Page) -> Result<(ImageFormat, Arc<[u8]>), Error> {
+        match &page.contents {
+            PageContents::Raster { data, .. } => Ok((ImageFormat::Rgba8, Arc::clone(data))),
+            _ => Err(Error::Render(format!("Page {} was not rendered to pixels", page.number))),
+        }
+    }
+
     /// Process a single job
-    async fn process_job(request: PrintJobRequest) -> Result<(), Error> {
-        // Simulate job processing
-        // In production, this would:
-        // 1. Parse document
-        // 2. Render pages
-        // 3. Apply layout transformations
-        // 4. Create preview window
-        // 5. Wait for user action
+    ///
+    /// Manual duplex jobs are held once their fronts are printed, until
+    /// they are released.
+    async fn process_job(
+        request: PrintJobRequest,
+        jobs: &RwLock<HashMap<String, JobMetadata>>,
+        cache: &Mutex<DocumentCache>,
+        notifications: &NotificationSender,
+        token: &CancellationToken,
+        pauses: &Pauses,
+        settings: &JobSettings,
+    ) -> Result<JobStatistics, Error> {
+        if token.is_cancelled() {
+            return Err(Self::cancelled());
+        }
+
+        let started = std::time::Instant::now();
+        let content_hash = jobs
+            .read()
+            .await
+            .get(&request.job_id.to_string())
+            .and_then(|job| job.content_hash.clone());
+
+        // Refuse oversized jobs before spending time parsing them
+        let spooled = Self::spooled_size(&request.file_path, &request.batch_documents).await;
+        if spooled > settings.max_job_size {
+            return Err(Error::Validation(format!(
+                "Job is {} bytes, larger than the {} byte limit",
+                spooled, settings.max_job_size
+            )));
+        }
+
+        let cached = match &content_hash {
+            Some(key) => cache.lock().await.get(key),
+            None => None,
+        };
+        let cached = match cached {
+            Some(cached) => {
+                debug!("Reusing cached document");
+                cached
+            }
+            None => {
+                let parsed = Arc::new(Self::parse_document(&request).await?);
+                if let Some(key) = content_hash {
+                    let size = tokio::fs::metadata(&request.file_path)
+                        .await
+                        .map(|metadata| metadata.len() as usize)
+                        .unwrap_or(0);
+                    let mut cache = cache.lock().await;
+                    cache.insert(key, Arc::clone(&parsed), size);
+                    debug!(statistics = ?cache.statistics(), "Cached parsed document");
+                }
+                parsed
+            }
+        };
+        let document = cached.bind(&request.job_id, &request.file_path);
+        let (document, section_starts) = if request.batch_documents.is_empty() {
+            (document, Vec::new())
+        } else {
+            Self::merge_batch(&request, document).await?
+        };
+        let (document, section_starts) = Self::select_range(document, section_starts, request.options.page_range)?;
+        // The range is applied, so later steps take every remaining page
+        let options = PrintOptions { page_range: None, ..request.options.clone() };
+        let document = Self::pad_for_duplex(document, &options, section_starts);
+        let bytes = document.total_size_bytes();
+        debug!(pages = document.page_count(), bytes, "Document ready");
+
+        let render = settings.render_quality.render_settings(request.options.print_quality);
+        debug!(dpi = render.dpi, antialias = render.antialias, dither = render.dither, "Render settings");
+        let job = RenderJob {
+            job_id: request.job_id.clone(),
+            color_mode: request.options.color_mode,
+            render,
+            passthrough: Self::passthrough(&document, &options)?,
+            threads: settings.render_threads,
+        };
+        let pages = if request.options.manual_duplex {
+            let passes = boomaga_layout_engine::manual_duplex_passes(document.page_count());
+            let fronts = Self::select_pages(&document, &passes.fronts);
+            let backs = Self::select_pages(&document, &passes.backs);
+            let mut pages = Self::render_pages(&fronts, &job, notifications, token).await?;
+            if !backs.is_empty() {
+                Self::pause_for_backs(&request.job_id, passes.fronts.len(), jobs, notifications, token, pauses).await?;
+                pages += Self::render_pages(&backs, &job, notifications, token).await?;
+            }
+            pages
+        } else {
+            Self::render_pages(&document, &job, notifications, token).await?
+        };
+        if token.is_cancelled() {
+            return Err(Self::cancelled());
+        }
+
+        let duration = started.elapsed();
+        Ok(JobStatistics {
+            job_id: request.job_id,
+            duration,
+            pages_processed: pages,
+            bytes_processed: bytes,
+            success_rate: 1.0,
+            average_processing_time_per_page: duration.checked_div(pages as u32).unwrap_or_default(),
+        })
+    }
+
+    /// Total size of a job's spooled documents in bytes
+    ///
+    /// Documents that cannot be read count as empty; parsing reports them.
+    async fn spooled_size(file_path: &Path, batch_documents: &[PathBuf]) -> u64 {
+        let mut size = 0;
+        for path in std::iter::once(file_path).chain(batch_documents.iter().map(PathBuf::as_path)) {
+            size += tokio::fs::metadata(path).await.map(|metadata| metadata.len()).unwrap_or(0);
+        }
+        size
+    }
+
+    /// Get the numbers of the pages a plain 1-up job copies to the output
+    /// unchanged, skipping their rasterization
+    ///
+    /// Empty when the job needs imposition or transforms.
+    fn passthrough(document: &Document, options: &PrintOptions) -> Result<HashSet<usize>, Error> {
+        if document.is_empty() || boomaga_layout_engine::passthrough_pages(document, options)?.is_none() {
+            return Ok(HashSet::new());
+        }
+        let (start, end) = options.page_range.unwrap_or((1, document.page_count()));
+        debug!(pages = end + 1 - start, "Skipping rasterization for passthrough pages");
+        Ok((start..=end).collect())
+    }
+
+    /// Get a copy of a document holding the given pages in the given order
+    ///
+    /// Page numbers count from 1; [`boomaga_layout_engine::BLANK_PAGE`] and
+    /// numbers past the end are skipped.
+    fn select_pages(document: &Document, numbers: &[usize]) -> Document {
+        let mut selected = document.clone();
+        selected.pages = numbers
+            .iter()
+            .filter_map(|number| number.checked_sub(1).and_then(|index| document.pages.get(index)))
+            .cloned()
+            .collect();
+        selected
+    }
+
+    /// Hold a manual duplex job until the user has turned its printed
+    /// fronts over, fed them back in and released the job
+    ///
+    /// The preview is asked to tell the user what to do.
+    async fn pause_for_backs(
+        job_id: &JobId,
+        sheets: usize,
+        jobs: &RwLock<HashMap<String, JobMetadata>>,
+        notifications: &NotificationSender,
+        token: &CancellationToken,
+        pauses: &Pauses,
+    ) -> Result<(), Error> {
+        let resume = Arc::new(Notify::new());
+        pauses.write().await.insert(job_id.to_string(), Arc::clone(&resume));
+        Self::transition(jobs, notifications, job_id, JobStatus::Held).await;
+        let _ = notifications.send(Message::new_notification(
+            MessageSource::Backend,
+            MessageDestination::Preview,
+            MessagePayload::ManualDuplexPrompt { job_id: job_id.clone(), sheets },
+        ));
+        info!(sheets, "Fronts printed, waiting for the sheets to be fed back in");
+
+        let resumed = tokio::select! {
+            _ = resume.notified() => true,
+            _ = token.cancelled() => false,
+        };
+        pauses.write().await.remove(&job_id.to_string());
+        if !resumed {
+            return Err(Self::cancelled());
+        }
+
+        Self::transition(jobs, notifications, job_id, JobStatus::Processing).await;
+        Ok(())
+    }
+
+    /// Render a document on up to `job.threads` blocking threads, stopping
+    /// at the first page boundary after the job is cancelled
+    ///
+    /// Finished pages are streamed to the preview in document order.
+    /// Passthrough pages are neither rasterized nor streamed. The first page
+    /// that fails to render fails the whole document. Returns the number of
+    /// pages done, passthrough pages included.
+    #[instrument(
+        name = "render",
+        level = "debug",
+        skip_all,
+        fields(pages = document.page_count(), threads = job.threads)
+    )]
+    async fn render_pages(
+        document: &Document,
+        job: &RenderJob,
+        notifications: &NotificationSender,
+        token: &CancellationToken,
+    ) -> Result<usize, Error> {
+        let started = (!tracing::Span::current().is_disabled()).then(std::time::Instant::now);
+        let slots = Arc::new(Semaphore::new(job.threads.max(1)));
+        let mut pending = VecDeque::new();
+        let mut rendered = 0;
+
+        for page in &document.pages {
+            if token.is_cancelled() {
+                debug!(rendered, total = document.page_count(), "Render cancelled");
+                return Err(Self::cancelled());
+            }
+            if job.passthrough.contains(&page.number) {
+                rendered += 1;
+                continue;
+            }
+
+            let slot = Arc::clone(&slots)
+                .acquire_owned()
+                .await
+                .map_err(|e| Error::Render(e.to_string()))?;
+            let (page, job_id, color_mode, render) = (page.clone(), job.job_id.clone(), job.color_mode, job.render);
+            pending.push_back(tokio::task::spawn_blocking(move || {
+                let _slot = slot;
+                Self::render_page(page, color_mode, render).and_then(|page| Self::page_notification(job_id, &page))
+            }));
+
+            // Stream the pages that are done, keeping document order
+            while pending.front().is_some_and(|task| task.is_finished()) {
+                if let Some(task) = pending.pop_front() {
+                    Self::finish_page(task, notifications).await?;
+                    rendered += 1;
+                }
+            }
+        }
 
-        debug!("Processing job: {:?}", request);
+        for task in pending {
+            if token.is_cancelled() {
+                debug!(rendered, total = document.page_count(), "Render cancelled");
+                return Err(Self::cancelled());
+            }
+            Self::finish_page(task, notifications).await?;
+            rendered += 1;
+        }
 
-        // Simulate processing time
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        if let Some(started) = started {
+            debug!(rendered, elapsed = ?started.elapsed(), "Rendered");
+        }
+        Ok(rendered)
+    }
 
+    /// Wait for a page render and stream the page to the preview
+    async fn finish_page(
+        task: tokio::task::JoinHandle<Result<Message, Error>>,
+        notifications: &NotificationSender,
+    ) -> Result<(), Error> {
+        let notification = task.await.map_err(|e| Error::Render(e.to_string()))??;
+        let _ = notifications.send(notification);
         Ok(())
     }
 
+    /// Render one page in the requested color mode
+    ///
+    /// Pages read from a PDF, and pages that are not raster yet, are drawn
+    /// once at the job's resolution, antialiased unless the settings say
+    /// otherwise. The pixels are repacked as RGBA rows
+    /// without padding. Fails with the page number when the page cannot be
+    /// drawn or its raster data is shorter than its dimensions and stride
+    /// need.
+    fn render_page(page: Page, color_mode: ColorMode, render: RenderSettings) -> Result<Page, Error> {
+        debug!(page = page.number, dpi = render.dpi, "Rendering page");
+        let number = page.number;
+        let page_error = |error| match error {
+            Error::Render(message) => Error::Render(format!("Page {}: {}", number, message)),
+            error => error,
+        };
+        let mut page = page;
+        if page.source.is_some() || !matches!(page.contents, PageContents::Raster { .. }) {
+            page.contents = Self::rasterize(&page, render).map_err(page_error)?;
+            page.source = None;
+        }
+        page.contents.pack_rgba().map_err(page_error)?;
+        if let PageContents::Raster { width, data, .. } = &mut page.contents {
+            if let Some(converted) = Self::convert_colors(data, *width, color_mode, render.dither) {
+                *data = converted;
+            }
+        }
+        Ok(page)
+    }
+
+    /// Draw a PDF or vector page into pixels
+    #[cfg(feature = "render")]
+    fn rasterize(page: &Page, render: RenderSettings) -> Result<PageContents, Error> {
+        page.render_to_raster(render.dpi as f32, render.antialias)
+    }
+
+    /// Refuse to draw pages, the backend being built without `render`
+    #[cfg(not(feature = "render"))]
+    fn rasterize(_page: &Page, _render: RenderSettings) -> Result<PageContents, Error> {
+        Err(Error::Unsupported("this backend was built without page rendering".into()))
+    }
+
+    /// Convert RGBA pixels to gray or black and white
+    ///
+    /// Gray levels use the Rec. 601 luma weights. Black and white pixels are
+    /// thresholded at mid gray, or against a 4x4 Bayer matrix when
+    /// dithering. Alpha is kept. Returns `None` when the mode keeps the
+    /// colors.
+    fn convert_colors(data: &[u8], width: usize, color_mode: ColorMode, dither: bool) -> Option<Arc<[u8]>> {
+        const BAYER: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+        let monochrome = match color_mode {
+            ColorMode::Auto | ColorMode::Color => return None,
+            ColorMode::Grayscale => false,
+            ColorMode::Monochrome => true,
+        };
+
+        let mut pixels = data.to_vec();
+        for (index, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            let luma = (299 * pixel[0] as u32 + 587 * pixel[1] as u32 + 114 * pixel[2] as u32 + 500) / 1000;
+            let gray = if !monochrome {
+                luma as u8
+            } else {
+                let threshold = if dither {
+                    let (x, y) = (index % width.max(1), index / width.max(1));
+                    BAYER[y % 4][x % 4] * 16 + 8
+                } else {
+                    128
+                };
+                if luma < threshold { 0 } else { 255 }
+            };
+            pixel[..3].fill(gray);
+        }
+        Some(Arc::from(pixels))
+    }
+
+    fn cancelled() -> Error {
+        Error::Job("Job cancelled".into())
+    }
+
+    /// Parse a job's document
+    ///
+    /// Pages are rendered later, once each, by [`Self::render_pages`].
+    async fn parse_document(request: &PrintJobRequest) -> Result<CachedDocument, Error> {
+        let mut document =
+            Self::open_document(&request.file_path, Some(request.file_type), &request.options).await?;
+        document.id = request.job_id.to_string();
+        Ok(CachedDocument { document })
+    }
+
+    /// Open one document of a job off the async workers, which parsing
+    /// would block
+    ///
+    /// A photo becomes one page of the job's media, fitted upright. Other
+    /// types are read by the parser registered for them, see
+    /// [`Document::from_path`].
+    async fn open_document(
+        path: &Path,
+        file_type: Option<FileType>,
+        options: &PrintOptions,
+    ) -> Result<Document, Error> {
+        let (path, page_size) = (path.to_path_buf(), options.media.size.unwrap_or_default());
+        let runtime = tokio::runtime::Handle::current();
+        tokio::task::spawn_blocking(move || {
+            if FileType::of_file(&path).ok().or(file_type) != Some(FileType::Jpeg) {
+                return runtime.block_on(Document::from_path(&path, file_type));
+            }
+            let mut document = Document::new(String::new(), path.clone(), FileType::Jpeg);
+            document.add_page(image_page(&path, page_size)?);
+            Ok(document)
+        })
+        .await
+        .map_err(|e| Error::Document(e.to_string()))?
+    }
+
+    /// Join the remaining documents of a batch onto its first one
+    ///
+    /// Also returns the numbers of the merged pages each later document
+    /// starts at.
+    async fn merge_batch(request: &PrintJobRequest, first: Document) -> Result<(Document, Vec<usize>), Error> {
+        let mut section_starts = Vec::with_capacity(request.batch_documents.len());
+        let mut next_page = first.page_count() + 1;
+        let mut documents = vec![first];
+        for path in &request.batch_documents {
+            // Documents may be of different types, each found from its contents
+            let document = Self::open_document(path, None, &request.options).await?;
+            section_starts.push(next_page);
+            next_page += document.page_count();
+            documents.push(document);
+        }
+        debug!(documents = documents.len(), "Merging batch");
+        Ok((Document::concatenate(request.job_id.to_string(), documents)?, section_starts))
+    }
+
+    /// Keep the pages of a job's page range, renumbered from 1
+    ///
+    /// Batch section starts move with their pages. A range running past the
+    /// last page ends there.
+    fn select_range(
+        mut document: Document,
+        section_starts: Vec<usize>,
+        range: Option<(usize, usize)>,
+    ) -> Result<(Document, Vec<usize>), Error> {
+        let Some((start, end)) = range else {
+            return Ok((document, section_starts));
+        };
+        let end = end.min(document.page_count());
+        let mut pages: Vec<Page> = document.pages_in_range(start, end)?.cloned().collect();
+        for (index, page) in pages.iter_mut().enumerate() {
+            page.number = index + 1;
+        }
+        document.pages = pages;
+        let section_starts = section_starts
+            .into_iter()
+            .filter(|section| (start + 1..=end).contains(section))
+            .map(|section| section + 1 - start)
+            .collect();
+        Ok((document, section_starts))
+    }
+
+    /// Pad a two-sided job with blank pages so that it ends on a whole sheet
+    /// and no sheet holds pages of two documents of a batch
+    ///
+    /// Blanks take the job's media size, or else the size of its last page.
+    fn pad_for_duplex(mut document: Document, options: &PrintOptions, section_starts: Vec<usize>) -> Document {
+        let output_size = options
+            .media
+            .size
+            .or_else(|| document.pages.last().map(|page| PageSize::Custom { width: page.width, height: page.height }))
+            .unwrap_or_default();
+        let padding = DuplexPadding { sections_start_on_right: !section_starts.is_empty(), section_starts };
+        let pages = std::mem::take(&mut document.pages);
+        document.pages = boomaga_layout_engine::pad_for_duplex(pages, options.duplex, output_size, &padding);
+        document
+    }
+
     /// Get job status
     pub async fn get_status(&self, job_id: String) -> Option<JobStatus> {
         let jobs = self.jobs.read().await;
-        jobs.get(&job_id).copied()
+        jobs.get(&job_id).map(|job| job.status)
+    }
+
+    /// Get the metadata of a job
+    pub async fn get_job(&self, job_id: &str) -> Option<JobMetadata> {
+        let jobs = self.jobs.read().await;
+        jobs.get(job_id).cloned()
+    }
+
+    /// Get the metadata of the job IPP clients know by `ipp_job_id`
+    pub async fn get_job_by_ipp_id(&self, ipp_job_id: i32) -> Option<JobMetadata> {
+        let jobs = self.jobs.read().await;
+        jobs.values().find(|job| job.ipp_job_id == ipp_job_id).cloned()
+    }
+
+    /// Get the metadata of all jobs, oldest first
+    pub async fn list_jobs(&self) -> Vec<JobMetadata> {
+        let jobs = self.jobs.read().await;
+        let mut list: Vec<JobMetadata> = jobs.values().cloned().collect();
+        list.sort_by_key(|job| job.created_at);
+        list
     }
 
     /// Get all jobs
     pub async fn get_all_jobs(&self) -> Vec<(String, JobStatus)> {
         let jobs = self.jobs.read().await;
-        jobs.iter().map(|(k, v)| (k.clone(), *v)).collect()
+        jobs.iter().map(|(k, v)| (k.clone(), v.status)).collect()
+    }
+
+    /// Get the processing times of the jobs completed so far
+    pub fn job_durations(&self) -> DurationHistogram {
+        self.durations.lock().map(|durations| durations.clone()).unwrap_or_default()
+    }
+
+    /// Get the number of jobs waiting in the queue
+    pub fn queue_size(&self) -> usize {
+        self.queue.size()
+    }
+
+    /// Get the printer status derived from the current jobs
+    pub async fn printer_status(&self) -> PrinterStatus {
+        let jobs = self.jobs.read().await;
+        if jobs.values().any(|job| job.status == JobStatus::Processing) {
+            PrinterStatus::Busy
+        } else {
+            PrinterStatus::Idle
+        }
+    }
+
+    /// Get the `printer-state-reasons` keywords for a status
+    ///
+    /// Adds `spool-area-full` while the queue cannot accept more jobs.
+    pub fn printer_state_reasons(&self, status: PrinterStatus) -> Vec<String> {
+        let mut reasons = status.reasons();
+        if self.queue.is_full() {
+            reasons.retain(|reason| reason != "none");
+            reasons.push("spool-area-full".to_string());
+        }
+        reasons
     }
 
     /// Cancel a job
+    ///
+    /// Held jobs are taken out of the queue and cancelled at once. Queued
+    /// jobs are cancelled when they are dequeued; running jobs stop at the
+    /// next page boundary.
     pub async fn cancel_job(&self, job_id: String) -> Result<(), Error> {
-        // TODO: Implement job cancellation
+        let held = self
+            .jobs
+            .read()
+            .await
+            .get(&job_id)
+            .filter(|job| job.status == JobStatus::Held)
+            .map(|job| job.job_id.clone());
+        if let Some(held) = held {
+            if let Some(request) = self.queue.remove_held(&held).await {
+                info!(job_id = %job_id, "Cancelling held job");
+                self.cancellations.write().await.remove(&job_id);
+                Self::remove_spooled(&self.settings.spool_dir, &request.file_path).await;
+                Self::transition(&self.jobs, &self.notifications, &held, JobStatus::Cancelled).await;
+                return Ok(());
+            }
+        }
+
+        match self.cancellations.read().await.get(&job_id) {
+            Some(token) => {
+                info!(job_id = %job_id, "Cancelling job");
+                token.cancel();
+                Ok(())
+            }
+            None if self.jobs.read().await.contains_key(&job_id) => {
+                warn!(job_id = %job_id, "Job already finished, not cancelling");
+                Err(Error::Validation(format!("Job {} has already finished", job_id)))
+            }
+            None => Err(Error::NotFound(format!("Job {} not found", job_id))),
+        }
+    }
+
+    /// Put a queued job on hold, so workers skip it until it is released
+    pub async fn hold_job(&self, job_id: String) -> Result<(), Error> {
+        let job = self.queued_job(&job_id, JobStatus::Queued).await?;
+        if !self.queue.hold(&job).await {
+            return Err(Error::Validation(format!("Job {} is already being processed", job_id)));
+        }
+
+        info!(job_id = %job_id, "Holding job");
+        Self::transition(&self.jobs, &self.notifications, &job, JobStatus::Held).await;
+        Ok(())
+    }
+
+    /// Release a held job back into the queue
+    ///
+    /// A manual duplex job held between its passes goes on to print its
+    /// backs.
+    pub async fn release_job(&self, job_id: String) -> Result<(), Error> {
+        let job = self.queued_job(&job_id, JobStatus::Held).await?;
+        if let Some(resume) = self.pauses.write().await.remove(&job_id) {
+            info!(job_id = %job_id, "Resuming manual duplex job");
+            resume.notify_one();
+            return Ok(());
+        }
+
+        // Mark it queued first, so a worker taking it right away is not undone
+        info!(job_id = %job_id, "Releasing job");
+        Self::transition(&self.jobs, &self.notifications, &job, JobStatus::Queued).await;
+        if !self.queue.release(&job).await {
+            Self::set_status(&self.jobs, &job_id, JobStatus::Held).await;
+            return Err(Error::Validation(format!("Job {} is not held", job_id)));
+        }
+        Ok(())
+    }
+
+    /// Move a queued job to a place in the queue, counted from 0 for the
+    /// next job served, without changing its priority
+    pub async fn move_job(&self, job_id: String, position: usize) -> Result<(), Error> {
+        let job = self.queued_job(&job_id, JobStatus::Queued).await?;
+        self.queue.move_to_position(&job, position).await?;
+
+        info!(job_id = %job_id, position, "Moved job in queue");
         Ok(())
     }
+
+    /// Look up a job that must currently have the given status
+    async fn queued_job(&self, job_id: &str, expected: JobStatus) -> Result<JobId, Error> {
+        match self.jobs.read().await.get(job_id) {
+            Some(job) if job.status == expected => Ok(job.job_id.clone()),
+            Some(job) => Err(Error::Validation(format!("Job {} is {}, not {}", job_id, job.status, expected))),
+            None => Err(Error::NotFound(format!("Job {} not found", job_id))),
+        }
+    }
+
+    /// List the waiting jobs in the order they would be started
+    pub async fn queued_jobs(&self) -> Vec<JobSummary> {
+        self.queue.snapshot().await
+    }
+
+    /// Drop every queued job and stop the running ones
+    ///
+    /// Queued jobs are marked cancelled. Running jobs are marked aborted and
+    /// stop at the next page boundary. Returns how many jobs were purged.
+    pub async fn purge(&self) -> usize {
+        let drained = self.queue.clear().await;
+        let mut purged = drained.len();
+
+        for request in drained {
+            Self::remove_spooled(&self.settings.spool_dir, &request.file_path).await;
+            self.cancellations.write().await.remove(&request.job_id.to_string());
+            Self::transition(&self.jobs, &self.notifications, &request.job_id, JobStatus::Cancelled).await;
+        }
+
+        let running: Vec<(String, CancellationToken)> = self
+            .cancellations
+            .read()
+            .await
+            .iter()
+            .map(|(job_id, token)| (job_id.clone(), token.clone()))
+            .collect();
+        for (job_id, token) in running {
+            let Some(notification_job_id) = self.jobs.read().await.get(&job_id).map(|job| job.job_id.clone()) else {
+                continue;
+            };
+            Self::transition(&self.jobs, &self.notifications, &notification_job_id, JobStatus::Aborted).await;
+            token.cancel();
+            purged += 1;
+        }
+
+        info!(purged, "Purged job queue");
+        purged
+    }
+}
+
+/// Get the sides the downstream printer is asked to print a `duplex` job with
+///
+/// Sides come from the printer's `sides-supported`. Printers that cannot
+/// print the job's sides get it one-sided when `unsupported_duplex`
+/// emulates duplex. Otherwise the job is refused.
+fn downstream_sides(
+    printer: &PrinterInfo,
+    duplex: DuplexMode,
+    unsupported_duplex: UnsupportedDuplex,
+) -> Result<DuplexMode, Error> {
+    // A printer that never answered may well have a duplex unit
+    let known = printer.attributes.contains_key("sides-supported");
+    if duplex == DuplexMode::None || !known || printer.capabilities.supported_duplex_modes.contains(&duplex) {
+        return Ok(duplex);
+    }
+
+    match unsupported_duplex {
+        UnsupportedDuplex::Emulate => Ok(DuplexMode::None),
+        UnsupportedDuplex::Reject => Err(Error::Unsupported(format!(
+            "Printer {} cannot print two-sided ({:?} binding)",
+            printer.name, duplex
+        ))),
+    }
+}
+
+/// Log a job failure at a level matching the error's severity
+fn log_by_severity(error: &Error, attempt: u32, message: &str) {
+    match error.severity() {
+        ErrorSeverity::High => error!(error = %error, attempt, "{}", message),
+        ErrorSeverity::Medium => warn!(error = %error, attempt, "{}", message),
+        ErrorSeverity::Low => info!(error = %error, attempt, "{}", message),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use boomaga_core::{FileType, PrintOptions};
     use std::path::PathBuf;
 
-    #[tokio::test]
-    async fn emits_job_status_notifications_in_order() {
-        let queue = Arc::new(JobQueue::new(4).unwrap());
-        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
-        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap();
-        let job_id: JobId =
-            serde_json::from_str("\"f7f04d62-a28d-4f7c-a55a-cf35dc913918\"").unwrap();
+    const RENDER: RenderSettings = RenderSettings { dpi: 300.0, antialias: true, dither: true };
 
-        processor
-            .add_job(PrintJobRequest {
-                job_id,
-                file_path: PathBuf::from("test.pdf"),
-                file_type: FileType::Pdf,
-                printer_name: None,
+    fn render_job(color_mode: ColorMode, threads: usize) -> RenderJob {
+        RenderJob { job_id: JobId::new(), color_mode, render: RENDER, passthrough: HashSet::new(), threads }
+    }
+
+    /// Page number and pixels of a page notification, wherever the pixels travel
+    fn rendered_pixels(message: Message) -> (usize, Vec<u8>) {
+        match message.payload {
+            MessagePayload::PageRendered { page_number, format: ImageFormat::Rgba8, image_data, .. } => {
+                (page_number, image_data.to_vec())
+            }
+            #[cfg(target_os = "linux")]
+            MessagePayload::PageRenderedShm { page_number, .. } => {
+                let fd = message.fd.expect("shared page should carry its segment");
+                let bitmap = shm::SharedBitmap::from_fd(fd.try_clone().unwrap()).unwrap();
+                (page_number, bitmap.map().unwrap().to_vec())
+            }
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+    }
+
+    async fn next_status(receiver: &mut tokio::sync::mpsc::UnboundedReceiver<Message>) -> JobStatus {
+        let message = tokio::time::timeout(tokio::time::Duration::from_secs(1), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match message.payload {
+            MessagePayload::PrintJobStatus { status, .. } => status,
+            payload => panic!("unexpected payload: {payload:?}"),
+        }
+    }
+
+    /// A three-page PDF with an outline
+    fn fixture_pdf() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../boomaga-core/tests/fixtures/outline.pdf")
+    }
+
+    /// A PostScript document without pages, which completes without rendering
+    fn no_pages_ps() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/no-pages.ps")
+    }
+
+    #[tokio::test]
+    async fn full_queue_reports_spool_area_full() {
+        let queue = Arc::new(JobQueue::new(1).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap();
+
+        assert_eq!(processor.printer_state_reasons(PrinterStatus::Idle), vec!["none"]);
+
+        queue
+            .push(PrintJobRequest {
+                job_id: JobId(boomaga_core::Uuid::new_v4()),
+                file_path: PathBuf::from("test.pdf"),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            processor.printer_state_reasons(PrinterStatus::Idle),
+            vec!["spool-area-full"]
+        );
+    }
+
+    #[tokio::test]
+    async fn identical_documents_are_parsed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap();
+        processor.start();
+
+        for name in ["first.ps", "second.ps"] {
+            let path = dir.path().join(name);
+            std::fs::copy(no_pages_ps(), &path).unwrap();
+            processor
+                .add_job(PrintJobRequest {
+                    job_id: JobId(boomaga_core::Uuid::new_v4()),
+                    file_path: path,
+                    file_type: FileType::PostScript,
+                    printer_name: None,
+                    options: PrintOptions::default(),
+                    batch_documents: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            // Finish each job before submitting the next
+            loop {
+                let message =
+                    tokio::time::timeout(tokio::time::Duration::from_secs(1), receiver.recv())
+                        .await
+                        .unwrap()
+                        .unwrap();
+                if let MessagePayload::PrintJobStatus { status: JobStatus::Completed, .. } =
+                    message.payload
+                {
+                    break;
+                }
+            }
+        }
+
+        let statistics = processor.cache.lock().await.statistics();
+        assert_eq!((statistics.misses, statistics.hits), (1, 1));
+        assert_eq!(statistics.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn submitted_pdf_is_parsed_into_its_pages() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap();
+        processor.start();
+        let job_id = JobId::new();
+
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: fixture_pdf(),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+        // Without rendering built in the job fails after it is parsed
+        while !matches!(next_status(&mut receiver).await, JobStatus::Completed | JobStatus::Failed) {}
+
+        let key = processor.jobs.read().await[&job_id.to_string()].content_hash.clone().unwrap();
+        let cached = processor.cache.lock().await.get(&key).unwrap();
+        assert_eq!(cached.document.page_count(), 3);
+        let page = cached.document.page(1).unwrap();
+        assert_eq!((page.width.round(), page.height.round()), (595.0, 842.0));
+    }
+
+    #[tokio::test]
+    async fn batches_merge_documents_of_each_type_within_the_page_range() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap();
+        let batch = PrintBatch {
+            documents: vec![fixture_pdf(), no_pages_ps(), fixture_pdf()],
+            options: PrintOptions { page_range: Some((3, 5)), ..PrintOptions::default() },
+        };
+
+        let job_id = processor.add_batch(batch).await.unwrap();
+        let request = queue.pop().await.unwrap();
+        assert_eq!((request.job_id.clone(), request.file_type), (job_id, FileType::Pdf));
+        assert_eq!(request.batch_documents, vec![no_pages_ps(), fixture_pdf()]);
+
+        let first = JobProcessor::parse_document(&request).await.unwrap().document;
+        let (merged, section_starts) = JobProcessor::merge_batch(&request, first).await.unwrap();
+        assert_eq!((merged.page_count(), section_starts.clone()), (6, vec![4, 4]));
+
+        let (selected, section_starts) =
+            JobProcessor::select_range(merged, section_starts, request.options.page_range).unwrap();
+        let numbers: Vec<_> = selected.pages.iter().map(|page| page.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert_eq!(section_starts, vec![2, 2]);
+    }
+
+    #[tokio::test]
+    async fn oversized_job_fails_before_it_is_parsed() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap().with_max_job_size(8);
+        processor.start();
+
+        let path = dir.path().join("large.pdf");
+        std::fs::write(&path, b"%PDF-1.7 more than eight bytes").unwrap();
+        processor
+            .add_job(PrintJobRequest {
+                job_id: JobId::new(),
+                file_path: path,
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Queued);
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Processing);
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Failed);
+        let statistics = processor.cache.lock().await.statistics();
+        assert_eq!((statistics.misses, statistics.entries), (0, 0));
+    }
+
+    #[cfg(feature = "render")]
+    #[tokio::test]
+    async fn odd_two_sided_jobs_print_a_trailing_blank_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("three-pages.pdf");
+        std::fs::write(&path, b"%PDF-1.7 three pages").unwrap();
+        let mut document = Document::new("doc".into(), path.clone(), FileType::Pdf);
+        for number in 1..=3 {
+            document.add_page(Page::new(number, 36.0, 36.0, boomaga_core::Orientation::Portrait));
+        }
+        let mut cache = DocumentCache::new(1 << 20, std::time::Duration::from_secs(60));
+        let key = DocumentCache::key_for_file(&path).await.unwrap();
+        cache.insert(key, Arc::new(CachedDocument { document }), 0);
+
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap().with_document_cache(cache);
+        processor.start();
+        let job_id = JobId::new();
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: path,
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::builder().duplex(DuplexMode::LongEdge).build().unwrap(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let mut rendered = Vec::new();
+        loop {
+            let message = tokio::time::timeout(tokio::time::Duration::from_secs(1), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            match message.payload {
+                MessagePayload::PrintJobStatus { status: JobStatus::Completed, .. } => break,
+                MessagePayload::PageRendered { .. } => rendered.push(rendered_pixels(message).0),
+                _ => {}
+            }
+        }
+
+        assert_eq!(rendered, vec![1, 2, 3, 4]);
+        let statistics = processor.get_job(&job_id.to_string()).await.unwrap().statistics.unwrap();
+        assert_eq!(statistics.pages_processed, 4);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn processed_job_events_carry_its_job_id() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap();
+        processor.start();
+        let job_id = JobId(boomaga_core::Uuid::new_v4());
+
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: no_pages_ps(),
+                file_type: FileType::PostScript,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+        loop {
+            let message = tokio::time::timeout(tokio::time::Duration::from_secs(1), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let MessagePayload::PrintJobStatus { status: JobStatus::Completed, .. } = message.payload {
+                break;
+            }
+        }
+
+        let field = format!("job_id={}", job_id);
+        logs_assert(|lines: &[&str]| {
+            // Starting the workers happens before, and outside, any job
+            let job_lines: Vec<_> = lines
+                .iter()
+                .filter(|line| line.contains("job_processor") && !line.contains("Started job workers"))
+                .collect();
+            if job_lines.len() < 3 {
+                return Err(format!("expected job events, got {:?}", job_lines));
+            }
+            match job_lines.iter().find(|line| !line.contains(&field)) {
+                Some(line) => Err(format!("event without {}: {}", field, line)),
+                None => Ok(()),
+            }
+        });
+    }
+
+    #[cfg(feature = "render")]
+    #[tokio::test]
+    async fn cancelled_render_stops_at_page_boundary() {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=100 {
+            document.add_page(boomaga_core::Page::new(number, 36.0, 36.0, boomaga_core::Orientation::Portrait));
+        }
+        let token = CancellationToken::new();
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        let job = render_job(ColorMode::Auto, 1);
+        let render = || JobProcessor::render_pages(&document, &job, &sender, &token);
+        assert_eq!(render().await.unwrap(), 100);
+
+        token.cancel();
+        assert!(render().await.is_err());
+    }
+
+    #[cfg(feature = "render")]
+    #[tokio::test]
+    async fn rendered_pages_are_streamed_to_the_preview() {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=3 {
+            document.add_page(boomaga_core::Page::new(number, 36.0, 36.0, boomaga_core::Orientation::Portrait));
+        }
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        JobProcessor::render_pages(&document, &render_job(ColorMode::Auto, 1), &sender, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        for expected in 1..=3 {
+            assert_eq!(rendered_pixels(receiver.try_recv().unwrap()).0, expected);
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[cfg(feature = "render")]
+    #[tokio::test]
+    async fn vector_pages_are_rendered_once_at_the_job_resolution() {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        document.add_page(Page::new(1, 36.0, 72.0, boomaga_core::Orientation::Portrait));
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        JobProcessor::render_pages(&document, &render_job(ColorMode::Auto, 1), &sender, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        // Half an inch by an inch at 300 dpi, white
+        let (_, image_data) = rendered_pixels(receiver.try_recv().unwrap());
+        assert_eq!(image_data.len(), 150 * 300 * 4);
+        assert!(image_data.iter().all(|&byte| byte == 255));
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn draft_jobs_render_at_a_lower_resolution_than_high_ones() {
+        let page = Page::new(1, 72.0, 72.0, boomaga_core::Orientation::Portrait);
+        let width = |quality| {
+            let render = RenderQuality::High.render_settings(quality);
+            match JobProcessor::render_page(page.clone(), ColorMode::Auto, render).unwrap().contents {
+                PageContents::Raster { width, .. } => width,
+                contents => panic!("unexpected contents: {contents:?}"),
+            }
+        };
+
+        assert_eq!(width(boomaga_core::PrintQuality::Draft), 150);
+        assert_eq!(width(boomaga_core::PrintQuality::High), 600);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn rendering_without_antialiasing_leaves_hard_edges() {
+        use boomaga_core::{Color, GraphicsElement, PathElement};
+
+        // A black triangle, whose slanted edge is smoothed when antialiased
+        let page = Page {
+            contents: PageContents::Vector(vec![GraphicsElement::Path {
+                elements: vec![
+                    PathElement::MoveTo { x: 0.0, y: 0.0 },
+                    PathElement::LineTo { x: 72.0, y: 0.0 },
+                    PathElement::LineTo { x: 0.0, y: 50.0 },
+                    PathElement::Close,
+                ],
+                stroke: None,
+                fill: Some(Color::black()),
+                stroke_width: 0.0,
+            }]),
+            ..Page::new(1, 72.0, 72.0, boomaga_core::Orientation::Portrait)
+        };
+        let mid_tones = |antialias| {
+            let render = RenderSettings { antialias, ..RENDER };
+            match JobProcessor::render_page(page.clone(), ColorMode::Auto, render).unwrap().contents {
+                PageContents::Raster { data, .. } => data.iter().filter(|&&byte| byte != 0 && byte != 255).count(),
+                contents => panic!("unexpected contents: {contents:?}"),
+            }
+        };
+
+        assert!(mid_tones(true) > 0);
+        assert_eq!(mid_tones(false), 0);
+    }
+
+    #[cfg(feature = "render")]
+    #[tokio::test]
+    async fn passthrough_pages_are_not_rasterized() {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=3 {
+            document.add_page(Page {
+                contents: PageContents::Pdf { stream: Arc::from(format!("q page {number} Q").into_bytes()) },
+                ..Page::new(number, 36.0, 36.0, boomaga_core::Orientation::Portrait)
+            });
+        }
+        let options = PrintOptions { page_range: Some((2, 3)), ..PrintOptions::default() };
+        let job = RenderJob {
+            passthrough: JobProcessor::passthrough(&document, &options).unwrap(),
+            ..render_job(ColorMode::Auto, 1)
+        };
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        // Page 1 has no file to be drawn from, so rasterizing it fails
+        let error = JobProcessor::render_pages(&document, &job, &sender, &CancellationToken::new()).await;
+        assert!(error.unwrap_err().to_string().contains("Page 1"));
+        assert!(receiver.try_recv().is_err());
+
+        document.pages.remove(0);
+        let done = JobProcessor::render_pages(&document, &job, &sender, &CancellationToken::new()).await.unwrap();
+        assert_eq!(done, 2);
+        assert!(receiver.try_recv().is_err(), "no page should have been rasterized");
+    }
+
+    fn raster_page(number: usize, bytes: usize) -> Page {
+        Page {
+            contents: PageContents::rgba(8, 8, Arc::from(vec![number as u8; bytes])),
+            ..Page::new(number, 595.0, 842.0, boomaga_core::Orientation::Portrait)
+        }
+    }
+
+    #[test]
+    fn only_small_pages_are_sent_inline_as_pixels() {
+        let small = JobProcessor::page_notification(JobId::new(), &raster_page(1, 8 * 8 * 4)).unwrap();
+        let large = JobProcessor::page_notification(JobId::new(), &Page {
+            contents: PageContents::rgba(256, 256, Arc::from(vec![0; 256 * 256 * 4])),
+            ..Page::new(2, 595.0, 842.0, boomaga_core::Orientation::Portrait)
+        })
+        .unwrap();
+
+        assert!(matches!(
+            small.payload,
+            MessagePayload::PageRendered { width: 8, height: 8, format: ImageFormat::Rgba8, .. }
+        ));
+        assert!(matches!(
+            large.payload,
+            MessagePayload::PageRenderedShm { width: 256, height: 256, .. }
+                | MessagePayload::PageRendered { width: 256, height: 256, format: ImageFormat::Png, .. }
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_render_keeps_page_order() {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=40 {
+            document.add_page(raster_page(number, 8 * 8 * 4));
+        }
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        let rendered =
+            JobProcessor::render_pages(&document, &render_job(ColorMode::Auto, 4), &sender, &CancellationToken::new())
+                .await
+                .unwrap();
+
+        assert_eq!(rendered, 40);
+        for expected in 1..=40 {
+            let (page_number, image_data) = rendered_pixels(receiver.try_recv().unwrap());
+            assert_eq!(page_number, expected);
+            assert!(image_data.iter().all(|&byte| byte == expected as u8));
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn failed_page_fails_render_with_its_number() {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=5 {
+            let bytes = if number == 3 { 10 } else { 8 * 8 * 4 };
+            document.add_page(raster_page(number, bytes));
+        }
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        let error =
+            JobProcessor::render_pages(&document, &render_job(ColorMode::Auto, 2), &sender, &CancellationToken::new())
+                .await
+                .unwrap_err();
+
+        assert!(error.to_string().contains("Page 3"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn grayscale_job_renders_gray_pixels() {
+        let colors: Vec<u8> = (0..8 * 8).flat_map(|i| [i as u8 * 4, 255 - i as u8, 40, 200]).collect();
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        document.add_page(Page {
+            contents: PageContents::rgba(8, 8, Arc::from(colors)),
+            ..Page::new(1, 595.0, 842.0, boomaga_core::Orientation::Portrait)
+        });
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        JobProcessor::render_pages(&document, &render_job(ColorMode::Grayscale, 1), &sender, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        let (_, image_data) = rendered_pixels(receiver.try_recv().unwrap());
+        assert_eq!(image_data.len(), 8 * 8 * 4);
+        for pixel in image_data.chunks_exact(4) {
+            assert!(pixel[0] == pixel[1] && pixel[1] == pixel[2], "{pixel:?} is not gray");
+            assert_eq!(pixel[3], 200);
+        }
+    }
+
+    #[test]
+    fn monochrome_dithering_mixes_black_and_white() {
+        let gray = [100u8, 100, 100, 255].repeat(4 * 4);
+        let black = |pixels: &[u8]| pixels.chunks_exact(4).filter(|pixel| pixel[0] == 0).count();
+
+        let thresholded = JobProcessor::convert_colors(&gray, 4, ColorMode::Monochrome, false).unwrap();
+        assert_eq!(black(&thresholded), 16);
+
+        let dithered = JobProcessor::convert_colors(&gray, 4, ColorMode::Monochrome, true).unwrap();
+        assert!((1..16).contains(&black(&dithered)), "{} black pixels", black(&dithered));
+        assert!(JobProcessor::convert_colors(&gray, 4, ColorMode::Color, true).is_none());
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_running_job_marks_it_cancelled() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap();
+        processor.start();
+        let job_id = JobId::new();
+        // Keep the job from getting past its cache lookup
+        let cache = processor.cache.lock().await;
+
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: fixture_pdf(),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Queued);
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Processing);
+
+        let started = std::time::Instant::now();
+        processor.cancel_job(job_id.to_string()).await.unwrap();
+        drop(cache);
+
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Cancelled);
+        assert!(started.elapsed() < std::time::Duration::from_millis(100));
+        assert_eq!(processor.get_status(job_id.to_string()).await, Some(JobStatus::Cancelled));
+        assert!(processor.cancel_job(job_id.to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn purging_cancels_queued_jobs_and_aborts_running_ones() {
+        let queue = Arc::new(JobQueue::new(8).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap();
+        processor.start();
+        // Keep the running job from getting past its cache lookup
+        let cache = processor.cache.lock().await;
+        let request = |job_id: &JobId| PrintJobRequest {
+            job_id: job_id.clone(),
+            file_path: fixture_pdf(),
+            file_type: FileType::Pdf,
+            printer_name: None,
+            options: PrintOptions::default(),
+            batch_documents: Vec::new(),
+        };
+
+        let running = JobId::new();
+        processor.add_job(request(&running)).await.unwrap();
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Queued);
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Processing);
+
+        // Queue more jobs behind the busy worker
+        let queued: Vec<JobId> = (0..3).map(|_| JobId::new()).collect();
+        for job_id in &queued {
+            queue.push(request(job_id)).await.unwrap();
+            let metadata = JobMetadata::from_request(&request(job_id));
+            processor.jobs.write().await.insert(job_id.to_string(), metadata);
+            processor
+                .cancellations
+                .write()
+                .await
+                .insert(job_id.to_string(), CancellationToken::new());
+        }
+
+        assert_eq!(processor.purge().await, 4);
+        assert!(queue.is_empty());
+        for job_id in &queued {
+            assert_eq!(processor.get_status(job_id.to_string()).await, Some(JobStatus::Cancelled));
+        }
+        assert_eq!(processor.get_status(running.to_string()).await, Some(JobStatus::Aborted));
+
+        let mut statuses: Vec<JobStatus> = Vec::new();
+        for _ in 0..4 {
+            statuses.push(next_status(&mut receiver).await);
+        }
+        assert_eq!(statuses.iter().filter(|&&status| status == JobStatus::Cancelled).count(), 3);
+        assert_eq!(statuses.last(), Some(&JobStatus::Aborted));
+
+        // The aborted job's worker stops without reporting it again
+        drop(cache);
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(processor.get_status(running.to_string()).await, Some(JobStatus::Aborted));
+    }
+
+    #[tokio::test]
+    async fn held_job_is_processed_only_after_release() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap();
+        let request = |job_id: &JobId| PrintJobRequest {
+            job_id: job_id.clone(),
+            file_path: no_pages_ps(),
+            file_type: FileType::PostScript,
+            printer_name: None,
+            options: PrintOptions::default(),
+            batch_documents: Vec::new(),
+        };
+
+        // Queue a job with no worker yet and hold it
+        let held = JobId::new();
+        queue.push(request(&held)).await.unwrap();
+        processor
+            .jobs
+            .write()
+            .await
+            .insert(held.to_string(), JobMetadata::from_request(&request(&held)));
+        processor.hold_job(held.to_string()).await.unwrap();
+        assert_eq!(next_status(&mut receiver).await, JobStatus::Held);
+
+        // The worker runs the next job and leaves the held one
+        processor.start();
+        let other = JobId::new();
+        processor.add_job(request(&other)).await.unwrap();
+        for expected in [JobStatus::Queued, JobStatus::Processing, JobStatus::Completed] {
+            assert_eq!(next_status(&mut receiver).await, expected);
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(processor.get_status(held.to_string()).await, Some(JobStatus::Held));
+        assert!(processor.hold_job(held.to_string()).await.is_err());
+
+        processor.release_job(held.to_string()).await.unwrap();
+        for expected in [JobStatus::Queued, JobStatus::Processing, JobStatus::Completed] {
+            assert_eq!(next_status(&mut receiver).await, expected);
+        }
+        assert_eq!(processor.get_status(held.to_string()).await, Some(JobStatus::Completed));
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_held_job_frees_its_queue_slot() {
+        let queue = Arc::new(JobQueue::new(1).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap();
+        let request = |job_id: &JobId| PrintJobRequest {
+            job_id: job_id.clone(),
+            file_path: PathBuf::from("test.pdf"),
+            file_type: FileType::Pdf,
+            printer_name: None,
+            options: PrintOptions::default(),
+            batch_documents: Vec::new(),
+        };
+
+        let held = JobId::new();
+        processor.add_job(request(&held)).await.unwrap();
+        processor.hold_job(held.to_string()).await.unwrap();
+        processor.cancel_job(held.to_string()).await.unwrap();
+
+        for expected in [JobStatus::Queued, JobStatus::Held, JobStatus::Cancelled] {
+            assert_eq!(next_status(&mut receiver).await, expected);
+        }
+        assert_eq!(processor.get_status(held.to_string()).await, Some(JobStatus::Cancelled));
+        assert!(queue.is_empty());
+        assert!(processor.release_job(held.to_string()).await.is_err());
+
+        // The slot it took is free for the next job
+        processor.add_job(request(&JobId::new())).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn completed_job_removes_its_spool_file() {
+        let spool = tempfile::tempdir().unwrap();
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender)
+            .unwrap()
+            .with_spool_dir(spool.path().to_path_buf());
+        processor.start();
+        let job_id = JobId::new();
+
+        let document = std::fs::read(no_pages_ps()).unwrap();
+        let file_path = processor.spool(&job_id, FileType::PostScript, &document).await.unwrap();
+        assert!(file_path.exists());
+        processor
+            .add_job(PrintJobRequest {
+                job_id,
+                file_path: file_path.clone(),
+                file_type: FileType::PostScript,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        for expected in [JobStatus::Queued, JobStatus::Processing, JobStatus::Completed] {
+            assert_eq!(next_status(&mut receiver).await, expected);
+        }
+        assert!(!file_path.exists());
+    }
+
+    #[tokio::test]
+    async fn startup_sweep_removes_orphaned_spool_files() {
+        let spool = tempfile::tempdir().unwrap();
+        for name in ["left-over.pdf", "crashed.pwg"] {
+            std::fs::write(spool.path().join(name), b"orphan").unwrap();
+        }
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::new(JobQueue::new(4).unwrap()), 1, 1, sender)
+            .unwrap()
+            .with_spool_dir(spool.path().to_path_buf());
+
+        assert_eq!(processor.sweep_spool().await.unwrap(), 2);
+        assert_eq!(std::fs::read_dir(spool.path()).unwrap().count(), 0);
+
+        let missing = spool.path().join("missing");
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(Arc::new(JobQueue::new(4).unwrap()), 1, 1, sender)
+            .unwrap()
+            .with_spool_dir(missing);
+        assert_eq!(processor.sweep_spool().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn emits_job_status_notifications_in_order() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 1, sender).unwrap();
+        processor.start();
+        let job_id: JobId =
+            serde_json::from_str("\"f7f04d62-a28d-4f7c-a55a-cf35dc913918\"").unwrap();
+
+        processor
+            .add_job(PrintJobRequest {
+                job_id,
+                file_path: no_pages_ps(),
+                file_type: FileType::PostScript,
+                printer_name: None,
                 options: PrintOptions::default(),
+                batch_documents: Vec::new(),
             })
             .await
             .unwrap();
@@ -231,4 +2010,121 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn workers_process_no_more_than_max_concurrent_jobs() {
+        let queue = Arc::new(JobQueue::new(8).unwrap());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 1, 3, sender).unwrap();
+        processor.start();
+        processor.start();
+
+        for _ in 0..3 {
+            processor
+                .add_job(PrintJobRequest {
+                    job_id: JobId::new(),
+                    file_path: no_pages_ps(),
+                    file_type: FileType::PostScript,
+                    printer_name: None,
+                    options: PrintOptions::default(),
+                    batch_documents: Vec::new(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let (mut processing, mut most, mut completed) = (0, 0, 0);
+        while completed < 3 {
+            match next_status(&mut receiver).await {
+                JobStatus::Processing => processing += 1,
+                JobStatus::Completed => {
+                    processing -= 1;
+                    completed += 1;
+                }
+                _ => {}
+            }
+            most = most.max(processing);
+        }
+        assert_eq!(most, 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_job_limit_resizes_the_slots() {
+        let queue = Arc::new(JobQueue::new(8).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = JobProcessor::new(queue, 2, 1, sender).unwrap();
+
+        processor.set_max_concurrent(4).await.unwrap();
+        assert_eq!(processor.slots.available_permits(), 4);
+
+        // Slots held by running jobs are taken away once the jobs release them
+        let running = Arc::clone(&processor.slots).acquire_many_owned(2).await.unwrap();
+        processor.set_max_concurrent(1).await.unwrap();
+        assert_eq!(processor.slots.available_permits(), 0);
+        drop(running);
+        tokio::task::yield_now().await;
+        assert_eq!(processor.slots.available_permits(), 1);
+
+        assert!(processor.set_max_concurrent(0).await.is_err());
+    }
+
+    /// Probe a mock printer that only prints one-sided
+    async fn simplex_printer() -> PrinterInfo {
+        use crate::ipp::{encode_attribute, END_OF_ATTRIBUTES_TAG, KEYWORD_TAG, PRINTER_ATTRIBUTES_TAG};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ipp://{}/ipp/print", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+
+            let mut body = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, PRINTER_ATTRIBUTES_TAG];
+            encode_attribute(&mut body, KEYWORD_TAG, "sides-supported", b"one-sided");
+            body.push(END_OF_ATTRIBUTES_TAG);
+            let header = format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).await.unwrap();
+            stream.write_all(&body).await.unwrap();
+        });
+
+        crate::discovery::probe_printer("Simplex", &uri, false).await
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn two_sided_jobs_for_a_simplex_printer_follow_the_policy() {
+        let printer = Arc::new(RwLock::new(simplex_printer().await));
+        let two_sided = || PrintJobRequest {
+            job_id: JobId(boomaga_core::Uuid::new_v4()),
+            file_path: PathBuf::from("test.pdf"),
+            file_type: FileType::Pdf,
+            printer_name: None,
+            options: PrintOptions::builder().duplex(DuplexMode::LongEdge).build().unwrap(),
+            batch_documents: Vec::new(),
+        };
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let rejecting = JobProcessor::new(Arc::new(JobQueue::new(4).unwrap()), 1, 1, sender)
+            .unwrap()
+            .with_downstream_printer(Arc::clone(&printer), UnsupportedDuplex::Reject);
+        let error = rejecting.add_job(two_sided()).await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(_)), "{error}");
+        assert!(rejecting.list_jobs().await.is_empty());
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let emulating = JobProcessor::new(Arc::new(JobQueue::new(4).unwrap()), 1, 1, sender)
+            .unwrap()
+            .with_downstream_printer(Arc::clone(&printer), UnsupportedDuplex::Emulate);
+        emulating.add_job(two_sided()).await.unwrap();
+        assert_eq!(emulating.list_jobs().await.len(), 1);
+        assert!(logs_contain("printing two-sided by hand"));
+
+        let printer = printer.read().await;
+        let sides = downstream_sides(&printer, DuplexMode::LongEdge, UnsupportedDuplex::Emulate).unwrap();
+        assert_eq!(sides, DuplexMode::None);
+    }
 }
@@ -1,18 +1,88 @@
 //! Job queue implementation
 
-use boomaga_core::{Error, PrintJobRequest};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use boomaga_core::{Error, JobId, JobPriority, JobSummary, PrintJobRequest};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::{mpsc, Mutex};
-use tracing::{debug, info};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{Mutex, Notify};
+use tracing::{debug, info, warn};
+
+/// A job waiting in the queue
+///
+/// Entries order by priority, then by submission time, then by the order
+/// they were pushed, so equal-priority jobs are served first come, first
+/// served. Jobs moved by an operator come before all others, in the order
+/// they were placed. The greatest entry is dequeued first.
+#[derive(Debug, Clone)]
+pub struct QueuedJob {
+    pub request: PrintJobRequest,
+    pub priority: JobPriority,
+    pub created_at: SystemTime,
+    /// Push order, breaking ties between identical timestamps
+    pub sequence: u64,
+    /// Place given by [`JobQueue::move_to_position`], lowest first
+    pub pinned: Option<u64>,
+}
+
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        let pinned = match (self.pinned, other.pinned) {
+            (Some(rank), Some(other_rank)) => other_rank.cmp(&rank),
+            (Some(_), None) => CmpOrdering::Greater,
+            (None, Some(_)) => CmpOrdering::Less,
+            (None, None) => CmpOrdering::Equal,
+        };
+        pinned
+            .then_with(|| self.priority.cmp(&other.priority))
+            .then_with(|| other.created_at.cmp(&self.created_at))
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for QueuedJob {}
+
+/// A waiting job as stored in the queue journal
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    request: PrintJobRequest,
+    priority: JobPriority,
+    created_at: SystemTime,
+    held: bool,
+}
 
 /// Job queue
+///
+/// Held jobs keep their place in the queue but are not dequeued until they
+/// are released.
 pub struct JobQueue {
-    sender: mpsc::Sender<PrintJobRequest>,
-    receiver: Mutex<mpsc::Receiver<PrintJobRequest>>,
+    jobs: Mutex<BinaryHeap<QueuedJob>>,
+    held: Mutex<HashMap<JobId, QueuedJob>>,
+    available: Notify,
+    /// Signalled when a job leaves the queue, for pushes waiting for space
+    space: Notify,
+    /// Bound on blocking waits, see [`JobQueue::with_timeout`]
+    timeout: Duration,
+    next_sequence: AtomicU64,
     queue_size: Arc<AtomicUsize>,
     max_size: usize,
+    /// Directory holding one file per waiting job, see [`JobQueue::with_journal`]
+    journal: Option<PathBuf>,
 }
 
 impl JobQueue {
@@ -24,28 +94,168 @@ impl JobQueue {
             ));
         }
 
-        let (sender, receiver) = mpsc::channel(max_size);
-
         Ok(Self {
-            sender,
-            receiver: Mutex::new(receiver),
+            jobs: Mutex::new(BinaryHeap::with_capacity(max_size)),
+            held: Mutex::new(HashMap::new()),
+            available: Notify::new(),
+            space: Notify::new(),
+            timeout: Duration::ZERO,
+            next_sequence: AtomicU64::new(0),
             queue_size: Arc::new(AtomicUsize::new(0)),
             max_size,
+            journal: None,
         })
     }
 
-    /// Push a job into the queue
-    pub async fn push(&self, request: PrintJobRequest) -> Result<(), Error> {
-        if self.queue_size.load(Ordering::Relaxed) >= self.max_size {
-            return Err(Error::Validation("Queue is full".into()));
+    /// Bound blocking queue operations by `timeout`
+    ///
+    /// Pushes into a full queue wait up to `timeout` for space before
+    /// failing, and idle workers log each time they have waited that long
+    /// for a job. Without a timeout, pushes into a full queue fail at once.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Keep a journal of the waiting jobs in `dir`, so they survive a restart
+    ///
+    /// Jobs journalled by an earlier run are queued again if their documents
+    /// still exist, held jobs staying held; the others are dropped. Jobs
+    /// leave the journal when they are dequeued, so jobs that had started,
+    /// finished or failed are not restored. Operator moves are not kept.
+    pub fn with_journal(mut self, dir: PathBuf) -> Result<Self, Error> {
+        std::fs::create_dir_all(&dir)?;
+
+        let mut restored = 0;
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+
+            let entry = std::fs::read(&path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<JournalEntry>(&bytes).ok());
+            match entry {
+                Some(entry)
+                    if std::iter::once(&entry.request.file_path)
+                        .chain(&entry.request.batch_documents)
+                        .all(|path| path.exists())
+                        && restored < self.max_size =>
+                {
+                    let job = QueuedJob {
+                        request: entry.request,
+                        priority: entry.priority,
+                        created_at: entry.created_at,
+                        sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+                        pinned: None,
+                    };
+                    if entry.held {
+                        self.held.get_mut().insert(job.request.job_id.clone(), job);
+                    } else {
+                        self.jobs.get_mut().push(job);
+                    }
+                    restored += 1;
+                }
+                _ => {
+                    warn!(path = %path.display(), "Dropping journalled job that cannot be restored");
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        warn!(path = %path.display(), error = %e, "Failed to remove journal entry");
+                    }
+                }
+            }
         }
 
-        self.sender
-            .send(request)
-            .await
-            .map_err(|e| Error::Job(format!("Failed to push job: {}", e)))?;
+        self.queue_size.store(restored, Ordering::Relaxed);
+        if restored > 0 {
+            info!(restored, journal = %dir.display(), "Restored queued jobs");
+        }
+        self.journal = Some(dir);
+        Ok(self)
+    }
+
+    /// Record a waiting job in the journal, if there is one
+    async fn journal_write(&self, job: &QueuedJob, held: bool) {
+        let Some(dir) = &self.journal else {
+            return;
+        };
+
+        let entry = JournalEntry {
+            request: job.request.clone(),
+            priority: job.priority,
+            created_at: job.created_at,
+            held,
+        };
+        let path = dir.join(format!("{}.json", job.request.job_id));
+        let result = match serde_json::to_vec(&entry) {
+            Ok(bytes) => tokio::fs::write(&path, bytes).await.map_err(Error::from),
+            Err(e) => Err(Error::Job(e.to_string())),
+        };
+        if let Err(e) = result {
+            warn!(path = %path.display(), error = %e, "Failed to journal queued job");
+        }
+    }
 
-        self.queue_size.fetch_add(1, Ordering::Relaxed);
+    /// Drop a job that is no longer waiting from the journal
+    async fn journal_remove(&self, job_id: &JobId) {
+        let Some(dir) = &self.journal else {
+            return;
+        };
+
+        let path = dir.join(format!("{}.json", job_id));
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            warn!(path = %path.display(), error = %e, "Failed to remove journal entry");
+        }
+    }
+
+    /// Push a normal-priority job submitted now
+    pub async fn push(&self, request: PrintJobRequest) -> Result<(), Error> {
+        self.push_with_priority(request, JobPriority::Normal, SystemTime::now()).await
+    }
+
+    /// Push a job, scheduled by priority and then submission time
+    ///
+    /// A full queue is waited on for up to the queue timeout, see
+    /// [`Self::with_timeout`].
+    pub async fn push_with_priority(
+        &self,
+        request: PrintJobRequest,
+        priority: JobPriority,
+        created_at: SystemTime,
+    ) -> Result<(), Error> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let (mut jobs, held) = loop {
+            let jobs = self.jobs.lock().await;
+            let held = self.held.lock().await.len();
+            if jobs.len() + held < self.max_size {
+                break (jobs, held);
+            }
+
+            // Start listening before unlocking, so no job can leave unnoticed
+            let space = self.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+            drop(jobs);
+            if tokio::time::timeout_at(deadline, space).await.is_err() {
+                return Err(Error::Busy(format!("Queue is full after waiting {:?}", self.timeout)));
+            }
+        };
+
+        let job = QueuedJob {
+            request,
+            priority,
+            created_at,
+            sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            pinned: None,
+        };
+        // Journal before the job can be dequeued, so a worker never removes
+        // the entry before it is written
+        self.journal_write(&job, false).await;
+        jobs.push(job);
+        self.queue_size.store(jobs.len() + held, Ordering::Relaxed);
+        drop(jobs);
+
+        self.available.notify_one();
 
         debug!(
             "Job pushed to queue. Current size: {}",
@@ -55,16 +265,160 @@ impl JobQueue {
         Ok(())
     }
 
-    /// Pop a job from the queue
+    /// Pop the next job, waiting until one is available
     pub async fn pop(&self) -> Result<PrintJobRequest, Error> {
-        let job = self.receiver.lock().await.recv().await;
-        match job {
-            Some(job) => {
-                self.queue_size.fetch_sub(1, Ordering::Relaxed);
-                Ok(job)
+        loop {
+            {
+                let mut jobs = self.jobs.lock().await;
+                if let Some(job) = jobs.pop() {
+                    self.queue_size.fetch_sub(1, Ordering::Relaxed);
+                    drop(jobs);
+                    self.space.notify_one();
+                    self.journal_remove(&job.request.job_id).await;
+                    return Ok(job.request);
+                }
             }
-            None => Err(Error::Job("Queue is empty".into())),
+
+            if self.timeout.is_zero() {
+                self.available.notified().await;
+            } else if tokio::time::timeout(self.timeout, self.available.notified()).await.is_err() {
+                debug!("No job for {:?}, still waiting", self.timeout);
+            }
+        }
+    }
+
+    /// Keep a waiting job from being dequeued until it is released
+    ///
+    /// Returns whether the job was waiting in the queue.
+    pub async fn hold(&self, job_id: &JobId) -> bool {
+        let mut jobs = self.jobs.lock().await;
+        let mut entries = std::mem::take(&mut *jobs).into_vec();
+        let position = entries.iter().position(|job| &job.request.job_id == job_id);
+        let entry = position.map(|index| entries.swap_remove(index));
+        *jobs = entries.into();
+
+        match entry {
+            Some(entry) => {
+                self.journal_write(&entry, true).await;
+                self.held.lock().await.insert(job_id.clone(), entry);
+                debug!(job_id = %job_id, "Job held in queue");
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Let a held job be dequeued again, in its original place
+    ///
+    /// Returns whether the job was held.
+    pub async fn release(&self, job_id: &JobId) -> bool {
+        let mut jobs = self.jobs.lock().await;
+        let Some(entry) = self.held.lock().await.remove(job_id) else {
+            return false;
+        };
+        self.journal_write(&entry, false).await;
+        jobs.push(entry);
+        drop(jobs);
+
+        self.available.notify_one();
+        debug!(job_id = %job_id, "Job released in queue");
+        true
+    }
+
+    /// Remove a held job from the queue for good
+    ///
+    /// Returns the job's request, or `None` when the job was not held.
+    pub async fn remove_held(&self, job_id: &JobId) -> Option<PrintJobRequest> {
+        let entry = self.held.lock().await.remove(job_id)?;
+        self.queue_size.fetch_sub(1, Ordering::Relaxed);
+        self.space.notify_one();
+        self.journal_remove(job_id).await;
+
+        debug!(job_id = %job_id, "Held job removed from queue");
+        Some(entry.request)
+    }
+
+    /// Serve a waiting job before every other one, see [`Self::move_to_position`]
+    pub async fn move_to_front(&self, job_id: &JobId) -> Result<(), Error> {
+        self.move_to_position(job_id, 0).await
+    }
+
+    /// Move a waiting job to a place in the dequeue order, counted from 0
+    ///
+    /// The job keeps its priority. It and the jobs ahead of it keep their
+    /// places until they are dequeued, even if more urgent jobs arrive.
+    /// Held jobs and jobs not in the queue, such as ones being processed,
+    /// cannot be moved.
+    pub async fn move_to_position(&self, job_id: &JobId, position: usize) -> Result<(), Error> {
+        let mut jobs = self.jobs.lock().await;
+        if self.held.lock().await.contains_key(job_id) {
+            return Err(Error::Validation(format!("Job {} is held", job_id)));
         }
+
+        let mut entries = std::mem::take(&mut *jobs).into_sorted_vec();
+        entries.reverse();
+        let result = match entries.iter().position(|job| &job.request.job_id == job_id) {
+            None => Err(Error::NotFound(format!("Job {} is not waiting in the queue", job_id))),
+            Some(_) if position >= entries.len() => Err(Error::Validation(format!(
+                "Position {} is past the end of the queue ({} jobs)",
+                position,
+                entries.len()
+            ))),
+            Some(from) => {
+                let entry = entries.remove(from);
+                entries.insert(position, entry);
+
+                // Pin the moved job, everything ahead of it and earlier moves
+                let pinned = entries
+                    .iter()
+                    .rposition(|job| job.pinned.is_some())
+                    .map_or(0, |last| last + 1)
+                    .max(position + 1);
+                for (rank, job) in entries.iter_mut().take(pinned).enumerate() {
+                    job.pinned = Some(rank as u64);
+                }
+                debug!(job_id = %job_id, position, "Job moved in queue");
+                Ok(())
+            }
+        };
+        *jobs = entries.into();
+        result
+    }
+
+    /// Copy the waiting jobs, held jobs included, in the order they would be
+    /// dequeued, each with whether it is held
+    pub async fn waiting_jobs(&self) -> Vec<(QueuedJob, bool)> {
+        let jobs = self.jobs.lock().await;
+        let held = self.held.lock().await;
+        let mut entries: Vec<(QueuedJob, bool)> = jobs
+            .iter()
+            .map(|job| (job.clone(), false))
+            .chain(held.values().map(|job| (job.clone(), true)))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+        entries
+    }
+
+    /// List the waiting jobs, held jobs included, in the order they would be
+    /// dequeued, without removing them
+    pub async fn snapshot(&self) -> Vec<JobSummary> {
+        let now = SystemTime::now();
+        self.waiting_jobs()
+            .await
+            .into_iter()
+            .map(|(job, held)| JobSummary {
+                name: job
+                    .request
+                    .file_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "untitled".to_string()),
+                job_id: job.request.job_id,
+                priority: job.priority,
+                age: now.duration_since(job.created_at).unwrap_or_default(),
+                held,
+            })
+            .collect()
     }
 
     /// Get current queue size
@@ -87,15 +441,21 @@ impl JobQueue {
         self.max_size
     }
 
-    /// Clear the queue
-    pub async fn clear(&self) {
-        // Drain the receiver
-        let mut receiver = self.receiver.lock().await;
-        while let Ok(_job) = receiver.try_recv() {
-            self.queue_size.fetch_sub(1, Ordering::Relaxed);
+    /// Clear the queue, held jobs included, returning the removed jobs in the
+    /// order they would have been dequeued
+    pub async fn clear(&self) -> Vec<PrintJobRequest> {
+        let mut jobs = self.jobs.lock().await;
+        let mut drained = std::mem::take(&mut *jobs).into_vec();
+        drained.extend(self.held.lock().await.drain().map(|(_, job)| job));
+        drained.sort();
+        self.queue_size.store(0, Ordering::Relaxed);
+        self.space.notify_waiters();
+        for job in &drained {
+            self.journal_remove(&job.request.job_id).await;
         }
 
-        info!("Queue cleared. Size: {}", self.size());
+        info!("Queue cleared. Removed {} jobs", drained.len());
+        drained.into_iter().rev().map(|job| job.request).collect()
     }
 }
 
@@ -124,3 +484,229 @@ impl JobQueue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boomaga_core::{FileType, JobId, PrintOptions};
+    use std::path::PathBuf;
+
+    fn request(job_id: &JobId) -> PrintJobRequest {
+        PrintJobRequest {
+            job_id: job_id.clone(),
+            file_path: PathBuf::from("test.pdf"),
+            file_type: FileType::Pdf,
+            printer_name: None,
+            options: PrintOptions::default(),
+            batch_documents: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn equal_priority_jobs_pop_in_submission_order() {
+        let queue = JobQueue::new(4).unwrap();
+        let ids: Vec<JobId> = (0..3).map(|_| JobId::new()).collect();
+        for id in &ids {
+            queue.push(request(id)).await.unwrap();
+        }
+
+        for id in &ids {
+            assert_eq!(&queue.pop().await.unwrap().job_id, id);
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn higher_priority_jobs_pop_first() {
+        let queue = JobQueue::new(4).unwrap();
+        let normal = JobId::new();
+        let urgent = JobId::new();
+        queue.push(request(&normal)).await.unwrap();
+        queue
+            .push_with_priority(request(&urgent), JobPriority::Urgent, SystemTime::now())
+            .await
+            .unwrap();
+
+        assert_eq!(queue.pop().await.unwrap().job_id, urgent);
+        assert_eq!(queue.pop().await.unwrap().job_id, normal);
+    }
+
+    #[tokio::test]
+    async fn held_jobs_are_skipped_until_released() {
+        let queue = JobQueue::new(4).unwrap();
+        let first = JobId::new();
+        let second = JobId::new();
+        queue.push(request(&first)).await.unwrap();
+        queue.push(request(&second)).await.unwrap();
+
+        assert!(queue.hold(&first).await);
+        assert!(!queue.hold(&first).await);
+        assert_eq!(queue.size(), 2);
+        assert_eq!(queue.pop().await.unwrap().job_id, second);
+
+        assert!(queue.release(&first).await);
+        assert!(!queue.release(&first).await);
+        assert_eq!(queue.pop().await.unwrap().job_id, first);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn snapshot_lists_jobs_in_priority_order_without_dequeuing() {
+        let queue = JobQueue::new(4).unwrap();
+        let submitted = SystemTime::now() - Duration::from_secs(60);
+        let low = JobId::new();
+        let normal = JobId::new();
+        let urgent = JobId::new();
+        let jobs = [(&low, JobPriority::Low), (&normal, JobPriority::Normal), (&urgent, JobPriority::Urgent)];
+        for (id, priority) in jobs {
+            queue.push_with_priority(request(id), priority, submitted).await.unwrap();
+        }
+
+        let snapshot = queue.snapshot().await;
+        let order: Vec<_> = snapshot.iter().map(|job| (&job.job_id, job.priority)).collect();
+        assert_eq!(
+            order,
+            vec![(&urgent, JobPriority::Urgent), (&normal, JobPriority::Normal), (&low, JobPriority::Low)]
+        );
+        assert!(snapshot.iter().all(|job| job.name == "test.pdf" && job.age >= Duration::from_secs(60)));
+        assert_eq!(queue.size(), 3);
+        assert_eq!(queue.pop().await.unwrap().job_id, urgent);
+    }
+
+    #[tokio::test]
+    async fn moving_a_job_to_the_front_changes_pop_order() {
+        let queue = JobQueue::new(4).unwrap();
+        let ids: Vec<JobId> = (0..3).map(|_| JobId::new()).collect();
+        for id in &ids {
+            queue.push(request(id)).await.unwrap();
+        }
+
+        queue.move_to_front(&ids[1]).await.unwrap();
+        // A more urgent job arriving later does not overtake the moved one
+        let urgent = JobId::new();
+        queue
+            .push_with_priority(request(&urgent), JobPriority::Urgent, SystemTime::now())
+            .await
+            .unwrap();
+
+        for id in [&ids[1], &urgent, &ids[0], &ids[2]] {
+            assert_eq!(&queue.pop().await.unwrap().job_id, id);
+        }
+    }
+
+    #[tokio::test]
+    async fn moving_a_job_not_waiting_fails() {
+        let queue = JobQueue::new(4).unwrap();
+        let waiting = JobId::new();
+        let held = JobId::new();
+        queue.push(request(&waiting)).await.unwrap();
+        queue.push(request(&held)).await.unwrap();
+        queue.hold(&held).await;
+
+        assert!(matches!(queue.move_to_front(&JobId::new()).await, Err(Error::NotFound(_))));
+        assert!(matches!(queue.move_to_front(&held).await, Err(Error::Validation(_))));
+        assert!(matches!(queue.move_to_position(&waiting, 1).await, Err(Error::Validation(_))));
+        assert_eq!(queue.snapshot().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn journalled_jobs_are_restored_by_a_new_queue() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let journal = dir.path().join("queue");
+        let document = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"%PDF-1.4").unwrap();
+            path
+        };
+        let with_document = |job_id: &JobId, path: PathBuf| PrintJobRequest {
+            file_path: path,
+            ..request(job_id)
+        };
+
+        let queue = JobQueue::new(4).unwrap().with_journal(journal.clone()).unwrap();
+        let (started, pending, held, gone) = (JobId::new(), JobId::new(), JobId::new(), JobId::new());
+        let jobs = [(&started, "started.pdf"), (&pending, "pending.pdf"), (&held, "held.pdf"), (&gone, "gone.pdf")];
+        for (id, name) in jobs {
+            queue.push(with_document(id, document(name))).await.unwrap();
+        }
+        assert_eq!(queue.pop().await.unwrap().job_id, started);
+        queue.hold(&held).await;
+        std::fs::remove_file(dir.path().join("gone.pdf")).unwrap();
+        drop(queue);
+
+        // A restart finds the jobs that had not started and still have a document
+        let queue = JobQueue::new(4).unwrap().with_journal(journal.clone()).unwrap();
+        let restored: Vec<_> = queue.snapshot().await.into_iter().map(|job| (job.job_id, job.held)).collect();
+        assert_eq!(restored, vec![(pending.clone(), false), (held.clone(), true)]);
+        assert_eq!(queue.size(), 2);
+        assert_eq!(queue.pop().await.unwrap().job_id, pending);
+        assert_eq!(std::fs::read_dir(&journal).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn journalled_batches_need_all_their_documents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let journal = dir.path().join("queue");
+        let documents: Vec<PathBuf> = ["first.pdf", "second.pdf"]
+            .into_iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                std::fs::write(&path, b"%PDF-1.4").unwrap();
+                path
+            })
+            .collect();
+        let batch = PrintJobRequest {
+            file_path: documents[0].clone(),
+            batch_documents: documents[1..].to_vec(),
+            ..request(&JobId::new())
+        };
+
+        let queue = JobQueue::new(4).unwrap().with_journal(journal.clone()).unwrap();
+        queue.push(batch.clone()).await.unwrap();
+        drop(queue);
+        let queue = JobQueue::new(4).unwrap().with_journal(journal.clone()).unwrap();
+        assert_eq!(queue.pop().await.unwrap().batch_documents, documents[1..]);
+
+        queue.push(batch).await.unwrap();
+        drop(queue);
+        std::fs::remove_file(&documents[1]).unwrap();
+        let queue = JobQueue::new(4).unwrap().with_journal(journal).unwrap();
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn push_into_a_full_queue_waits_for_the_queue_timeout() {
+        let queue = Arc::new(JobQueue::new(1).unwrap().with_timeout(Duration::from_secs(1)));
+        queue.push(request(&JobId::new())).await.unwrap();
+
+        let started = Instant::now();
+        let result = queue.push(request(&JobId::new())).await;
+        assert!(matches!(result, Err(Error::Busy(_))));
+        assert!(started.elapsed() >= Duration::from_secs(1));
+
+        // A job leaving during the wait makes room
+        let waiting = tokio::spawn({
+            let queue = Arc::clone(&queue);
+            async move { queue.push(request(&JobId::new())).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        queue.pop().await.unwrap();
+        waiting.await.unwrap().unwrap();
+        assert_eq!(queue.size(), 1);
+    }
+
+    #[test]
+    fn earlier_submission_orders_first_within_a_priority() {
+        let now = SystemTime::now();
+        let entry = |created_at, sequence| QueuedJob {
+            request: request(&JobId::new()),
+            priority: JobPriority::Normal,
+            created_at,
+            sequence,
+            pinned: None,
+        };
+
+        assert!(entry(now, 1) > entry(now + Duration::from_secs(1), 0));
+        assert!(entry(now, 0) > entry(now, 1));
+    }
+}
@@ -4,12 +4,21 @@
 //! that receives print jobs and manages the print queue.
 
 mod server;
+mod control;
 mod job_processor;
 mod job_queue;
+mod document_cache;
+mod discovery;
+mod health;
+mod ipp;
+mod metrics;
 
-use tracing::{info, error, warn, Level};
+use tracing::{info, error, warn, Level, Subscriber};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::fmt::MakeWriter;
 use std::env;
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use boomaga_core::Error;
 
@@ -25,10 +34,27 @@ async fn main() -> boomaga_core::Result<()> {
         Level::INFO
     };
 
-    tracing_subscriber::fmt()
-        .with_max_level(log_level)
-        .with_target(false)
-        .init();
+    let log_format = parse_log_format(&args)
+        .map_err(|e| boomaga_core::Error::Config(e.to_string()))?;
+
+    tracing::subscriber::set_global_default(log_subscriber(log_level, log_format, std::io::stdout))
+        .map_err(|e| boomaga_core::Error::Config(e.to_string()))?;
+
+    if let Some(index) = args.iter().position(|arg| arg == "--reset-config") {
+        let target = args
+            .get(index + 1)
+            .filter(|arg| !arg.starts_with("--"))
+            .map(String::as_str)
+            .unwrap_or("all");
+        return reset_config(target);
+    }
+
+    if args.iter().any(|arg| arg == "--list-printers") {
+        for printer in discovery::discover_printers(discovery::DISCOVERY_TIMEOUT).await {
+            println!("{}\t{}\t{}", printer.name, printer.status, printer.uri);
+        }
+        return Ok(());
+    }
 
     info!("{} v{} starting...", boomaga_core::constants::APP_NAME, boomaga_core::constants::APP_VERSION);
 
@@ -36,41 +62,133 @@ async fn main() -> boomaga_core::Result<()> {
     let config: AppConfig = parse_config(&args)
         .map_err(|e| boomaga_core::Error::Config(e.to_string()))?;
 
+    if args.iter().any(|arg| arg == "--purge") {
+        return purge_running_backend(&config.ipc_socket_path).await;
+    }
+
+    if !config.print_batch.is_empty() {
+        return print_batch_on_running_backend(&config.ipc_socket_path, &config.print_batch).await;
+    }
+
+    if let Some(max_concurrent_jobs) = config.set_concurrent {
+        let patch = boomaga_core::SettingsPatch {
+            max_concurrent_jobs: Some(max_concurrent_jobs),
+            ..Default::default()
+        };
+        return update_running_backend(&config.ipc_socket_path, patch).await;
+    }
+
     info!("Configuration loaded:");
     info!("  - IPC socket: {:?}", config.ipc_socket_path);
     info!("  - D-Bus service: {}", config.dbus_service_name);
+    info!("  - IPP address: {}", config.bind_address);
     info!("  - IPP port: {}", config.ipp_port);
 
-    // Create job queue
-    let job_queue = Arc::new(job_queue::JobQueue::new(config.job_queue_size)?);
-
     // Start backend-to-preview notification socket.
-    let (notification_server, notification_sender) =
+    let (mut notification_server, notification_sender) =
         boomaga_ipc::NotificationServer::bind(config.ipc_socket_path.clone())?;
+    let control_requests = notification_server.requests();
     tokio::spawn(async move {
         if let Err(error) = notification_server.run().await {
             error!("IPC notification server error: {}", error);
         }
     });
 
+    // Render jobs no finer than the user's render quality allows
+    let render_quality = boomaga_config::ConfigManager::new()
+        .map_err(|e| e.to_string())
+        .and_then(|manager| manager.load_settings().map_err(|e| e.to_string()))
+        .map(|settings| settings.performance.render_quality)
+        .unwrap_or_else(|e| {
+            warn!("Failed to load settings, using the default render quality: {}", e);
+            boomaga_config::PerformanceSettings::default().render_quality
+        });
+
+    // Spool documents and size the document cache as the backend
+    // configuration says
+    let backend_config = boomaga_config::ConfigManager::new()
+        .map_err(|e| e.to_string())
+        .and_then(|manager| manager.load_backend().map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            warn!("Failed to load backend configuration, using defaults: {}", e);
+            boomaga_config::BackendConfig::default()
+        });
+    info!("  - Spool directory: {:?}", backend_config.spool_dir);
+    info!("  - Render quality: {:?}", render_quality);
+
+    // Create job queue, restoring the jobs journalled by a previous run
+    let mut job_queue = job_queue::JobQueue::new(config.job_queue_size)?
+        .with_timeout(std::time::Duration::from_secs(backend_config.queue_timeout));
+    if backend_config.persist_queue {
+        job_queue = job_queue.with_journal(backend_config.spool_dir.join("queue"))?;
+    }
+    let job_queue = Arc::new(job_queue);
+
+    // Watch the downstream printer, if there is one
+    let mut downstream = None;
+    if let Some(uri) = &config.downstream_printer {
+        let printer = discovery::probe_printer(uri, uri, true).await;
+        info!("  - Downstream printer: {} ({})", uri, printer.status);
+        let monitor = health::PrinterMonitor::new(printer, notification_sender.clone())
+            .with_interval(config.health_check_interval);
+        downstream = Some(monitor.printer());
+        tokio::spawn(monitor.run(tokio_util::sync::CancellationToken::new()));
+    }
+
     // Start job processor
-    let processor = Arc::new(job_processor::JobProcessor::new(
+    let mut processor = job_processor::JobProcessor::new(
         Arc::clone(&job_queue),
         config.max_concurrent_jobs,
         config.worker_threads,
         notification_sender,
-    )?);
+    )?
+    .with_document_cache(document_cache::DocumentCache::from_config(&backend_config))
+    .with_max_job_size(backend_config.max_job_size)
+    .with_render_quality(render_quality)
+    .with_retry_policy(backend_config.retry)
+    .with_spool_dir(backend_config.spool_dir);
+    if let Some(printer) = downstream {
+        processor = processor.with_downstream_printer(printer, backend_config.unsupported_duplex);
+    }
+    let processor = Arc::new(processor);
+
+    // Restored jobs keep their documents; anything else left behind by a
+    // previous run belongs to no job
+    processor.recover_queued().await;
+    match processor.sweep_spool().await {
+        Ok(0) => {}
+        Ok(removed) => info!("Removed {} orphaned spool files", removed),
+        Err(e) => warn!("Failed to clean the spool directory: {}", e),
+    }
+    processor.start();
+    let config_manager = boomaga_config::ConfigManager::new()
+        .inspect_err(|e| warn!("Configuration updates will not be saved: {}", e))
+        .ok();
+    tokio::spawn(control::serve(control_requests, Arc::clone(&processor), config_manager));
 
     // Start IPP server
-    let mut ipp_server = server::IppServer::new(
+    let ipp_server = server::IppServer::new(
+        config.bind_address,
         config.ipp_port,
         config.ipc_socket_path,
         config.dbus_service_name,
         Arc::clone(&processor),
-    )?;
+    )?
+    .with_max_clients(config.max_clients)
+    .with_idle_timeout(config.idle_timeout)
+    .with_metrics(config.metrics);
 
     info!("Starting IPP server on port {}", config.ipp_port);
 
+    // Stop accepting clients on Ctrl-C
+    let shutdown = ipp_server.shutdown_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Interrupted, shutting down");
+            shutdown.cancel();
+        }
+    });
+
     // Start server
     if let Err(e) = ipp_server.run().await {
         error!("IPP server error: {}", e);
@@ -80,24 +198,174 @@ async fn main() -> boomaga_core::Result<()> {
     Ok(())
 }
 
+/// How long `--purge`, `--set-concurrent` and `--print-batch` wait for the running backend to answer
+const PURGE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Ask the backend listening on `socket_path` to purge its job queue
+async fn purge_running_backend(socket_path: &Path) -> boomaga_core::Result<()> {
+    use boomaga_ipc::{Message, MessageDestination, MessagePayload, MessageSource};
+
+    let transport = boomaga_ipc::UnixSocketTransport::new(socket_path.to_path_buf());
+    let request = Message::new_request(MessageSource::Ipc, MessageDestination::Backend, MessagePayload::PurgeJobs);
+    match transport.request(request, PURGE_TIMEOUT).await?.payload {
+        MessagePayload::PurgeJobsResult { purged } => {
+            println!("Purged {} jobs", purged);
+            Ok(())
+        }
+        payload => Err(Error::Ipc(format!("Unexpected reply to purge: {:?}", payload))),
+    }
+}
+
+/// Ask the backend listening on `socket_path` to print `documents`, in order, as one job
+async fn print_batch_on_running_backend(socket_path: &Path, documents: &[PathBuf]) -> boomaga_core::Result<()> {
+    use boomaga_ipc::{Message, MessageDestination, MessagePayload, MessageSource};
+
+    // The backend opens the documents from its own working directory
+    let documents = documents.iter().map(std::path::absolute).collect::<std::io::Result<Vec<_>>>()?;
+    let batch = boomaga_core::PrintBatch { documents, options: boomaga_core::PrintOptions::default() };
+    let transport = boomaga_ipc::UnixSocketTransport::new(socket_path.to_path_buf());
+    let request = Message::new_request(MessageSource::Ipc, MessageDestination::Backend, MessagePayload::PrintBatch { batch });
+    match transport.request(request, PURGE_TIMEOUT).await?.payload {
+        MessagePayload::PrintBatchResult { job_id: Some(job_id) } => {
+            println!("Queued job {}", job_id);
+            Ok(())
+        }
+        MessagePayload::PrintBatchResult { job_id: None } => {
+            Err(Error::Validation("The backend refused the batch".into()))
+        }
+        payload => Err(Error::Ipc(format!("Unexpected reply to batch: {:?}", payload))),
+    }
+}
+
+/// Send a configuration update to the backend listening on `socket_path`
+async fn update_running_backend(socket_path: &Path, patch: boomaga_core::SettingsPatch) -> boomaga_core::Result<()> {
+    use boomaga_ipc::{Message, MessageDestination, MessagePayload, MessageSource};
+
+    let transport = boomaga_ipc::UnixSocketTransport::new(socket_path.to_path_buf());
+    let payload = MessagePayload::ConfigUpdate { patch };
+    let request = Message::new_request(MessageSource::Ipc, MessageDestination::Backend, payload);
+    match transport.request(request, PURGE_TIMEOUT).await?.payload {
+        MessagePayload::ConfigUpdateResult { outcome } if outcome == boomaga_core::PatchOutcome::default() => {
+            Err(Error::Config("The backend rejected the configuration update".into()))
+        }
+        MessagePayload::ConfigUpdateResult { outcome } => {
+            if !outcome.applied.is_empty() {
+                println!("Applied: {}", outcome.applied.join(", "));
+            }
+            if !outcome.requires_restart.is_empty() {
+                println!("Applied after a restart: {}", outcome.requires_restart.join(", "));
+            }
+            Ok(())
+        }
+        payload => Err(Error::Ipc(format!("Unexpected reply to configuration update: {:?}", payload))),
+    }
+}
+
+/// Restore one configuration file, or all of them, to defaults
+fn reset_config(target: &str) -> boomaga_core::Result<()> {
+    if !matches!(target, "backend" | "preview" | "settings" | "all") {
+        return Err(Error::Config(format!(
+            "Unknown configuration {} (expected backend, preview, settings or all)",
+            target
+        )));
+    }
+
+    let config_error = |e: &dyn std::fmt::Display| Error::Config(e.to_string());
+    let manager = boomaga_config::ConfigManager::new().map_err(|e| config_error(&e))?;
+
+    if matches!(target, "backend" | "all") {
+        manager.reset_backend().map_err(|e| config_error(&e))?;
+        info!("Backend configuration reset: {:?}", manager.backend_config_path());
+    }
+    if matches!(target, "preview" | "all") {
+        manager.reset_preview().map_err(|e| config_error(&e))?;
+        info!("Preview configuration reset: {:?}", manager.preview_config_path());
+    }
+    if matches!(target, "settings" | "all") {
+        manager.reset_settings().map_err(|e| config_error(&e))?;
+        info!("Settings reset: {:?}", manager.settings_path());
+    }
+    Ok(())
+}
+
+/// Log output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+/// Read `--log-format` ahead of the rest of the arguments
+fn parse_log_format(args: &[String]) -> anyhow::Result<LogFormat> {
+    let Some(index) = args.iter().position(|arg| arg == "--log-format") else {
+        return Ok(LogFormat::Text);
+    };
+    match args.get(index + 1).map(String::as_str) {
+        Some("text") => Ok(LogFormat::Text),
+        Some("json") => Ok(LogFormat::Json),
+        Some(other) => anyhow::bail!("Unknown log format: {} (expected text or json)", other),
+        None => anyhow::bail!("--log-format requires a format argument"),
+    }
+}
+
+/// Build the log subscriber for the chosen format
+///
+/// `RUST_LOG` directives, e.g. `boomaga=debug`, take precedence over `level`.
+fn log_subscriber<W>(level: Level, format: LogFormat, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::from_level(level).into())
+        .from_env_lossy();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => Box::new(builder.with_target(false).finish()),
+        LogFormat::Json => Box::new(builder.json().flatten_event(true).finish()),
+    }
+}
+
 /// Application configuration
 struct AppConfig {
     ipc_socket_path: PathBuf,
     dbus_service_name: String,
+    bind_address: IpAddr,
     ipp_port: u16,
+    max_clients: usize,
+    idle_timeout: std::time::Duration,
     max_concurrent_jobs: usize,
     worker_threads: usize,
     job_queue_size: usize,
+    downstream_printer: Option<String>,
+    health_check_interval: std::time::Duration,
+    metrics: bool,
+    /// New concurrent job limit sent to the running backend
+    set_concurrent: Option<usize>,
+    /// Documents sent to the running backend to print as one job
+    print_batch: Vec<PathBuf>,
 }
 
 /// Parse command line arguments and configuration
 fn parse_config(args: &[String]) -> anyhow::Result<AppConfig> {
     let mut ipc_socket_path = std::path::PathBuf::from(boomaga_core::constants::IPC_SOCKET_PATH);
     let mut dbus_service_name = boomaga_core::constants::DBUS_SERVICE_NAME.to_string();
+    let mut bind_address: IpAddr = boomaga_core::constants::DEFAULT_IPP_BIND_ADDRESS.parse()?;
     let mut ipp_port = boomaga_core::constants::IPP_PORT;
+    let mut max_clients = server::DEFAULT_MAX_CLIENTS;
+    let mut idle_timeout = server::DEFAULT_IDLE_TIMEOUT;
     let mut max_concurrent_jobs = boomaga_core::constants::MAX_CONCURRENT_JOBS;
     let mut worker_threads = boomaga_core::constants::WORKER_THREADS;
     let mut job_queue_size = boomaga_core::constants::JOB_QUEUE_SIZE;
+    let mut downstream_printer = None;
+    let mut health_check_interval = health::DEFAULT_HEALTH_CHECK_INTERVAL;
+    let mut metrics = false;
+    let mut set_concurrent = None;
+    let mut print_batch = Vec::new();
 
     // Parse arguments
     let mut i = 1;
@@ -119,6 +387,16 @@ fn parse_config(args: &[String]) -> anyhow::Result<AppConfig> {
                     anyhow::bail!("--dbus requires a service name argument");
                 }
             }
+            "--bind" => {
+                if i + 1 < args.len() {
+                    bind_address = args[i + 1]
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("Invalid --bind address {}: {}", args[i + 1], e))?;
+                    i += 2;
+                } else {
+                    anyhow::bail!("--bind requires an address argument");
+                }
+            }
             "--port" => {
                 if i + 1 < args.len() {
                     ipp_port = args[i + 1].parse().unwrap_or(631);
@@ -127,6 +405,28 @@ fn parse_config(args: &[String]) -> anyhow::Result<AppConfig> {
                     anyhow::bail!("--port requires a port number argument");
                 }
             }
+            "--max-clients" => {
+                if i + 1 < args.len() {
+                    max_clients = args[i + 1].parse().unwrap_or(server::DEFAULT_MAX_CLIENTS);
+                    i += 2;
+                } else {
+                    anyhow::bail!("--max-clients requires a number argument");
+                }
+            }
+            "--idle-timeout" => {
+                if i + 1 < args.len() {
+                    let seconds: u64 = args[i + 1]
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("Invalid --idle-timeout {}: {}", args[i + 1], e))?;
+                    if seconds == 0 {
+                        anyhow::bail!("--idle-timeout must be greater than 0");
+                    }
+                    idle_timeout = std::time::Duration::from_secs(seconds);
+                    i += 2;
+                } else {
+                    anyhow::bail!("--idle-timeout requires a number of seconds");
+                }
+            }
             "--concurrent" => {
                 if i + 1 < args.len() {
                     max_concurrent_jobs = args[i + 1].parse().unwrap_or(4);
@@ -151,6 +451,65 @@ fn parse_config(args: &[String]) -> anyhow::Result<AppConfig> {
                     anyhow::bail!("--queue-size requires a number argument");
                 }
             }
+            "--printer" => {
+                if i + 1 < args.len() {
+                    downstream_printer = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    anyhow::bail!("--printer requires a printer URI argument");
+                }
+            }
+            "--health-interval" => {
+                if i + 1 < args.len() {
+                    let seconds: u64 = args[i + 1]
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("Invalid --health-interval {}: {}", args[i + 1], e))?;
+                    if seconds == 0 {
+                        anyhow::bail!("--health-interval must be greater than 0");
+                    }
+                    health_check_interval = std::time::Duration::from_secs(seconds);
+                    i += 2;
+                } else {
+                    anyhow::bail!("--health-interval requires a number of seconds");
+                }
+            }
+            "--metrics" => {
+                metrics = true;
+                i += 1;
+            }
+            "--log-format" => {
+                // Already applied by `parse_log_format`
+                i += 2;
+            }
+            "--list-printers" => {
+                // Handled before startup
+                i += 1;
+            }
+            "--set-concurrent" => {
+                if i + 1 < args.len() {
+                    let limit = args[i + 1]
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("Invalid --set-concurrent limit {}: {}", args[i + 1], e))?;
+                    set_concurrent = Some(limit);
+                    i += 2;
+                } else {
+                    anyhow::bail!("--set-concurrent requires a number argument");
+                }
+            }
+            "--purge" => {
+                // Handled once the socket path is known
+                i += 1;
+            }
+            "--print-batch" => {
+                i += 1;
+                while i < args.len() && !args[i].starts_with("--") {
+                    print_batch.push(PathBuf::from(&args[i]));
+                    i += 1;
+                }
+                if print_batch.is_empty() {
+                    anyhow::bail!("--print-batch requires at least one document");
+                }
+            }
             "--help" | "-h" => {
                 print_help();
                 std::process::exit(0);
@@ -165,10 +524,18 @@ fn parse_config(args: &[String]) -> anyhow::Result<AppConfig> {
     Ok(AppConfig {
         ipc_socket_path,
         dbus_service_name,
+        bind_address,
         ipp_port,
+        max_clients,
+        idle_timeout,
         max_concurrent_jobs,
         worker_threads,
         job_queue_size,
+        downstream_printer,
+        health_check_interval,
+        metrics,
+        set_concurrent,
+        print_batch,
     })
 }
 
@@ -181,10 +548,22 @@ fn print_help() {
     println!("Options:");
     println!("  --socket <path>        Set IPC socket path (default: {})", boomaga_core::constants::IPC_SOCKET_PATH);
     println!("  --dbus <name>          Set D-Bus service name (default: {})", boomaga_core::constants::DBUS_SERVICE_NAME);
+    println!("  --bind <address>       Set IPP listen address, e.g. 0.0.0.0 for all interfaces (default: {})", boomaga_core::constants::DEFAULT_IPP_BIND_ADDRESS);
     println!("  --port <number>        Set IPP port (default: {})", boomaga_core::constants::IPP_PORT);
+    println!("  --max-clients <number> Maximum simultaneous IPP clients (default: {})", server::DEFAULT_MAX_CLIENTS);
+    println!("  --idle-timeout <s>     Seconds a silent IPP client is kept (default: {})", server::DEFAULT_IDLE_TIMEOUT.as_secs());
     println!("  --concurrent <number>  Maximum concurrent jobs (default: {})", boomaga_core::constants::MAX_CONCURRENT_JOBS);
     println!("  --workers <number>     Number of worker threads (default: {})", boomaga_core::constants::WORKER_THREADS);
     println!("  --queue-size <number>  Job queue size (default: {})", boomaga_core::constants::JOB_QUEUE_SIZE);
+    println!("  --printer <uri>        Downstream IPP printer, monitored and checked for two-sided support");
+    println!("  --health-interval <s>  Seconds between downstream printer checks (default: {})", health::DEFAULT_HEALTH_CHECK_INTERVAL.as_secs());
+    println!("  --metrics              Serve Prometheus metrics at /metrics on the IPP port");
+    println!("  --log-format <format>  Log output format: text or json (default: text)");
+    println!("  --list-printers        List IPP printers found on the network and exit");
+    println!("  --purge                Cancel every job of the running backend on --socket and exit");
+    println!("  --set-concurrent <n>   Change the concurrent job limit of the running backend on --socket and exit");
+    println!("  --print-batch <file>...  Print documents, in order, as one job on the running backend on --socket and exit");
+    println!("  --reset-config [which] Restore backend, preview, settings or all (default) config files and exit");
     println!("  --debug                 Enable debug logging");
     println!("  --help, -h              Show this help message");
     println!();
@@ -193,3 +572,47 @@ fn print_help() {
     println!();
     println!("{}", boomaga_core::constants::APP_DESCRIPTION);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Log writer collecting output in memory
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_log_format_emits_json_lines() {
+        let args: Vec<String> = ["boomaga-ipp-backend", "--log-format", "json"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let format = parse_log_format(&args).unwrap();
+        let captured = Captured::default();
+        let writer = captured.clone();
+
+        let subscriber = log_subscriber(Level::INFO, format, move || writer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            info!(job_id = "42", "Job completed successfully");
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "Job completed successfully");
+        assert_eq!(line["job_id"], "42");
+        assert!(line["timestamp"].is_string());
+    }
+}
@@ -0,0 +1,79 @@
+//! Backend metrics in the Prometheus text exposition format
+
+use crate::job_processor::JobProcessor;
+use boomaga_core::JobStatus;
+use std::fmt::Write;
+
+/// Upper bounds, in seconds, of the job duration histogram buckets
+const DURATION_BUCKETS: [f64; 8] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+/// Every job status, so each has a series even while it counts no jobs
+const JOB_STATUSES: [JobStatus; 7] = [
+    JobStatus::Queued,
+    JobStatus::Held,
+    JobStatus::Processing,
+    JobStatus::Completed,
+    JobStatus::Cancelled,
+    JobStatus::Failed,
+    JobStatus::Aborted,
+];
+
+/// Processing times of completed jobs
+///
+/// Observed as each job completes, so the histogram only ever grows.
+#[derive(Debug, Clone, Default)]
+pub struct DurationHistogram {
+    /// Observations at or below each of `DURATION_BUCKETS`
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl DurationHistogram {
+    /// Record one job's processing time
+    pub fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Render the current metrics
+///
+/// Job counts are gauges over the jobs the processor knows about, by their
+/// current status.
+pub async fn render(processor: &JobProcessor, active_clients: usize) -> String {
+    let jobs = processor.list_jobs().await;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP boomaga_queue_size Jobs waiting in the queue.");
+    let _ = writeln!(out, "# TYPE boomaga_queue_size gauge");
+    let _ = writeln!(out, "boomaga_queue_size {}", processor.queue_size());
+
+    let _ = writeln!(out, "# HELP boomaga_jobs Jobs known to the backend, by current status.");
+    let _ = writeln!(out, "# TYPE boomaga_jobs gauge");
+    for status in JOB_STATUSES {
+        let count = jobs.iter().filter(|job| job.status == status).count();
+        let _ = writeln!(out, "boomaga_jobs{{status=\"{}\"}} {}", status.to_string().to_lowercase(), count);
+    }
+
+    let durations = processor.job_durations();
+    let _ = writeln!(out, "# HELP boomaga_job_duration_seconds Time spent processing completed jobs.");
+    let _ = writeln!(out, "# TYPE boomaga_job_duration_seconds histogram");
+    for (bound, count) in DURATION_BUCKETS.iter().zip(durations.buckets) {
+        let _ = writeln!(out, "boomaga_job_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+    }
+    let _ = writeln!(out, "boomaga_job_duration_seconds_bucket{{le=\"+Inf\"}} {}", durations.count);
+    let _ = writeln!(out, "boomaga_job_duration_seconds_sum {}", durations.sum);
+    let _ = writeln!(out, "boomaga_job_duration_seconds_count {}", durations.count);
+
+    let _ = writeln!(out, "# HELP boomaga_active_clients IPP client connections being served.");
+    let _ = writeln!(out, "# TYPE boomaga_active_clients gauge");
+    let _ = writeln!(out, "boomaga_active_clients {}", active_clients);
+
+    out
+}
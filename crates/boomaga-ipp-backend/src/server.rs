@@ -1,12 +1,25 @@
 //! IPP server implementation
 
 use std::collections::HashMap;
-use std::net::{TcpListener, TcpStream};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{info, warn, debug};
-use boomaga_core::{JobId, PrintJobRequest, PrintOptions, Error, Uuid, FileType};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument, warn, debug};
+use boomaga_core::{
+    ColorMode, DuplexMode, Error, FileType, JobId, JobMetadata, JobStatus, MarginMode, Media, Orientation,
+    PagesPerSheet, PrintJobRequest, PrintOptions, PrintQuality, PrinterCapabilities, PrinterStatus, Uuid,
+};
+use crate::ipp::{
+    encode_attribute, parse_attributes, IppValue, END_OF_ATTRIBUTES_TAG, JOB_ATTRIBUTES_TAG, NO_VALUE_TAG,
+    OPERATION_ATTRIBUTES_TAG, PRINTER_ATTRIBUTES_TAG,
+};
+use crate::discovery::{page_size_from_dimensions, page_size_from_media};
 use crate::job_processor::JobProcessor;
+use crate::metrics;
 
 /// IPP version
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,24 +28,53 @@ pub enum IppVersion {
     Ipp2_1,
 }
 
-/// IPP operation codes
+/// IPP operation codes (RFC 8011, section 5.4.15; Close-Job from PWG 5100.11)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
 pub enum IppOperation {
-    GetPrinterAttributes = 0x0002,
-    GetJobs = 0x0010,
-    CreateJob = 0x0004,
-    SendDocument = 0x0011,
-    CloseJob = 0x0006,
-    CancelJob = 0x0005,
-    ValidateJob = 0x000A,
+    PrintJob = 0x0002,
+    ValidateJob = 0x0004,
+    CreateJob = 0x0005,
+    SendDocument = 0x0006,
+    CancelJob = 0x0008,
     GetJobAttributes = 0x0009,
+    GetJobs = 0x000A,
+    GetPrinterAttributes = 0x000B,
+    HoldJob = 0x000C,
+    ReleaseJob = 0x000D,
+    PurgeJobs = 0x0012,
+    PromoteJob = 0x0030,
+    CloseJob = 0x003B,
+}
+
+impl TryFrom<u16> for IppOperation {
+    type Error = Error;
+
+    fn try_from(code: u16) -> Result<Self, Error> {
+        Ok(match code {
+            0x0002 => IppOperation::PrintJob,
+            0x0004 => IppOperation::ValidateJob,
+            0x0005 => IppOperation::CreateJob,
+            0x0006 => IppOperation::SendDocument,
+            0x0008 => IppOperation::CancelJob,
+            0x0009 => IppOperation::GetJobAttributes,
+            0x000A => IppOperation::GetJobs,
+            0x000B => IppOperation::GetPrinterAttributes,
+            0x000C => IppOperation::HoldJob,
+            0x000D => IppOperation::ReleaseJob,
+            0x0012 => IppOperation::PurgeJobs,
+            0x0030 => IppOperation::PromoteJob,
+            0x003B => IppOperation::CloseJob,
+            _ => return Err(Error::Unsupported(format!("Operation 0x{:04x} not supported", code))),
+        })
+    }
 }
 
 /// IPP request
 pub struct IppRequest {
     pub version: IppVersion,
     pub operation_id: IppOperation,
-    pub request_id: u16,
+    pub request_id: u32,
     pub attributes: HashMap<String, Vec<String>>,
     pub data: Vec<u8>,
 }
@@ -41,196 +83,1819 @@ pub struct IppRequest {
 pub struct IppResponse {
     pub status_code: IppStatusCode,
     pub operation_id: IppOperation,
-    pub request_id: u16,
-    pub attributes: HashMap<String, Vec<String>>,
+    pub request_id: u32,
+    pub attributes: HashMap<String, Vec<IppValue>>,
+    /// One job attributes group per job, for Get-Jobs
+    pub jobs: Vec<HashMap<String, Vec<IppValue>>>,
 }
 
-/// IPP status codes
+/// IPP status codes (RFC 8011, section 5.4.15)
+///
+/// The discriminants are the values sent on the wire.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
 pub enum IppStatusCode {
     Successful = 0x0000,
-    ClientError = 0x0040,
-    ServerError = 0x0080,
-    BadRequest = 0x0041,
-    NotFound = 0x0044,
-    InternalError = 0x0081,
-    ServiceUnavailable = 0x0086,
+    BadRequest = 0x0400,
+    NotAuthorized = 0x0403,
+    NotFound = 0x0406,
+    ClientErrorDocumentFormatNotSupported = 0x040A,
+    ClientErrorAttributesOrValuesNotSupported = 0x040B,
+    InternalError = 0x0500,
+    NotSupported = 0x0501,
+    ServiceUnavailable = 0x0502,
+    ServerErrorBusy = 0x0507,
+}
+
+impl IppStatusCode {
+    /// Get the status code as sent on the wire
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Check whether the code is in the client error class (0x0400-0x04FF)
+    pub fn is_client_error(self) -> bool {
+        (0x0400..0x0500).contains(&self.code())
+    }
+}
+
+impl From<&Error> for IppStatusCode {
+    fn from(error: &Error) -> Self {
+        match error.root() {
+            Error::Validation(_) => IppStatusCode::BadRequest,
+            Error::Permission(_) => IppStatusCode::NotAuthorized,
+            Error::NotFound(_) => IppStatusCode::NotFound,
+            Error::Unsupported(_) => IppStatusCode::ClientErrorAttributesOrValuesNotSupported,
+            Error::Timeout(_) => IppStatusCode::ServiceUnavailable,
+            Error::Busy(_) => IppStatusCode::ServerErrorBusy,
+            _ => IppStatusCode::InternalError,
+        }
+    }
 }
 
+/// Default number of clients served at once
+pub const DEFAULT_MAX_CLIENTS: usize = 64;
+
+/// Default time a silent client is kept before it is dropped
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Seconds a busy or unavailable server asks clients to wait before retrying
+const RETRY_AFTER_SECS: u64 = 10;
+
+/// Language of responses when the client asks for one we do not support
+pub const DEFAULT_NATURAL_LANGUAGE: &str = "en";
+
+/// Languages human-readable attribute values are translated into
+pub const GENERATED_LANGUAGES: [&str; 2] = ["en", "de"];
+
+/// Document formats accepted in `document-format`
+const DOCUMENT_FORMATS: [(&str, FileType); 3] = [
+    ("application/pdf", FileType::Pdf),
+    ("application/postscript", FileType::PostScript),
+    ("image/jpeg", FileType::Jpeg),
+];
+
+/// `document-format` asking for the format to be detected from the document
+const AUTO_DETECT_FORMAT: &str = "application/octet-stream";
+
+/// Keywords accepted in `print-color-mode`
+const PRINT_COLOR_MODES: [&str; 7] = [
+    "auto",
+    "auto-monochrome",
+    "bi-level",
+    "color",
+    "monochrome",
+    "process-bi-level",
+    "process-monochrome",
+];
+
+/// Longest HTTP request or header line accepted
+const MAX_HTTP_LINE: usize = 8 * 1024;
+
+/// Most HTTP headers accepted in one request
+const MAX_HTTP_HEADERS: usize = 100;
+
+/// Largest HTTP request body accepted, document included
+const MAX_HTTP_BODY: usize = 256 * 1024 * 1024;
+
 /// Client handler data
 struct ClientData {
     processor: Arc<JobProcessor>,
-    clients: Arc<RwLock<HashMap<u32, TcpStream>>>,
+    clients: Arc<RwLock<HashMap<u32, SocketAddr>>>,
+    /// Cancelled when the server shuts down
+    shutdown: CancellationToken,
+    idle_timeout: Duration,
+    capabilities: Arc<PrinterCapabilities>,
+    metrics: bool,
+}
+
+/// An HTTP request carrying an IPP message
+struct HttpRequest {
+    method: String,
+    target: String,
+    body: Vec<u8>,
+    /// Whether the client wants the connection kept open afterwards
+    keep_alive: bool,
 }
 
 /// IPP server
 pub struct IppServer {
+    bind_address: IpAddr,
     port: u16,
     ipc_socket_path: std::path::PathBuf,
     dbus_service_name: String,
     processor: Arc<JobProcessor>,
-    running: Arc<RwLock<bool>>,
-    clients: Arc<RwLock<HashMap<u32, TcpStream>>>,
+    /// Cancelled to stop accepting clients and close the open connections
+    shutdown: CancellationToken,
+    /// Addresses of the clients being served
+    clients: Arc<RwLock<HashMap<u32, SocketAddr>>>,
     client_counter: Arc<RwLock<u32>>,
+    client_slots: Arc<Semaphore>,
+    idle_timeout: Duration,
+    capabilities: Arc<PrinterCapabilities>,
+    metrics: bool,
 }
 
 impl IppServer {
     /// Create a new IPP server
     pub fn new(
+        bind_address: IpAddr,
         port: u16,
         ipc_socket_path: std::path::PathBuf,
         dbus_service_name: String,
         processor: Arc<JobProcessor>,
     ) -> Result<Self, Error> {
         Ok(Self {
+            bind_address,
             port,
             ipc_socket_path,
             dbus_service_name,
             processor: Arc::clone(&processor),
-            running: Arc::new(RwLock::new(false)),
+            shutdown: CancellationToken::new(),
             clients: Arc::new(RwLock::new(HashMap::new())),
             client_counter: Arc::new(RwLock::new(0)),
+            client_slots: Arc::new(Semaphore::new(DEFAULT_MAX_CLIENTS)),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            capabilities: Arc::new(PrinterCapabilities {
+                supported_languages: GENERATED_LANGUAGES.iter().map(|language| language.to_string()).collect(),
+                ..PrinterCapabilities::default()
+            }),
+            metrics: false,
         })
     }
 
-    /// Start the IPP server
-    pub async fn run(&mut self) -> Result<(), Error> {
-        *self.running.write().await = true;
+    /// Limit how many clients are served at once
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.client_slots = Arc::new(Semaphore::new(max_clients));
+        self
+    }
 
-        let listener = TcpListener::bind(format!("127.0.0.1:{}", self.port))?;
-        info!("IPP server listening on 127.0.0.1:{}", self.port);
+    /// Drop clients that send or accept nothing for this long
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
 
-        loop {
-            match listener.accept() {
-                Ok((stream, addr)) => {
-                    let client_id = *self.client_counter.write().await;
-                    *self.client_counter.write().await = client_id + 1;
+    /// Serve Prometheus metrics to `GET /metrics` on the IPP port
+    pub fn with_metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
 
-                    info!("New client connected: {} (ID: {})", addr, client_id);
+    /// Get the token that stops the server when cancelled
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
 
-                    // Store client connection
-                    {
-                        let mut clients = self.clients.write().await;
-                        clients.insert(client_id, stream);
-                    }
+    /// Start the IPP server
+    ///
+    /// Serves clients until the shutdown token is cancelled.
+    pub async fn run(&self) -> Result<(), Error> {
+        let listener = self.bind().await?;
 
-                    // Handle client in a task
-                    let client_data = ClientData {
-                        processor: Arc::clone(&self.processor),
-                        clients: Arc::clone(&self.clients),
-                    };
-                    tokio::spawn(Self::handle_client(client_data, client_id, addr));
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => {
+                    info!("IPP server shutting down");
+                    break;
                 }
-                Err(e) => {
-                    if *self.running.read().await {
-                        warn!("Error accepting client: {}", e);
-                    } else {
-                        break;
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        self.accept_client(stream, addr).await;
                     }
-                }
+                    Err(e) => warn!(error = %e, "Error accepting client"),
+                },
             }
         }
 
         Ok(())
     }
 
-    /// Handle a client connection
-    async fn handle_client(client_data: ClientData, client_id: u32, addr: std::net::SocketAddr) -> Result<(), Error> {
-        // Read IPP request (placeholder - implement real parsing)
-        let request = IppRequest {
-            version: IppVersion::Ipp2_0,
+    /// Bind the IPP listener to the configured address
+    async fn bind(&self) -> Result<TcpListener, Error> {
+        let listener = TcpListener::bind(SocketAddr::new(self.bind_address, self.port)).await?;
+        info!("IPP server listening on {}", listener.local_addr()?);
+        Ok(listener)
+    }
+
+    /// Start serving a client, or turn it away when every slot is taken
+    ///
+    /// Returns whether the client was accepted.
+    async fn accept_client(&self, stream: TcpStream, addr: SocketAddr) -> bool {
+        let Ok(permit) = Arc::clone(&self.client_slots).try_acquire_owned() else {
+            warn!(%addr, "Too many clients, rejecting connection");
+            Self::reject_client(stream).await;
+            return false;
+        };
+
+        let client_id = *self.client_counter.write().await;
+        *self.client_counter.write().await = client_id + 1;
+
+        info!(client_id, %addr, "New client connected");
+
+        // Track the client while it is served
+        {
+            let mut clients = self.clients.write().await;
+            clients.insert(client_id, addr);
+        }
+
+        // Handle client in a task, holding its slot until it finishes
+        let client_data = ClientData {
+            processor: Arc::clone(&self.processor),
+            clients: Arc::clone(&self.clients),
+            shutdown: self.shutdown.child_token(),
+            idle_timeout: self.idle_timeout,
+            capabilities: Arc::clone(&self.capabilities),
+            metrics: self.metrics,
+        };
+        tokio::spawn(Self::serve_client(client_data, stream, client_id, addr, permit));
+        true
+    }
+
+    /// Run a client handler and release its slot afterwards
+    async fn serve_client(
+        client_data: ClientData,
+        stream: TcpStream,
+        client_id: u32,
+        addr: SocketAddr,
+        _permit: OwnedSemaphorePermit,
+    ) -> Result<(), Error> {
+        Self::handle_client(client_data, stream, client_id, addr).await
+    }
+
+    /// Answer with `ServiceUnavailable` and close the connection
+    async fn reject_client(mut stream: TcpStream) {
+        let response = IppResponse {
+            status_code: IppStatusCode::ServiceUnavailable,
             operation_id: IppOperation::GetPrinterAttributes,
-            request_id: 1,
+            request_id: 0,
             attributes: HashMap::new(),
-            data: Vec::new(),
+            jobs: Vec::new(),
+        };
+        let reply = Self::encode_http_response(&response, false);
+        match tokio::time::timeout(DEFAULT_IDLE_TIMEOUT, stream.write_all(&reply)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => debug!(error = %e, "Could not send ServiceUnavailable response"),
+            Err(_) => debug!("Client stopped reading the ServiceUnavailable response"),
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    /// Handle a client connection
+    #[instrument(skip(client_data, stream), fields(%addr))]
+    async fn handle_client(
+        client_data: ClientData,
+        stream: TcpStream,
+        client_id: u32,
+        addr: SocketAddr,
+    ) -> Result<(), Error> {
+        let result = Self::serve_connection(&client_data, stream).await;
+        if let Err(e) = &result {
+            warn!(error = %e, "Client connection failed");
+        }
+
+        // Remove client connection
+        {
+            let mut clients = client_data.clients.write().await;
+            clients.remove(&client_id);
+        }
+
+        result
+    }
+
+    /// Answer requests on a client connection until the client is done
+    ///
+    /// The connection is kept open between requests, as CUPS sends
+    /// Validate-Job, Create-Job and Send-Document over one connection. It
+    /// is closed once the client asks for `Connection: close`, hangs up,
+    /// stays silent for the idle timeout or the server shuts down.
+    async fn serve_connection(client_data: &ClientData, stream: TcpStream) -> Result<(), Error> {
+        let mut stream = BufReader::new(stream);
+
+        loop {
+            let next = tokio::select! {
+                _ = client_data.shutdown.cancelled() => {
+                    debug!("Server shutting down, closing the connection");
+                    return Ok(());
+                }
+                next = tokio::time::timeout(client_data.idle_timeout, Self::read_http_request(&mut stream)) => next,
+            };
+            let request = match next {
+                Ok(Ok(Some(request))) => request,
+                Ok(Ok(None)) => {
+                    debug!("Client closed the connection");
+                    return Ok(());
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) => {
+                    debug!("Client idle, closing the connection");
+                    return Ok(());
+                }
+            };
+
+            let (reply, keep_alive) = if request.method == "POST" {
+                let response = Self::respond(&client_data.processor, &client_data.capabilities, &request.body).await;
+                debug!(status = ?response.status_code, keep_alive = request.keep_alive, "Sending response");
+                (Self::encode_http_response(&response, request.keep_alive), request.keep_alive)
+            } else if request.method == "GET" && request.target == "/metrics" && client_data.metrics {
+                let active_clients = client_data.clients.read().await.len();
+                let body = metrics::render(&client_data.processor, active_clients).await;
+                (Self::encode_metrics_response(&body, request.keep_alive), request.keep_alive)
+            } else {
+                debug!(method = %request.method, "Rejecting non-IPP request");
+                let reply =
+                    b"HTTP/1.1 405 Method Not Allowed\r\nAllow: POST\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                (reply.to_vec(), false)
+            };
+            tokio::time::timeout(client_data.idle_timeout, stream.write_all(&reply))
+                .await
+                .map_err(|_| Error::Timeout("Client stopped reading the response".to_string()))??;
+
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read one HTTP request, or `None` if the client closed the connection
+    /// before sending one
+    ///
+    /// Bodies are read by `Content-Length` or chunked transfer encoding.
+    /// `Expect: 100-continue` is answered before the body is read.
+    async fn read_http_request<S>(stream: &mut S) -> Result<Option<HttpRequest>, Error>
+    where
+        S: AsyncBufRead + AsyncWrite + Unpin,
+    {
+        // Blank lines between requests are allowed (RFC 9112 §2.2)
+        let request_line = loop {
+            let line = Self::read_http_line(stream).await?;
+            if line.is_empty() {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                break line;
+            }
+        };
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(Error::Ipp(format!("Malformed HTTP request line: {}", request_line.trim())));
+        };
+
+        let mut keep_alive = version == "HTTP/1.1";
+        let mut content_length = 0;
+        let mut chunked = false;
+        let mut expect_continue = false;
+        let mut headers = 0;
+        loop {
+            let line = Self::read_http_line(stream).await?;
+            if line.is_empty() {
+                return Err(Error::Ipp("Connection closed inside HTTP headers".to_string()));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            headers += 1;
+            if headers > MAX_HTTP_HEADERS {
+                return Err(Error::Ipp("Too many HTTP headers".to_string()));
+            }
+
+            let Some((name, value)) = line.split_once(':') else {
+                return Err(Error::Ipp(format!("Malformed HTTP header: {}", line)));
+            };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value
+                        .parse()
+                        .map_err(|_| Error::Ipp(format!("Invalid Content-Length: {}", value)))?;
+                }
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                "connection" => {
+                    for option in value.split(',').map(str::trim) {
+                        if option.eq_ignore_ascii_case("close") {
+                            keep_alive = false;
+                        } else if option.eq_ignore_ascii_case("keep-alive") {
+                            keep_alive = true;
+                        }
+                    }
+                }
+                "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+                _ => {}
+            }
+        }
+
+        if expect_continue {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+        }
+        let body = if chunked {
+            Self::read_chunked_body(stream).await?
+        } else {
+            if content_length > MAX_HTTP_BODY {
+                return Err(Error::Ipp(format!("HTTP body of {} bytes is too large", content_length)));
+            }
+            let mut body = vec![0; content_length];
+            stream.read_exact(&mut body).await?;
+            body
         };
 
-        // Process request
-        let response = match Self::process_request(&client_data.processor, request).await {
+        Ok(Some(HttpRequest {
+            method: method.to_string(),
+            target: target.to_string(),
+            body,
+            keep_alive,
+        }))
+    }
+
+    /// Read a chunked HTTP body, discarding any trailers
+    async fn read_chunked_body<S>(stream: &mut S) -> Result<Vec<u8>, Error>
+    where
+        S: AsyncBufRead + Unpin,
+    {
+        let mut body = Vec::new();
+        loop {
+            let line = Self::read_http_line(stream).await?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| Error::Ipp(format!("Invalid HTTP chunk size: {}", line.trim())))?;
+            if size == 0 {
+                while !Self::read_http_line(stream).await?.trim().is_empty() {}
+                return Ok(body);
+            }
+            if body.len() + size > MAX_HTTP_BODY {
+                return Err(Error::Ipp("Chunked HTTP body is too large".to_string()));
+            }
+
+            let start = body.len();
+            body.resize(start + size, 0);
+            stream.read_exact(&mut body[start..]).await?;
+            let mut line_end = [0u8; 2];
+            stream.read_exact(&mut line_end).await?;
+        }
+    }
+
+    /// Read one line including its terminator; empty at end of stream
+    async fn read_http_line<S>(stream: &mut S) -> Result<String, Error>
+    where
+        S: AsyncBufRead + Unpin,
+    {
+        let mut line = String::new();
+        (&mut *stream).take(MAX_HTTP_LINE as u64 + 1).read_line(&mut line).await?;
+        if line.len() > MAX_HTTP_LINE {
+            return Err(Error::Ipp("HTTP line too long".to_string()));
+        }
+        Ok(line)
+    }
+
+    /// Decode and answer one IPP request
+    ///
+    /// Every response carries the negotiated `attributes-natural-language`.
+    async fn respond(processor: &Arc<JobProcessor>, capabilities: &PrinterCapabilities, body: &[u8]) -> IppResponse {
+        let request = match Self::parse_ipp_request(body) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!(error = %e, "Unreadable IPP request");
+                let status_code = match e {
+                    Error::Unsupported(_) => IppStatusCode::NotSupported,
+                    _ => IppStatusCode::BadRequest,
+                };
+                return IppResponse {
+                    status_code,
+                    operation_id: IppOperation::GetPrinterAttributes,
+                    request_id: body.get(4..8).map_or(0, |id| u32::from_be_bytes([id[0], id[1], id[2], id[3]])),
+                    attributes: HashMap::new(),
+                    jobs: Vec::new(),
+                };
+            }
+        };
+
+        let language = Self::natural_language(
+            request.attributes.get("attributes-natural-language").and_then(|values| values.first()),
+            &capabilities.supported_languages,
+        );
+        let (operation_id, request_id) = (request.operation_id, request.request_id);
+        let mut response = match Self::process_request(processor, request, &language).await {
             Ok(resp) => resp,
             Err(e) => {
-                warn!("Error processing request from {}: {}", addr, e);
+                let status_code = IppStatusCode::from(&e);
+                if status_code.is_client_error() {
+                    debug!(error = %e, ?status_code, "Refusing request");
+                } else {
+                    warn!(error = %e, ?status_code, "Error processing request");
+                }
                 IppResponse {
-                    status_code: IppStatusCode::InternalError,
-                    operation_id: IppOperation::CreateJob,
-                    request_id: 1,
+                    status_code,
+                    operation_id,
+                    request_id,
                     attributes: HashMap::new(),
+                    jobs: Vec::new(),
                 }
             }
         };
+        response
+            .attributes
+            .insert("attributes-natural-language".to_string(), vec![IppValue::NaturalLanguage(language)]);
+        response
+    }
 
-        // Send response
-        warn!("Sending response to {}: {:?}", addr, response.status_code);
+    /// Pick the supported language closest to the one a client asked for
+    ///
+    /// An exact match wins over one on the primary subtag alone, so `de-AT`
+    /// falls back to `de`. Without any match the answer is in
+    /// [`DEFAULT_NATURAL_LANGUAGE`].
+    fn natural_language(requested: Option<&String>, supported: &[String]) -> String {
+        let primary = |tag: &str| tag.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        requested
+            .and_then(|requested| {
+                supported
+                    .iter()
+                    .find(|language| language.eq_ignore_ascii_case(requested))
+                    .or_else(|| supported.iter().find(|language| primary(language) == primary(requested)))
+            })
+            .map(|language| language.to_ascii_lowercase())
+            .unwrap_or_else(|| DEFAULT_NATURAL_LANGUAGE.to_string())
+    }
 
-        // Remove client connection
-        {
-            let mut clients = client_data.clients.write().await;
-            clients.remove(&client_id);
+    /// Describe the printer state in one of [`GENERATED_LANGUAGES`]
+    fn state_message(status: PrinterStatus, language: &str) -> &'static str {
+        let german = language.split(['-', '_']).next() == Some("de");
+        match (status.ipp_state(), german) {
+            ("idle", false) => "Ready to print",
+            ("idle", true) => "Bereit zum Drucken",
+            ("processing", false) => "Printing",
+            ("processing", true) => "Druckt",
+            (_, false) => "Stopped",
+            (_, true) => "Angehalten",
         }
+    }
 
-        Ok(())
+    /// Get the IPP `printer-state` enum value (RFC 8011, section 5.4.11)
+    fn printer_state(status: PrinterStatus) -> i32 {
+        match status.ipp_state() {
+            "idle" => 3,
+            "processing" => 4,
+            _ => 5,
+        }
+    }
+
+    /// Look up the job named by the integer `job-id` operation attribute
+    ///
+    /// Answers BadRequest when the attribute is missing or not an integer,
+    /// and NotFound when no job has that id.
+    async fn requested_job(
+        processor: &JobProcessor,
+        request: &IppRequest,
+    ) -> Result<JobMetadata, IppStatusCode> {
+        let ipp_job_id = request
+            .attributes
+            .get("job-id")
+            .and_then(|values| values.first())
+            .and_then(|value| value.parse::<i32>().ok())
+            .ok_or(IppStatusCode::BadRequest)?;
+        processor.get_job_by_ipp_id(ipp_job_id).await.ok_or(IppStatusCode::NotFound)
     }
 
     /// Process IPP request
-    async fn process_request(processor: &Arc<JobProcessor>, request: IppRequest) -> Result<IppResponse, Error> {
+    #[instrument(skip_all, fields(operation = ?request.operation_id, request_id = request.request_id))]
+    async fn process_request(
+        processor: &Arc<JobProcessor>,
+        request: IppRequest,
+        language: &str,
+    ) -> Result<IppResponse, Error> {
         match request.operation_id {
+            IppOperation::ValidateJob => {
+                if Self::document_format(&request).is_err() {
+                    return Ok(Self::error_response(&request, IppStatusCode::ClientErrorDocumentFormatNotSupported));
+                }
+                Self::print_options(&request)?;
+                Ok(Self::error_response(&request, IppStatusCode::Successful))
+            }
             IppOperation::CreateJob => {
-                let job_id = JobId(Uuid::new_v4());
-
-                let print_job = PrintJobRequest {
-                    job_id,
-                    file_path: std::path::PathBuf::new(),
-                    file_type: FileType::Pdf,
-                    printer_name: None,
-                    options: PrintOptions::default(),
+                // The document arrives later; without a declared format assume PDF
+                let Ok(file_type) = Self::document_format(&request) else {
+                    return Ok(Self::error_response(&request, IppStatusCode::ClientErrorDocumentFormatNotSupported));
                 };
+                let print_job = Self::job_request(&request, file_type.unwrap_or(FileType::Pdf))?;
+                let job_id = print_job.job_id.clone();
 
                 processor.add_job(print_job).await?;
 
+                Self::job_response(processor, &request, &job_id).await
+            }
+            IppOperation::PrintJob => {
+                if request.data.is_empty() {
+                    return Ok(Self::error_response(&request, IppStatusCode::BadRequest));
+                }
+                let detected = FileType::detect(&request.data);
+                let file_type = match (Self::document_format(&request), detected) {
+                    (Ok(Some(declared)), Some(detected)) if declared != detected => {
+                        warn!(?declared, ?detected, "document-format does not match the document");
+                        detected
+                    }
+                    (Ok(Some(file_type)), _) | (Ok(None), Some(file_type)) => file_type,
+                    (Ok(None), None) | (Err(_), _) => {
+                        return Ok(Self::error_response(
+                            &request,
+                            IppStatusCode::ClientErrorDocumentFormatNotSupported,
+                        ));
+                    }
+                };
+                // Detection also recognizes formats the job processor cannot read
+                if !DOCUMENT_FORMATS.iter().any(|(_, supported)| *supported == file_type) {
+                    return Ok(Self::error_response(&request, IppStatusCode::ClientErrorDocumentFormatNotSupported));
+                }
+                let mut print_job = Self::job_request(&request, file_type)?;
+                let job_id = print_job.job_id.clone();
+
+                print_job.file_path = processor.spool(&job_id, print_job.file_type, &request.data).await?;
+                let file_path = print_job.file_path.clone();
+                if let Err(e) = processor.add_job(print_job).await {
+                    // The job never runs, so nothing else would remove its spool file
+                    let _ = tokio::fs::remove_file(&file_path).await;
+                    return Err(e);
+                }
+
+                Self::job_response(processor, &request, &job_id).await
+            }
+            IppOperation::GetPrinterAttributes => {
+                let mut attributes = HashMap::new();
+                attributes.insert("printer-name".to_string(), vec![IppValue::Name("boomaga-ipp".to_string())]);
+                attributes.insert(
+                    "printer-info".to_string(),
+                    vec![IppValue::Text("Boomaga Virtual Printer".to_string())],
+                );
+
+                let status = processor.printer_status().await;
+                attributes.insert("printer-state".to_string(), vec![IppValue::Enum(Self::printer_state(status))]);
+                attributes.insert(
+                    "printer-state-reasons".to_string(),
+                    IppValue::keywords(processor.printer_state_reasons(status)),
+                );
+                attributes.insert(
+                    "printer-state-message".to_string(),
+                    vec![IppValue::Text(Self::state_message(status, language).to_string())],
+                );
+                attributes.insert(
+                    "document-format-supported".to_string(),
+                    DOCUMENT_FORMATS
+                        .iter()
+                        .map(|(format, _)| *format)
+                        .chain([AUTO_DETECT_FORMAT])
+                        .map(|format| IppValue::MimeMediaType(format.to_string()))
+                        .collect(),
+                );
+                attributes.insert(
+                    "document-format-default".to_string(),
+                    vec![IppValue::MimeMediaType(AUTO_DETECT_FORMAT.to_string())],
+                );
+                attributes.insert("print-color-mode-supported".to_string(), IppValue::keywords(PRINT_COLOR_MODES));
+                attributes.insert("print-color-mode-default".to_string(), IppValue::keywords(["auto"]));
+                attributes.insert(
+                    "print-quality-supported".to_string(),
+                    vec![IppValue::Enum(3), IppValue::Enum(4), IppValue::Enum(5)],
+                );
+                attributes.insert("print-quality-default".to_string(), vec![IppValue::Enum(4)]);
+
+                let mut attributes =
+                    Self::requested_attributes(attributes, request.attributes.get("requested-attributes"));
+                attributes.insert("attributes-charset".to_string(), vec![IppValue::Charset("utf-8".to_string())]);
+                attributes.insert(
+                    "attributes-natural-language".to_string(),
+                    vec![IppValue::NaturalLanguage(language.to_string())],
+                );
+
                 Ok(IppResponse {
                     status_code: IppStatusCode::Successful,
                     operation_id: request.operation_id,
                     request_id: request.request_id,
-                    attributes: HashMap::new(),
+                    attributes,
+                    jobs: Vec::new(),
                 })
             }
-            IppOperation::GetPrinterAttributes => {
-                let mut attributes = HashMap::new();
-                attributes.insert("printer-name".to_string(), vec!["boomaga-ipp".to_string()]);
-                attributes.insert("printer-info".to_string(), vec!["Boomaga Virtual Printer".to_string()]);
-                attributes.insert("printer-state".to_string(), vec!["idle".to_string()]);
+            IppOperation::GetJobs => {
+                let completed = match request
+                    .attributes
+                    .get("which-jobs")
+                    .and_then(|v| v.first())
+                    .map(String::as_str)
+                {
+                    None | Some("not-completed") => false,
+                    Some("completed") => true,
+                    Some(_) => return Ok(Self::error_response(&request, IppStatusCode::BadRequest)),
+                };
+
+                let limit = match request.attributes.get("limit").and_then(|v| v.first()) {
+                    None => usize::MAX,
+                    Some(limit) => match limit.parse::<usize>() {
+                        Ok(limit) if limit > 0 => limit,
+                        _ => return Ok(Self::error_response(&request, IppStatusCode::BadRequest)),
+                    },
+                };
+
+                let jobs: Vec<_> = processor
+                    .list_jobs()
+                    .await
+                    .into_iter()
+                    .filter(|job| job.status.is_terminal() == completed)
+                    .take(limit)
+                    .collect();
+
+                let jobs = jobs
+                    .iter()
+                    .map(|job| {
+                        let mut attributes = Self::job_attributes(job);
+                        attributes.insert("job-name".to_string(), vec![IppValue::Name(job.name.clone())]);
+                        attributes
+                    })
+                    .collect();
 
                 Ok(IppResponse {
                     status_code: IppStatusCode::Successful,
                     operation_id: request.operation_id,
                     request_id: request.request_id,
-                    attributes,
+                    attributes: HashMap::new(),
+                    jobs,
                 })
             }
-            IppOperation::GetJobs => {
-                let mut attributes = HashMap::new();
-                attributes.insert("job-count".to_string(), vec!["0".to_string()]);
+            IppOperation::GetJobAttributes => {
+                let job = match Self::requested_job(processor, &request).await {
+                    Ok(job) => job,
+                    Err(status_code) => return Ok(Self::error_response(&request, status_code)),
+                };
+
+                let mut attributes = Self::job_attributes(&job);
+                attributes.insert("job-state-reasons".to_string(), IppValue::keywords(job.status.reasons()));
+                attributes.insert(
+                    "job-impressions-completed".to_string(),
+                    vec![IppValue::Integer(job.pages_printed as i32)],
+                );
 
                 Ok(IppResponse {
                     status_code: IppStatusCode::Successful,
                     operation_id: request.operation_id,
                     request_id: request.request_id,
                     attributes,
+                    jobs: Vec::new(),
                 })
             }
+            IppOperation::CancelJob
+            | IppOperation::HoldJob
+            | IppOperation::ReleaseJob
+            | IppOperation::PromoteJob => {
+                let job = match Self::requested_job(processor, &request).await {
+                    Ok(job) => job,
+                    Err(status_code) => return Ok(Self::error_response(&request, status_code)),
+                };
+
+                let job_id = job.job_id.to_string();
+                let result = match request.operation_id {
+                    IppOperation::CancelJob => processor.cancel_job(job_id).await,
+                    IppOperation::HoldJob => processor.hold_job(job_id).await,
+                    IppOperation::ReleaseJob => processor.release_job(job_id).await,
+                    _ => processor.move_job(job_id, 0).await,
+                };
+                let status_code = match result {
+                    Ok(()) => IppStatusCode::Successful,
+                    Err(e) => IppStatusCode::from(&e),
+                };
+                Ok(Self::error_response(&request, status_code))
+            }
+            IppOperation::PurgeJobs => {
+                processor.purge().await;
+                Ok(Self::error_response(&request, IppStatusCode::Successful))
+            }
             _ => {
-                Err(Error::Unsupported(format!("Operation not supported: {:?}", request.operation_id)))
+                debug!(operation = ?request.operation_id, "Operation not supported");
+                Ok(Self::error_response(&request, IppStatusCode::NotSupported))
+            }
+        }
+    }
+
+    /// Read the `document-format` operation attribute
+    ///
+    /// Returns `None` when the format is left to auto-detection, and the
+    /// format itself as the error when it is not supported.
+    fn document_format(request: &IppRequest) -> Result<Option<FileType>, String> {
+        match request.attributes.get("document-format").and_then(|values| values.first()) {
+            None => Ok(None),
+            Some(format) if format == AUTO_DETECT_FORMAT => Ok(None),
+            Some(format) => DOCUMENT_FORMATS
+                .iter()
+                .find(|(supported, _)| supported == format)
+                .map(|(_, file_type)| Some(*file_type))
+                .ok_or_else(|| format.clone()),
+        }
+    }
+
+    /// Build a job request from the operation attributes of Create-Job or
+    /// Print-Job
+    fn job_request(request: &IppRequest, file_type: FileType) -> Result<PrintJobRequest, Error> {
+        Ok(PrintJobRequest {
+            job_id: JobId(Uuid::new_v4()),
+            file_path: std::path::PathBuf::new(),
+            file_type,
+            printer_name: None,
+            options: Self::print_options(request)?,
+            batch_documents: Vec::new(),
+        })
+    }
+
+    /// Read the job template attributes into print options
+    fn print_options(request: &IppRequest) -> Result<PrintOptions, Error> {
+        let first = |name: &str| request.attributes.get(name).and_then(|values| values.first());
+        let invalid = |name: &str, value: &str| Error::Validation(format!("Invalid {} value {}", name, value));
+        let unsupported = |name: &str, value: &str| Error::Unsupported(format!("Unsupported {} value {}", name, value));
+        let mut options = PrintOptions::builder();
+
+        if let Some(copies) = first("copies") {
+            options = options.copies(copies.parse().map_err(|_| invalid("copies", copies))?);
+        }
+        if let Some(sides) = first("sides") {
+            options = options.duplex(match sides.as_str() {
+                "one-sided" => DuplexMode::None,
+                "two-sided-long-edge" => DuplexMode::LongEdge,
+                "two-sided-short-edge" => DuplexMode::ShortEdge,
+                _ => return Err(unsupported("sides", sides)),
+            });
+        }
+        if let Some(orientation) = first("orientation-requested") {
+            options = options.orientation(match orientation.as_str() {
+                "3" => Orientation::Portrait,
+                "4" => Orientation::Landscape,
+                "5" => Orientation::UpsideDownLandscape,
+                "6" => Orientation::UpsideDownPortrait,
+                _ => return Err(unsupported("orientation-requested", orientation)),
+            });
+        }
+        if let Some(number_up) = first("number-up") {
+            options = options.number_up(match number_up.as_str() {
+                "1" => PagesPerSheet::One,
+                "2" => PagesPerSheet::Two,
+                "4" => PagesPerSheet::Four,
+                "6" => PagesPerSheet::Six,
+                "8" => PagesPerSheet::Eight,
+                _ => return Err(unsupported("number-up", number_up)),
+            });
+        }
+        if let Some(range) = first("page-ranges") {
+            let (start, end) = range
+                .split_once('-')
+                .and_then(|(start, end)| Some((start.trim().parse().ok()?, end.trim().parse().ok()?)))
+                .ok_or_else(|| invalid("page-ranges", range))?;
+            options = options.page_range(start, end);
+        }
+        if let Some(color_mode) = first("print-color-mode") {
+            options = options.color_mode(match color_mode.as_str() {
+                "auto" => ColorMode::Auto,
+                "color" => ColorMode::Color,
+                "monochrome" | "auto-monochrome" | "process-monochrome" => ColorMode::Grayscale,
+                "bi-level" | "process-bi-level" => ColorMode::Monochrome,
+                _ => return Err(unsupported("print-color-mode", color_mode)),
+            });
+        }
+        let (media, margins) = Self::media(request)?;
+        options = options.media(media);
+        if let Some(margins) = margins {
+            options = options.margins(margins);
+        }
+        if let Some(quality) = first("print-quality") {
+            options = options.print_quality(match quality.as_str() {
+                "3" => PrintQuality::Draft,
+                "4" => PrintQuality::Normal,
+                "5" => PrintQuality::High,
+                _ => return Err(unsupported("print-quality", quality)),
+            });
+        }
+
+        options.build()
+    }
+
+    /// Read the requested medium from `media-col`, or the `media` keyword
+    /// when there is no `media-col`
+    ///
+    /// The size comes from `media-size`, or else `media-size-name`. Margins
+    /// are returned only when all four `media-*-margin` members are given.
+    fn media(request: &IppRequest) -> Result<(Media, Option<MarginMode>), Error> {
+        let first = |name: &str| request.attributes.get(name).and_then(|values| values.first());
+        let invalid = |name: &str, value: &str| Error::Validation(format!("Invalid {} value {}", name, value));
+        let unsupported = |name: &str, value: &str| Error::Unsupported(format!("Unsupported {} value {}", name, value));
+
+        if !request.attributes.contains_key("media-col") {
+            let size = match first("media") {
+                Some(keyword) => Some(page_size_from_media(keyword).ok_or_else(|| unsupported("media", keyword))?),
+                None => None,
+            };
+            return Ok((Media { size, ..Media::default() }, None));
+        }
+
+        // Sizes and margins are in hundredths of a millimeter
+        let member = |name: &str| first(&format!("media-col.{}", name));
+        let hundredths = |name: &str| -> Result<Option<u32>, Error> {
+            member(name)
+                .map(|value| value.parse().map_err(|_| invalid(name, value)))
+                .transpose()
+        };
+
+        let size = match (hundredths("media-size.x-dimension")?, hundredths("media-size.y-dimension")?) {
+            (Some(x), Some(y)) if x > 0 && y > 0 => Some(page_size_from_dimensions(x, y)),
+            (Some(_), Some(_)) => return Err(Error::Validation("Invalid media-size".into())),
+            _ => match member("media-size-name") {
+                Some(name) => Some(page_size_from_media(name).ok_or_else(|| unsupported("media-size-name", name))?),
+                None => None,
+            },
+        };
+        let media = Media {
+            size,
+            source: member("media-source").cloned(),
+            media_type: member("media-type").cloned(),
+        };
+
+        let margins = match (
+            hundredths("media-top-margin")?,
+            hundredths("media-bottom-margin")?,
+            hundredths("media-left-margin")?,
+            hundredths("media-right-margin")?,
+        ) {
+            (Some(0), Some(0), Some(0), Some(0)) => Some(MarginMode::None),
+            (Some(top), Some(bottom), Some(left), Some(right)) => {
+                let points = |hundredths: u32| hundredths as f64 / 100.0 * 72.0 / 25.4;
+                Some(MarginMode::Custom {
+                    top: points(top),
+                    bottom: points(bottom),
+                    left: points(left),
+                    right: points(right),
+                })
             }
+            _ => None,
+        };
+
+        Ok((media, margins))
+    }
+
+    /// Build the answer to a job creation
+    async fn job_response(
+        processor: &JobProcessor,
+        request: &IppRequest,
+        job_id: &JobId,
+    ) -> Result<IppResponse, Error> {
+        let job = processor
+            .get_job(&job_id.to_string())
+            .await
+            .ok_or_else(|| Error::NotFound(format!("Job {}", job_id)))?;
+
+        Ok(IppResponse {
+            status_code: IppStatusCode::Successful,
+            operation_id: request.operation_id,
+            request_id: request.request_id,
+            attributes: Self::job_attributes(&job),
+            jobs: Vec::new(),
+        })
+    }
+
+    /// Describe a job with its `job-id`, `job-uri` and `job-state`
+    fn job_attributes(job: &JobMetadata) -> HashMap<String, Vec<IppValue>> {
+        HashMap::from([
+            ("job-id".to_string(), vec![IppValue::Integer(job.ipp_job_id)]),
+            (
+                "job-uri".to_string(),
+                vec![IppValue::Uri(format!("ipp://localhost/jobs/{}", job.ipp_job_id))],
+            ),
+            ("job-state".to_string(), vec![IppValue::Enum(job.status.ipp_state())]),
+        ])
+    }
+
+    /// Keep only the attributes a client asked for with `requested-attributes`
+    ///
+    /// Everything is returned when the client did not ask or asked for `all`.
+    /// Unknown names are left out.
+    fn requested_attributes(
+        mut attributes: HashMap<String, Vec<IppValue>>,
+        requested: Option<&Vec<String>>,
+    ) -> HashMap<String, Vec<IppValue>> {
+        match requested {
+            Some(names) if !names.iter().any(|name| name == "all") => {
+                attributes.retain(|name, _| names.contains(name));
+                attributes
+            }
+            _ => attributes,
+        }
+    }
+
+    /// Build an empty response carrying only a status code
+    fn error_response(request: &IppRequest, status_code: IppStatusCode) -> IppResponse {
+        IppResponse {
+            status_code,
+            operation_id: request.operation_id,
+            request_id: request.request_id,
+            attributes: HashMap::new(),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Decode an IPP request message
+    fn parse_ipp_request(data: &[u8]) -> Result<IppRequest, Error> {
+        if data.len() < 8 {
+            return Err(Error::Ipp("Truncated IPP request".to_string()));
+        }
+        let version = match (data[0], data[1]) {
+            (2, 0) => IppVersion::Ipp2_0,
+            (2, 1) => IppVersion::Ipp2_1,
+            (major, minor) => return Err(Error::Unsupported(format!("IPP version {}.{} not supported", major, minor))),
+        };
+        let operation_id = IppOperation::try_from(u16::from_be_bytes([data[2], data[3]]))?;
+        let request_id = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let (attributes, end) = parse_attributes(data)?;
+
+        Ok(IppRequest {
+            version,
+            operation_id,
+            request_id,
+            attributes,
+            data: data[end..].to_vec(),
+        })
+    }
+
+    /// Encode an IPP response message
+    ///
+    /// The charset and natural language go in the operation group, and
+    /// everything else in the printer group for Get-Printer-Attributes or
+    /// the job group otherwise. Each job of a Get-Jobs answer gets a job
+    /// group of its own.
+    fn encode_ipp_response(response: &IppResponse) -> Vec<u8> {
+        let first = |name: &str, default: IppValue| {
+            response
+                .attributes
+                .get(name)
+                .and_then(|values| values.first())
+                .cloned()
+                .unwrap_or(default)
+        };
+
+        let mut buffer = vec![0x02, 0x00];
+        buffer.extend_from_slice(&response.status_code.code().to_be_bytes());
+        buffer.extend_from_slice(&response.request_id.to_be_bytes());
+        buffer.push(OPERATION_ATTRIBUTES_TAG);
+        for (name, default) in [
+            ("attributes-charset", IppValue::Charset("utf-8".to_string())),
+            ("attributes-natural-language", IppValue::NaturalLanguage("en".to_string())),
+        ] {
+            let value = first(name, default);
+            encode_attribute(&mut buffer, value.tag(), name, &value.to_bytes());
+        }
+
+        let attributes: HashMap<&String, &Vec<IppValue>> = response
+            .attributes
+            .iter()
+            .filter(|(name, _)| !matches!(name.as_str(), "attributes-charset" | "attributes-natural-language"))
+            .collect();
+        if !attributes.is_empty() {
+            let tag = if response.operation_id == IppOperation::GetPrinterAttributes {
+                PRINTER_ATTRIBUTES_TAG
+            } else {
+                JOB_ATTRIBUTES_TAG
+            };
+            Self::encode_group(&mut buffer, tag, attributes);
+        }
+        for job in &response.jobs {
+            Self::encode_group(&mut buffer, JOB_ATTRIBUTES_TAG, job.iter().collect());
+        }
+
+        buffer.push(END_OF_ATTRIBUTES_TAG);
+        buffer
+    }
+
+    /// Append an attribute group, its attributes sorted by name
+    fn encode_group(buffer: &mut Vec<u8>, tag: u8, attributes: HashMap<&String, &Vec<IppValue>>) {
+        let mut names: Vec<&String> = attributes.keys().copied().collect();
+        names.sort();
+        buffer.push(tag);
+        for name in names {
+            let values = attributes[name];
+            if values.is_empty() {
+                encode_attribute(buffer, NO_VALUE_TAG, name, &[]);
+            }
+            for (index, value) in values.iter().enumerate() {
+                encode_attribute(buffer, value.tag(), if index == 0 { name } else { "" }, &value.to_bytes());
+            }
+        }
+    }
+
+    /// Wrap an IPP response in an HTTP response
+    ///
+    /// Busy and unavailable responses carry `Retry-After`, so clients wait
+    /// before resubmitting.
+    fn encode_http_response(response: &IppResponse, keep_alive: bool) -> Vec<u8> {
+        let body = Self::encode_ipp_response(response);
+        let retry_after = match response.status_code {
+            IppStatusCode::ServerErrorBusy | IppStatusCode::ServiceUnavailable => {
+                format!("Retry-After: {}\r\n", RETRY_AFTER_SECS)
+            }
+            _ => String::new(),
+        };
+        let mut reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n{}Connection: {}\r\n\r\n",
+            body.len(),
+            retry_after,
+            if keep_alive { "keep-alive" } else { "close" }
+        )
+        .into_bytes();
+        reply.extend_from_slice(&body);
+        reply
+    }
+
+    /// Wrap rendered metrics in an HTTP response
+    fn encode_metrics_response(body: &str, keep_alive: bool) -> Vec<u8> {
+        let mut reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: {}\r\n\r\n",
+            body.len(),
+            if keep_alive { "keep-alive" } else { "close" }
+        )
+        .into_bytes();
+        reply.extend_from_slice(body.as_bytes());
+        reply
+    }
+}
+
+impl Drop for IppServer {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipp::{CHARSET_TAG, INTEGER_TAG, KEYWORD_TAG, NATURAL_LANGUAGE_TAG};
+    use crate::job_queue::JobQueue;
+
+    fn request(operation_id: IppOperation, attributes: HashMap<String, Vec<String>>) -> IppRequest {
+        IppRequest {
+            version: IppVersion::Ipp2_0,
+            operation_id,
+            request_id: 1,
+            attributes,
+            data: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn listener_binds_to_configured_address() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let address: IpAddr = "127.0.0.1".parse().unwrap();
+        let server = IppServer::new(address, 0, std::path::PathBuf::new(), String::new(), processor).unwrap();
+
+        let listener = server.bind().await.unwrap();
+
+        assert_eq!(listener.local_addr().unwrap().ip(), address);
+    }
+
+    #[tokio::test]
+    async fn connection_beyond_max_clients_is_rejected() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let server = IppServer::new("127.0.0.1".parse().unwrap(), 0, std::path::PathBuf::new(), String::new(), processor)
+            .unwrap()
+            .with_max_clients(1);
+        let listener = server.bind().await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // The first client still holds the only slot
+        let _busy = Arc::clone(&server.client_slots).try_acquire_owned().unwrap();
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let (stream, addr) = listener.accept().await.unwrap();
+
+        assert!(!server.accept_client(stream, addr).await);
+        // The server answered ServiceUnavailable and closed the connection
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await.unwrap();
+        let body = &reply[reply.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4..];
+        assert_eq!(&body[2..4], &IppStatusCode::ServiceUnavailable.code().to_be_bytes());
+    }
+
+    /// Encode a request carrying only the charset and natural language
+    fn encode_request(operation: IppOperation, request_id: u32, language: &str) -> Vec<u8> {
+        let mut body = vec![0x02, 0x00];
+        body.extend_from_slice(&(operation as u16).to_be_bytes());
+        body.extend_from_slice(&request_id.to_be_bytes());
+        body.push(OPERATION_ATTRIBUTES_TAG);
+        encode_attribute(&mut body, CHARSET_TAG, "attributes-charset", b"utf-8");
+        encode_attribute(&mut body, NATURAL_LANGUAGE_TAG, "attributes-natural-language", language.as_bytes());
+        body.push(END_OF_ATTRIBUTES_TAG);
+        body
+    }
+
+    /// Read one HTTP response and return its IPP body
+    async fn read_http_response(stream: &mut BufReader<TcpStream>) -> Vec<u8> {
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if let Some(length) = line.strip_prefix("Content-Length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).await.unwrap();
+        body
+    }
+
+    #[tokio::test]
+    async fn connection_is_kept_alive_between_operations() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let server = IppServer::new("127.0.0.1".parse().unwrap(), 0, std::path::PathBuf::new(), String::new(), processor)
+            .unwrap();
+        let listener = server.bind().await.unwrap();
+        let mut client = BufReader::new(TcpStream::connect(listener.local_addr().unwrap()).await.unwrap());
+        let (stream, addr) = listener.accept().await.unwrap();
+        assert!(server.accept_client(stream, addr).await);
+
+        for (request_id, operation, connection) in [
+            (1u32, IppOperation::GetPrinterAttributes, "keep-alive"),
+            (2, IppOperation::GetJobs, "close"),
+        ] {
+            let body = encode_request(operation, request_id, "en");
+            let header = format!(
+                "POST /ipp/print HTTP/1.1\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n",
+                body.len(),
+                connection
+            );
+            client.write_all(header.as_bytes()).await.unwrap();
+            client.write_all(&body).await.unwrap();
+
+            let response = read_http_response(&mut client).await;
+            assert_eq!(&response[2..4], &IppStatusCode::Successful.code().to_be_bytes());
+            assert_eq!(&response[4..8], &request_id.to_be_bytes());
+        }
+
+        // Connection: close ends the conversation
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelling_the_shutdown_token_stops_the_server() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let server = IppServer::new("127.0.0.1".parse().unwrap(), 0, std::path::PathBuf::new(), String::new(), processor)
+            .unwrap();
+        let listener = server.bind().await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, addr) = listener.accept().await.unwrap();
+        assert!(server.accept_client(stream, addr).await);
+
+        let shutdown = server.shutdown_token();
+        let running = tokio::spawn(async move { server.run().await });
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), running).await.unwrap().unwrap().unwrap();
+
+        // The open connection is closed as well
+        let mut rest = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut rest)).await.unwrap().unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn responses_use_the_negotiated_language() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let capabilities = PrinterCapabilities {
+            supported_languages: vec!["en".to_string(), "de".to_string()],
+            ..PrinterCapabilities::default()
+        };
+
+        let body = encode_request(IppOperation::GetPrinterAttributes, 1, "de");
+        let response = IppServer::respond(&processor, &capabilities, &body).await;
+        assert_eq!(response.attributes["attributes-natural-language"], vec![IppValue::NaturalLanguage("de".into())]);
+        assert_eq!(response.attributes["printer-state-message"], vec![IppValue::Text("Bereit zum Drucken".into())]);
+
+        let body = encode_request(IppOperation::GetJobs, 2, "fr-CA");
+        let response = IppServer::respond(&processor, &capabilities, &body).await;
+        assert_eq!(response.attributes["attributes-natural-language"], vec![IppValue::NaturalLanguage("en".into())]);
+        assert_eq!(IppServer::natural_language(Some(&"de-AT".to_string()), &capabilities.supported_languages), "de");
+    }
+
+    #[tokio::test]
+    async fn processing_job_reports_state_5() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let job_id = JobId(Uuid::new_v4());
+
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                file_path: std::path::PathBuf::from("test.pdf"),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+        // No worker runs, so the job stays in the state set here
+        let ipp_job_id = {
+            let mut jobs = processor.jobs.write().await;
+            let job = jobs.get_mut(&job_id.to_string()).unwrap();
+            job.status = JobStatus::Processing;
+            job.ipp_job_id
+        };
+
+        let attributes = HashMap::from([("job-id".to_string(), vec![ipp_job_id.to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::GetJobAttributes, attributes), "en")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+        assert_eq!(response.attributes["job-id"], vec![IppValue::Integer(ipp_job_id)]);
+        assert_eq!(response.attributes["job-state"], vec![IppValue::Enum(5)]);
+        assert_eq!(response.attributes["job-state-reasons"], IppValue::keywords(["job-printing"]));
+    }
+
+    #[tokio::test]
+    async fn jobs_are_addressed_by_their_integer_job_id() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+
+        let response = IppServer::process_request(&processor, request(IppOperation::CreateJob, HashMap::new()), "en")
+            .await
+            .unwrap();
+        let [IppValue::Integer(ipp_job_id)] = response.attributes["job-id"][..] else {
+            panic!("job-id is not one integer: {:?}", response.attributes["job-id"]);
+        };
+        assert_eq!(ipp_job_id, 1);
+
+        let job_id = || HashMap::from([("job-id".to_string(), vec![ipp_job_id.to_string()])]);
+        for (operation, status) in [
+            (IppOperation::HoldJob, JobStatus::Held),
+            (IppOperation::ReleaseJob, JobStatus::Queued),
+            (IppOperation::PromoteJob, JobStatus::Queued),
+        ] {
+            let response = IppServer::process_request(&processor, request(operation, job_id()), "en")
+                .await
+                .unwrap();
+            assert_eq!(response.status_code, IppStatusCode::Successful, "{operation:?}");
+            assert_eq!(processor.get_job_by_ipp_id(ipp_job_id).await.unwrap().status, status);
         }
+        let response = IppServer::process_request(&processor, request(IppOperation::CancelJob, job_id()), "en")
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+
+        let uuid = HashMap::from([("job-id".to_string(), vec![Uuid::new_v4().to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::CancelJob, uuid), "en")
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, IppStatusCode::BadRequest);
     }
 
-    /// Read IPP request from stream
-    async fn read_ipp_request(stream: &TcpStream) -> Result<IppRequest, Error> {
-        Err(Error::Ipp("IPP parsing not yet implemented".to_string()))
+    #[tokio::test]
+    async fn get_jobs_lists_not_completed_jobs() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+
+        for name in ["first.pdf", "123"] {
+            processor
+                .add_job(PrintJobRequest {
+                    job_id: JobId(Uuid::new_v4()),
+                    file_path: std::path::PathBuf::from(name),
+                    file_type: FileType::Pdf,
+                    printer_name: None,
+                    options: PrintOptions::default(),
+                    batch_documents: Vec::new(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let attributes = HashMap::from([("which-jobs".to_string(), vec!["not-completed".to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::GetJobs, attributes), "en")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+        let jobs: Vec<_> = response
+            .jobs
+            .iter()
+            .map(|job| (job["job-id"].clone(), job["job-name"].clone()))
+            .collect();
+        assert_eq!(
+            jobs,
+            [
+                (vec![IppValue::Integer(1)], vec![IppValue::Name("first.pdf".into())]),
+                (vec![IppValue::Integer(2)], vec![IppValue::Name("123".into())]),
+            ]
+        );
+
+        // Each job is a group of its own, opened by a job attributes tag
+        let body = IppServer::encode_ipp_response(&response);
+        let (groups, _) = parse_attributes(&body).unwrap();
+        assert_eq!(groups["job-id"], vec!["1", "2"]);
+        let group_start = [&[JOB_ATTRIBUTES_TAG, INTEGER_TAG, 0x00, 0x06][..], &b"job-id"[..]].concat();
+        assert_eq!(body.windows(group_start.len()).filter(|window| *window == group_start).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn cancel_job_of_unknown_job_is_not_found() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+
+        let attributes = HashMap::from([("job-id".to_string(), vec!["42".to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::CancelJob, attributes), "en")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_code, IppStatusCode::NotFound);
     }
 
-    /// Write IPP response to stream
-    async fn write_ipp_response(stream: &TcpStream, response: &IppResponse) -> Result<(), Error> {
-        Err(Error::Ipp("IPP response not yet implemented".to_string()))
+    #[tokio::test]
+    async fn unknown_job_is_not_found() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+
+        let attributes = HashMap::from([("job-id".to_string(), vec!["42".to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::GetJobAttributes, attributes), "en")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_code, IppStatusCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn printer_attributes_are_limited_to_the_requested_ones() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+
+        let attributes = HashMap::from([(
+            "requested-attributes".to_string(),
+            vec!["printer-state".to_string(), "no-such-attribute".to_string()],
+        )]);
+        let req = request(IppOperation::GetPrinterAttributes, attributes);
+        let response = IppServer::process_request(&processor, req, "en")
+            .await
+            .unwrap();
+
+        let mut names: Vec<&str> = response.attributes.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["attributes-charset", "attributes-natural-language", "printer-state"]);
+        assert_eq!(response.attributes["printer-state"], vec![IppValue::Enum(3)]);
+
+        let attributes = HashMap::from([("requested-attributes".to_string(), vec!["all".to_string()])]);
+        let req = request(IppOperation::GetPrinterAttributes, attributes);
+        let response = IppServer::process_request(&processor, req, "en")
+            .await
+            .unwrap();
+        assert!(response.attributes.contains_key("printer-name"));
+        assert!(response.attributes.contains_key("printer-state-reasons"));
+    }
+
+    #[test]
+    fn errors_map_to_ipp_status_codes() {
+        assert_eq!(IppStatusCode::from(&Error::Validation("bad".into())), IppStatusCode::BadRequest);
+        assert_eq!(IppStatusCode::from(&Error::Permission("no".into())), IppStatusCode::NotAuthorized);
+        assert_eq!(IppStatusCode::from(&Error::NotFound("job".into())), IppStatusCode::NotFound);
+        assert_eq!(
+            IppStatusCode::from(&Error::Unsupported("sides".into())),
+            IppStatusCode::ClientErrorAttributesOrValuesNotSupported
+        );
+        assert_eq!(IppStatusCode::from(&Error::Timeout("slow".into())), IppStatusCode::ServiceUnavailable);
+        assert_eq!(IppStatusCode::from(&Error::Pdf("broken".into())), IppStatusCode::InternalError);
+        let context = Error::NotFound("job".into()).context("Failed to cancel job 7");
+        assert_eq!(IppStatusCode::from(&context), IppStatusCode::NotFound);
+    }
+
+    #[test]
+    fn status_codes_use_rfc_8011_values() {
+        assert_eq!(IppStatusCode::NotFound.code(), 0x0406);
+        assert_eq!(IppStatusCode::BadRequest.code(), 0x0400);
+        assert_eq!(IppStatusCode::ServiceUnavailable.code(), 0x0502);
+        assert_eq!(IppStatusCode::NotFound.code().to_be_bytes(), [0x04, 0x06]);
+        assert!(IppStatusCode::ClientErrorDocumentFormatNotSupported.is_client_error());
+        assert!(!IppStatusCode::Successful.is_client_error());
+    }
+
+    #[tokio::test]
+    async fn print_job_spools_inline_document_and_queues_it() {
+        let spool = tempfile::tempdir().unwrap();
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(
+            JobProcessor::new(queue, 1, 1, sender)
+                .unwrap()
+                .with_spool_dir(spool.path().to_path_buf()),
+        );
+
+        let attributes = HashMap::from([
+            ("document-format".to_string(), vec!["application/pdf".to_string()]),
+            ("copies".to_string(), vec!["2".to_string()]),
+        ]);
+        let mut print_job = request(IppOperation::PrintJob, attributes);
+        print_job.data = b"%PDF-1.7 inline".to_vec();
+        let response = IppServer::process_request(&processor, print_job, "en").await.unwrap();
+
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+        let jobs = processor.list_jobs().await;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(response.attributes["job-id"], vec![IppValue::Integer(jobs[0].ipp_job_id)]);
+        assert_eq!(std::fs::read(&jobs[0].file_path).unwrap(), b"%PDF-1.7 inline");
+        assert!(jobs[0].file_path.starts_with(spool.path()));
+    }
+
+    #[tokio::test]
+    async fn mislabeled_postscript_is_spooled_as_postscript() {
+        let spool = tempfile::tempdir().unwrap();
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(
+            JobProcessor::new(queue, 1, 1, sender)
+                .unwrap()
+                .with_spool_dir(spool.path().to_path_buf()),
+        );
+
+        let attributes = HashMap::from([("document-format".to_string(), vec!["application/pdf".to_string()])]);
+        let mut print_job = request(IppOperation::PrintJob, attributes);
+        print_job.data = b"%!PS-Adobe-3.0\nshowpage\n".to_vec();
+        let response = IppServer::process_request(&processor, print_job, "en").await.unwrap();
+
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+        let jobs = processor.list_jobs().await;
+        assert_eq!(jobs[0].file_path.extension().unwrap(), "ps");
+    }
+
+    #[tokio::test]
+    async fn unsupported_document_format_is_rejected() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        let text = || HashMap::from([("document-format".to_string(), vec!["text/plain".to_string()])]);
+
+        for operation in [IppOperation::ValidateJob, IppOperation::CreateJob, IppOperation::PrintJob] {
+            let mut job = request(operation, text());
+            job.data = b"Just some notes".to_vec();
+            let response = IppServer::process_request(&processor, job, "en").await.unwrap();
+            assert_eq!(response.status_code, IppStatusCode::ClientErrorDocumentFormatNotSupported);
+        }
+        assert!(processor.list_jobs().await.is_empty());
+
+        let pwg = HashMap::from([("document-format".to_string(), vec!["image/pwg-raster".to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::ValidateJob, pwg), "en")
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, IppStatusCode::ClientErrorDocumentFormatNotSupported);
+        let mut job = request(IppOperation::PrintJob, HashMap::new());
+        job.data = b"RaS2PwgRaster".to_vec();
+        let response = IppServer::process_request(&processor, job, "en").await.unwrap();
+        assert_eq!(response.status_code, IppStatusCode::ClientErrorDocumentFormatNotSupported);
+        assert!(processor.list_jobs().await.is_empty());
+
+        let pdf = HashMap::from([("document-format".to_string(), vec!["application/pdf".to_string()])]);
+        let response = IppServer::process_request(&processor, request(IppOperation::ValidateJob, pdf), "en")
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+
+        let attributes = request(IppOperation::GetPrinterAttributes, HashMap::new());
+        let response = IppServer::process_request(&processor, attributes, "en").await.unwrap();
+        let postscript = IppValue::MimeMediaType("application/postscript".to_string());
+        assert!(response.attributes["document-format-supported"].contains(&postscript));
+    }
+
+    #[test]
+    fn print_color_mode_selects_color_mode() {
+        let color_mode = |keyword: &str| {
+            let attributes = HashMap::from([("print-color-mode".to_string(), vec![keyword.to_string()])]);
+            IppServer::print_options(&request(IppOperation::PrintJob, attributes)).map(|options| options.color_mode)
+        };
+
+        assert_eq!(color_mode("auto").unwrap(), ColorMode::Auto);
+        assert_eq!(color_mode("color").unwrap(), ColorMode::Color);
+        assert_eq!(color_mode("monochrome").unwrap(), ColorMode::Grayscale);
+        assert_eq!(color_mode("bi-level").unwrap(), ColorMode::Monochrome);
+        assert!(matches!(color_mode("sepia"), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn print_quality_selects_print_quality() {
+        let quality = |value: &str| {
+            let attributes = HashMap::from([("print-quality".to_string(), vec![value.to_string()])]);
+            let options = IppServer::print_options(&request(IppOperation::PrintJob, attributes));
+            options.map(|options| options.print_quality)
+        };
+
+        assert_eq!(quality("3").unwrap(), PrintQuality::Draft);
+        assert_eq!(quality("4").unwrap(), PrintQuality::Normal);
+        assert_eq!(quality("5").unwrap(), PrintQuality::High);
+        assert!(matches!(quality("6"), Err(Error::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn unsupported_attribute_values_are_refused_by_validate_job() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+
+        let mut body = encode_request(IppOperation::ValidateJob, 1, "en");
+        body.pop();
+        encode_attribute(&mut body, KEYWORD_TAG, "sides", b"three-sided");
+        body.push(END_OF_ATTRIBUTES_TAG);
+        let response = IppServer::respond(&processor, &PrinterCapabilities::default(), &body).await;
+        assert_eq!(response.status_code, IppStatusCode::ClientErrorAttributesOrValuesNotSupported);
+        assert_eq!(response.status_code.code(), 0x040B);
+
+        let number_up = HashMap::from([("number-up".to_string(), vec!["3".to_string()])]);
+        let error = IppServer::process_request(&processor, request(IppOperation::ValidateJob, number_up), "en")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(IppStatusCode::from(&error), IppStatusCode::ClientErrorAttributesOrValuesNotSupported);
+        let copies = HashMap::from([("copies".to_string(), vec!["many".to_string()])]);
+        let error = IppServer::process_request(&processor, request(IppOperation::ValidateJob, copies), "en")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(IppStatusCode::from(&error), IppStatusCode::BadRequest);
+    }
+
+    #[test]
+    fn media_col_selects_page_size_and_tray() {
+        use crate::ipp::{BEGIN_COLLECTION_TAG, END_COLLECTION_TAG, MEMBER_ATTR_NAME_TAG};
+        use boomaga_core::PageSize;
+
+        let mut body = encode_request(IppOperation::PrintJob, 1, "en");
+        body.pop();
+        body.push(JOB_ATTRIBUTES_TAG);
+        encode_attribute(&mut body, KEYWORD_TAG, "media", b"na_letter_8.5x11in");
+        encode_attribute(&mut body, BEGIN_COLLECTION_TAG, "media-col", b"");
+        encode_attribute(&mut body, MEMBER_ATTR_NAME_TAG, "", b"media-size");
+        encode_attribute(&mut body, BEGIN_COLLECTION_TAG, "", b"");
+        encode_attribute(&mut body, MEMBER_ATTR_NAME_TAG, "", b"x-dimension");
+        encode_attribute(&mut body, INTEGER_TAG, "", &21000i32.to_be_bytes());
+        encode_attribute(&mut body, MEMBER_ATTR_NAME_TAG, "", b"y-dimension");
+        encode_attribute(&mut body, INTEGER_TAG, "", &29700i32.to_be_bytes());
+        encode_attribute(&mut body, END_COLLECTION_TAG, "", b"");
+        encode_attribute(&mut body, MEMBER_ATTR_NAME_TAG, "", b"media-source");
+        encode_attribute(&mut body, KEYWORD_TAG, "", b"tray-2");
+        for margin in ["media-top-margin", "media-bottom-margin", "media-left-margin", "media-right-margin"] {
+            encode_attribute(&mut body, MEMBER_ATTR_NAME_TAG, "", margin.as_bytes());
+            encode_attribute(&mut body, INTEGER_TAG, "", &0i32.to_be_bytes());
+        }
+        encode_attribute(&mut body, END_COLLECTION_TAG, "", b"");
+        body.push(END_OF_ATTRIBUTES_TAG);
+
+        let parsed = IppServer::parse_ipp_request(&body).unwrap();
+        assert_eq!(parsed.attributes["media-col.media-size.x-dimension"], vec!["21000"]);
+        let options = IppServer::print_options(&parsed).unwrap();
+        assert_eq!(options.media.size, Some(PageSize::A4));
+        assert_eq!(options.media.source.as_deref(), Some("tray-2"));
+        assert_eq!(options.margins, MarginMode::None);
+
+        // Without media-col the media keyword is used
+        let attributes = HashMap::from([("media".to_string(), vec!["na_letter_8.5x11in".to_string()])]);
+        let options = IppServer::print_options(&request(IppOperation::PrintJob, attributes)).unwrap();
+        assert_eq!(options.media.size, Some(PageSize::Letter));
+        assert_eq!(options.margins, MarginMode::Normal);
+    }
+
+    #[tokio::test]
+    async fn octet_stream_documents_are_detected() {
+        let spool = tempfile::tempdir().unwrap();
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(
+            JobProcessor::new(queue, 1, 1, sender)
+                .unwrap()
+                .with_spool_dir(spool.path().to_path_buf()),
+        );
+        let octet_stream =
+            || HashMap::from([("document-format".to_string(), vec!["application/octet-stream".to_string()])]);
+
+        let mut unknown = request(IppOperation::PrintJob, octet_stream());
+        unknown.data = b"Just some notes".to_vec();
+        let response = IppServer::process_request(&processor, unknown, "en").await.unwrap();
+        assert_eq!(response.status_code, IppStatusCode::ClientErrorDocumentFormatNotSupported);
+
+        let mut postscript = request(IppOperation::PrintJob, octet_stream());
+        postscript.data = b"%!PS-Adobe-3.0\nshowpage\n".to_vec();
+        let response = IppServer::process_request(&processor, postscript, "en").await.unwrap();
+        assert_eq!(response.status_code, IppStatusCode::Successful);
+        assert_eq!(processor.list_jobs().await[0].file_path.extension().unwrap(), "ps");
+    }
+
+    #[tokio::test]
+    async fn metrics_are_served_when_enabled() {
+        let queue = Arc::new(JobQueue::new(4).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(queue, 1, 1, sender).unwrap());
+        processor.start();
+        let job_id = JobId::new();
+        processor
+            .add_job(PrintJobRequest {
+                job_id: job_id.clone(),
+                // Without pages the job completes whether or not rendering is built in
+                file_path: std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/no-pages.ps"),
+                file_type: FileType::PostScript,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+        while processor.get_status(job_id.to_string()).await != Some(JobStatus::Completed) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let server = IppServer::new("127.0.0.1".parse().unwrap(), 0, std::path::PathBuf::new(), String::new(), processor)
+            .unwrap()
+            .with_metrics(true);
+        let listener = server.bind().await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, addr) = listener.accept().await.unwrap();
+        assert!(server.accept_client(stream, addr).await);
+
+        client
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await.unwrap();
+
+        assert!(reply.starts_with("HTTP/1.1 200 OK"));
+        assert!(reply.contains("boomaga_queue_size 0\n"));
+        assert!(reply.contains("boomaga_jobs{status=\"completed\"} 1\n"));
+        assert!(reply.contains("boomaga_jobs{status=\"failed\"} 0\n"));
+        assert!(reply.contains("boomaga_job_duration_seconds_count 1\n"));
+        assert!(reply.contains("boomaga_job_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(reply.contains("boomaga_active_clients 1\n"));
+    }
+
+    #[tokio::test]
+    async fn full_queue_answers_busy_with_retry_after() {
+        let queue = Arc::new(JobQueue::new(1).unwrap());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let processor = Arc::new(JobProcessor::new(Arc::clone(&queue), 1, 1, sender).unwrap());
+        queue
+            .push(PrintJobRequest {
+                job_id: JobId::new(),
+                file_path: std::path::PathBuf::from("test.pdf"),
+                file_type: FileType::Pdf,
+                printer_name: None,
+                options: PrintOptions::default(),
+                batch_documents: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let response = IppServer::process_request(&processor, request(IppOperation::CreateJob, HashMap::new()), "en")
+            .await
+            .unwrap_or_else(|e| IppResponse {
+                status_code: IppStatusCode::from(&e),
+                operation_id: IppOperation::CreateJob,
+                request_id: 1,
+                attributes: HashMap::new(),
+                jobs: Vec::new(),
+            });
+        assert_eq!(response.status_code, IppStatusCode::ServerErrorBusy);
+        let reply = String::from_utf8_lossy(&IppServer::encode_http_response(&response, true)).into_owned();
+        assert!(reply.contains("\r\nRetry-After: 10\r\n"));
+
+        let attributes = request(IppOperation::GetPrinterAttributes, HashMap::new());
+        let response = IppServer::process_request(&processor, attributes, "en").await.unwrap();
+        assert_eq!(response.attributes["printer-state-reasons"], IppValue::keywords(["spool-area-full"]));
     }
 }
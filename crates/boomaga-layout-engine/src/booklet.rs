@@ -2,9 +2,16 @@
 
 use boomaga_core::{PageSize, Error, Result};
 use crate::n_up::{NUpCalculator, PagePosition, NUpLayout, PageResult};
-use tracing::{info, debug};
+use std::time::Instant;
+use tracing::{info, debug, instrument};
+
+/// Marks an empty slot on a booklet sheet
+pub const BLANK_PAGE: usize = 0;
 
 /// Booklet layout result
+///
+/// Each output page is one sheet whose `input_pages` are four slots:
+/// front-left, front-right, back-left, back-right. Padding is [`BLANK_PAGE`].
 pub struct BookletLayout {
     /// The output pages
     pub pages: Vec<PageResult>,
@@ -16,6 +23,8 @@ pub struct BookletLayout {
     pub booklet_type: BookletType,
     /// Page arrangement
     pub arrangement: PageArrangement,
+    /// Output sheet (1-based) carrying the covers, if any
+    pub cover_sheet: Option<usize>,
 }
 
 /// Booklet types
@@ -46,6 +55,12 @@ pub struct BookletCalculator {
     margins: MarginConfig,
     /// Number of pages
     page_count: usize,
+    /// Front cover page number (1-based)
+    front_cover: Option<usize>,
+    /// Back cover page number (1-based)
+    back_cover: Option<usize>,
+    /// Leave the inside of the cover sheet unprinted
+    blank_inside_covers: bool,
 }
 
 impl BookletCalculator {
@@ -63,11 +78,39 @@ impl BookletCalculator {
             booklet_type,
             margins: MarginConfig::default(),
             page_count,
+            front_cover: None,
+            back_cover: None,
+            blank_inside_covers: false,
         })
     }
 
+    /// Print the given pages (1-based) on the outside of the first sheet
+    pub fn with_covers(mut self, front_cover: Option<usize>, back_cover: Option<usize>) -> Self {
+        self.front_cover = front_cover;
+        self.back_cover = back_cover;
+        self
+    }
+
+    /// Leave the inside of the cover sheet blank, e.g. for separate cover stock
+    pub fn with_blank_inside_covers(mut self, blank: bool) -> Self {
+        self.blank_inside_covers = blank;
+        self
+    }
+
     /// Calculate booklet layout
+    ///
+    /// Runs in a debug `impose` span and logs the time it took.
+    #[instrument(name = "impose", level = "debug", skip_all, fields(layout = "booklet", pages = self.page_count))]
     pub fn calculate(&self, output_size: PageSize) -> Result<BookletLayout> {
+        let started = (!tracing::Span::current().is_disabled()).then(Instant::now);
+        let layout = self.impose(output_size)?;
+        if let Some(started) = started {
+            debug!(sheets = layout.page_count, elapsed = ?started.elapsed(), "Imposed");
+        }
+        Ok(layout)
+    }
+
+    fn impose(&self, output_size: PageSize) -> Result<BookletLayout> {
         info!("Calculating {}-page booklet layout ({} pages per sheet)", self.page_count, self.page_count);
 
         // For a booklet, we need an even number of pages
@@ -75,20 +118,22 @@ impl BookletCalculator {
             return Err(Error::Validation("Booklet requires an even number of pages".into()));
         }
 
+        if self.front_cover.is_some() || self.back_cover.is_some() {
+            return self.calculate_with_covers(output_size);
+        }
+
         // Calculate number of output sheets needed
         let output_sheets = (self.page_count + 3) / 4; // Ceiling division
 
         info!("Need {} output sheets for {} input pages", output_sheets, self.page_count);
 
-        // For a booklet, we need 4 pages per sheet at minimum
-        let pages_per_sheet = std::cmp::max(4, (self.page_count + 3) / 4);
-
+        let all_pages: Vec<usize> = (1..=self.page_count).collect();
         let mut pages = Vec::new();
 
         // Generate pages in booklet order
         for sheet_index in 0..output_sheets {
             // Determine input pages for this sheet
-            let input_pages = self.generate_sheet_pages(sheet_index);
+            let input_pages = saddle_sheet(&all_pages, sheet_index);
 
             // Create output page for this sheet
             let output_page = self.create_booklet_page(sheet_index, input_pages, output_size)?;
@@ -104,26 +149,63 @@ impl BookletCalculator {
             page_count,
             booklet_type: self.booklet_type,
             arrangement: PageArrangement::CorrectOrder,
+            cover_sheet: None,
         })
     }
 
-    /// Generate input pages for a sheet
-    fn generate_sheet_pages(&self, sheet_index: usize) -> Vec<usize> {
-        let mut pages = Vec::new();
+    /// Calculate a booklet whose first sheet wraps the body as a cover
+    ///
+    /// The back and front covers take the front side of sheet 1; its back side
+    /// holds the first and last body pages unless the inside is blank. The
+    /// remaining pages are imposed on the following sheets.
+    fn calculate_with_covers(&self, output_size: PageSize) -> Result<BookletLayout> {
+        let covers: Vec<usize> = [self.front_cover, self.back_cover].into_iter().flatten().collect();
+        if covers.iter().any(|&page| page == 0 || page > self.page_count) {
+            return Err(Error::Validation(format!(
+                "Cover pages must be between 1 and {}",
+                self.page_count
+            )));
+        }
+        if self.front_cover.is_some() && self.front_cover == self.back_cover {
+            return Err(Error::Validation("Front and back cover must be different pages".into()));
+        }
 
-        // Calculate which input pages belong to this sheet
-        for i in 0..4 {
-            let page_num = sheet_index * 4 + i;
+        let mut body: Vec<usize> = (1..=self.page_count)
+            .filter(|page| !covers.contains(page))
+            .collect();
+
+        let mut cover_pages = vec![
+            self.back_cover.unwrap_or(BLANK_PAGE),
+            self.front_cover.unwrap_or(BLANK_PAGE),
+            BLANK_PAGE,
+            BLANK_PAGE,
+        ];
+        if !self.blank_inside_covers && body.len() >= 2 {
+            cover_pages[2] = body.remove(0);
+            cover_pages[3] = body.pop().unwrap_or(BLANK_PAGE);
+        }
 
-            // Skip empty pages at the end
-            if page_num < self.page_count {
-                pages.push(page_num + 1); // Convert to 1-based
-            }
+        let mut pages = vec![self.create_booklet_page(0, cover_pages, output_size)?];
+        for sheet_index in 0..body.len().div_ceil(4) {
+            let input_pages = saddle_sheet(&body, sheet_index);
+            pages.push(self.create_booklet_page(sheet_index + 1, input_pages, output_size)?);
         }
 
-        pages
+        debug!("Imposed {} body pages inside the cover sheet", body.len());
+
+        let page_count = pages.len();
+
+        Ok(BookletLayout {
+            pages,
+            output_size,
+            page_count,
+            booklet_type: self.booklet_type,
+            arrangement: PageArrangement::CorrectOrder,
+            cover_sheet: Some(1),
+        })
     }
 
+
     /// Create a booklet page
     fn create_booklet_page(
         &self,
@@ -134,12 +216,16 @@ impl BookletCalculator {
         let position = self.determine_page_position(sheet_index, input_pages.clone())?;
 
         let content = None; // In production, would render the page content
+        let input_transforms = vec![None; input_pages.len()];
 
         Ok(PageResult {
             output_page: sheet_index + 1,
             input_pages,
             position,
             content,
+            transform: None,
+            input_transforms,
+            decorations: Vec::new(),
         })
     }
 
@@ -183,6 +269,51 @@ impl BookletCalculator {
     }
 }
 
+impl BookletLayout {
+    /// Describe which input page lands where on each side of each sheet
+    ///
+    /// For example `Sheet 1 front: [8@MiddleLeft, 1@MiddleRight]`.
+    pub fn explain(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for page in &self.pages {
+            let cover = if self.cover_sheet == Some(page.output_page) { " (cover)" } else { "" };
+            for (side, slots) in ["front", "back"].iter().zip(page.input_pages.chunks(2)) {
+                let placements: Vec<String> = slots
+                    .iter()
+                    .zip([PagePosition::MiddleLeft, PagePosition::MiddleRight])
+                    .map(|(&number, position)| match number {
+                        BLANK_PAGE => format!("blank@{:?}", position),
+                        number => format!("{}@{:?}", number, position),
+                    })
+                    .collect();
+                lines.push(format!(
+                    "Sheet {}{} {}: [{}]",
+                    page.output_page,
+                    cover,
+                    side,
+                    placements.join(", ")
+                ));
+            }
+        }
+        lines
+    }
+}
+
+/// Saddle-stitch slots of sheet `sheet_index` for `pages` in reading order
+///
+/// Sheet 1 carries the outermost pages; missing pages become [`BLANK_PAGE`].
+fn saddle_sheet(pages: &[usize], sheet_index: usize) -> Vec<usize> {
+    let slots = pages.len().div_ceil(4) * 4;
+    let page = |slot: usize| pages.get(slot).copied().unwrap_or(BLANK_PAGE);
+    let outer = 2 * sheet_index;
+    vec![
+        page(slots - 1 - outer),
+        page(outer),
+        page(outer + 1),
+        page(slots - 2 - outer),
+    ]
+}
+
 /// Margin configuration
 #[derive(Debug, Clone, Copy)]
 pub struct MarginConfig {
@@ -218,6 +349,99 @@ mod tests {
         assert_eq!(result.booklet_type, BookletType::Standard);
     }
 
+    #[test]
+    fn eight_page_booklet_explains_saddle_stitch_order() {
+        let calculator = BookletCalculator::new(BookletType::Standard, 8).unwrap();
+
+        let result = calculator.calculate(PageSize::A4).unwrap();
+
+        assert_eq!(
+            result.explain(),
+            vec![
+                "Sheet 1 front: [8@MiddleLeft, 1@MiddleRight]",
+                "Sheet 1 back: [2@MiddleLeft, 7@MiddleRight]",
+                "Sheet 2 front: [6@MiddleLeft, 3@MiddleRight]",
+                "Sheet 2 back: [4@MiddleLeft, 5@MiddleRight]",
+            ]
+        );
+    }
+
+    #[test]
+    fn short_booklet_is_padded_with_blanks() {
+        let calculator = BookletCalculator::new(BookletType::Standard, 6).unwrap();
+
+        let result = calculator.calculate(PageSize::A4).unwrap();
+
+        assert_eq!(result.pages[0].input_pages, vec![BLANK_PAGE, 1, 2, BLANK_PAGE]);
+        assert_eq!(result.pages[1].input_pages, vec![6, 3, 4, 5]);
+    }
+
+    #[test]
+    fn covers_land_on_outside_of_first_sheet() {
+        let calculator = BookletCalculator::new(BookletType::Standard, 8)
+            .unwrap()
+            .with_covers(Some(1), Some(8));
+
+        let result = calculator.calculate(PageSize::A4).unwrap();
+
+        assert_eq!(result.cover_sheet, Some(1));
+        assert_eq!(result.pages[0].output_page, 1);
+        assert_eq!(result.pages[0].input_pages[..2], [8, 1]);
+        assert_eq!(result.pages[0].input_pages[2..], [2, 7]);
+        assert_eq!(result.pages[1].input_pages, vec![6, 3, 4, 5]);
+    }
+
+    #[test]
+    fn blank_inside_covers_keeps_body_off_cover_sheet() {
+        let calculator = BookletCalculator::new(BookletType::Standard, 6)
+            .unwrap()
+            .with_covers(Some(1), Some(6))
+            .with_blank_inside_covers(true);
+
+        let result = calculator.calculate(PageSize::A4).unwrap();
+
+        assert_eq!(result.pages[0].input_pages, vec![6, 1, BLANK_PAGE, BLANK_PAGE]);
+        assert_eq!(result.pages[1].input_pages, vec![5, 2, 3, 4]);
+    }
+
+    #[test]
+    fn out_of_range_cover_is_rejected() {
+        let calculator = BookletCalculator::new(BookletType::Standard, 4)
+            .unwrap()
+            .with_covers(Some(5), None);
+
+        assert!(calculator.calculate(PageSize::A4).is_err());
+    }
+
+    #[test]
+    fn batch_booklet_imposes_across_merged_documents() {
+        use boomaga_core::{Document, DuplexMode, FileType, Orientation, Page};
+        use crate::duplex::{pad_for_duplex, DuplexPadding};
+        use std::path::PathBuf;
+
+        let document = |name: &str, count: usize| {
+            let mut document = Document::new(name.into(), PathBuf::from(name), FileType::Pdf);
+            for number in 1..=count {
+                document.add_page(Page::new(number, 595.0, 842.0, Orientation::Portrait));
+            }
+            document
+        };
+        let merged =
+            Document::concatenate("batch".into(), vec![document("first.pdf", 2), document("second.pdf", 3)])
+                .unwrap();
+        assert_eq!(merged.page_count(), 5);
+
+        let pages = pad_for_duplex(merged.pages, DuplexMode::LongEdge, PageSize::A4, &DuplexPadding::default());
+        let result = BookletCalculator::new(BookletType::Standard, pages.len())
+            .unwrap()
+            .calculate(PageSize::A4)
+            .unwrap();
+
+        // Pages 3-5 come from the second document; 6 is the padding blank
+        assert_eq!(result.pages[0].input_pages, vec![BLANK_PAGE, 1, 2, BLANK_PAGE]);
+        assert_eq!(result.pages[1].input_pages, vec![6, 3, 4, 5]);
+    }
+
     #[test]
     fn test_odd_page_count() {
         let calculator = BookletCalculator::new(BookletType::Standard, 7);
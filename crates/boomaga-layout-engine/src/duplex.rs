@@ -0,0 +1,199 @@
+//! Blank-page padding for two-sided printing
+
+use crate::booklet::BLANK_PAGE;
+use crate::n_up::PageResult;
+use crate::transforms::PageTransformer;
+use boomaga_core::{DuplexMode, Orientation, Page, PageSize};
+use tracing::debug;
+
+/// Duplex padding options
+#[derive(Debug, Clone, Default)]
+pub struct DuplexPadding {
+    /// Start each section on a right-hand (odd) page
+    pub sections_start_on_right: bool,
+    /// 1-based numbers of the first page of each section
+    pub section_starts: Vec<usize>,
+}
+
+/// Pad a page list with blank pages so duplex sheets don't mix documents
+///
+/// Single-sided jobs are returned unchanged. Pages are renumbered from 1.
+pub fn pad_for_duplex(
+    pages: Vec<Page>,
+    duplex: DuplexMode,
+    output_size: PageSize,
+    padding: &DuplexPadding,
+) -> Vec<Page> {
+    if duplex == DuplexMode::None {
+        return pages;
+    }
+
+    let input_count = pages.len();
+    let mut padded = Vec::with_capacity(input_count + 1);
+    for (index, page) in pages.into_iter().enumerate() {
+        let starts_section = padding.sections_start_on_right
+            && index > 0
+            && padding.section_starts.contains(&(index + 1));
+        // A section start must land on an odd output position
+        if starts_section && padded.len() % 2 == 1 {
+            padded.push(blank_page(output_size));
+        }
+        padded.push(page);
+    }
+
+    if padded.len() % 2 == 1 {
+        padded.push(blank_page(output_size));
+    }
+
+    for (index, page) in padded.iter_mut().enumerate() {
+        page.number = index + 1;
+    }
+
+    debug!(
+        "Padded {} pages to {} for duplex printing",
+        input_count,
+        padded.len()
+    );
+
+    padded
+}
+
+/// Turn the back side of each sheet for short-edge binding
+///
+/// Back sides (even output pages) are rotated 180° so they read the right way
+/// up after the sheet is flipped over its short edge. Long-edge and
+/// single-sided jobs are left untouched.
+pub fn apply_duplex_flips(pages: &mut [PageResult], duplex: DuplexMode) {
+    if duplex != DuplexMode::ShortEdge {
+        return;
+    }
+
+    let transformer = PageTransformer::new();
+    for page in pages.iter_mut().filter(|page| page.output_page % 2 == 0) {
+        page.transform = Some(transformer.rotate(180.0).transform);
+    }
+}
+
+/// The two runs through a one-sided printer of a manually duplexed job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManualDuplexPasses {
+    /// Fronts of the sheets, in printing order
+    pub fronts: Vec<usize>,
+    /// Backs of the sheets, in printing order once the printed stack is
+    /// turned over and fed back in. A front without a back gets [`BLANK_PAGE`].
+    pub backs: Vec<usize>,
+}
+
+/// Split `page_count` pages, numbered from 1, into manual duplex passes
+///
+/// Odd pages go on the fronts. The turned-over stack starts with the last
+/// sheet, so the even pages are printed in reverse.
+pub fn manual_duplex_passes(page_count: usize) -> ManualDuplexPasses {
+    let fronts: Vec<usize> = (1..=page_count).step_by(2).collect();
+    let backs = (1..=fronts.len())
+        .rev()
+        .map(|sheet| if sheet * 2 <= page_count { sheet * 2 } else { BLANK_PAGE })
+        .collect();
+    ManualDuplexPasses { fronts, backs }
+}
+
+/// Create an empty page of the output size
+fn blank_page(output_size: PageSize) -> Page {
+    let (width, height) = (output_size.width_points(), output_size.height_points());
+    let orientation = if width > height {
+        Orientation::Landscape
+    } else {
+        Orientation::Portrait
+    };
+    Page::new(0, width, height, orientation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transforms::TransformOperation;
+
+    /// Letter-sized input pages, so A4 blanks are easy to tell apart
+    fn pages(count: usize) -> Vec<Page> {
+        (1..=count)
+            .map(|number| Page::new(number, 612.0, 792.0, Orientation::Portrait))
+            .collect()
+    }
+
+    fn is_blank(page: &Page) -> bool {
+        page.width == 595.0 && !page.has_content()
+    }
+
+    #[test]
+    fn odd_duplex_job_gets_trailing_blank() {
+        let padded = pad_for_duplex(pages(3), DuplexMode::LongEdge, PageSize::A4, &DuplexPadding::default());
+
+        assert_eq!(padded.len(), 4);
+        assert_eq!(padded[3].number, 4);
+        assert!(is_blank(&padded[3]));
+        assert_eq!(padded[3].height, 842.0);
+    }
+
+    fn imposed_pages(count: usize) -> Vec<PageResult> {
+        crate::NUpCalculator::new(1)
+            .unwrap()
+            .calculate(&(1..=count).collect::<Vec<_>>(), PageSize::A4)
+            .unwrap()
+            .pages
+    }
+
+    #[test]
+    fn short_edge_rotates_back_sides_only() {
+        let mut pages = imposed_pages(4);
+
+        apply_duplex_flips(&mut pages, DuplexMode::ShortEdge);
+
+        let transforms: Vec<_> = pages.iter().map(|page| page.transform).collect();
+        assert_eq!(
+            transforms,
+            vec![None, Some(TransformOperation::Rotate180), None, Some(TransformOperation::Rotate180)]
+        );
+    }
+
+    #[test]
+    fn long_edge_leaves_back_sides_unrotated() {
+        let mut pages = imposed_pages(4);
+
+        apply_duplex_flips(&mut pages, DuplexMode::LongEdge);
+
+        assert!(pages.iter().all(|page| page.transform.is_none()));
+    }
+
+    #[test]
+    fn manual_duplex_prints_odd_pages_then_even_pages_reversed() {
+        let passes = manual_duplex_passes(8);
+
+        assert_eq!(passes.fronts, vec![1, 3, 5, 7]);
+        assert_eq!(passes.backs, vec![8, 6, 4, 2]);
+
+        // The last sheet of an odd job has a blank back, fed first
+        assert_eq!(manual_duplex_passes(5).backs, vec![BLANK_PAGE, 4, 2]);
+    }
+
+    #[test]
+    fn simplex_job_is_unchanged() {
+        let padded = pad_for_duplex(pages(3), DuplexMode::None, PageSize::A4, &DuplexPadding::default());
+
+        assert_eq!(padded.len(), 3);
+    }
+
+    #[test]
+    fn sections_start_on_right_hand_pages() {
+        let padding = DuplexPadding {
+            sections_start_on_right: true,
+            section_starts: vec![1, 2],
+        };
+
+        let padded = pad_for_duplex(pages(3), DuplexMode::ShortEdge, PageSize::A4, &padding);
+
+        // Page 1, blank, then the second section from position 3
+        assert_eq!(padded.len(), 4);
+        assert!(is_blank(&padded[1]));
+        assert!(!is_blank(&padded[2]));
+    }
+}
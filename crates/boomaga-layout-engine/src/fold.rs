@@ -0,0 +1,130 @@
+//! Fold lines for brochure layouts
+
+use crate::n_up::NUpLayout;
+use boomaga_core::{Color, GraphicsElement, PageSize, PathElement};
+use tracing::debug;
+
+/// Length of each dash and of each gap in a fold line (points)
+const FOLD_DASH_LENGTH: f64 = 6.0;
+
+/// Brochure fold types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldType {
+    /// One fold in the middle
+    BiFold,
+    /// Two folds rolling the outer panels inwards (letter fold)
+    TriFold,
+    /// Two folds in alternating directions
+    ZFold,
+}
+
+/// Direction a sheet is folded in, seen from the printed side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldDirection {
+    /// Folded towards the viewer
+    Valley,
+    /// Folded away from the viewer
+    Mountain,
+}
+
+/// A vertical fold across the output sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoldLine {
+    /// Distance from the left edge of the sheet (points)
+    pub x: f64,
+    /// Fold direction
+    pub direction: FoldDirection,
+}
+
+impl FoldType {
+    /// Get the fold lines for a sheet of the given size, left to right
+    pub fn fold_lines(&self, output_size: PageSize) -> Vec<FoldLine> {
+        let width = output_size.width_points();
+        let line = |fold: f64, panels: f64, direction| FoldLine { x: width * fold / panels, direction };
+
+        match self {
+            FoldType::BiFold => vec![line(1.0, 2.0, FoldDirection::Valley)],
+            FoldType::TriFold => vec![
+                line(1.0, 3.0, FoldDirection::Valley),
+                line(2.0, 3.0, FoldDirection::Valley),
+            ],
+            FoldType::ZFold => vec![
+                line(1.0, 3.0, FoldDirection::Valley),
+                line(2.0, 3.0, FoldDirection::Mountain),
+            ],
+        }
+    }
+
+    /// Get the number of panels the sheet is folded into
+    pub fn panels(&self) -> usize {
+        self.fold_lines(PageSize::default()).len() + 1
+    }
+}
+
+/// Draw a fold line as a dashed path across the full sheet height
+pub fn fold_line_element(line: &FoldLine, output_size: PageSize) -> GraphicsElement {
+    let height = output_size.height_points();
+    let mut elements = Vec::new();
+    let mut y = 0.0;
+    while y < height {
+        elements.push(PathElement::MoveTo { x: line.x, y });
+        elements.push(PathElement::LineTo { x: line.x, y: (y + FOLD_DASH_LENGTH).min(height) });
+        y += 2.0 * FOLD_DASH_LENGTH;
+    }
+
+    GraphicsElement::Path {
+        elements,
+        stroke: Some(Color::rgb(128, 128, 128)),
+        fill: None,
+        stroke_width: 0.5,
+    }
+}
+
+/// Add fold lines to every sheet of a layout and record their positions
+pub fn apply_folds(layout: &mut NUpLayout, fold: FoldType) {
+    let lines = fold.fold_lines(layout.output_size);
+    for page in &mut layout.pages {
+        page.decorations
+            .extend(lines.iter().map(|line| fold_line_element(line, layout.output_size)));
+    }
+
+    debug!("Added {:?} fold lines to {} sheets", fold, layout.pages.len());
+    layout.folds = lines;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::n_up::NUpCalculator;
+
+    #[test]
+    fn tri_fold_splits_sheet_into_thirds() {
+        let mut layout = NUpCalculator::new(1).unwrap().calculate(&[1, 2], PageSize::A4).unwrap();
+
+        apply_folds(&mut layout, FoldType::TriFold);
+
+        let width = PageSize::A4.width_points();
+        let positions: Vec<f64> = layout.folds.iter().map(|line| line.x).collect();
+        assert_eq!(positions, vec![width * 1.0 / 3.0, width * 2.0 / 3.0]);
+        for page in &layout.pages {
+            let fold_paths = page
+                .decorations
+                .iter()
+                .filter(|element| matches!(element, GraphicsElement::Path { .. }))
+                .count();
+            assert_eq!(fold_paths, 2);
+        }
+    }
+
+    #[test]
+    fn z_fold_alternates_direction() {
+        let directions: Vec<_> = FoldType::ZFold
+            .fold_lines(PageSize::A4)
+            .iter()
+            .map(|line| line.direction)
+            .collect();
+
+        assert_eq!(directions, vec![FoldDirection::Valley, FoldDirection::Mountain]);
+        assert_eq!(FoldType::BiFold.panels(), 2);
+    }
+}
@@ -0,0 +1,207 @@
+//! Orientation-aware placement of image elements
+
+use crate::transforms::TransformOperation;
+use boomaga_core::{
+    Error, GraphicsElement, Orientation, Page, PageContents, PageSize, Result, MAX_PAGE_PIXELS,
+};
+use image::imageops::{self, FilterType};
+use image::metadata::Orientation as ExifOrientation;
+use image::{DynamicImage, ImageDecoder, ImageReader, Rgba, RgbaImage};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Where and how an image is drawn inside its target box
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagePlacement {
+    /// Transforms that bring the stored pixels upright, in order
+    pub transforms: Vec<TransformOperation>,
+    /// Left edge of the fitted image (points)
+    pub x: f64,
+    /// Top edge of the fitted image (points)
+    pub y: f64,
+    /// Fitted width after orientation (points)
+    pub width: f64,
+    /// Fitted height after orientation (points)
+    pub height: f64,
+}
+
+/// Place a `GraphicsElement::Image`, returning `None` for other elements
+pub fn place_image_element(element: &GraphicsElement) -> Result<Option<ImagePlacement>> {
+    match element {
+        GraphicsElement::Image { path, x, y, width, height } => {
+            place_image(path, *x, *y, *width, *height).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Fit an image file into a box, honouring its EXIF orientation
+///
+/// The image keeps its aspect ratio and is centred in the box.
+pub fn place_image(path: &Path, x: f64, y: f64, width: f64, height: f64) -> Result<ImagePlacement> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()
+        .map_err(|e| Error::Document(format!("Failed to read image {:?}: {}", path, e)))?;
+    let (pixel_width, pixel_height) = decoder.dimensions();
+    let orientation = decoder.orientation().unwrap_or(ExifOrientation::NoTransforms);
+
+    let transforms = orientation_transforms(orientation);
+    let swaps_axes = matches!(
+        orientation,
+        ExifOrientation::Rotate90
+            | ExifOrientation::Rotate270
+            | ExifOrientation::Rotate90FlipH
+            | ExifOrientation::Rotate270FlipH
+    );
+    let (natural_width, natural_height) = if swaps_axes {
+        (pixel_height as f64, pixel_width as f64)
+    } else {
+        (pixel_width as f64, pixel_height as f64)
+    };
+
+    if natural_width <= 0.0 || natural_height <= 0.0 {
+        return Err(Error::Document(format!("Image {:?} has no pixels", path)));
+    }
+
+    let scale = (width / natural_width).min(height / natural_height);
+    let fitted_width = natural_width * scale;
+    let fitted_height = natural_height * scale;
+
+    debug!(
+        "Placing {:?} ({}x{}, {:?}) at {:.1}x{:.1}",
+        path, pixel_width, pixel_height, orientation, fitted_width, fitted_height
+    );
+
+    Ok(ImagePlacement {
+        transforms,
+        x: x + (width - fitted_width) / 2.0,
+        y: y + (height - fitted_height) / 2.0,
+        width: fitted_width,
+        height: fitted_height,
+    })
+}
+
+/// Lay out an image file as a one-page raster of `page_size`
+///
+/// The image is placed with [`place_image`], turned upright and centred on
+/// white, at its own resolution up to [`MAX_PAGE_PIXELS`].
+pub fn image_page(path: &Path, page_size: PageSize) -> Result<Page> {
+    let (page_width, page_height) = (page_size.width_points(), page_size.height_points());
+    let placement = place_image(path, 0.0, 0.0, page_width, page_height)?;
+    let image = ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| Error::Document(format!("Failed to decode image {:?}: {}", path, e)))?;
+    let image = placement.transforms.iter().fold(image, apply_transform);
+
+    // Pixels per point: the image's own resolution, capped by the pixel budget
+    let budget = (MAX_PAGE_PIXELS as f64 / (page_width * page_height)).sqrt();
+    let scale = (f64::from(image.width()) / placement.width).min(budget);
+    let pixels = |points: f64| ((points * scale).round() as u32).max(1);
+
+    let fitted = image.resize_exact(pixels(placement.width), pixels(placement.height), FilterType::Triangle);
+    let (width, height) = (pixels(page_width), pixels(page_height));
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+    let (left, top) = ((placement.x * scale).round() as i64, (placement.y * scale).round() as i64);
+    imageops::overlay(&mut canvas, &fitted.to_rgba8(), left, top);
+
+    let orientation = if page_width > page_height { Orientation::Landscape } else { Orientation::Portrait };
+    let mut page = Page::new(1, page_width, page_height, orientation);
+    page.contents = PageContents::rgba(width as usize, height as usize, Arc::from(canvas.into_raw()));
+    Ok(page)
+}
+
+/// Apply one placement transform to decoded pixels
+fn apply_transform(image: DynamicImage, transform: &TransformOperation) -> DynamicImage {
+    match transform {
+        TransformOperation::Rotate90 => image.rotate90(),
+        TransformOperation::Rotate180 => image.rotate180(),
+        TransformOperation::Rotate270 => image.rotate270(),
+        TransformOperation::FlipHorizontal => image.fliph(),
+        TransformOperation::FlipVertical => image.flipv(),
+        _ => image,
+    }
+}
+
+/// Map an EXIF orientation onto page transforms
+fn orientation_transforms(orientation: ExifOrientation) -> Vec<TransformOperation> {
+    match orientation {
+        ExifOrientation::NoTransforms => vec![],
+        ExifOrientation::Rotate90 => vec![TransformOperation::Rotate90],
+        ExifOrientation::Rotate180 => vec![TransformOperation::Rotate180],
+        ExifOrientation::Rotate270 => vec![TransformOperation::Rotate270],
+        ExifOrientation::FlipHorizontal => vec![TransformOperation::FlipHorizontal],
+        ExifOrientation::FlipVertical => vec![TransformOperation::FlipVertical],
+        ExifOrientation::Rotate90FlipH => {
+            vec![TransformOperation::Rotate90, TransformOperation::FlipHorizontal]
+        }
+        ExifOrientation::Rotate270FlipH => {
+            vec![TransformOperation::Rotate270, TransformOperation::FlipHorizontal]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ExtendedColorType, ImageEncoder};
+
+    /// A 40x20 JPEG whose EXIF says "rotate 90° clockwise" (orientation 6)
+    fn rotated_jpeg_fixture() -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new(&mut jpeg)
+            .write_image(&[128u8; 40 * 20 * 3], 40, 20, ExtendedColorType::Rgb8)
+            .unwrap();
+
+        let tiff: &[u8] = &[
+            b'M', b'M', 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08, // big-endian header, IFD at 8
+            0x00, 0x01, // one entry
+            0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, // Orientation = 6
+            0x00, 0x00, 0x00, 0x00, // no next IFD
+        ];
+        let mut app1 = vec![0xFF, 0xE1];
+        app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        app1.extend_from_slice(b"Exif\0\0");
+        app1.extend_from_slice(tiff);
+
+        // Insert right after SOI
+        jpeg.splice(2..2, app1);
+        jpeg
+    }
+
+    #[test]
+    fn exif_rotation_swaps_axes_and_preserves_aspect() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, rotated_jpeg_fixture()).unwrap();
+
+        let placement = place_image(&path, 0.0, 0.0, 100.0, 100.0).unwrap();
+
+        assert_eq!(placement.transforms, vec![TransformOperation::Rotate90]);
+        // Upright the photo is 20 wide by 40 tall, so it fills the height
+        assert_eq!((placement.width, placement.height), (50.0, 100.0));
+        assert_eq!((placement.x, placement.y), (25.0, 0.0));
+    }
+
+    #[test]
+    fn image_pages_are_upright_rasters_of_the_page_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, rotated_jpeg_fixture()).unwrap();
+
+        let page = image_page(&path, PageSize::Custom { width: 100.0, height: 100.0 }).unwrap();
+
+        assert_eq!((page.number, page.width, page.height), (1, 100.0, 100.0));
+        // 20 upright pixels span the 50pt fitted width, so 0.4 pixels per point
+        let PageContents::Raster { width, height, .. } = page.contents else {
+            panic!("expected raster contents");
+        };
+        assert_eq!((width, height), (40, 40));
+        // The photo fills the middle half of the page, the sides stay white
+        assert_eq!(page.contents.pixel(0, 20), Some([255, 255, 255, 255]));
+        assert_ne!(page.contents.pixel(20, 20), Some([255, 255, 255, 255]));
+    }
+}
@@ -1,7 +1,9 @@
 //! Page layout templates for N-up and booklet layouts
 
-use crate::n_up::PagePosition;
-use boomaga_core::{Error, PageSize, Result};
+use crate::booklet::{BookletCalculator, BookletLayout, BookletType};
+use crate::n_up::{MarginConfig, NUpCalculator, NUpConfig, NUpLayout, PagePosition};
+use boomaga_core::{BuiltInLayout, CustomLayout, Error, PageSize, Result};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::info;
@@ -91,6 +93,44 @@ impl LayoutTemplate {
         Ok(positions[index])
     }
 
+    /// Get the (columns, rows) grid used for this many pages per sheet
+    pub fn grid(&self) -> (usize, usize) {
+        match self.pages_per_sheet {
+            2 => (1, 2),
+            4 => (2, 2),
+            6 => (3, 2),
+            8 => (3, 3),
+            _ => (1, 1),
+        }
+    }
+
+    /// Get the size of one cell of the grid (points)
+    pub fn cell_size(&self) -> (f64, f64) {
+        let (columns, rows) = self.grid();
+        (
+            self.output_size.width_points() / columns as f64,
+            self.output_size.height_points() / rows as f64,
+        )
+    }
+
+    /// Get the `(x, y, width, height)` of cell `index`, row by row from the top-left
+    ///
+    /// The grid fills the sheet inside `margin`, with `gutter` between cells.
+    pub fn cell_rect(&self, index: usize, margin: f64, gutter: f64) -> (f64, f64, f64, f64) {
+        let (columns, rows) = self.grid();
+        let width = (self.output_size.width_points() - 2.0 * margin - gutter * (columns - 1) as f64)
+            / columns as f64;
+        let height = (self.output_size.height_points() - 2.0 * margin - gutter * (rows - 1) as f64)
+            / rows as f64;
+        let (column, row) = (index % columns, index / columns);
+        (
+            margin + column as f64 * (width + gutter),
+            margin + row as f64 * (height + gutter),
+            width,
+            height,
+        )
+    }
+
     /// Get scaled size
     pub fn scaled_size(&self) -> (f64, f64) {
         self.scaled_size
@@ -107,47 +147,154 @@ impl LayoutTemplate {
     }
 }
 
+/// Layout produced by applying a [`PresetLayout`]
+pub enum LayoutResult {
+    NUp(NUpLayout),
+    Booklet(BookletLayout),
+}
+
 /// Preset layout templates
+#[derive(Debug, Clone)]
 pub struct PresetLayout {
-    pub name: &'static str,
+    /// Built-in presets have static names, saved ones owned names
+    pub name: Cow<'static, str>,
     pub pages_per_sheet: u8,
     pub output_size: PageSize,
     pub description: &'static str,
+    /// Binding for presets that fold the sheets into a booklet
+    pub booklet: Option<BookletType>,
+    /// Margins around and between the cells of N-up sheets
+    pub margins: MarginConfig,
 }
 
 impl PresetLayout {
     /// Get standard preset layouts
     pub fn presets() -> Vec<Self> {
-        vec![
-            PresetLayout {
-                name: "Single Page",
-                pages_per_sheet: 1,
-                output_size: PageSize::A4,
-                description: "One page per sheet",
-            },
-            PresetLayout {
-                name: "2-Up",
-                pages_per_sheet: 2,
-                output_size: PageSize::A4,
-                description: "Two pages per sheet",
-            },
-            PresetLayout {
-                name: "4-Up",
-                pages_per_sheet: 4,
-                output_size: PageSize::A4,
-                description: "Four pages per sheet",
-            },
-            PresetLayout {
-                name: "8-Up",
-                pages_per_sheet: 8,
-                output_size: PageSize::A4,
-                description: "Eight pages per sheet",
-            },
-        ]
+        BuiltInLayout::ALL.into_iter().map(Self::from).collect()
+    }
+
+    /// Get the standard presets followed by the user's saved layouts
+    pub fn with_custom(layouts: &[CustomLayout]) -> Vec<Self> {
+        let mut presets = Self::presets();
+        presets.extend(layouts.iter().map(Self::from));
+        presets
     }
 
     /// Find preset by name
     pub fn find(name: &str) -> Option<Self> {
         Self::presets().into_iter().find(|p| p.name == name)
     }
+
+    /// Get the N-up configuration for this preset
+    pub fn to_config(&self) -> NUpConfig {
+        NUpConfig {
+            pages_per_sheet: self.pages_per_sheet,
+            margins: self.margins,
+            ..NUpConfig::default()
+        }
+    }
+
+    /// Get a booklet calculator for `page_count` pages
+    ///
+    /// Fails for presets that are not booklets.
+    pub fn to_booklet(&self, page_count: usize) -> Result<BookletCalculator> {
+        let booklet_type = self
+            .booklet
+            .ok_or_else(|| Error::Validation(format!("{} is not a booklet layout", self.name)))?;
+        BookletCalculator::new(booklet_type, page_count)
+    }
+
+    /// Lay out `pages` (page numbers) with this preset
+    pub fn apply(&self, pages: &[usize]) -> Result<LayoutResult> {
+        if self.booklet.is_some() {
+            let layout = self.to_booklet(pages.len())?.calculate(self.output_size)?;
+            return Ok(LayoutResult::Booklet(layout));
+        }
+
+        let layout = NUpCalculator::new(self.pages_per_sheet)?
+            .with_config(self.to_config())?
+            .calculate(pages, self.output_size)?;
+        Ok(LayoutResult::NUp(layout))
+    }
+}
+
+impl From<BuiltInLayout> for PresetLayout {
+    fn from(layout: BuiltInLayout) -> Self {
+        PresetLayout {
+            name: Cow::Borrowed(layout.name()),
+            pages_per_sheet: layout.pages_per_sheet(),
+            output_size: PageSize::A4,
+            description: layout.description(),
+            booklet: (layout == BuiltInLayout::Booklet).then_some(BookletType::Standard),
+            margins: MarginConfig::default(),
+        }
+    }
+}
+
+impl From<&CustomLayout> for PresetLayout {
+    fn from(layout: &CustomLayout) -> Self {
+        PresetLayout {
+            name: Cow::Owned(layout.name.clone()),
+            pages_per_sheet: layout.pages_per_sheet,
+            output_size: layout.output_size,
+            description: "Saved layout",
+            booklet: layout.booklet.then_some(BookletType::Standard),
+            margins: MarginConfig {
+                margin: layout.margin,
+                gutter: layout.gutter,
+                ..MarginConfig::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn four_up_preset_configures_four_pages_per_sheet() {
+        let preset = PresetLayout::find("4-Up").unwrap();
+        assert_eq!(preset.to_config().pages_per_sheet, 4);
+
+        match preset.apply(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap() {
+            LayoutResult::NUp(layout) => {
+                assert_eq!(layout.pages_per_sheet, 4);
+                assert_eq!(layout.pages.len(), 2);
+            }
+            LayoutResult::Booklet(_) => panic!("4-Up is not a booklet"),
+        }
+        assert!(preset.to_booklet(8).is_err());
+    }
+
+    #[test]
+    fn booklet_preset_lays_out_a_booklet() {
+        let preset = PresetLayout::find("Booklet").unwrap();
+
+        match preset.apply(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap() {
+            LayoutResult::Booklet(layout) => assert_eq!(layout.booklet_type, BookletType::Standard),
+            LayoutResult::NUp(_) => panic!("expected a booklet"),
+        }
+    }
+
+    #[test]
+    fn saved_layouts_follow_the_built_in_presets() {
+        let handout = CustomLayout {
+            name: "Handout".to_string(),
+            pages_per_sheet: 2,
+            booklet: false,
+            output_size: PageSize::A5,
+            margin: 18.0,
+            gutter: 6.0,
+        };
+
+        let presets = PresetLayout::with_custom(&[handout]);
+
+        assert_eq!(presets.len(), BuiltInLayout::ALL.len() + 1);
+        assert_eq!(presets[0].name, BuiltInLayout::SinglePage.name());
+        let handout = presets.last().unwrap();
+        assert_eq!(handout.name, "Handout");
+        assert_eq!(handout.to_config().margins.gutter, 6.0);
+        assert!(handout.booklet.is_none());
+    }
 }
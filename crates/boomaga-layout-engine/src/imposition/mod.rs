@@ -1,3 +1,4 @@
 //! Page layout templates for N-up and booklet layouts
 
 pub mod layout_template;
+pub mod sheet;
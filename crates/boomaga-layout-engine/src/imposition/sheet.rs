@@ -0,0 +1,184 @@
+//! Imposed sheet geometry, for drawing what a layout will print
+
+use crate::booklet::{BookletLayout, BLANK_PAGE};
+use crate::imposition::layout_template::LayoutResult;
+use crate::n_up::{MarginConfig, NUpLayout};
+use crate::transforms::TransformOperation;
+
+/// One printed side of an output sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetSide {
+    /// Sheet width (points)
+    pub width: f64,
+    /// Sheet height (points)
+    pub height: f64,
+    /// Pages drawn on this side, in layout order
+    pub cells: Vec<SheetCell>,
+}
+
+/// A page drawn into a rectangle of a sheet
+///
+/// Coordinates are in points from the top-left corner of the sheet. The
+/// page is scaled to fit the rectangle and centered in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetCell {
+    /// Input page, numbered as it was passed to the layout
+    pub page: usize,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Transform applied to the page inside its rectangle
+    pub transform: Option<TransformOperation>,
+}
+
+impl NUpLayout {
+    /// Get the sides of the output sheets, one per output page
+    ///
+    /// Cells fill the template grid row by row inside `margins`. Sides
+    /// turned 180°, such as short-edge back sides, have their cells turned
+    /// with them.
+    pub fn sheet_sides(&self, margins: &MarginConfig) -> Vec<SheetSide> {
+        let (sheet_width, sheet_height) = (self.output_size.width_points(), self.output_size.height_points());
+        self.pages
+            .iter()
+            .map(|page| SheetSide {
+                width: sheet_width,
+                height: sheet_height,
+                cells: page
+                    .input_pages
+                    .iter()
+                    .zip(&page.input_transforms)
+                    .enumerate()
+                    .map(|(index, (&number, &transform))| {
+                        let (x, y, width, height) = self.template.cell_rect(index, margins.margin, margins.gutter);
+                        let cell = SheetCell { page: number, x, y, width, height, transform };
+                        if page.transform == Some(TransformOperation::Rotate180) {
+                            cell.turned(sheet_width, sheet_height)
+                        } else {
+                            cell
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl SheetCell {
+    /// Get this cell on a sheet of the given size turned 180°
+    fn turned(self, sheet_width: f64, sheet_height: f64) -> Self {
+        let transform = match self.transform {
+            None | Some(TransformOperation::Identity) => Some(TransformOperation::Rotate180),
+            Some(TransformOperation::Rotate90) => Some(TransformOperation::Rotate270),
+            Some(TransformOperation::Rotate180) => None,
+            Some(TransformOperation::Rotate270) => Some(TransformOperation::Rotate90),
+            Some(TransformOperation::FlipHorizontal) => Some(TransformOperation::FlipVertical),
+            Some(TransformOperation::FlipVertical) => Some(TransformOperation::FlipHorizontal),
+            other => other,
+        };
+        Self {
+            x: sheet_width - self.x - self.width,
+            y: sheet_height - self.y - self.height,
+            transform,
+            ..self
+        }
+    }
+}
+
+impl BookletLayout {
+    /// Get the front and back of each sheet, in printing order
+    ///
+    /// Sheets lie in landscape with two pages side by side. Blank slots
+    /// have no cell.
+    pub fn sheet_sides(&self) -> Vec<SheetSide> {
+        let (short, long) = {
+            let (width, height) = (self.output_size.width_points(), self.output_size.height_points());
+            (width.min(height), width.max(height))
+        };
+
+        self.pages
+            .iter()
+            .flat_map(|page| page.input_pages.chunks(2))
+            .map(|slots| SheetSide {
+                width: long,
+                height: short,
+                cells: slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &number)| number != BLANK_PAGE)
+                    .map(|(half, &number)| SheetCell {
+                        page: number,
+                        x: half as f64 * long / 2.0,
+                        y: 0.0,
+                        width: long / 2.0,
+                        height: short,
+                        transform: None,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl LayoutResult {
+    /// Get the sides of the output sheets, see [`NUpLayout::sheet_sides`]
+    /// and [`BookletLayout::sheet_sides`]
+    pub fn sheet_sides(&self, margins: &MarginConfig) -> Vec<SheetSide> {
+        match self {
+            LayoutResult::NUp(layout) => layout.sheet_sides(margins),
+            LayoutResult::Booklet(layout) => layout.sheet_sides(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::booklet::{BookletCalculator, BookletType};
+    use crate::n_up::NUpCalculator;
+    use boomaga_core::PageSize;
+
+    #[test]
+    fn four_up_sheet_has_a_cell_per_quadrant() {
+        let layout = NUpCalculator::new(4).unwrap().calculate(&[1, 2, 3, 4, 5], PageSize::A4).unwrap();
+        let sides = layout.sheet_sides(&MarginConfig::default());
+
+        assert_eq!(sides.len(), 2);
+        let (width, height) = (PageSize::A4.width_points(), PageSize::A4.height_points());
+        let corners: Vec<_> = sides[0].cells.iter().map(|cell| (cell.page, cell.x, cell.y)).collect();
+        assert_eq!(
+            corners,
+            vec![(1, 0.0, 0.0), (2, width / 2.0, 0.0), (3, 0.0, height / 2.0), (4, width / 2.0, height / 2.0)]
+        );
+        assert_eq!(sides[1].cells.len(), 1);
+    }
+
+    #[test]
+    fn short_edge_back_sides_turn_their_cells() {
+        let mut layout = NUpCalculator::new(2).unwrap().calculate(&[1, 2, 3, 4], PageSize::A4).unwrap();
+        crate::duplex::apply_duplex_flips(&mut layout.pages, boomaga_core::DuplexMode::ShortEdge);
+        let sides = layout.sheet_sides(&MarginConfig::default());
+
+        let front: Vec<_> = sides[0].cells.iter().map(|cell| (cell.page, cell.y, cell.transform)).collect();
+        let back: Vec<_> = sides[1].cells.iter().map(|cell| (cell.page, cell.y, cell.transform)).collect();
+        let half = PageSize::A4.height_points() / 2.0;
+        assert_eq!(front, vec![(1, 0.0, None), (2, half, None)]);
+        let turned = Some(TransformOperation::Rotate180);
+        assert_eq!(back, vec![(3, half, turned), (4, 0.0, turned)]);
+    }
+
+    #[test]
+    fn booklet_sheets_have_two_sides_without_blank_cells() {
+        let layout = BookletCalculator::new(BookletType::Standard, 6).unwrap().calculate(PageSize::A4).unwrap();
+        let sides = layout.sheet_sides();
+
+        assert_eq!(sides.len(), 4);
+        assert!(sides[0].width > sides[0].height);
+        let pages: Vec<Vec<usize>> = sides
+            .iter()
+            .map(|side| side.cells.iter().map(|cell| cell.page).collect())
+            .collect();
+        assert_eq!(pages, vec![vec![1], vec![2], vec![6, 3], vec![4, 5]]);
+    }
+}
@@ -6,9 +6,18 @@
 pub mod n_up;
 pub mod booklet;
 pub mod transforms;
+pub mod duplex;
+pub mod fold;
+pub mod image_placement;
+pub mod passthrough;
 pub mod imposition;
 
 pub use n_up::*;
 pub use booklet::*;
 pub use transforms::*;
-pub use imposition::layout_template::LayoutTemplate;
+pub use duplex::{apply_duplex_flips, manual_duplex_passes, pad_for_duplex, DuplexPadding, ManualDuplexPasses};
+pub use fold::{apply_folds, fold_line_element, FoldDirection, FoldLine, FoldType};
+pub use image_placement::{image_page, place_image, place_image_element, ImagePlacement};
+pub use passthrough::{is_passthrough, passthrough_pages};
+pub use imposition::layout_template::{LayoutResult, LayoutTemplate, PresetLayout};
+pub use imposition::sheet::{SheetCell, SheetSide};
@@ -1,9 +1,12 @@
 //! N-up page layout algorithms
 
+use crate::fold::FoldLine;
 use crate::imposition::layout_template::LayoutTemplate;
-use boomaga_core::{Error, PageSize, Result};
+use crate::transforms::TransformOperation;
+use boomaga_core::{Color, Error, GraphicsElement, Page, PageSize, Result};
 use std::sync::Arc;
-use tracing::{debug, info};
+use std::time::Instant;
+use tracing::{debug, info, instrument, warn};
 
 /// N-up layout result
 pub struct NUpLayout {
@@ -15,6 +18,29 @@ pub struct NUpLayout {
     pub pages_per_sheet: u8,
     /// The layout template
     pub template: LayoutTemplate,
+    /// Fold lines across each sheet, see [`crate::fold::apply_folds`]
+    pub folds: Vec<FoldLine>,
+}
+
+impl NUpLayout {
+    /// Describe which input page lands where on each output sheet
+    ///
+    /// For example `Sheet 1: [1@TopLeft, 2@TopRight, 3@BottomLeft, 4@BottomRight]`.
+    pub fn explain(&self) -> Vec<String> {
+        let positions = self.template.generate_positions();
+        self.pages
+            .iter()
+            .map(|page| {
+                let placements: Vec<String> = page
+                    .input_pages
+                    .iter()
+                    .zip(&positions)
+                    .map(|(number, position)| format!("{}@{:?}", number, position))
+                    .collect();
+                format!("Sheet {}: [{}]", page.output_page, placements.join(", "))
+            })
+            .collect()
+    }
 }
 
 /// A single page in the layout
@@ -27,7 +53,13 @@ pub struct PageResult {
     /// Page position on the output sheet
     pub position: PagePosition,
     /// Page content (in production, would be rendered image)
-    pub content: Option<Arc<Vec<u8>>>,
+    pub content: Option<Arc<[u8]>>,
+    /// Transform applied to the whole output page (e.g. duplex back sides)
+    pub transform: Option<TransformOperation>,
+    /// Transform applied to each input page in its cell, parallel to `input_pages`
+    pub input_transforms: Vec<Option<TransformOperation>>,
+    /// Extra marks drawn on the output sheet, such as cell borders
+    pub decorations: Vec<GraphicsElement>,
 }
 
 /// Page position on the output sheet
@@ -65,6 +97,10 @@ pub struct NUpCalculator {
     scale_mode: ScaleMode,
     /// Rotation mode
     rotation_mode: RotationMode,
+    /// Outline color for each page cell
+    cell_borders: Option<Color>,
+    /// Outline width (points)
+    cell_border_width: f64,
 }
 
 /// Margin configuration
@@ -120,6 +156,8 @@ impl Default for NUpCalculator {
             margins: MarginConfig::default(),
             scale_mode: ScaleMode::Fit,
             rotation_mode: RotationMode::None,
+            cell_borders: None,
+            cell_border_width: DEFAULT_CELL_BORDER_WIDTH,
         }
     }
 }
@@ -146,11 +184,17 @@ impl NUpCalculator {
         self.margins = config.margins;
         self.scale_mode = config.scale_mode;
         self.rotation_mode = config.rotation_mode;
+        self.cell_borders = config.cell_borders;
+        self.cell_border_width = config.cell_border_width;
         Ok(self)
     }
 
     /// Calculate N-up layout
+    ///
+    /// Runs in a debug `impose` span and logs the time it took.
+    #[instrument(name = "impose", level = "debug", skip_all, fields(layout = "n-up", pages = input_pages.len()))]
     pub fn calculate(&self, input_pages: &[usize], output_size: PageSize) -> Result<NUpLayout> {
+        let started = (!tracing::Span::current().is_disabled()).then(Instant::now);
         info!(
             "Calculating {}-up layout for {} pages",
             self.pages_per_sheet,
@@ -173,15 +217,52 @@ impl NUpCalculator {
 
         // Generate layout
         let pages = self.generate_layout(input_pages, &template)?;
+        if let Some(started) = started {
+            debug!(sheets = pages.len(), elapsed = ?started.elapsed(), "Imposed");
+        }
 
         Ok(NUpLayout {
             pages,
             output_size,
             pages_per_sheet: self.pages_per_sheet,
             template,
+            folds: Vec::new(),
         })
     }
 
+    /// Calculate N-up layout for pages with known dimensions
+    ///
+    /// With [`RotationMode::Rotate`], pages whose detected orientation differs
+    /// from their cell are turned 90° to fill it.
+    pub fn calculate_for_pages(&self, pages: &[Page], output_size: PageSize) -> Result<NUpLayout> {
+        let numbers: Vec<usize> = pages.iter().map(|page| page.number).collect();
+        let mut layout = self.calculate(&numbers, output_size)?;
+
+        for page in pages.iter().filter(|page| page.has_orientation_mismatch()) {
+            warn!(
+                "Page {} is marked {:?} but measures {}x{}",
+                page.number, page.orientation, page.width, page.height
+            );
+        }
+
+        if self.rotation_mode == RotationMode::Rotate {
+            let (cell_width, cell_height) = layout.template.cell_size();
+            let cell_is_landscape = cell_width > cell_height;
+            for result in &mut layout.pages {
+                for (number, transform) in result.input_pages.iter().zip(&mut result.input_transforms) {
+                    let Some(page) = pages.iter().find(|page| page.number == *number) else {
+                        continue;
+                    };
+                    if page.detected_orientation().is_landscape() != cell_is_landscape {
+                        *transform = Some(TransformOperation::Rotate90);
+                    }
+                }
+            }
+        }
+
+        Ok(layout)
+    }
+
     /// Find minimum page size among input pages
     fn find_min_page_size(&self, _page_indices: &[usize]) -> PageSize {
         // TODO: Implement actual page size lookup
@@ -264,6 +345,9 @@ impl NUpCalculator {
                 input_pages: input_pages.clone(),
                 position,
                 content: None,
+                transform: None,
+                input_transforms: vec![None; input_pages.len()],
+                decorations: self.cell_border_elements(template, input_pages.len()),
             });
         }
 
@@ -275,8 +359,34 @@ impl NUpCalculator {
 
         Ok(pages)
     }
+
+    /// Stroke-only outlines around the first `cells` cells of a sheet
+    fn cell_border_elements(&self, template: &LayoutTemplate, cells: usize) -> Vec<GraphicsElement> {
+        let Some(color) = &self.cell_borders else {
+            return Vec::new();
+        };
+
+        (0..cells)
+            .map(|index| {
+                let (x, y, width, height) =
+                    template.cell_rect(index, self.margins.margin, self.margins.gutter);
+                GraphicsElement::Rectangle {
+                    x,
+                    y,
+                    width,
+                    height,
+                    fill: None,
+                    stroke: Some(color.clone()),
+                    stroke_width: self.cell_border_width,
+                }
+            })
+            .collect()
+    }
 }
 
+/// Default cell border width (points)
+pub const DEFAULT_CELL_BORDER_WIDTH: f64 = 0.5;
+
 /// N-up configuration
 #[derive(Debug, Clone)]
 pub struct NUpConfig {
@@ -284,6 +394,10 @@ pub struct NUpConfig {
     pub margins: MarginConfig,
     pub scale_mode: ScaleMode,
     pub rotation_mode: RotationMode,
+    /// Outline each page cell in this color; unrelated to crop marks
+    pub cell_borders: Option<Color>,
+    /// Outline width (points)
+    pub cell_border_width: f64,
 }
 
 impl Default for NUpConfig {
@@ -293,6 +407,8 @@ impl Default for NUpConfig {
             margins: MarginConfig::default(),
             scale_mode: ScaleMode::Fit,
             rotation_mode: RotationMode::None,
+            cell_borders: None,
+            cell_border_width: DEFAULT_CELL_BORDER_WIDTH,
         }
     }
 }
@@ -308,6 +424,9 @@ impl NUpConfig {
         if self.pages_per_sheet > 8 {
             return Err(Error::Validation("Maximum pages per sheet is 8".into()));
         }
+        if self.cell_borders.is_some() && self.cell_border_width <= 0.0 {
+            return Err(Error::Validation("Cell border width must be greater than 0".into()));
+        }
         Ok(())
     }
 }
@@ -315,6 +434,7 @@ impl NUpConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use boomaga_core::Orientation;
 
     #[test]
     fn test_n_up_calculation() {
@@ -343,6 +463,102 @@ mod tests {
         assert_eq!(result.pages[2].input_pages, vec![5]);
     }
 
+    #[test]
+    fn landscape_page_is_rotated_into_portrait_cell() {
+        let calculator = NUpCalculator::new(1)
+            .unwrap()
+            .with_config(NUpConfig {
+                rotation_mode: RotationMode::Rotate,
+                ..Default::default()
+            })
+            .unwrap();
+        let pages = vec![
+            Page::new(1, 842.0, 595.0, Orientation::Landscape),
+            Page::new(2, 595.0, 842.0, Orientation::Portrait),
+        ];
+
+        let result = calculator.calculate_for_pages(&pages, PageSize::A4).unwrap();
+
+        assert_eq!(pages[0].detected_orientation(), Orientation::Landscape);
+        assert_eq!(result.pages[0].input_transforms, vec![Some(TransformOperation::Rotate90)]);
+        assert_eq!(result.pages[1].input_transforms, vec![None]);
+    }
+
+    #[test]
+    fn pages_are_not_rotated_without_rotate_mode() {
+        let calculator = NUpCalculator::new(1).unwrap();
+        let pages = vec![Page::new(1, 842.0, 595.0, Orientation::Landscape)];
+
+        let result = calculator.calculate_for_pages(&pages, PageSize::A4).unwrap();
+
+        assert_eq!(result.pages[0].input_transforms, vec![None]);
+    }
+
+    #[test]
+    fn bordered_four_up_sheet_outlines_each_cell() {
+        let calculator = NUpCalculator::new(4)
+            .unwrap()
+            .with_config(NUpConfig {
+                pages_per_sheet: 4,
+                margins: MarginConfig { gutter: 10.0, ..Default::default() },
+                cell_borders: Some(Color::black()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let result = calculator.calculate(&[1, 2, 3, 4], PageSize::A4).unwrap();
+
+        let cells: Vec<_> = result.pages[0]
+            .decorations
+            .iter()
+            .map(|element| match element {
+                GraphicsElement::Rectangle { x, y, width, fill: None, stroke: Some(_), .. } => (*x, *y, *width),
+                other => panic!("unexpected decoration: {other:?}"),
+            })
+            .collect();
+        assert_eq!(cells.len(), 4);
+        // The second column starts one cell width plus the gutter across
+        assert_eq!(cells[1].0, cells[0].2 + 10.0);
+        assert_eq!(cells[2].0, 0.0);
+    }
+
+    #[test]
+    fn sheets_have_no_borders_by_default() {
+        let calculator = NUpCalculator::new(4).unwrap();
+
+        let result = calculator.calculate(&[1, 2, 3, 4], PageSize::A4).unwrap();
+
+        assert!(result.pages[0].decorations.is_empty());
+    }
+
+    #[test]
+    fn four_up_layout_explains_cell_positions() {
+        let calculator = NUpCalculator::new(4).unwrap();
+
+        let result = calculator.calculate(&[1, 2, 3, 4, 5], PageSize::A4).unwrap();
+
+        assert_eq!(
+            result.explain(),
+            vec![
+                "Sheet 1: [1@TopLeft, 2@TopRight, 3@BottomLeft, 4@BottomRight]",
+                "Sheet 2: [5@TopLeft]",
+            ]
+        );
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn calculation_is_timed_in_an_impose_span() {
+        NUpCalculator::new(4).unwrap().calculate(&[1, 2, 3, 4, 5], PageSize::A4).unwrap();
+
+        logs_assert(|lines: &[&str]| {
+            match lines.iter().find(|line| line.contains("impose{") && line.contains("Imposed")) {
+                Some(line) if line.contains("pages=5") && line.contains("sheets=2 elapsed=") => Ok(()),
+                other => Err(format!("Expected a timed impose span over 5 pages, got {:?}", other)),
+            }
+        });
+    }
+
     #[test]
     fn test_config_validation() {
         let config = NUpConfig {
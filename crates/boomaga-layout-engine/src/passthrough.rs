@@ -0,0 +1,131 @@
+//! Direct PDF passthrough for jobs that need no layout work
+
+use boomaga_core::{Document, DuplexMode, Orientation, Page, PageContents, PagesPerSheet, PrintOptions, Result};
+use tracing::debug;
+
+/// Check whether pages can be copied to the output unchanged
+///
+/// True for 1-up, unscaled, unrotated jobs whose pages are all raw PDF.
+/// Short-edge duplex is excluded because it turns every back side.
+pub fn is_passthrough(options: &PrintOptions, pages: &[Page]) -> bool {
+    options.pages_per_sheet == PagesPerSheet::One
+        && options.scale == 1.0
+        && options.orientation == Orientation::Portrait
+        && options.duplex != DuplexMode::ShortEdge
+        && pages
+            .iter()
+            .all(|page| matches!(page.contents, PageContents::Pdf { .. }))
+}
+
+/// Copy the original page streams into the output, skipping rasterization
+///
+/// Honors the page range and copies (collated copies repeat the whole range,
+/// uncollated ones repeat each page). Streams are shared, not copied, and
+/// output pages are renumbered from 1. Returns `None` when the job needs
+/// imposition or transforms.
+pub fn passthrough_pages(document: &Document, options: &PrintOptions) -> Result<Option<Vec<Page>>> {
+    let selected: Vec<&Page> = match options.page_range {
+        Some((start, end)) => document.pages_in_range(start, end)?.collect(),
+        None => document.pages.iter().collect(),
+    };
+
+    if !is_passthrough(options, &document.pages) {
+        return Ok(None);
+    }
+
+    let copies = options.copies as usize;
+    let mut pages: Vec<Page> = if options.collate {
+        (0..copies)
+            .flat_map(|_| selected.iter().map(|page| (*page).clone()))
+            .collect()
+    } else {
+        selected
+            .iter()
+            .flat_map(|page| std::iter::repeat_n((*page).clone(), copies))
+            .collect()
+    };
+
+    for (index, page) in pages.iter_mut().enumerate() {
+        page.number = index + 1;
+    }
+
+    debug!(
+        "Passing {} of {} pages through unchanged as {} output pages",
+        selected.len(),
+        document.page_count(),
+        pages.len()
+    );
+
+    Ok(Some(pages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boomaga_core::FileType;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn pdf_document(count: usize) -> Document {
+        let mut document = Document::new("doc".into(), PathBuf::from("doc.pdf"), FileType::Pdf);
+        for number in 1..=count {
+            let mut page = Page::new(number, 595.0, 842.0, Orientation::Portrait);
+            page.contents = PageContents::Pdf {
+                stream: Arc::from(format!("q page {number} Q").into_bytes()),
+            };
+            document.add_page(page);
+        }
+        document
+    }
+
+    fn stream(page: &Page) -> &[u8] {
+        match &page.contents {
+            PageContents::Pdf { stream } => stream,
+            other => panic!("expected a PDF stream, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn default_job_passes_page_streams_through() {
+        let document = pdf_document(3);
+
+        let pages = passthrough_pages(&document, &PrintOptions::default()).unwrap().unwrap();
+
+        assert_eq!(pages.len(), 3);
+        for (output, input) in pages.iter().zip(&document.pages) {
+            assert_eq!(stream(output), stream(input));
+        }
+    }
+
+    #[test]
+    fn passthrough_honors_page_range_and_copies() {
+        let document = pdf_document(4);
+        let options = PrintOptions {
+            copies: 2,
+            page_range: Some((2, 3)),
+            ..PrintOptions::default()
+        };
+
+        let uncollated = passthrough_pages(&document, &options).unwrap().unwrap();
+        let collated = passthrough_pages(&document, &PrintOptions { collate: true, ..options.clone() })
+            .unwrap()
+            .unwrap();
+
+        let streams = |pages: &[Page]| pages.iter().map(|page| stream(page).to_vec()).collect::<Vec<_>>();
+        let (two, three) = (stream(&document.pages[1]).to_vec(), stream(&document.pages[2]).to_vec());
+        assert_eq!(streams(&uncollated), vec![two.clone(), two.clone(), three.clone(), three.clone()]);
+        assert_eq!(streams(&collated), vec![two.clone(), three.clone(), two, three]);
+        assert_eq!(collated.last().unwrap().number, 4);
+    }
+
+    #[test]
+    fn imposed_jobs_are_not_passed_through() {
+        let document = pdf_document(2);
+        let options = PrintOptions {
+            pages_per_sheet: PagesPerSheet::Two,
+            ..PrintOptions::default()
+        };
+
+        assert!(passthrough_pages(&document, &options).unwrap().is_none());
+    }
+}
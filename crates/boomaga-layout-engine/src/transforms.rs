@@ -6,6 +6,8 @@ use tracing::debug;
 /// Page transformation operation
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TransformOperation {
+    /// Leave the page as it is
+    Identity,
     /// Rotate 90 degrees clockwise
     Rotate90,
     /// Rotate 180 degrees
@@ -70,12 +72,7 @@ impl PageTransformer {
         orientation: Orientation,
         _rotation_degrees: f64,
     ) -> TransformedPage {
-        let (width, height) = if orientation.is_landscape() {
-            // Swap dimensions for landscape
-            (page_size.height_points(), page_size.width_points())
-        } else {
-            (page_size.width_points(), page_size.height_points())
-        };
+        let (width, height) = page_size.dimensions(orientation);
 
         // Apply rotation
         let (transformed_width, transformed_height) = if _rotation_degrees % 180.0 == 90.0 {
@@ -113,10 +110,18 @@ impl PageTransformer {
 
     /// Apply page rotation
     pub fn rotate(&self, degrees: f64) -> TransformedPage {
+        let transform = match degrees.rem_euclid(360.0) {
+            0.0 => TransformOperation::Identity,
+            90.0 => TransformOperation::Rotate90,
+            180.0 => TransformOperation::Rotate180,
+            270.0 => TransformOperation::Rotate270,
+            d => TransformOperation::Custom { x: 0.0, y: 0.0, rotation: d, scale: self.default_scale },
+        };
+
         // In production, this would be called on existing pages
         TransformedPage {
             original_page: 0,
-            transform: TransformOperation::Rotate90,
+            transform,
             transformed_size: (0.0, 0.0),
             position: (0.0, 0.0),
         }
@@ -239,4 +244,13 @@ mod tests {
         assert_eq!(result.transformed_size.0, 595.0);
         assert_eq!(result.transformed_size.1, 842.0);
     }
+
+    #[test]
+    fn whole_turns_rotate_to_the_identity() {
+        let transformer = PageTransformer::new();
+
+        assert_eq!(transformer.rotate(0.0).transform, TransformOperation::Identity);
+        assert_eq!(transformer.rotate(360.0).transform, TransformOperation::Identity);
+        assert_eq!(transformer.rotate(-180.0).transform, TransformOperation::Rotate180);
+    }
 }
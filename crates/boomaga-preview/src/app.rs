@@ -4,12 +4,22 @@
 //! `app_logic` (see `main.rs`) and delivers renderer events through the worker
 //! channel stored here. Matches the `AppData` in `docs/uml/C2-class.puml`.
 
-use boomaga_core::{Document, JobId, JobStatus, PageSize, PagesPerSheet, PrintOptions};
+use boomaga_config::{parse_shortcut, PreviewConfig, Profile, Shortcut, UISettings, ZoomMode};
+use boomaga_core::constants::{MAX_ZOOM, MIN_ZOOM};
+use boomaga_core::{
+    Document, FileType, JobId, JobStatus, Orientation, Page, PageSize, PagesPerSheet, PrintOptions,
+};
 use boomaga_ipc::MessagePayload;
-use boomaga_layout_engine::NUpCalculator;
+use boomaga_layout_engine::n_up::{MarginConfig, NUpCalculator, NUpConfig, NUpLayout, RotationMode};
+use boomaga_layout_engine::{apply_duplex_flips, PresetLayout, SheetSide};
 use std::collections::{BTreeSet, HashMap};
 use std::path::PathBuf;
+use std::time::Duration;
+use tracing::debug;
 
+#[cfg(target_os = "linux")]
+use crate::document_renderer::shared_page_image;
+use crate::document_renderer::{streamed_page_image, RenderError};
 use crate::ipc_worker::{IpcCommand, IpcEvent, IpcSender};
 use crate::pdf_canvas::CanvasImage;
 use crate::render_worker::{RendererCommand, RendererEvent, RendererSender};
@@ -38,6 +48,34 @@ pub enum IpcState {
     Disconnected,
 }
 
+/// Preview actions that can be bound to a keyboard shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutAction {
+    NextPage,
+    PreviousPage,
+    FirstPage,
+    LastPage,
+    ZoomIn,
+    ZoomOut,
+    FitPage,
+}
+
+impl ShortcutAction {
+    /// Look up the action for a keybinding name from the preview config.
+    fn from_binding(name: &str) -> Option<Self> {
+        Some(match name {
+            "next_page" => Self::NextPage,
+            "prev_page" => Self::PreviousPage,
+            "first_page" => Self::FirstPage,
+            "last_page" => Self::LastPage,
+            "zoom_in" => Self::ZoomIn,
+            "zoom_out" => Self::ZoomOut,
+            "fit_page" => Self::FitPage,
+            _ => return None,
+        })
+    }
+}
+
 /// Preview application state.
 pub struct AppData {
     /// Path of the document being previewed, if any.
@@ -54,13 +92,20 @@ pub struct AppData {
     pub error_message: Option<String>,
     /// Whether the native file chooser is currently open.
     pub choosing_file: bool,
-    /// Zoom factor (1.0 == 100%).
+    /// Zoom factor (1.0 == 100%), used when `zoom_mode` is custom.
     pub zoom: f64,
+    /// Active zoom mode; fit modes follow the canvas size.
+    pub zoom_mode: ZoomMode,
     renderer_sender: Option<RendererSender>,
     pending_document_path: Option<PathBuf>,
     render_generation: u64,
     rendering_pages: BTreeSet<usize>,
     imposition_revision: u64,
+    /// Whether the canvas shows the composited output sheet instead of the
+    /// source pages placed on it.
+    pub imposed_preview: bool,
+    rendered_sheets: HashMap<usize, CanvasImage>,
+    rendering_sheets: BTreeSet<usize>,
     /// Page fill order for multi-page imposed sheets.
     pub fill_order: FillOrder,
     /// Imposition / print options.
@@ -73,6 +118,23 @@ pub struct AppData {
     pub ipc_state: IpcState,
     /// Most recent IPC connection error.
     pub ipc_error: Option<String>,
+    /// Round trip of the last backend heartbeat, while connected.
+    pub ipc_latency: Option<Duration>,
+    /// How old a backend notification may be before it is dropped.
+    pub ipc_message_ttl: Duration,
+    /// Whether the status bar is shown.
+    pub show_status_bar: bool,
+    /// Whether the bookmarks panel is shown.
+    pub show_bookmarks: bool,
+    /// Built-in and saved layout presets offered in the toolbar.
+    pub layout_presets: Vec<PresetLayout>,
+    /// Saved print profiles offered in the toolbar.
+    pub profiles: Vec<Profile>,
+    /// Name of the profile whose settings are in use.
+    pub active_profile: Option<String>,
+    /// Configured shortcuts bound to more than one action, as warnings.
+    pub shortcut_conflicts: Vec<String>,
+    shortcuts: HashMap<Shortcut, ShortcutAction>,
 }
 
 impl Default for AppData {
@@ -83,6 +145,7 @@ impl Default for AppData {
             current_page: 0,
             rendered_pages: Vec::new(),
             zoom: 1.0,
+            zoom_mode: ZoomMode::Custom,
             load_state: LoadState::Idle,
             error_message: None,
             choosing_file: false,
@@ -91,27 +154,123 @@ impl Default for AppData {
             job_statuses: HashMap::new(),
             ipc_state: IpcState::Disconnected,
             ipc_error: None,
+            ipc_latency: None,
+            ipc_message_ttl: boomaga_ipc::transport::DEFAULT_MESSAGE_TTL,
+            show_status_bar: true,
+            show_bookmarks: true,
+            layout_presets: PresetLayout::presets(),
+            profiles: Vec::new(),
+            active_profile: None,
+            shortcut_conflicts: Vec::new(),
+            shortcuts: HashMap::new(),
             renderer_sender: None,
             pending_document_path: None,
             render_generation: 0,
             rendering_pages: BTreeSet::new(),
             imposition_revision: 0,
+            imposed_preview: false,
+            rendered_sheets: HashMap::new(),
+            rendering_sheets: BTreeSet::new(),
             fill_order: FillOrder::Horizontal,
         }
     }
 }
 
 impl AppData {
-    /// Create initial state which asynchronously loads a command-line PDF.
-    pub fn with_document_path(path: PathBuf) -> Self {
+    /// Create initial state from the loaded preview configuration.
+    pub fn from_config(config: &PreviewConfig) -> Self {
+        let mut data = Self::default();
+        data.set_zoom(config.default_zoom);
+        data.ipc_message_ttl = Duration::from_secs(config.message_ttl_secs);
+        if config.enable_shortcuts {
+            data.bind_shortcuts(config);
+            data.shortcut_conflicts = config
+                .keybindings
+                .conflicts()
+                .into_iter()
+                .map(|(shortcut, actions)| format!("{shortcut} is bound to {}", actions.join(", ")))
+                .collect();
+        }
+        data
+    }
+
+    /// Bind the navigation, zoom and view shortcuts; the first binding of a
+    /// shortcut wins. Print shortcuts are not handled by the preview state.
+    fn bind_shortcuts(&mut self, config: &PreviewConfig) {
+        let keybindings = &config.keybindings;
+        for group in [&keybindings.navigation, &keybindings.zoom, &keybindings.view] {
+            for (name, text) in group.bindings() {
+                let (Ok(shortcut), Some(action)) = (parse_shortcut(text), ShortcutAction::from_binding(name)) else {
+                    continue;
+                };
+                self.shortcuts.entry(shortcut).or_insert(action);
+            }
+        }
+    }
+
+    /// Run the action bound to a pressed shortcut, returning whether one was bound.
+    pub fn handle_shortcut(&mut self, shortcut: &Shortcut) -> bool {
+        let Some(action) = self.shortcuts.get(shortcut).copied() else {
+            return false;
+        };
+        match action {
+            ShortcutAction::NextPage => self.next_page(),
+            ShortcutAction::PreviousPage => self.previous_page(),
+            ShortcutAction::FirstPage => self.first_page(),
+            ShortcutAction::LastPage => self.last_page(),
+            ShortcutAction::ZoomIn => self.zoom_in(),
+            ShortcutAction::ZoomOut => self.zoom_out(),
+            ShortcutAction::FitPage => self.set_zoom_mode(ZoomMode::Fit),
+        }
+        true
+    }
+
+    /// Create state showing a loaded document with none of its pages rendered yet.
+    #[cfg(test)]
+    pub(crate) fn with_document(document: Document) -> Self {
         Self {
-            pending_document_path: Some(path),
+            rendered_pages: vec![None; document.page_count()],
+            document: Some(document),
+            load_state: LoadState::Ready,
             ..Self::default()
         }
     }
 
+    /// Offer the built-in presets and the user's saved layouts.
+    pub fn with_layout_presets(mut self, presets: Vec<PresetLayout>) -> Self {
+        self.layout_presets = presets;
+        self
+    }
+
+    /// Offer the user's print profiles, starting with the active one applied.
+    ///
+    /// Call after [`Self::with_layout_presets`], so profile layouts are found.
+    pub fn with_profiles(mut self, profiles: Vec<Profile>, active: Option<&str>) -> Self {
+        if let Some(profile) = active.and_then(|name| profiles.iter().find(|profile| profile.name == name)) {
+            self.apply_profile(&profile.clone());
+        }
+        self.profiles = profiles;
+        self
+    }
+
+    /// Apply the user's UI settings.
+    pub fn with_ui_settings(mut self, ui: &UISettings) -> Self {
+        self.show_status_bar = ui.show_status_bar;
+        self.show_bookmarks = ui.show_bookmarks;
+        self
+    }
+
+    /// Asynchronously load a command-line PDF once the renderer is installed.
+    pub fn opening(mut self, path: PathBuf) -> Self {
+        self.pending_document_path = Some(path);
+        self
+    }
+
     /// Rasterized image for the page currently selected, if available.
     pub fn current_canvas_image(&self) -> Option<&CanvasImage> {
+        if self.imposed_preview {
+            return self.rendered_sheets.get(&self.current_page);
+        }
         let source_page = self.current_sheet_pages().into_iter().next()?;
         self.rendered_pages
             .get(source_page)
@@ -119,13 +278,27 @@ impl AppData {
     }
 
     /// Ordered rendered-image slots for the source pages on the current sheet.
+    ///
+    /// In imposed preview the single slot holds the composited sheet.
     pub fn current_canvas_images(&self) -> Vec<Option<CanvasImage>> {
+        if self.imposed_preview {
+            return vec![self.rendered_sheets.get(&self.current_page).cloned()];
+        }
         self.current_sheet_pages()
             .into_iter()
             .map(|page_index| self.rendered_pages.get(page_index).cloned().flatten())
             .collect()
     }
 
+    /// Pages per sheet for the canvas grid; an imposed sheet fills the canvas.
+    pub fn canvas_pages_per_sheet(&self) -> u8 {
+        if self.imposed_preview {
+            1
+        } else {
+            self.print_options.pages_per_sheet as u8
+        }
+    }
+
     /// Number of pages which have been rendered into the on-demand cache.
     pub fn rendered_page_count(&self) -> usize {
         self.rendered_pages
@@ -146,9 +319,10 @@ impl AppData {
     pub fn install_ipc(&mut self, sender: IpcSender) {
         self.ipc_state = IpcState::Connecting;
         if sender
-            .send(IpcCommand::Connect(PathBuf::from(
-                boomaga_core::constants::IPC_SOCKET_PATH,
-            )))
+            .send(IpcCommand::Connect {
+                socket_path: PathBuf::from(boomaga_core::constants::IPC_SOCKET_PATH),
+                message_ttl: self.ipc_message_ttl,
+            })
             .is_err()
         {
             self.ipc_state = IpcState::Disconnected;
@@ -162,21 +336,123 @@ impl AppData {
             IpcEvent::Message(message) => {
                 self.ipc_state = IpcState::Connected;
                 self.ipc_error = None;
-                if let MessagePayload::PrintJobStatus { job_id, status } = message.payload {
-                    let key = job_id.to_string();
-                    if !self.job_statuses.contains_key(&key) {
-                        self.job_history.push(job_id);
+                match message.payload {
+                    MessagePayload::PrintJobStatus { job_id, status } => {
+                        let key = job_id.to_string();
+                        if !self.job_statuses.contains_key(&key) {
+                            self.job_history.push(job_id);
+                        }
+                        self.job_statuses.insert(key, status);
+                    }
+                    MessagePayload::PageRendered {
+                        job_id,
+                        page_number,
+                        width,
+                        height,
+                        format,
+                        image_data,
+                    } => {
+                        let image = streamed_page_image(format, &image_data, width, height);
+                        self.receive_streamed_page(&job_id, page_number, image);
                     }
-                    self.job_statuses.insert(key, status);
+                    #[cfg(target_os = "linux")]
+                    MessagePayload::PageRenderedShm {
+                        job_id,
+                        page_number,
+                        width,
+                        height,
+                        stride,
+                        ..
+                    } => {
+                        let image = match &message.fd {
+                            Some(fd) => shared_page_image(fd, width, height, stride),
+                            None => Err(RenderError::StreamedImage(
+                                "shared page arrived without its segment".to_owned(),
+                            )),
+                        };
+                        self.receive_streamed_page(&job_id, page_number, image);
+                    }
+                    _ => {}
                 }
             }
+            IpcEvent::Heartbeat(latency) => {
+                self.ipc_state = IpcState::Connected;
+                self.ipc_error = None;
+                self.ipc_latency = Some(latency);
+            }
             IpcEvent::Disconnected(error) => {
                 self.ipc_state = IpcState::Disconnected;
                 self.ipc_error = Some(error);
+                self.ipc_latency = None;
             }
         }
     }
 
+    /// Show a page the backend rendered for `job_id`.
+    ///
+    /// Only pages of the latest job are shown; pages of other jobs are dropped.
+    fn receive_streamed_page(
+        &mut self,
+        job_id: &JobId,
+        page_number: usize,
+        image: Result<CanvasImage, RenderError>,
+    ) {
+        if self.job_history.last() != Some(job_id) {
+            debug!(%job_id, page_number, "Dropping page of a job that is not shown");
+            return;
+        }
+        match image {
+            Ok(image) => self.append_streamed_page(job_id, page_number, image),
+            Err(error) => {
+                self.error_message = Some(format!("Page {page_number} of the print job: {error}"));
+            }
+        }
+    }
+
+    /// Add a page streamed by the backend to the document of its job.
+    ///
+    /// The first page of a job replaces whatever document is open. Pages are
+    /// kept in page-number order and sized A4 wide at the image's aspect
+    /// ratio; repeated page numbers are ignored.
+    fn append_streamed_page(&mut self, job_id: &JobId, page_number: usize, image: CanvasImage) {
+        let key = job_id.to_string();
+        let streaming = self.document_path.is_none()
+            && self.document.as_ref().is_some_and(|document| document.id == key);
+        if !streaming {
+            self.render_generation = self.render_generation.wrapping_add(1);
+            self.document_path = None;
+            self.document = Some(Document::new(key, PathBuf::new(), FileType::Pdf));
+            self.current_page = 0;
+            self.rendered_pages.clear();
+            self.rendering_pages.clear();
+            self.error_message = None;
+        }
+        let Some(document) = self.document.as_mut() else {
+            return;
+        };
+        let Err(index) = document
+            .pages
+            .binary_search_by_key(&page_number, |page| page.number)
+        else {
+            return;
+        };
+
+        let (pixel_width, pixel_height) = image.dimensions();
+        let width = PageSize::A4.width_points();
+        let height = width * f64::from(pixel_height) / f64::from(pixel_width.max(1));
+        let orientation = if width > height {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        };
+        document
+            .pages
+            .insert(index, Page::new(page_number, width, height, orientation));
+        self.rendered_pages.insert(index.min(self.rendered_pages.len()), Some(image));
+        self.invalidate_sheets();
+        self.load_state = LoadState::Ready;
+    }
+
     /// Most recently seen backend job and its current status.
     pub fn latest_job_status(&self) -> Option<(&JobId, JobStatus)> {
         let job_id = self.job_history.last()?;
@@ -204,6 +480,8 @@ impl AppData {
         self.current_page = 0;
         self.rendered_pages.clear();
         self.rendering_pages.clear();
+        self.rendered_sheets.clear();
+        self.rendering_sheets.clear();
         self.error_message = None;
         self.load_state = LoadState::Loading;
 
@@ -249,6 +527,31 @@ impl AppData {
                     *slot = Some(image);
                 }
             }
+            RendererEvent::SheetRendered {
+                generation,
+                revision,
+                sheet_index,
+                image,
+            } => {
+                if generation != self.render_generation || revision != self.imposition_revision {
+                    return;
+                }
+                self.rendering_sheets.remove(&sheet_index);
+                self.error_message = None;
+                self.rendered_sheets.insert(sheet_index, image);
+            }
+            RendererEvent::SheetFailed {
+                generation,
+                revision,
+                sheet_index,
+                message,
+            } => {
+                if generation != self.render_generation || revision != self.imposition_revision {
+                    return;
+                }
+                self.rendering_sheets.remove(&sheet_index);
+                self.error_message = Some(message);
+            }
             RendererEvent::Failed {
                 generation,
                 page_index,
@@ -281,6 +584,10 @@ impl AppData {
     }
 
     fn request_current_page(&mut self) {
+        if self.imposed_preview {
+            self.request_current_sheet();
+            return;
+        }
         for page_index in self.current_sheet_pages() {
             if self.rendered_pages.get(page_index).is_none()
                 || self.rendered_pages[page_index].is_some()
@@ -297,8 +604,30 @@ impl AppData {
         }
     }
 
-    fn source_page_count(&self) -> usize {
-        self.document.as_ref().map_or(0, Document::page_count)
+    fn request_current_sheet(&mut self) {
+        let sheet_index = self.current_page;
+        if self.rendered_sheets.contains_key(&sheet_index) || self.rendering_sheets.contains(&sheet_index) {
+            return;
+        }
+        let Some(side) = self.current_sheet_side() else {
+            return;
+        };
+        self.rendering_sheets.insert(sheet_index);
+        if !self.send_command(RendererCommand::RenderSheet {
+            generation: self.render_generation,
+            revision: self.imposition_revision,
+            sheet_index,
+            side,
+        }) {
+            self.rendering_sheets.remove(&sheet_index);
+        }
+    }
+
+    /// Drop composited sheets after the imposition or the document changed.
+    fn invalidate_sheets(&mut self) {
+        self.imposition_revision = self.imposition_revision.wrapping_add(1);
+        self.rendered_sheets.clear();
+        self.rendering_sheets.clear();
     }
 
     pub fn current_sheet_pages(&self) -> Vec<usize> {
@@ -309,9 +638,7 @@ impl AppData {
     }
 
     fn sheet_pages(&self) -> Vec<Vec<usize>> {
-        let pages: Vec<_> = (0..self.source_page_count()).collect();
-        NUpCalculator::new(self.print_options.pages_per_sheet as u8)
-            .and_then(|calculator| calculator.calculate(&pages, PageSize::A4))
+        self.sheet_layout()
             .map(|layout| {
                 layout
                     .pages
@@ -322,6 +649,48 @@ impl AppData {
             .unwrap_or_default()
     }
 
+    /// N-up layout of the loaded document, numbering source pages from zero.
+    ///
+    /// Back sides are turned for short-edge duplex, as they will print.
+    fn sheet_layout(&self) -> Option<NUpLayout> {
+        // Only the page sizes matter to the layout, so leave the contents behind
+        let pages: Vec<_> = self.document.as_ref().map_or_else(Vec::new, |document| {
+            document
+                .pages
+                .iter()
+                .enumerate()
+                .map(|(index, page)| Page::new(index, page.width, page.height, page.orientation))
+                .collect()
+        });
+        let config = NUpConfig {
+            pages_per_sheet: self.print_options.pages_per_sheet as u8,
+            rotation_mode: RotationMode::Rotate,
+            ..NUpConfig::default()
+        };
+        let mut layout = NUpCalculator::default()
+            .with_config(config)
+            .and_then(|calculator| calculator.calculate_for_pages(&pages, PageSize::A4))
+            .ok()?;
+        apply_duplex_flips(&mut layout.pages, self.print_options.duplex);
+        Some(layout)
+    }
+
+    /// Geometry of the current output sheet, as it will be printed.
+    pub fn current_sheet_side(&self) -> Option<SheetSide> {
+        self.sheet_layout()?
+            .sheet_sides(&MarginConfig::default())
+            .into_iter()
+            .nth(self.current_page)
+    }
+
+    /// Show the composited output sheet, or the source pages placed on it.
+    pub fn set_imposed_preview(&mut self, imposed_preview: bool) {
+        if self.imposed_preview != imposed_preview {
+            self.imposed_preview = imposed_preview;
+            self.request_current_page();
+        }
+    }
+
     /// Number of imposed sheets in the loaded document (0 if none).
     pub fn page_count(&self) -> usize {
         self.sheet_pages().len()
@@ -333,14 +702,52 @@ impl AppData {
         }
         self.print_options.pages_per_sheet = pages_per_sheet;
         self.current_page = 0;
-        self.imposition_revision = self.imposition_revision.wrapping_add(1);
+        self.invalidate_sheets();
         self.request_current_page();
     }
 
+    /// Switch to an N-up layout preset, see [`PresetLayout::presets`].
+    pub fn apply_preset(&mut self, preset: &PresetLayout) {
+        let pages_per_sheet = match preset.to_config().pages_per_sheet {
+            2 => PagesPerSheet::Two,
+            4 => PagesPerSheet::Four,
+            6 => PagesPerSheet::Six,
+            8 => PagesPerSheet::Eight,
+            _ => PagesPerSheet::One,
+        };
+        self.set_pages_per_sheet(pages_per_sheet);
+    }
+
+    /// Switch to a profile's print settings and layout.
+    ///
+    /// A profile without a known layout keeps its own pages per sheet.
+    pub fn apply_profile(&mut self, profile: &Profile) {
+        let pages_per_sheet = self.print_options.pages_per_sheet;
+        let duplex = self.print_options.duplex;
+        profile.print.apply_to(&mut self.print_options);
+        if self.print_options.duplex != duplex {
+            self.invalidate_sheets();
+        }
+        // Changed through the setter below, so the sheets are imposed again
+        let profile_pages_per_sheet = std::mem::replace(&mut self.print_options.pages_per_sheet, pages_per_sheet);
+
+        let preset = profile
+            .layout
+            .as_deref()
+            .and_then(|name| self.layout_presets.iter().find(|preset| preset.name == name))
+            .cloned();
+        match preset {
+            Some(preset) => self.apply_preset(&preset),
+            None => self.set_pages_per_sheet(profile_pages_per_sheet),
+        }
+        self.active_profile = Some(profile.name.clone());
+    }
+
     pub fn set_fill_order(&mut self, fill_order: FillOrder) {
         if self.fill_order != fill_order {
             self.fill_order = fill_order;
-            self.imposition_revision = self.imposition_revision.wrapping_add(1);
+            self.invalidate_sheets();
+            self.request_current_page();
         }
     }
 
@@ -371,9 +778,28 @@ impl AppData {
         self.request_current_page();
     }
 
+    /// Jump to the sheet showing a zero-based source page, if there is one.
+    pub fn go_to_source_page(&mut self, page_index: usize) {
+        let Some(sheet) = self
+            .sheet_pages()
+            .iter()
+            .position(|pages| pages.contains(&page_index))
+        else {
+            return;
+        };
+        self.current_page = sheet;
+        self.request_current_page();
+    }
+
     /// Set the zoom factor, clamped to a sane range.
     pub fn set_zoom(&mut self, zoom: f64) {
-        self.zoom = zoom.clamp(0.25, 4.0);
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom_mode = ZoomMode::Custom;
+    }
+
+    /// Switch to a zoom mode such as fit-to-width.
+    pub fn set_zoom_mode(&mut self, zoom_mode: ZoomMode) {
+        self.zoom_mode = zoom_mode;
     }
 
     /// Zoom in one step (20%).
@@ -388,7 +814,7 @@ impl AppData {
 
     /// Reset zoom to 100%.
     pub fn reset_zoom(&mut self) {
-        self.zoom = 1.0;
+        self.set_zoom(1.0);
     }
 }
 
@@ -444,24 +870,81 @@ mod tests {
         assert_eq!(data.current_page, 0);
     }
 
+    #[test]
+    fn starts_at_environment_configured_zoom() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let manager = boomaga_config::ConfigManager::with_dirs(
+            &dir.path().join("config"),
+            &dir.path().join("state"),
+        )
+        .unwrap();
+        let env = HashMap::from([("BOOMAGA_DEFAULT_ZOOM".to_string(), "2.0".to_string())]);
+
+        let config = manager.load_preview_with_env(env).unwrap();
+        let data = AppData::from_config(&config);
+
+        assert_eq!(data.zoom, 2.0);
+    }
+
     #[test]
     fn zoom_is_clamped_and_can_be_reset() {
         let mut data = AppData::default();
 
         data.set_zoom(0.1);
-        assert_eq!(data.zoom, 0.25);
+        assert_eq!(data.zoom, MIN_ZOOM);
 
         data.set_zoom(10.0);
-        assert_eq!(data.zoom, 4.0);
+        assert_eq!(data.zoom, MAX_ZOOM);
 
         data.reset_zoom();
         assert_eq!(data.zoom, 1.0);
     }
 
+    #[test]
+    fn configured_shortcuts_dispatch_actions() {
+        let mut data = AppData::from_config(&PreviewConfig::default());
+        data.document = Some(document_with_pages(3));
+
+        assert!(data.handle_shortcut(&parse_shortcut("Ctrl+Right").unwrap()));
+        assert_eq!(data.current_page, 1);
+        assert!(data.handle_shortcut(&parse_shortcut("End").unwrap()));
+        assert_eq!(data.current_page, 2);
+        assert!(data.handle_shortcut(&parse_shortcut("Ctrl++").unwrap()));
+        assert!(data.zoom > 1.0);
+        assert!(!data.handle_shortcut(&parse_shortcut("Ctrl+Q").unwrap()));
+
+        let disabled = PreviewConfig { enable_shortcuts: false, ..Default::default() };
+        assert!(!AppData::from_config(&disabled).handle_shortcut(&parse_shortcut("Ctrl+Right").unwrap()));
+    }
+
+    #[test]
+    fn conflicting_shortcuts_are_listed() {
+        assert!(AppData::from_config(&PreviewConfig::default()).shortcut_conflicts.is_empty());
+
+        let mut config = PreviewConfig::default();
+        config.keybindings.view.next_page = "Ctrl+Right".to_string();
+        let data = AppData::from_config(&config);
+        assert_eq!(
+            data.shortcut_conflicts,
+            vec!["Ctrl+Right is bound to navigation.next_page, view.next_page"]
+        );
+    }
+
+    #[test]
+    fn manual_zoom_leaves_fit_modes() {
+        let mut data = AppData::default();
+
+        data.set_zoom_mode(ZoomMode::FitWidth);
+        assert_eq!(data.zoom_mode, ZoomMode::FitWidth);
+
+        data.zoom_in();
+        assert_eq!(data.zoom_mode, ZoomMode::Custom);
+    }
+
     #[test]
     fn command_line_path_is_loaded_after_worker_connects() {
         let path = PathBuf::from("large.pdf");
-        let mut data = AppData::with_document_path(path.clone());
+        let mut data = AppData::default().opening(path.clone());
         let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
 
         data.install_renderer(sender);
@@ -510,6 +993,52 @@ mod tests {
         assert!(receiver.try_recv().is_err());
     }
 
+    #[test]
+    fn loading_a_new_document_starts_on_its_first_page() {
+        let mut data = AppData {
+            document: Some(document_with_pages(5)),
+            current_page: 3,
+            ..AppData::default()
+        };
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        data.install_renderer(sender);
+
+        data.handle_renderer_event(RendererEvent::FileSelected(PathBuf::from("next.pdf")));
+        assert!(data.document.is_none());
+        assert_eq!(data.current_page, 0);
+
+        data.handle_renderer_event(RendererEvent::DocumentLoaded {
+            generation: 1,
+            path: PathBuf::from("next.pdf"),
+            document: document_with_pages(2),
+        });
+
+        assert_eq!(data.document.as_ref().map(Document::page_count), Some(2));
+        assert_eq!(data.document_path, Some(PathBuf::from("next.pdf")));
+        assert_eq!(data.current_page, 0);
+    }
+
+    #[test]
+    fn cancelling_the_file_dialog_keeps_the_open_document() {
+        let mut data = AppData {
+            document: Some(document_with_pages(3)),
+            current_page: 1,
+            load_state: LoadState::Ready,
+            ..AppData::default()
+        };
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        data.install_renderer(sender);
+
+        data.choose_document();
+        assert!(matches!(receiver.try_recv().unwrap(), RendererCommand::OpenFileDialog));
+        data.handle_renderer_event(RendererEvent::FileDialogCancelled);
+
+        assert!(!data.choosing_file);
+        assert_eq!(data.load_state, LoadState::Ready);
+        assert_eq!(data.current_page, 1);
+        assert_eq!(data.document.as_ref().map(Document::page_count), Some(3));
+    }
+
     #[test]
     fn two_up_navigation_uses_sheet_count() {
         let mut data = AppData {
@@ -523,6 +1052,75 @@ mod tests {
         assert_eq!(data.current_sheet_pages(), vec![4]);
     }
 
+    #[test]
+    fn applying_a_preset_sets_pages_per_sheet() {
+        let mut data = AppData {
+            document: Some(document_with_pages(8)),
+            ..AppData::default()
+        };
+        data.apply_preset(&PresetLayout::find("4-Up").unwrap());
+        assert_eq!(data.print_options.pages_per_sheet, PagesPerSheet::Four);
+        assert_eq!(data.page_count(), 2);
+    }
+
+    #[test]
+    fn applying_a_profile_sets_print_options_and_layout() {
+        let profile = Profile {
+            name: "Office duplex".to_string(),
+            printer: None,
+            print: boomaga_config::PrintSettings { copies: 3, pages_per_sheet: 2, ..Default::default() },
+            layout: Some("4-Up".to_string()),
+        };
+        let data = AppData {
+            document: Some(document_with_pages(8)),
+            ..AppData::default()
+        }
+        .with_profiles(vec![profile.clone()], Some("Office duplex"));
+
+        assert_eq!(data.active_profile.as_deref(), Some("Office duplex"));
+        assert_eq!(data.print_options.copies, 3);
+        // The profile's layout wins over its pages per sheet
+        assert_eq!(data.print_options.pages_per_sheet, PagesPerSheet::Four);
+
+        let mut data = AppData::default();
+        data.apply_profile(&Profile { layout: None, ..profile });
+        assert_eq!(data.print_options.pages_per_sheet, PagesPerSheet::Two);
+    }
+
+    #[test]
+    fn short_edge_duplex_turns_back_sides() {
+        use boomaga_core::DuplexMode;
+        use boomaga_layout_engine::TransformOperation;
+
+        let mut data = AppData {
+            document: Some(document_with_pages(2)),
+            ..AppData::default()
+        };
+        data.print_options.duplex = DuplexMode::ShortEdge;
+
+        assert_eq!(data.current_sheet_side().unwrap().cells[0].transform, None);
+        data.next_page();
+        let back = data.current_sheet_side().unwrap();
+        assert_eq!(back.cells[0].transform, Some(TransformOperation::Rotate180));
+    }
+
+    #[test]
+    fn landscape_pages_turn_to_fit_portrait_cells() {
+        use boomaga_layout_engine::TransformOperation;
+
+        let mut document = document_with_pages(1);
+        document.add_page(Page::new(1, 842.0, 595.0, Orientation::Landscape));
+        let mut data = AppData {
+            document: Some(document),
+            ..AppData::default()
+        };
+
+        assert_eq!(data.current_sheet_side().unwrap().cells[0].transform, None);
+        data.next_page();
+        let sheet = data.current_sheet_side().unwrap();
+        assert_eq!(sheet.cells[0].transform, Some(TransformOperation::Rotate90));
+    }
+
     #[test]
     fn changing_n_up_invalidates_imposition_without_discarding_rasters() {
         let image = CanvasImage::from_cairo_bgra(vec![0; 4], 1, 1).unwrap();
@@ -539,6 +1137,21 @@ mod tests {
         assert_eq!(data.rendered_page_count(), 1);
     }
 
+    #[test]
+    fn going_to_a_source_page_selects_its_sheet() {
+        let mut data = AppData {
+            document: Some(document_with_pages(5)),
+            ..AppData::default()
+        };
+        data.set_pages_per_sheet(PagesPerSheet::Two);
+
+        data.go_to_source_page(3);
+        assert_eq!(data.current_page, 1);
+
+        data.go_to_source_page(9);
+        assert_eq!(data.current_page, 1);
+    }
+
     #[test]
     fn two_up_requests_every_page_on_current_sheet() {
         let mut data = AppData {
@@ -560,6 +1173,52 @@ mod tests {
         assert!(receiver.try_recv().is_err());
     }
 
+    #[test]
+    fn imposed_preview_renders_the_four_up_sheet() {
+        let mut data = AppData {
+            document: Some(document_with_pages(5)),
+            rendered_pages: vec![None; 5],
+            imposed_preview: true,
+            ..AppData::default()
+        };
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        data.install_renderer(sender);
+        data.set_pages_per_sheet(PagesPerSheet::Four);
+
+        let revision = match receiver.try_recv().unwrap() {
+            RendererCommand::RenderSheet {
+                revision,
+                sheet_index,
+                side,
+                ..
+            } => {
+                assert_eq!(sheet_index, 0);
+                let pages: Vec<_> = side.cells.iter().map(|cell| cell.page).collect();
+                assert_eq!(pages, vec![0, 1, 2, 3]);
+                revision
+            }
+            command => panic!("unexpected renderer command: {command:?}"),
+        };
+        assert!(receiver.try_recv().is_err());
+
+        data.handle_renderer_event(RendererEvent::SheetRendered {
+            generation: data.render_generation,
+            revision,
+            sheet_index: 0,
+            image: CanvasImage::from_cairo_bgra(vec![0; 4], 1, 1).unwrap(),
+        });
+        assert_eq!(data.canvas_pages_per_sheet(), 1);
+        assert!(data.current_canvas_images()[0].is_some());
+
+        // A new layout drops the composited sheet and renders it again
+        data.set_pages_per_sheet(PagesPerSheet::Two);
+        assert!(data.current_canvas_images()[0].is_none());
+        match receiver.try_recv().unwrap() {
+            RendererCommand::RenderSheet { side, .. } => assert_eq!(side.cells.len(), 2),
+            command => panic!("unexpected renderer command: {command:?}"),
+        }
+    }
+
     #[test]
     fn job_notifications_update_existing_status_without_duplicates() {
         let job_id: JobId =
@@ -583,4 +1242,69 @@ mod tests {
         assert_eq!(latest_id.to_string(), job_id.to_string());
         assert_eq!(latest_status, JobStatus::Processing);
     }
+
+    /// A one-pixel page of `job_id`, sent inline
+    fn streamed_page(job_id: &JobId, page_number: usize) -> IpcEvent {
+        IpcEvent::Message(Message::new_notification(
+            MessageSource::Backend,
+            MessageDestination::Preview,
+            MessagePayload::PageRendered {
+                job_id: job_id.clone(),
+                page_number,
+                width: 1,
+                height: 1,
+                format: boomaga_ipc::ImageFormat::Rgba8,
+                image_data: std::sync::Arc::from([0, 0, 0, 255]),
+            },
+        ))
+    }
+
+    fn job_started(job_id: &JobId) -> IpcEvent {
+        IpcEvent::Message(Message::new_notification(
+            MessageSource::Backend,
+            MessageDestination::Preview,
+            MessagePayload::PrintJobStatus {
+                job_id: job_id.clone(),
+                status: JobStatus::Processing,
+            },
+        ))
+    }
+
+    #[test]
+    fn streamed_pages_populate_document_without_duplicates() {
+        let mut data = AppData::default();
+        let job_id = JobId::new();
+        data.handle_ipc_event(job_started(&job_id));
+
+        for page_number in [2, 1, 2, 3] {
+            data.handle_ipc_event(streamed_page(&job_id, page_number));
+        }
+
+        let document = data.document.as_ref().unwrap();
+        let numbers: Vec<_> = document.pages.iter().map(|page| page.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert_eq!(document.id, job_id.to_string());
+        assert_eq!(data.rendered_page_count(), 3);
+        assert_eq!(data.load_state, LoadState::Ready);
+    }
+
+    #[test]
+    fn streamed_pages_of_other_jobs_are_dropped() {
+        let mut data = AppData::default();
+        let (old_job, new_job) = (JobId::new(), JobId::new());
+        data.handle_ipc_event(job_started(&old_job));
+        data.handle_ipc_event(streamed_page(&old_job, 1));
+        data.handle_ipc_event(job_started(&new_job));
+
+        data.handle_ipc_event(streamed_page(&old_job, 2));
+        assert_eq!(data.document.as_ref().unwrap().id, old_job.to_string());
+        assert_eq!(data.document.as_ref().unwrap().page_count(), 1);
+
+        // The first page of the new job replaces the old job's pages
+        data.handle_ipc_event(streamed_page(&new_job, 1));
+        let document = data.document.as_ref().unwrap();
+        assert_eq!(document.id, new_job.to_string());
+        assert_eq!(document.page_count(), 1);
+        assert_eq!(data.rendered_page_count(), 1);
+    }
 }
@@ -0,0 +1,110 @@
+//! Side panel listing the document outline.
+
+use boomaga_core::Bookmark;
+use xilem::view::{button, flex, label, Axis};
+use xilem::WidgetView;
+
+use crate::app::AppData;
+
+/// Spaces added per outline level.
+const INDENT: &str = "    ";
+
+/// One outline entry as shown in the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkEntry {
+    pub depth: usize,
+    pub title: String,
+    /// Target page (1-based), if any.
+    pub page: Option<usize>,
+}
+
+/// Bookmarks panel, or nothing when it is hidden.
+///
+/// Documents without an outline show an empty panel.
+pub fn bookmarks_panel(data: &AppData) -> Option<impl WidgetView<AppData> + use<>> {
+    if !data.show_bookmarks {
+        return None;
+    }
+
+    let entries = data
+        .document
+        .as_ref()
+        .map(|document| flatten_outline(&document.outline))
+        .unwrap_or_default();
+    let buttons: Vec<_> = entries
+        .into_iter()
+        .map(|entry| {
+            let text = format!("{}{}", INDENT.repeat(entry.depth), entry.title);
+            button(label(text), move |d: &mut AppData| {
+                if let Some(page) = entry.page {
+                    d.go_to_source_page(page - 1);
+                }
+            })
+        })
+        .collect();
+
+    Some(flex(Axis::Vertical, buttons))
+}
+
+/// List an outline depth-first, parents before their children.
+pub fn flatten_outline(outline: &[Bookmark]) -> Vec<BookmarkEntry> {
+    fn visit(bookmarks: &[Bookmark], depth: usize, entries: &mut Vec<BookmarkEntry>) {
+        for bookmark in bookmarks {
+            entries.push(BookmarkEntry {
+                depth,
+                title: bookmark.title.clone(),
+                page: bookmark.page,
+            });
+            visit(&bookmark.children, depth + 1, entries);
+        }
+    }
+
+    let mut entries = Vec::new();
+    visit(outline, 0, &mut entries);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boomaga_config::UISettings;
+
+    fn bookmark(title: &str, page: usize, children: Vec<Bookmark>) -> Bookmark {
+        Bookmark {
+            title: title.to_owned(),
+            page: Some(page),
+            children,
+        }
+    }
+
+    #[test]
+    fn outline_is_flattened_depth_first() {
+        let outline = vec![
+            bookmark("Chapter 1", 1, vec![bookmark("Section 1.1", 2, Vec::new())]),
+            bookmark("Chapter 2", 3, Vec::new()),
+        ];
+
+        let entries = flatten_outline(&outline);
+
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.depth, entry.title.as_str(), entry.page))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, "Chapter 1", Some(1)),
+                (1, "Section 1.1", Some(2)),
+                (0, "Chapter 2", Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_panel_builds_no_view() {
+        let data = AppData::default().with_ui_settings(&UISettings { show_bookmarks: false, ..UISettings::default() });
+
+        assert!(bookmarks_panel(&data).is_none());
+        assert!(bookmarks_panel(&AppData::default()).is_some());
+    }
+}
@@ -4,15 +4,23 @@
 //! and retains this type on one dedicated thread; only core document metadata
 //! and completed canvas images cross back to Xilem's UI thread.
 
+use std::f64::consts::{FRAC_PI_2, PI};
+#[cfg(target_os = "linux")]
+use std::os::fd::OwnedFd;
 use std::path::Path;
 
+use boomaga_config::{PerformanceSettings, RenderSettings};
 use boomaga_core::{
     Color, Document as CoreDocument, FileType, GraphicsElement, Orientation, Page as CorePage,
     PageContents,
 };
-use cairo::{Context, Format, ImageSurface};
+#[cfg(target_os = "linux")]
+use boomaga_ipc::shm::SharedBitmap;
+use boomaga_ipc::ImageFormat;
+use boomaga_layout_engine::{SheetSide, TransformOperation};
+use cairo::{Antialias, Context, Format, ImageSurface};
 use poppler::{PopplerDocument, PopplerPage};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::pdf_canvas::{CanvasImage, CanvasImageError};
 
@@ -37,9 +45,15 @@ pub enum RenderError {
     #[error("rendered page dimensions exceed Cairo limits")]
     InvalidDimensions,
 
+    #[error("page does not fit the {budget}-pixel render budget")]
+    OverBudget { budget: u64 },
+
     #[error("failed to borrow rendered Cairo pixels: {0}")]
     SurfaceData(String),
 
+    #[error("unreadable page image from the backend: {0}")]
+    StreamedImage(String),
+
     #[error(transparent)]
     CanvasImage(#[from] CanvasImageError),
 }
@@ -48,6 +62,7 @@ pub enum RenderError {
 pub struct DocumentRenderer {
     poppler_document: Option<PopplerDocument>,
     document_id: String,
+    pixel_budget: u64,
 }
 
 impl DocumentRenderer {
@@ -56,9 +71,16 @@ impl DocumentRenderer {
         Self {
             poppler_document: None,
             document_id: document_id.into(),
+            pixel_budget: PerformanceSettings::default().page_pixel_budget(),
         }
     }
 
+    /// Limit the pixels allocated for one page; larger pages are downscaled.
+    pub fn with_pixel_budget(mut self, pixel_budget: u64) -> Self {
+        self.pixel_budget = pixel_budget.min(boomaga_core::MAX_PAGE_PIXELS);
+        self
+    }
+
     /// Load a PDF and build the framework-independent document model.
     pub fn load(&mut self, path: &Path) -> Result<CoreDocument, RenderError> {
         info!(path = ?path, "loading PDF document");
@@ -83,6 +105,11 @@ impl DocumentRenderer {
             document.add_page(page);
         }
 
+        match boomaga_core::outline::read_outline(path) {
+            Ok(outline) => document.outline = outline,
+            Err(error) => warn!(%error, "could not read PDF outline"),
+        }
+
         info!(pages = document.page_count(), "loaded PDF document");
         self.poppler_document = Some(poppler_document);
         Ok(document)
@@ -90,26 +117,87 @@ impl DocumentRenderer {
 
     /// Render a zero-based page index directly into a Masonry canvas image.
     pub fn render_page(&self, page_index: usize, dpi: f64) -> Result<CanvasImage, RenderError> {
-        let mut surface = self.render_page_to_surface(page_index, dpi)?;
-        surface.flush();
+        canvas_image(self.render_page_to_surface(page_index, dpi)?)
+    }
+
+    /// Composite one side of an imposed sheet into a Masonry canvas image.
+    pub fn render_sheet(&self, side: &SheetSide, dpi: f64) -> Result<CanvasImage, RenderError> {
+        canvas_image(self.render_sheet_to_surface(side, dpi)?)
+    }
+
+    /// Composite one side of an imposed sheet to a Cairo ARGB32 image surface.
+    ///
+    /// Cell pages are zero-based page indexes. Each page is scaled to fit its
+    /// cell, centered, and outlined so that blank pages stay visible.
+    pub fn render_sheet_to_surface(
+        &self,
+        side: &SheetSide,
+        dpi: f64,
+    ) -> Result<ImageSurface, RenderError> {
+        if !dpi.is_finite() || dpi <= 0.0 {
+            return Err(RenderError::InvalidDpi);
+        }
+
+        let document = self
+            .poppler_document
+            .as_ref()
+            .ok_or(RenderError::EmptyDocument)?;
+        let (width, height, scale) =
+            fit_to_budget(side.width, side.height, dpi / 72.0, self.pixel_budget)?;
+
+        let surface = ImageSurface::create(Format::ARgb32, width, height)?;
+        let context = Context::new(&surface)?;
+        context.set_source_rgb(1.0, 1.0, 1.0);
+        context.paint()?;
+        context.scale(scale, scale);
 
-        let width = u32::try_from(surface.width()).map_err(|_| RenderError::InvalidDimensions)?;
-        let height = u32::try_from(surface.height()).map_err(|_| RenderError::InvalidDimensions)?;
-        let stride =
-            usize::try_from(surface.stride()).map_err(|_| RenderError::InvalidDimensions)?;
-        let row_bytes = width as usize * 4;
-        let pixels = {
-            let data = surface
-                .data()
-                .map_err(|error| RenderError::SurfaceData(error.to_string()))?;
-            let mut pixels = Vec::with_capacity(row_bytes * height as usize);
-            for row in data.chunks(stride).take(height as usize) {
-                pixels.extend_from_slice(&row[..row_bytes]);
+        for cell in &side.cells {
+            let page = document
+                .get_page(cell.page)
+                .ok_or(RenderError::InvalidPage(cell.page))?;
+            let (page_width, page_height) = page.get_size();
+            let quarter_turn = matches!(
+                cell.transform,
+                Some(TransformOperation::Rotate90 | TransformOperation::Rotate270)
+            );
+            let (turned_width, turned_height) = if quarter_turn {
+                (page_height, page_width)
+            } else {
+                (page_width, page_height)
+            };
+            let fit = (cell.width / turned_width).min(cell.height / turned_height);
+
+            context.save()?;
+            context.translate(cell.x + cell.width / 2.0, cell.y + cell.height / 2.0);
+            context.scale(fit, fit);
+            match cell.transform {
+                Some(TransformOperation::Rotate90) => context.rotate(FRAC_PI_2),
+                Some(TransformOperation::Rotate180) => context.rotate(PI),
+                Some(TransformOperation::Rotate270) => context.rotate(-FRAC_PI_2),
+                Some(TransformOperation::FlipHorizontal) => context.scale(-1.0, 1.0),
+                Some(TransformOperation::FlipVertical) => context.scale(1.0, -1.0),
+                _ => {}
             }
-            pixels
-        };
+            context.translate(-page_width / 2.0, -page_height / 2.0);
+            page.render(&context);
+
+            // A one-pixel outline just inside the page edge
+            let line_width = 1.0 / (fit * scale);
+            context.set_source_rgb(0.6, 0.6, 0.6);
+            context.set_line_width(line_width);
+            context.rectangle(
+                line_width / 2.0,
+                line_width / 2.0,
+                page_width - line_width,
+                page_height - line_width,
+            );
+            context.stroke()?;
+            context.restore()?;
+        }
+        drop(context);
+        surface.flush();
 
-        CanvasImage::from_cairo_bgra(pixels, width, height).map_err(Into::into)
+        Ok(surface)
     }
 
     /// Render a zero-based page index to a Cairo ARGB32 image surface.
@@ -117,6 +205,29 @@ impl DocumentRenderer {
         &self,
         page_index: usize,
         dpi: f64,
+    ) -> Result<ImageSurface, RenderError> {
+        self.render_surface(page_index, dpi, Antialias::Default)
+    }
+
+    /// Render a zero-based page index for printing with a job's render settings.
+    pub fn render_page_for_print(
+        &self,
+        page_index: usize,
+        settings: RenderSettings,
+    ) -> Result<ImageSurface, RenderError> {
+        let antialias = if settings.antialias {
+            Antialias::Default
+        } else {
+            Antialias::None
+        };
+        self.render_surface(page_index, settings.dpi, antialias)
+    }
+
+    fn render_surface(
+        &self,
+        page_index: usize,
+        dpi: f64,
+        antialias: Antialias,
     ) -> Result<ImageSurface, RenderError> {
         if !dpi.is_finite() || dpi <= 0.0 {
             return Err(RenderError::InvalidDpi);
@@ -130,22 +241,113 @@ impl DocumentRenderer {
             .get_page(page_index)
             .ok_or(RenderError::InvalidPage(page_index))?;
         let (width_points, height_points) = page.get_size();
-        let scale = dpi / 72.0;
-        let width = pixel_dimension(width_points, scale)?;
-        let height = pixel_dimension(height_points, scale)?;
+        let (width, height, scale) =
+            fit_to_budget(width_points, height_points, dpi / 72.0, self.pixel_budget)?;
 
         let surface = ImageSurface::create(Format::ARgb32, width, height)?;
         let context = Context::new(&surface)?;
         context.set_source_rgb(1.0, 1.0, 1.0);
         context.paint()?;
+        context.set_antialias(antialias);
         context.scale(scale, scale);
         page.render(&context);
+        // The context holds a reference to the surface; callers need the only one
+        drop(context);
         surface.flush();
 
         Ok(surface)
     }
 }
 
+/// Copy a rendered surface into a Masonry canvas image, dropping row padding.
+fn canvas_image(mut surface: ImageSurface) -> Result<CanvasImage, RenderError> {
+    surface.flush();
+
+    let width = u32::try_from(surface.width()).map_err(|_| RenderError::InvalidDimensions)?;
+    let height = u32::try_from(surface.height()).map_err(|_| RenderError::InvalidDimensions)?;
+    let stride =
+        usize::try_from(surface.stride()).map_err(|_| RenderError::InvalidDimensions)?;
+    let row_bytes = width as usize * 4;
+    let pixels = {
+        let data = surface
+            .data()
+            .map_err(|error| RenderError::SurfaceData(error.to_string()))?;
+        let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+        for row in data.chunks(stride).take(height as usize) {
+            pixels.extend_from_slice(&row[..row_bytes]);
+        }
+        pixels
+    };
+
+    CanvasImage::from_cairo_bgra(pixels, width, height).map_err(Into::into)
+}
+
+/// Decode a page image the backend streamed inline.
+pub fn streamed_page_image(
+    format: ImageFormat,
+    data: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<CanvasImage, RenderError> {
+    match format {
+        ImageFormat::Rgba8 => rgba_canvas_image(data, width, height, width * 4),
+        ImageFormat::Png => {
+            let surface = ImageSurface::create_from_png(&mut &data[..])
+                .map_err(|error| RenderError::StreamedImage(error.to_string()))?;
+            canvas_image(surface)
+        }
+    }
+}
+
+/// Read a page image the backend rendered into a shared-memory segment.
+#[cfg(target_os = "linux")]
+pub fn shared_page_image(
+    fd: &OwnedFd,
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Result<CanvasImage, RenderError> {
+    let pixels = fd
+        .try_clone()
+        .and_then(SharedBitmap::from_fd)
+        .and_then(|bitmap| bitmap.map())
+        .map_err(|error| RenderError::StreamedImage(error.to_string()))?;
+    rgba_canvas_image(&pixels, width, height, stride)
+}
+
+/// Convert straight RGBA rows, `stride` bytes apart, into a canvas image.
+fn rgba_canvas_image(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Result<CanvasImage, RenderError> {
+    let row_bytes = width * 4;
+    if stride < row_bytes || (height > 0 && data.len() < stride * (height - 1) + row_bytes) {
+        return Err(RenderError::StreamedImage(format!(
+            "{} bytes do not hold {width}x{height} pixels",
+            data.len()
+        )));
+    }
+    let mut pixels = Vec::with_capacity(row_bytes * height);
+    for row in data.chunks(stride.max(1)).take(height) {
+        for pixel in row[..row_bytes].chunks_exact(4) {
+            let premultiply =
+                |value: u8| ((u32::from(value) * u32::from(pixel[3]) + 127) / 255) as u8;
+            pixels.extend_from_slice(&[
+                premultiply(pixel[2]),
+                premultiply(pixel[1]),
+                premultiply(pixel[0]),
+                pixel[3],
+            ]);
+        }
+    }
+
+    let width = u32::try_from(width).map_err(|_| RenderError::InvalidDimensions)?;
+    let height = u32::try_from(height).map_err(|_| RenderError::InvalidDimensions)?;
+    CanvasImage::from_cairo_bgra(pixels, width, height).map_err(Into::into)
+}
+
 fn pixel_dimension(points: f64, scale: f64) -> Result<i32, RenderError> {
     let pixels = (points * scale).ceil();
     if !pixels.is_finite() || pixels <= 0.0 || pixels > i32::MAX as f64 {
@@ -154,6 +356,35 @@ fn pixel_dimension(points: f64, scale: f64) -> Result<i32, RenderError> {
     Ok(pixels as i32)
 }
 
+/// Pixel size and scale for a page, downscaled to stay within `budget` pixels.
+fn fit_to_budget(
+    width_points: f64,
+    height_points: f64,
+    scale: f64,
+    budget: u64,
+) -> Result<(i32, i32, f64), RenderError> {
+    let mut scale = scale;
+    let pixels = (width_points * scale).ceil() * (height_points * scale).ceil();
+    if pixels > budget as f64 {
+        let reduced = scale * (budget as f64 / pixels).sqrt();
+        warn!(
+            width_points,
+            height_points,
+            budget,
+            "page exceeds the render budget, downscaling"
+        );
+        scale = reduced;
+    }
+
+    let width = pixel_dimension(width_points, scale).map_err(|_| RenderError::OverBudget { budget })?;
+    let height = pixel_dimension(height_points, scale).map_err(|_| RenderError::OverBudget { budget })?;
+    if width as u64 * height as u64 > budget {
+        return Err(RenderError::OverBudget { budget });
+    }
+
+    Ok((width, height, scale))
+}
+
 fn page_orientation(width: f64, height: f64) -> Orientation {
     if width > height {
         Orientation::Landscape
@@ -189,6 +420,98 @@ mod tests {
         assert_eq!(page_orientation(500.0, 500.0), Orientation::Portrait);
     }
 
+    #[test]
+    fn streamed_rgba_pixels_become_premultiplied_bgra() {
+        let image = streamed_page_image(ImageFormat::Rgba8, &[255, 0, 0, 255, 0, 0, 255, 128], 2, 1).unwrap();
+
+        let expected = CanvasImage::from_cairo_bgra(vec![0, 0, 255, 255, 128, 0, 0, 128], 2, 1).unwrap();
+        assert!(image == expected);
+        assert!(matches!(
+            streamed_page_image(ImageFormat::Rgba8, &[0; 4], 2, 1),
+            Err(RenderError::StreamedImage(_))
+        ));
+    }
+
+    #[test]
+    fn oversized_page_is_downscaled_to_budget() {
+        // The largest PDF MediaBox at 600 DPI would need ~16 gigapixels
+        let (width, height, scale) = fit_to_budget(14_400.0, 14_400.0, 600.0 / 72.0, 10_000).unwrap();
+
+        assert!(width as u64 * height as u64 <= 10_000);
+        assert!(scale < 600.0 / 72.0);
+        assert_eq!(fit_to_budget(72.0, 72.0, 1.0, 10_000).unwrap(), (72, 72, 1.0));
+    }
+
+    #[test]
+    fn page_that_cannot_fit_budget_fails() {
+        assert!(matches!(
+            fit_to_budget(14_400.0, 14_400.0, 600.0 / 72.0, 0),
+            Err(RenderError::OverBudget { budget: 0 })
+        ));
+    }
+
+    #[test]
+    fn draft_quality_renders_fewer_pixels_than_high() {
+        use boomaga_config::RenderQuality;
+        use boomaga_core::PrintQuality;
+
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("../boomaga-core/tests/fixtures/outline.pdf");
+        let mut renderer = DocumentRenderer::new("doc");
+        renderer.load(&fixture).unwrap();
+
+        let render = |quality| {
+            let settings = RenderQuality::Ultra.render_settings(quality);
+            renderer.render_page_for_print(0, settings).unwrap()
+        };
+        let draft = render(PrintQuality::Draft);
+        let high = render(PrintQuality::High);
+
+        assert!(draft.width() < high.width());
+        assert!(draft.height() < high.height());
+    }
+
+    #[test]
+    fn rendered_surfaces_are_not_shared() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("../boomaga-core/tests/fixtures/outline.pdf");
+        let mut renderer = DocumentRenderer::new("doc");
+        renderer.load(&fixture).unwrap();
+
+        // Borrowing the pixels fails while anything else holds the surface
+        for _ in 0..2 {
+            let mut surface = renderer.render_page_to_surface(0, 36.0).unwrap();
+            assert!(surface.data().is_ok());
+        }
+        assert!(renderer.render_page(0, 36.0).is_ok());
+    }
+
+    #[test]
+    fn four_up_sheet_outlines_each_placed_page() {
+        use boomaga_core::PageSize;
+        use boomaga_layout_engine::n_up::{MarginConfig, NUpCalculator};
+
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("../boomaga-core/tests/fixtures/outline.pdf");
+        let mut renderer = DocumentRenderer::new("doc");
+        renderer.load(&fixture).unwrap();
+
+        // Three pages leave the last quadrant of the sheet empty
+        let layout = NUpCalculator::new(4).unwrap().calculate(&[0, 1, 2], PageSize::A4).unwrap();
+        let side = &layout.sheet_sides(&MarginConfig::default())[0];
+        let mut surface = renderer.render_sheet_to_surface(side, 72.0).unwrap();
+
+        let (width, height) = (surface.width() as usize, surface.height() as usize);
+        let stride = surface.stride() as usize;
+        let data = surface.data().unwrap();
+        let drawn = |columns: std::ops::Range<usize>, rows: std::ops::Range<usize>| {
+            rows.flat_map(|y| columns.clone().map(move |x| y * stride + x * 4))
+                .any(|at| data[at..at + 3].iter().any(|&channel| channel < 250))
+        };
+        let (middle_x, middle_y) = (width / 2, height / 2);
+        assert!(drawn(0..middle_x, 0..middle_y));
+        assert!(drawn(middle_x..width, 0..middle_y));
+        assert!(drawn(0..middle_x, middle_y..height));
+        assert!(!drawn(middle_x + 1..width, middle_y + 1..height));
+    }
+
     #[test]
     fn validates_pixel_dimensions() {
         assert_eq!(pixel_dimension(72.0, 2.0).unwrap(), 144);
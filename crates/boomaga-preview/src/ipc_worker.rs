@@ -14,14 +14,27 @@ use crate::app::AppData;
 
 #[derive(Debug)]
 pub enum IpcCommand {
-    Connect(PathBuf),
+    /// Receive notifications on `socket_path`, dropping those older than
+    /// `message_ttl`.
+    Connect {
+        socket_path: PathBuf,
+        message_ttl: Duration,
+    },
 }
 
 pub enum IpcEvent {
     Message(Message),
+    /// The backend answered a heartbeat after this round trip.
+    Heartbeat(Duration),
     Disconnected(String),
 }
 
+/// How often the backend is pinged while waiting for notifications.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a heartbeat may take before the backend counts as gone.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(2);
+
 impl fmt::Debug for IpcEvent {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -29,6 +42,7 @@ impl fmt::Debug for IpcEvent {
                 .debug_tuple("Message")
                 .field(&message.payload)
                 .finish(),
+            Self::Heartbeat(latency) => formatter.debug_tuple("Heartbeat").field(latency).finish(),
             Self::Disconnected(message) => formatter
                 .debug_tuple("Disconnected")
                 .field(message)
@@ -49,24 +63,35 @@ async fn run_ipc_worker(
     proxy: MessageProxy<IpcEvent>,
     mut receiver: UnboundedReceiver<IpcCommand>,
 ) {
-    while let Some(IpcCommand::Connect(path)) = receiver.recv().await {
-        let transport = UnixSocketTransport::new(path);
+    while let Some(IpcCommand::Connect {
+        socket_path,
+        message_ttl,
+    }) = receiver.recv().await
+    {
+        let transport = UnixSocketTransport::new(socket_path).with_message_ttl(message_ttl);
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        // Kept across heartbeats, so the waiting connection is not dropped
+        let mut receiving = Box::pin(transport.receive_message());
         loop {
-            match transport.receive_message().await {
-                Ok(message) => {
-                    if proxy.message(IpcEvent::Message(message)).is_err() {
-                        return;
-                    }
-                }
-                Err(error) => {
-                    if proxy
-                        .message(IpcEvent::Disconnected(error.to_string()))
-                        .is_err()
-                    {
-                        return;
-                    }
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-                }
+            let (received, event) = tokio::select! {
+                received = &mut receiving => match received {
+                    Ok(message) => (true, IpcEvent::Message(message)),
+                    Err(error) => (true, IpcEvent::Disconnected(error.to_string())),
+                },
+                _ = heartbeat.tick() => match transport.ping(HEARTBEAT_TIMEOUT).await {
+                    Ok(latency) => (false, IpcEvent::Heartbeat(latency)),
+                    Err(error) => (false, IpcEvent::Disconnected(format!("Backend is not responding: {error}"))),
+                },
+            };
+            let disconnected = matches!(event, IpcEvent::Disconnected(_));
+            if received || disconnected {
+                receiving = Box::pin(transport.receive_message());
+            }
+            if proxy.message(event).is_err() {
+                return;
+            }
+            if disconnected {
+                tokio::time::sleep(Duration::from_secs(1)).await;
             }
         }
     }
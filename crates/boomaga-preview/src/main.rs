@@ -5,19 +5,26 @@
 //! thread through Xilem's worker/message mechanism.
 
 mod app;
+mod bookmarks_panel;
 mod document_renderer;
 mod ipc_worker;
 mod pdf_canvas;
 mod render_worker;
+mod status_bar;
 
-use app::{AppData, FillOrder, LoadState};
-use boomaga_core::PagesPerSheet;
+use app::{AppData, FillOrder};
+use bookmarks_panel::bookmarks_panel;
 use ipc_worker::ipc_worker;
 use pdf_canvas::pdf_canvas;
 use render_worker::renderer_worker;
-use std::ffi::OsStr;
+use status_bar::status_bar;
+use boomaga_config::{ConfigManager, PreviewConfig, Settings, Shortcut, ZoomMode};
+use boomaga_layout_engine::PresetLayout;
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
-use tracing::{info, Level};
+use tracing::{debug, info, warn};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use winit::dpi::LogicalSize;
 use xilem::core::fork;
 use xilem::masonry::properties::types::AsUnit;
 use xilem::style::Style as _;
@@ -33,43 +40,68 @@ fn app_logic(data: &mut AppData) -> impl WidgetView<AppData> + use<> {
             button(label("−"), |d: &mut AppData| d.zoom_out()),
             button(label("100%"), |d: &mut AppData| d.reset_zoom()),
             button(label("+"), |d: &mut AppData| d.zoom_in()),
+            button(label("Fit Page"), |d: &mut AppData| d.set_zoom_mode(ZoomMode::Fit)),
+            button(label("Fit Width"), |d: &mut AppData| {
+                d.set_zoom_mode(ZoomMode::FitWidth)
+            }),
+            button(label("Fit Height"), |d: &mut AppData| {
+                d.set_zoom_mode(ZoomMode::FitHeight)
+            }),
         ),
     );
 
+    // The canvas only draws N-up sheets, so booklet presets are left out
+    let presets: Vec<_> = data
+        .layout_presets
+        .iter()
+        .filter(|preset| preset.booklet.is_none())
+        .cloned()
+        .map(|preset| {
+            button(label(preset.name.to_string()), move |d: &mut AppData| {
+                d.apply_preset(&preset)
+            })
+        })
+        .collect();
+    let profiles: Vec<_> = data
+        .profiles
+        .iter()
+        .cloned()
+        .map(|profile| {
+            let active = data.active_profile.as_deref() == Some(profile.name.as_str());
+            let name = if active { format!("● {}", profile.name) } else { profile.name.clone() };
+            button(label(name), move |d: &mut AppData| {
+                d.apply_profile(&profile);
+                remember_profile(&profile.name);
+            })
+        })
+        .collect();
     let imposition_toolbar = flex(
         Axis::Horizontal,
         (
-            button(label("1-up"), |d: &mut AppData| {
-                d.set_pages_per_sheet(PagesPerSheet::One)
-            }),
-            button(label("2-up"), |d: &mut AppData| {
-                d.set_pages_per_sheet(PagesPerSheet::Two)
-            }),
-            button(label("4-up"), |d: &mut AppData| {
-                d.set_pages_per_sheet(PagesPerSheet::Four)
-            }),
-            button(label("6-up"), |d: &mut AppData| {
-                d.set_pages_per_sheet(PagesPerSheet::Six)
-            }),
-            button(label("8-up"), |d: &mut AppData| {
-                d.set_pages_per_sheet(PagesPerSheet::Eight)
-            }),
+            profiles,
+            presets,
             button(label("Horizontal"), |d: &mut AppData| {
                 d.set_fill_order(FillOrder::Horizontal)
             }),
             button(label("Vertical"), |d: &mut AppData| {
                 d.set_fill_order(FillOrder::Vertical)
             }),
+            button(
+                label(if data.imposed_preview { "Source Pages" } else { "Printed Sheet" }),
+                |d: &mut AppData| d.set_imposed_preview(!d.imposed_preview),
+            ),
         ),
     );
 
     let canvas = pdf_canvas(
         data.current_canvas_images(),
-        data.print_options.pages_per_sheet as u8,
+        data.canvas_pages_per_sheet(),
         data.fill_order == FillOrder::Vertical,
         data.zoom,
+        data.zoom_mode,
+        run_shortcut,
     );
-    let status = status_text(data);
+    let status = status_bar(data);
     let footer = sized_box(flex(
         Axis::Horizontal,
         (
@@ -78,16 +110,23 @@ fn app_logic(data: &mut AppData) -> impl WidgetView<AppData> + use<> {
             button(label("Next ▶"), |d: &mut AppData| d.next_page()),
             button(label("Last ⏭"), |d: &mut AppData| d.last_page()),
             FlexSpacer::Flex(1.0),
-            label(status),
+            status,
         ),
     ))
     .expand_width()
     .height(32.px())
     .border(Color::from_rgb8(96, 96, 96), 1.0);
+    let document_view = flex(Axis::Horizontal, (bookmarks_panel(data), canvas.flex(1.0)));
+    // Shortcut conflicts stay visible until the configuration is fixed
+    let shortcut_warnings: Vec<_> = data
+        .shortcut_conflicts
+        .iter()
+        .map(|conflict| label(format!("Shortcut conflict: {conflict}")))
+        .collect();
     let content = sized_box(
         flex(
             Axis::Vertical,
-            (toolbar, imposition_toolbar, canvas.flex(1.0)),
+            (shortcut_warnings, toolbar, imposition_toolbar, document_view.flex(1.0)),
         )
         .must_fill_major_axis(true),
     )
@@ -97,49 +136,27 @@ fn app_logic(data: &mut AppData) -> impl WidgetView<AppData> + use<> {
     fork(fork(interface, renderer_worker()), ipc_worker())
 }
 
-fn status_text(data: &AppData) -> String {
-    if data.choosing_file {
-        return "Selecting a PDF…".to_owned();
-    }
-
-    if let Some(error) = &data.error_message {
-        return format!("Error: {error}");
+/// Run the action bound to a key pressed over the canvas, if any.
+fn run_shortcut(data: &mut AppData, shortcut: &Shortcut) {
+    if !data.handle_shortcut(shortcut) {
+        debug!(%shortcut, "No action bound to shortcut");
     }
+}
 
-    match data.load_state {
-        LoadState::Idle => "No PDF open".to_owned(),
-        LoadState::Loading => data.document_path.as_ref().map_or_else(
-            || "Loading PDF…".to_owned(),
-            |path| format!("Loading {}…", path.display()),
-        ),
-        LoadState::Error => "Unable to load PDF".to_owned(),
-        LoadState::Ready => {
-            let page_count = data.page_count();
-            let rendered = data.rendered_page_count();
-            let page_status = if data.current_canvas_image().is_some() {
-                "ready"
-            } else {
-                "rendering"
-            };
-            let job_status = data.latest_job_status().map_or_else(String::new, |(job_id, status)| {
-                format!("   ·   job {job_id}: {status}")
-            });
-            format!(
-                "Sheet {} of {page_count} ({page_status})   ·   {}-up   ·   cached {rendered}/{}   ·   zoom {:.0}%{}",
-                data.current_page + 1,
-                data.print_options.pages_per_sheet as u8,
-                data.rendered_pages.len(),
-                data.zoom * 100.0,
-                job_status
-            )
-        }
-    }
+/// Command-line options; set values take precedence over the configuration.
+#[derive(Debug, Default)]
+struct CliArgs {
+    debug: bool,
+    document_path: Option<PathBuf>,
+    zoom: Option<f64>,
+    window_size: Option<(u32, u32)>,
 }
 
 fn main() -> anyhow::Result<()> {
-    let (debug, document_path) = parse_args()?;
+    let args = parse_args(std::env::args_os().skip(1))?;
+    let level = if args.debug { LevelFilter::DEBUG } else { LevelFilter::INFO };
     tracing_subscriber::fmt()
-        .with_max_level(if debug { Level::DEBUG } else { Level::INFO })
+        .with_env_filter(EnvFilter::builder().with_default_directive(level.into()).from_env_lossy())
         .with_target(false)
         .init();
 
@@ -149,29 +166,160 @@ fn main() -> anyhow::Result<()> {
         boomaga_core::constants::APP_VERSION
     );
 
-    let initial_state = document_path.map_or_else(AppData::default, AppData::with_document_path);
-    let app = Xilem::new_simple(
-        initial_state,
-        app_logic,
-        WindowOptions::new(boomaga_core::constants::APP_NAME),
-    );
+    let config = load_config();
+    let settings = load_settings();
+    let mut initial_state = AppData::from_config(&config)
+        .with_ui_settings(&settings.ui)
+        .with_layout_presets(PresetLayout::with_custom(&settings.custom_layouts))
+        .with_profiles(settings.profiles.clone(), settings.active_profile.as_deref());
+    if let Some(zoom) = args.zoom {
+        initial_state.set_zoom(zoom);
+    }
+    if let Some(path) = args.document_path {
+        initial_state = initial_state.opening(path);
+    }
+
+    let (width, height) = args.window_size.unwrap_or(config.default_window_size);
+    let window = WindowOptions::new(boomaga_core::constants::APP_NAME)
+        .with_initial_inner_size(LogicalSize::new(f64::from(width), f64::from(height)));
+    let app = Xilem::new_simple(initial_state, app_logic, window);
     app.run_in(EventLoop::with_user_event())?;
     Ok(())
 }
 
-fn parse_args() -> anyhow::Result<(bool, Option<PathBuf>)> {
-    let mut debug = false;
-    let mut document_path = None;
+/// Load the preview configuration, including `BOOMAGA_*` overrides.
+fn load_config() -> PreviewConfig {
+    ConfigManager::new()
+        .map_err(|e| e.to_string())
+        .and_then(|manager| manager.load_preview().map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            warn!("Failed to load preview configuration, using defaults: {}", e);
+            PreviewConfig::default()
+        })
+}
+
+/// Save `name` as the active profile, so the next start uses it.
+fn remember_profile(name: &str) {
+    let saved = ConfigManager::new()
+        .map_err(|e| e.to_string())
+        .and_then(|manager| {
+            manager
+                .update_settings(|settings| settings.apply_profile(name).map(|_| ()))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        warn!("Failed to save the active profile {}: {}", name, e);
+    }
+}
+
+/// Load the user settings, falling back to the defaults.
+fn load_settings() -> Settings {
+    ConfigManager::new()
+        .map_err(|e| e.to_string())
+        .and_then(|manager| manager.load_settings().map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            warn!("Failed to load settings, using defaults: {}", e);
+            Settings::default()
+        })
+}
 
-    for argument in std::env::args_os().skip(1) {
+fn parse_args(arguments: impl IntoIterator<Item = OsString>) -> anyhow::Result<CliArgs> {
+    let mut args = CliArgs::default();
+    let mut arguments = arguments.into_iter();
+
+    while let Some(argument) = arguments.next() {
         if argument == OsStr::new("--debug") {
-            debug = true;
-        } else if document_path.is_none() {
-            document_path = Some(PathBuf::from(argument));
+            args.debug = true;
+        } else if argument == OsStr::new("--zoom") {
+            let value = arguments
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("--zoom requires a value"))?;
+            let zoom = value
+                .to_str()
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| anyhow::anyhow!("invalid zoom factor: {}", value.to_string_lossy()))?;
+            args.zoom = Some(zoom);
+        } else if argument == OsStr::new("--window") {
+            let value = arguments
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("--window requires a WIDTHxHEIGHT value"))?;
+            args.window_size = Some(parse_window_size(&value.to_string_lossy())?);
+        } else if args.document_path.is_none() {
+            args.document_path = Some(PathBuf::from(argument));
         } else {
             anyhow::bail!("expected at most one PDF path");
         }
     }
 
-    Ok((debug, document_path))
+    Ok(args)
+}
+
+/// Parse a `WIDTHxHEIGHT` window size such as `1200x900`.
+fn parse_window_size(value: &str) -> anyhow::Result<(u32, u32)> {
+    let invalid = || anyhow::anyhow!("invalid window size {value:?}, expected WIDTHxHEIGHT");
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+    let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boomaga_core::{Document, FileType, Orientation, Page};
+    use pdf_canvas::key_shortcut;
+    use xilem::masonry::core::keyboard::{Code, Key, KeyState, KeyboardEvent, Modifiers, NamedKey};
+
+    fn args(values: &[&str]) -> anyhow::Result<CliArgs> {
+        parse_args(values.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn window_flag_parses_a_size() {
+        let parsed = args(&["--window", "1200x900", "doc.pdf"]).unwrap();
+
+        assert_eq!(parsed.window_size, Some((1200, 900)));
+        assert_eq!(parsed.document_path, Some(PathBuf::from("doc.pdf")));
+    }
+
+    #[test]
+    fn window_flag_rejects_malformed_sizes() {
+        assert!(args(&["--window", "abc"]).is_err());
+        assert!(args(&["--window", "1200x"]).is_err());
+        assert!(args(&["--window", "0x900"]).is_err());
+    }
+
+    #[test]
+    fn canvas_key_presses_run_configured_shortcuts() {
+        let mut document = Document::new("keys".to_owned(), PathBuf::from("keys.pdf"), FileType::Pdf);
+        for number in 1..=3 {
+            document.add_page(Page::new(number, 595.0, 842.0, Orientation::Portrait));
+        }
+        let mut data = AppData::from_config(&PreviewConfig::default());
+        data.document = Some(document);
+        let mut press = KeyboardEvent::key_down(NamedKey::ArrowRight, Code::ArrowRight);
+        press.modifiers = Modifiers::CONTROL;
+
+        run_shortcut(&mut data, &key_shortcut(&press).unwrap());
+        assert_eq!(data.current_page, 1);
+
+        // Shift typed the plus sign, so this is Ctrl++
+        let mut press = KeyboardEvent::key_down(Key::Character("+".to_owned()), Code::Equal);
+        press.modifiers = Modifiers::CONTROL | Modifiers::SHIFT;
+        run_shortcut(&mut data, &key_shortcut(&press).unwrap());
+        assert!(data.zoom > 1.0);
+
+        press.state = KeyState::Up;
+        assert!(key_shortcut(&press).is_none());
+    }
+
+    #[test]
+    fn window_flag_requires_a_value() {
+        assert!(args(&["--window"]).is_err());
+        assert_eq!(args(&[]).unwrap().window_size, None);
+    }
 }
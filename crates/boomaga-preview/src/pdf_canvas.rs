@@ -1,10 +1,14 @@
 //! Masonry PDF-page canvas and its Xilem view adapter.
 
+use boomaga_config::{Key, Modifiers, Shortcut, ZoomMode};
+use boomaga_core::constants::{MAX_ZOOM, MIN_ZOOM};
 use xilem::core::{MessageContext, MessageResult, Mut, View, ViewMarker};
 use xilem::masonry::accesskit::{Node, Role};
+use xilem::masonry::core::keyboard::{Key as KeyValue, KeyState, NamedKey};
 use xilem::masonry::core::{
-    AccessCtx, BoxConstraints, ChildrenIds, LayoutCtx, NoAction, PaintCtx, PropertiesMut,
-    PropertiesRef, RegisterCtx, Widget, WidgetId, WidgetMut,
+    AccessCtx, BoxConstraints, ChildrenIds, EventCtx, KeyboardEvent, LayoutCtx, PaintCtx,
+    PointerEvent, PropertiesMut, PropertiesRef, RegisterCtx, TextEvent, Widget, WidgetId,
+    WidgetMut,
 };
 use xilem::masonry::kurbo::{Affine, Size};
 use xilem::masonry::peniko::{Color, Fill, ImageBrush, ImageFormat};
@@ -54,6 +58,11 @@ impl CanvasImage {
         })
     }
 
+    /// Width and height in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
     fn size(&self) -> Size {
         Size::new(self.width as f64, self.height as f64)
     }
@@ -86,6 +95,22 @@ fn grid_slot(index: usize, pages_per_sheet: u8, vertical: bool) -> usize {
     }
 }
 
+/// Zoom that fits a sheet into the viewport for a fit mode.
+///
+/// Returns `None` for custom zoom, and for viewports without a finite size.
+pub fn fit_zoom(mode: ZoomMode, sheet: Size, viewport: Size) -> Option<f64> {
+    let width = viewport.width / sheet.width;
+    let height = viewport.height / sheet.height;
+    let zoom = match mode {
+        ZoomMode::FitWidth => width,
+        ZoomMode::FitHeight => height,
+        ZoomMode::Fit => width.min(height),
+        ZoomMode::ActualSize => 1.0,
+        ZoomMode::Custom => return None,
+    };
+    (zoom.is_finite() && zoom > 0.0).then(|| zoom.clamp(MIN_ZOOM, MAX_ZOOM))
+}
+
 fn imposed_sheet_size(source_size: Size, pages_per_sheet: u8) -> Size {
     if matches!(pages_per_sheet, 2 | 6 | 8) {
         Size::new(source_size.height, source_size.width)
@@ -94,12 +119,73 @@ fn imposed_sheet_size(source_size: Size, pages_per_sheet: u8) -> Size {
     }
 }
 
+/// Translate a key press into the shortcut it may be bound to.
+///
+/// Shift is dropped for symbols, as it is what typed them, so `Ctrl++`
+/// matches however the plus sign was reached.
+pub(crate) fn key_shortcut(event: &KeyboardEvent) -> Option<Shortcut> {
+    if event.state != KeyState::Down {
+        return None;
+    }
+    let mut modifiers = Modifiers {
+        ctrl: event.modifiers.ctrl(),
+        shift: event.modifiers.shift(),
+        alt: event.modifiers.alt(),
+        super_key: event.modifiers.meta(),
+    };
+    let key = match &event.key {
+        KeyValue::Character(text) if text == " " => Key::Space,
+        KeyValue::Character(text) => {
+            let mut chars = text.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return None;
+            };
+            modifiers.shift &= c.is_alphabetic();
+            Key::Character(c.to_ascii_uppercase())
+        }
+        KeyValue::Named(named) => match named {
+            NamedKey::ArrowLeft => Key::Left,
+            NamedKey::ArrowRight => Key::Right,
+            NamedKey::ArrowUp => Key::Up,
+            NamedKey::ArrowDown => Key::Down,
+            NamedKey::Home => Key::Home,
+            NamedKey::End => Key::End,
+            NamedKey::PageUp => Key::PageUp,
+            NamedKey::PageDown => Key::PageDown,
+            NamedKey::Escape => Key::Escape,
+            NamedKey::Enter => Key::Enter,
+            NamedKey::Tab => Key::Tab,
+            NamedKey::Backspace => Key::Backspace,
+            NamedKey::Delete => Key::Delete,
+            NamedKey::Insert => Key::Insert,
+            NamedKey::F1 => Key::Function(1),
+            NamedKey::F2 => Key::Function(2),
+            NamedKey::F3 => Key::Function(3),
+            NamedKey::F4 => Key::Function(4),
+            NamedKey::F5 => Key::Function(5),
+            NamedKey::F6 => Key::Function(6),
+            NamedKey::F7 => Key::Function(7),
+            NamedKey::F8 => Key::Function(8),
+            NamedKey::F9 => Key::Function(9),
+            NamedKey::F10 => Key::Function(10),
+            NamedKey::F11 => Key::Function(11),
+            NamedKey::F12 => Key::Function(12),
+            _ => return None,
+        },
+    };
+    Some(Shortcut { modifiers, key })
+}
+
 /// Masonry leaf widget that paints one rendered PDF page.
+///
+/// Clicking it takes keyboard focus; key presses are then submitted as
+/// shortcuts.
 pub struct PdfCanvasWidget {
     images: Vec<Option<CanvasImage>>,
     pages_per_sheet: u8,
     vertical_fill: bool,
     zoom: f64,
+    zoom_mode: ZoomMode,
 }
 
 impl PdfCanvasWidget {
@@ -108,12 +194,14 @@ impl PdfCanvasWidget {
         pages_per_sheet: u8,
         vertical_fill: bool,
         zoom: f64,
+        zoom_mode: ZoomMode,
     ) -> Self {
         Self {
             images,
             pages_per_sheet,
             vertical_fill,
             zoom,
+            zoom_mode,
         }
     }
 
@@ -123,17 +211,48 @@ impl PdfCanvasWidget {
         pages_per_sheet: u8,
         vertical_fill: bool,
         zoom: f64,
+        zoom_mode: ZoomMode,
     ) {
         this.widget.images = images;
         this.widget.pages_per_sheet = pages_per_sheet;
         this.widget.vertical_fill = vertical_fill;
         this.widget.zoom = zoom;
+        this.widget.zoom_mode = zoom_mode;
         this.ctx.request_layout();
     }
 }
 
 impl Widget for PdfCanvasWidget {
-    type Action = NoAction;
+    type Action = Shortcut;
+
+    fn on_pointer_event(
+        &mut self,
+        ctx: &mut EventCtx<'_>,
+        _props: &mut PropertiesMut<'_>,
+        event: &PointerEvent,
+    ) {
+        if let PointerEvent::Down(_) = event {
+            ctx.request_focus();
+        }
+    }
+
+    fn on_text_event(
+        &mut self,
+        ctx: &mut EventCtx<'_>,
+        _props: &mut PropertiesMut<'_>,
+        event: &TextEvent,
+    ) {
+        if let TextEvent::Keyboard(key) = event {
+            if let Some(shortcut) = key_shortcut(key) {
+                ctx.submit_action::<Shortcut>(shortcut);
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn accepts_focus(&self) -> bool {
+        true
+    }
 
     fn register_children(&mut self, _ctx: &mut RegisterCtx<'_>) {}
 
@@ -150,7 +269,9 @@ impl Widget for PdfCanvasWidget {
             .next()
             .map_or(Size::new(595.0, 842.0), CanvasImage::size);
         let sheet_size = imposed_sheet_size(natural, self.pages_per_sheet);
-        let preferred = Size::new(sheet_size.width * self.zoom, sheet_size.height * self.zoom);
+        // Fit modes follow the space available, so resizes recompute them
+        let zoom = fit_zoom(self.zoom_mode, sheet_size, bc.max()).unwrap_or(self.zoom);
+        let preferred = Size::new(sheet_size.width * zoom, sheet_size.height * zoom);
         bc.constrain_aspect_ratio(preferred.height / preferred.width, preferred.width)
     }
 
@@ -203,42 +324,58 @@ impl Widget for PdfCanvasWidget {
 
 /// Xilem view that owns the reactive inputs to [`PdfCanvasWidget`].
 #[must_use = "View values do nothing unless provided to Xilem"]
-pub struct PdfCanvas {
+pub struct PdfCanvas<F> {
     images: Vec<Option<CanvasImage>>,
     pages_per_sheet: u8,
     vertical_fill: bool,
     zoom: f64,
+    zoom_mode: ZoomMode,
+    on_shortcut: F,
 }
 
-/// Create a PDF canvas view.
-pub fn pdf_canvas(
+/// Create a PDF canvas view calling `on_shortcut` for keys pressed while it
+/// has focus.
+pub fn pdf_canvas<State, Action, F>(
     images: Vec<Option<CanvasImage>>,
     pages_per_sheet: u8,
     vertical_fill: bool,
     zoom: f64,
-) -> PdfCanvas {
+    zoom_mode: ZoomMode,
+    on_shortcut: F,
+) -> PdfCanvas<F>
+where
+    F: Fn(&mut State, &Shortcut) -> Action + Send + Sync + 'static,
+{
     PdfCanvas {
         images,
         pages_per_sheet,
         vertical_fill,
         zoom,
+        zoom_mode,
+        on_shortcut,
     }
 }
 
-impl ViewMarker for PdfCanvas {}
+impl<F> ViewMarker for PdfCanvas<F> {}
 
-impl<State, Action> View<State, Action, ViewCtx> for PdfCanvas {
+impl<State, Action, F> View<State, Action, ViewCtx> for PdfCanvas<F>
+where
+    F: Fn(&mut State, &Shortcut) -> Action + Send + Sync + 'static,
+{
     type Element = Pod<PdfCanvasWidget>;
     type ViewState = ();
 
     fn build(&self, ctx: &mut ViewCtx, _: &mut State) -> (Self::Element, Self::ViewState) {
         (
-            ctx.create_pod(PdfCanvasWidget::new(
-                self.images.clone(),
-                self.pages_per_sheet,
-                self.vertical_fill,
-                self.zoom,
-            )),
+            ctx.with_action_widget(|ctx| {
+                ctx.create_pod(PdfCanvasWidget::new(
+                    self.images.clone(),
+                    self.pages_per_sheet,
+                    self.vertical_fill,
+                    self.zoom,
+                    self.zoom_mode,
+                ))
+            }),
             (),
         )
     }
@@ -255,6 +392,7 @@ impl<State, Action> View<State, Action, ViewCtx> for PdfCanvas {
             || self.pages_per_sheet != prev.pages_per_sheet
             || self.vertical_fill != prev.vertical_fill
             || self.zoom != prev.zoom
+            || self.zoom_mode != prev.zoom_mode
         {
             PdfCanvasWidget::update(
                 &mut element,
@@ -262,6 +400,7 @@ impl<State, Action> View<State, Action, ViewCtx> for PdfCanvas {
                 self.pages_per_sheet,
                 self.vertical_fill,
                 self.zoom,
+                self.zoom_mode,
             );
         }
     }
@@ -280,10 +419,15 @@ impl<State, Action> View<State, Action, ViewCtx> for PdfCanvas {
         (): &mut Self::ViewState,
         message: &mut MessageContext,
         _: Mut<'_, Self::Element>,
-        _: &mut State,
+        state: &mut State,
     ) -> MessageResult<Action> {
-        tracing::error!(?message, "unexpected message delivered to PdfCanvas");
-        MessageResult::Stale
+        match message.take_message::<Shortcut>() {
+            Some(shortcut) => MessageResult::Action((self.on_shortcut)(state, &shortcut)),
+            None => {
+                tracing::error!(?message, "unexpected message delivered to PdfCanvas");
+                MessageResult::Stale
+            }
+        }
     }
 }
 
@@ -334,6 +478,34 @@ mod tests {
         assert_eq!(imposed_sheet_size(portrait, 8), Size::new(842.0, 595.0));
     }
 
+    #[test]
+    fn fit_modes_scale_to_the_matching_viewport_edge() {
+        let sheet = Size::new(600.0, 800.0);
+        let viewport = Size::new(900.0, 1000.0);
+
+        assert_eq!(fit_zoom(ZoomMode::FitWidth, sheet, viewport), Some(1.5));
+        assert_eq!(fit_zoom(ZoomMode::FitHeight, sheet, viewport), Some(1.25));
+        assert_eq!(fit_zoom(ZoomMode::Fit, sheet, viewport), Some(1.25));
+        assert_eq!(fit_zoom(ZoomMode::ActualSize, sheet, viewport), Some(1.0));
+        assert_eq!(fit_zoom(ZoomMode::Custom, sheet, viewport), None);
+
+        let wide = Size::new(1200.0, 1000.0);
+        assert_eq!(fit_zoom(ZoomMode::FitWidth, sheet, wide), Some(2.0));
+        assert_eq!(fit_zoom(ZoomMode::Fit, sheet, wide), Some(1.25));
+
+        let huge = Size::new(6000.0, 8000.0);
+        assert_eq!(fit_zoom(ZoomMode::Fit, sheet, huge), Some(MAX_ZOOM));
+    }
+
+    #[test]
+    fn fit_zoom_ignores_unbounded_viewports() {
+        let sheet = Size::new(600.0, 800.0);
+        let unbounded = Size::new(f64::INFINITY, 1000.0);
+
+        assert_eq!(fit_zoom(ZoomMode::FitWidth, sheet, unbounded), None);
+        assert_eq!(fit_zoom(ZoomMode::FitHeight, sheet, unbounded), Some(1.25));
+    }
+
     #[test]
     fn vertical_fill_matches_classic_boomaga_order() {
         let four_up: Vec<_> = (0..4).map(|index| grid_slot(index, 4, true)).collect();
@@ -3,7 +3,9 @@
 use std::fmt;
 use std::path::PathBuf;
 
+use boomaga_config::{ConfigManager, PerformanceSettings};
 use boomaga_core::Document;
+use boomaga_layout_engine::SheetSide;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use xilem::core::{MessageProxy, NoElement, View};
 use xilem::view::worker;
@@ -21,6 +23,12 @@ pub enum RendererCommand {
     OpenFileDialog,
     Load { generation: u64, path: PathBuf },
     RenderPage { generation: u64, page_index: usize },
+    RenderSheet {
+        generation: u64,
+        revision: u64,
+        sheet_index: usize,
+        side: SheetSide,
+    },
 }
 
 /// Results delivered to `AppData` on Xilem's UI thread.
@@ -37,6 +45,18 @@ pub enum RendererEvent {
         page_index: usize,
         image: CanvasImage,
     },
+    SheetRendered {
+        generation: u64,
+        revision: u64,
+        sheet_index: usize,
+        image: CanvasImage,
+    },
+    SheetFailed {
+        generation: u64,
+        revision: u64,
+        sheet_index: usize,
+        message: String,
+    },
     Failed {
         generation: Option<u64>,
         page_index: Option<usize>,
@@ -68,6 +88,29 @@ impl fmt::Debug for RendererEvent {
                 .field("generation", generation)
                 .field("page_index", page_index)
                 .finish_non_exhaustive(),
+            Self::SheetRendered {
+                generation,
+                revision,
+                sheet_index,
+                ..
+            } => formatter
+                .debug_struct("SheetRendered")
+                .field("generation", generation)
+                .field("revision", revision)
+                .field("sheet_index", sheet_index)
+                .finish_non_exhaustive(),
+            Self::SheetFailed {
+                generation,
+                revision,
+                sheet_index,
+                message,
+            } => formatter
+                .debug_struct("SheetFailed")
+                .field("generation", generation)
+                .field("revision", revision)
+                .field("sheet_index", sheet_index)
+                .field("message", message)
+                .finish(),
             Self::Failed {
                 generation,
                 page_index,
@@ -91,6 +134,17 @@ pub fn renderer_worker() -> impl View<AppData, (), ViewCtx, Element = NoElement>
     )
 }
 
+/// Per-page pixel budget from the user's performance settings.
+fn page_pixel_budget() -> u64 {
+    ConfigManager::new()
+        .ok()
+        .and_then(|manager| manager.load_settings().ok())
+        .map_or_else(
+            || PerformanceSettings::default().page_pixel_budget(),
+            |settings| settings.performance.page_pixel_budget(),
+        )
+}
+
 async fn run_renderer_thread(
     proxy: MessageProxy<RendererEvent>,
     receiver: UnboundedReceiver<RendererCommand>,
@@ -114,6 +168,7 @@ fn renderer_loop(
 ) {
     let mut active_generation = None;
     let mut renderer = None;
+    let pixel_budget = page_pixel_budget();
 
     while let Some(command) = receiver.blocking_recv() {
         let event = match command {
@@ -128,7 +183,8 @@ fn renderer_loop(
                 )
             }
             RendererCommand::Load { generation, path } => {
-                let mut next_renderer = DocumentRenderer::new(path.to_string_lossy());
+                let mut next_renderer =
+                    DocumentRenderer::new(path.to_string_lossy()).with_pixel_budget(pixel_budget);
                 match next_renderer.load(&path) {
                     Ok(document) => {
                         active_generation = Some(generation);
@@ -169,6 +225,33 @@ fn renderer_loop(
                     },
                 }
             }
+            RendererCommand::RenderSheet {
+                generation,
+                revision,
+                sheet_index,
+                side,
+            } => {
+                if active_generation != Some(generation) {
+                    continue;
+                }
+                let Some(active_renderer) = renderer.as_ref() else {
+                    continue;
+                };
+                match active_renderer.render_sheet(&side, PREVIEW_DPI) {
+                    Ok(image) => RendererEvent::SheetRendered {
+                        generation,
+                        revision,
+                        sheet_index,
+                        image,
+                    },
+                    Err(error) => RendererEvent::SheetFailed {
+                        generation,
+                        revision,
+                        sheet_index,
+                        message: error.to_string(),
+                    },
+                }
+            }
         };
 
         if proxy.message(event).is_err() {
@@ -0,0 +1,123 @@
+//! Status bar summarizing the current page, zoom, and backend job.
+
+use boomaga_config::ZoomMode;
+use xilem::view::label;
+use xilem::WidgetView;
+
+use crate::app::{AppData, IpcState, LoadState};
+
+/// Status bar label, or nothing when the status bar is hidden.
+pub fn status_bar(data: &AppData) -> Option<impl WidgetView<AppData> + use<>> {
+    data.show_status_bar.then(|| label(status_text(data)))
+}
+
+/// Text shown in the status bar for the current state.
+pub fn status_text(data: &AppData) -> String {
+    if data.choosing_file {
+        return "Selecting a PDF…".to_owned();
+    }
+
+    if let Some(error) = &data.error_message {
+        return format!("Error: {error}");
+    }
+
+    match data.load_state {
+        LoadState::Idle => "No PDF open".to_owned(),
+        LoadState::Loading => data.document_path.as_ref().map_or_else(
+            || "Loading PDF…".to_owned(),
+            |path| format!("Loading {}…", path.display()),
+        ),
+        LoadState::Error => "Unable to load PDF".to_owned(),
+        LoadState::Ready => {
+            let page_count = data.page_count();
+            let rendered = data.rendered_page_count();
+            let page_status = if data.current_canvas_image().is_some() {
+                "ready"
+            } else {
+                "rendering"
+            };
+            let job_status = data.latest_job_status().map_or_else(String::new, |(job_id, status)| {
+                format!("   ·   job {job_id}: {status}")
+            });
+            format!(
+                "Page {}/{page_count} ({page_status})   ·   {}-up   ·   cached {rendered}/{}   ·   zoom {}{}{}",
+                data.current_page + 1,
+                data.print_options.pages_per_sheet as u8,
+                data.rendered_pages.len(),
+                zoom_text(data),
+                job_status,
+                backend_text(data)
+            )
+        }
+    }
+}
+
+/// Backend heartbeat latency, or that the backend stopped answering.
+fn backend_text(data: &AppData) -> String {
+    match (data.ipc_state, data.ipc_latency, &data.ipc_error) {
+        (IpcState::Connected, Some(latency), _) => format!("   ·   backend {} ms", latency.as_millis()),
+        (IpcState::Disconnected, _, Some(_)) => "   ·   backend offline".to_owned(),
+        _ => String::new(),
+    }
+}
+
+fn zoom_text(data: &AppData) -> String {
+    match data.zoom_mode {
+        ZoomMode::Fit => "fit page".to_owned(),
+        ZoomMode::FitWidth => "fit width".to_owned(),
+        ZoomMode::FitHeight => "fit height".to_owned(),
+        ZoomMode::ActualSize => "100%".to_owned(),
+        ZoomMode::Custom => format!("{:.0}%", data.zoom * 100.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc_worker::IpcEvent;
+    use boomaga_config::UISettings;
+    use boomaga_core::{Document, FileType, JobId, JobStatus, Orientation, Page};
+    use std::path::PathBuf;
+
+    #[test]
+    fn ready_status_shows_page_zoom_and_job() {
+        let mut document = Document::new("status".to_owned(), PathBuf::from("status.pdf"), FileType::Pdf);
+        for number in 1..=3 {
+            document.add_page(Page::new(number, 595.0, 842.0, Orientation::Portrait));
+        }
+        let job_id: JobId =
+            serde_json::from_str("\"f7f04d62-a28d-4f7c-a55a-cf35dc913918\"").unwrap();
+        let mut data = AppData::with_document(document);
+        data.current_page = 1;
+        data.zoom = 1.5;
+        data.job_history = vec![job_id.clone()];
+        data.job_statuses.insert(job_id.to_string(), JobStatus::Processing);
+
+        assert_eq!(
+            status_text(&data),
+            "Page 2/3 (rendering)   ·   1-up   ·   cached 0/3   ·   zoom 150%   ·   \
+             job f7f04d62-a28d-4f7c-a55a-cf35dc913918: Processing"
+        );
+    }
+
+    #[test]
+    fn ready_status_shows_backend_heartbeat() {
+        let mut document = Document::new("status".to_owned(), PathBuf::from("status.pdf"), FileType::Pdf);
+        document.add_page(Page::new(1, 595.0, 842.0, Orientation::Portrait));
+        let mut data = AppData::with_document(document);
+
+        data.handle_ipc_event(IpcEvent::Heartbeat(std::time::Duration::from_millis(3)));
+        assert!(status_text(&data).ends_with("zoom 100%   ·   backend 3 ms"));
+
+        data.handle_ipc_event(IpcEvent::Disconnected("Backend is not responding".to_owned()));
+        assert!(status_text(&data).ends_with("zoom 100%   ·   backend offline"));
+    }
+
+    #[test]
+    fn hidden_status_bar_builds_no_view() {
+        let data = AppData::default().with_ui_settings(&UISettings { show_status_bar: false, ..UISettings::default() });
+
+        assert!(status_bar(&data).is_none());
+        assert!(status_bar(&AppData::default()).is_some());
+    }
+}